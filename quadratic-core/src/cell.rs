@@ -1,14 +1,22 @@
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use wasm_bindgen::prelude::*;
 
+use crate::formulas::ErrorKind;
+
 /// Contents of a single spreadsheet cell.
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub enum Cell {
     #[default]
     Empty,
     Int(i64),
+    Number(f64),
     Text(String),
+    Bool(bool),
+    Error(ErrorKind),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
 }
 impl Cell {
     pub fn is_empty(&self) -> bool {
@@ -19,7 +27,13 @@ impl Cell {
         match self {
             Cell::Empty => "".into(),
             Cell::Int(i) => i.to_string().into(),
+            Cell::Number(n) => n.to_string().into(),
             Cell::Text(s) => s.into(),
+            Cell::Bool(true) => "TRUE".into(),
+            Cell::Bool(false) => "FALSE".into(),
+            Cell::Error(e) => e.code().into(),
+            Cell::Date(d) => d.format("%Y-%m-%d").to_string().into(),
+            Cell::DateTime(dt) => dt.format("%Y-%m-%d %H:%M:%S").to_string().into(),
         }
     }
 }
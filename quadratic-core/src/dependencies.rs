@@ -0,0 +1,457 @@
+//! Graph of dependencies between cells, used to decide which cells need to be
+//! recalculated when other cells change.
+
+use futures::Future;
+use petgraph::graphmap::{DiGraphMap, NodeTrait};
+use petgraph::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map, HashMap, HashSet, VecDeque};
+
+use crate::Pos;
+
+/// Graph of which cells read the values of which other cells.
+///
+/// There is an edge from `a` to `b` if `b` reads the value of `a`. In other
+/// words, `a` is a precedent of `b` and `b` is a dependent of `a`.
+///
+/// Cells are identified by `N`, which is a position on a single sheet by
+/// default.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph<N: NodeTrait = Pos> {
+    graph: DiGraphMap<N, ()>,
+}
+impl<N: NodeTrait> Default for DependencyGraph<N> {
+    fn default() -> Self {
+        Self {
+            graph: DiGraphMap::new(),
+        }
+    }
+}
+impl<N: NodeTrait> DependencyGraph<N> {
+    /// Constructs an empty dependency graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the set of cells that `cell` reads (e.g., the cells accessed
+    /// while evaluating its formula).
+    pub fn set_precedents(&mut self, cell: N, precedents: impl IntoIterator<Item = N>) {
+        self.remove_precedents(cell);
+        self.graph.add_node(cell);
+        for precedent in precedents {
+            self.graph.add_edge(precedent, cell, ());
+        }
+        self.remove_if_isolated(cell);
+    }
+    /// Removes all the cells that `cell` reads, such as when its formula is
+    /// deleted. Cells that read `cell` are not affected.
+    pub fn remove_precedents(&mut self, cell: N) {
+        for precedent in self.precedents(cell).collect::<Vec<_>>() {
+            self.graph.remove_edge(precedent, cell);
+            self.remove_if_isolated(precedent);
+        }
+        self.remove_if_isolated(cell);
+    }
+    /// Removes all dependencies.
+    pub fn clear(&mut self) {
+        self.graph.clear();
+    }
+
+    /// Removes a cell from the graph if it has no dependencies in either
+    /// direction, to keep the graph small.
+    fn remove_if_isolated(&mut self, cell: N) {
+        if self.graph.contains_node(cell)
+            && self
+                .graph
+                .neighbors_directed(cell, Direction::Incoming)
+                .next()
+                .is_none()
+            && self
+                .graph
+                .neighbors_directed(cell, Direction::Outgoing)
+                .next()
+                .is_none()
+        {
+            self.graph.remove_node(cell);
+        }
+    }
+
+    /// Returns the cells that `cell` reads.
+    pub fn precedents(&self, cell: N) -> impl '_ + Iterator<Item = N> {
+        self.neighbors(cell, Direction::Incoming)
+    }
+    /// Returns the cells that read `cell`.
+    pub fn dependents(&self, cell: N) -> impl '_ + Iterator<Item = N> {
+        self.neighbors(cell, Direction::Outgoing)
+    }
+    fn neighbors(&self, cell: N, dir: Direction) -> impl '_ + Iterator<Item = N> {
+        self.graph
+            .contains_node(cell)
+            .then(|| self.graph.neighbors_directed(cell, dir))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Returns every cell that transitively reads any of the `changed` cells,
+    /// in an order such that each cell comes after all the cells it reads.
+    ///
+    /// The `changed` cells themselves are only included if they read another
+    /// changed cell. Cells that are part of or depend on a circular reference
+    /// can't be ordered, so they are returned in `blocked_cells` instead.
+    pub fn cells_to_recalculate(&self, changed: &[N]) -> RecalculationOrder<N> {
+        // Find all cells downstream of the changed cells.
+        let mut dirty = HashSet::new();
+        let mut queue: VecDeque<N> = changed.iter().copied().collect();
+        while let Some(cell) = queue.pop_front() {
+            for dependent in self.dependents(cell) {
+                if dirty.insert(dependent) {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        // Sort them topologically using Kahn's algorithm, only counting edges
+        // between dirty cells.
+        let mut in_degrees: HashMap<N, usize> = dirty
+            .iter()
+            .map(|&cell| {
+                let in_degree = self.precedents(cell).filter(|p| dirty.contains(p)).count();
+                (cell, in_degree)
+            })
+            .collect();
+        let mut ready: Vec<N> = in_degrees
+            .iter()
+            .filter(|(_, &in_degree)| in_degree == 0)
+            .map(|(&cell, _)| cell)
+            .collect();
+        // Sort for deterministic output.
+        ready.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut order = vec![];
+        while let Some(cell) = ready.pop() {
+            order.push(cell);
+            let mut newly_ready = vec![];
+            for dependent in self.dependents(cell) {
+                if let Some(in_degree) = in_degrees.get_mut(&dependent) {
+                    *in_degree -= 1;
+                    if *in_degree == 0 {
+                        newly_ready.push(dependent);
+                    }
+                }
+            }
+            newly_ready.sort_unstable_by(|a, b| b.cmp(a));
+            ready.extend(newly_ready);
+        }
+
+        // Any cells left over are part of or downstream of a cycle.
+        let blocked: HashSet<N> = in_degrees
+            .into_iter()
+            .filter(|&(_, in_degree)| in_degree > 0)
+            .map(|(cell, _)| cell)
+            .collect();
+        let circular_references = self.find_cycles_among(&blocked);
+
+        let mut blocked_cells: Vec<N> = blocked.into_iter().collect();
+        blocked_cells.sort_unstable();
+
+        RecalculationOrder {
+            cells: order,
+            blocked_cells,
+            circular_references,
+        }
+    }
+
+    /// Returns every circular reference in the graph, as a list of cycles.
+    pub fn find_cycles(&self) -> Vec<Vec<N>> {
+        let all_cells = self.graph.nodes().collect();
+        self.find_cycles_among(&all_cells)
+    }
+
+    /// Returns the circular references among a set of cells, as a list of
+    /// cycles. Each cycle starts at its lowest cell and lists each cell once,
+    /// in the order that values flow between them.
+    fn find_cycles_among(&self, cells: &HashSet<N>) -> Vec<Vec<N>> {
+        let mut subgraph = DiGraphMap::<N, ()>::new();
+        for &cell in cells {
+            subgraph.add_node(cell);
+            for dependent in self.dependents(cell).filter(|d| cells.contains(d)) {
+                subgraph.add_edge(cell, dependent, ());
+            }
+        }
+
+        let mut cycles: Vec<Vec<N>> = petgraph::algo::tarjan_scc(&subgraph)
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || subgraph.contains_edge(component[0], component[0])
+            })
+            .filter_map(|component| {
+                let start = *component.iter().min()?;
+                let component: HashSet<N> = component.into_iter().collect();
+                self.cycle_path(start, |cell| component.contains(&cell))
+            })
+            .collect();
+        cycles.sort_unstable();
+        cycles
+    }
+
+    /// Returns a path of dependencies from `start` back to itself, passing
+    /// only through cells for which `allowed` returns `true`. The path starts
+    /// at `start` and does not repeat it at the end.
+    pub fn cycle_path(&self, start: N, allowed: impl Fn(N) -> bool) -> Option<Vec<N>> {
+        // Breadth-first search finds the shortest cycle.
+        let mut came_from: HashMap<N, N> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            let mut dependents: Vec<N> = self.dependents(cell).filter(|&d| allowed(d)).collect();
+            dependents.sort_unstable();
+            for dependent in dependents {
+                if dependent == start {
+                    let mut path = vec![cell];
+                    while let Some(&prev) = path.last().and_then(|c| came_from.get(c)) {
+                        path.push(prev);
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                if let hash_map::Entry::Vacant(e) = came_from.entry(dependent) {
+                    e.insert(cell);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+        None
+    }
+    /// Returns the circular reference that `cell` is part of, if any, starting
+    /// at `cell`.
+    pub fn cycle_through(&self, cell: N) -> Option<Vec<N>> {
+        self.cycle_path(cell, |_| true)
+    }
+}
+
+/// Order in which to recalculate cells after some cells change.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct RecalculationOrder<N = Pos> {
+    /// Cells to recalculate, in order.
+    pub cells: Vec<N>,
+    /// Cells that cannot be recalculated because they are part of or depend on
+    /// a circular reference.
+    pub blocked_cells: Vec<N>,
+    /// Circular references among the cells, each listed in the order that
+    /// values flow between them.
+    pub circular_references: Vec<Vec<N>>,
+}
+
+/// Settings for calculating circular references iteratively, rather than
+/// reporting them as errors.
+///
+/// The cells in a cycle are recalculated repeatedly, each reading the values
+/// the others had in the previous iteration, until no value changes by more
+/// than `epsilon` or `max_iterations` is reached.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct IterativeCalculation {
+    /// Maximum number of times to recalculate the cells.
+    pub max_iterations: u32,
+    /// Largest change in any value that is considered converged.
+    pub epsilon: f64,
+}
+impl Default for IterativeCalculation {
+    fn default() -> Self {
+        Self {
+            max_iterations: 100,
+            epsilon: 0.001,
+        }
+    }
+}
+impl IterativeCalculation {
+    /// Repeatedly calls `step`, which should recalculate each cell in a
+    /// circular reference and return their new numeric values, until the
+    /// values converge.
+    pub async fn run<F: Future<Output = Vec<f64>>>(
+        &self,
+        mut step: impl FnMut() -> F,
+    ) -> IterationOutcome {
+        let mut previous: Option<Vec<f64>> = None;
+        for iterations in 1..=self.max_iterations {
+            let values = step().await;
+            if let Some(previous) = previous {
+                if self.has_converged(&previous, &values) {
+                    return IterationOutcome {
+                        iterations,
+                        converged: true,
+                    };
+                }
+            }
+            previous = Some(values);
+        }
+        IterationOutcome {
+            iterations: self.max_iterations,
+            converged: false,
+        }
+    }
+
+    fn has_converged(&self, previous: &[f64], values: &[f64]) -> bool {
+        previous.len() == values.len()
+            && std::iter::zip(previous, values).all(|(a, b)| (a - b).abs() <= self.epsilon)
+    }
+}
+
+/// Result of calculating a circular reference iteratively.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IterationOutcome {
+    /// Number of times the cells were recalculated.
+    pub iterations: u32,
+    /// Whether the values converged before reaching the maximum number of
+    /// iterations.
+    pub converged: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(x: i64, y: i64) -> Pos {
+        Pos { x, y }
+    }
+
+    #[test]
+    fn test_recalculation_order() {
+        let mut g = DependencyGraph::new();
+        // B1 = A1 + 1; C1 = A1 + B1; D1 = C1; E1 = Z1
+        g.set_precedents(p(1, 1), [p(0, 1)]);
+        g.set_precedents(p(2, 1), [p(0, 1), p(1, 1)]);
+        g.set_precedents(p(3, 1), [p(2, 1)]);
+        g.set_precedents(p(4, 1), [p(25, 1)]);
+
+        let order = g.cells_to_recalculate(&[p(0, 1)]);
+        assert_eq!(vec![p(1, 1), p(2, 1), p(3, 1)], order.cells);
+        assert!(order.blocked_cells.is_empty());
+        assert!(order.circular_references.is_empty());
+
+        let order = g.cells_to_recalculate(&[p(1, 1)]);
+        assert_eq!(vec![p(2, 1), p(3, 1)], order.cells);
+
+        let order = g.cells_to_recalculate(&[p(9, 9)]);
+        assert!(order.cells.is_empty());
+
+        // Changing precedents replaces the old ones.
+        g.set_precedents(p(2, 1), [p(1, 1)]);
+        assert_eq!(vec![p(1, 1)], g.precedents(p(2, 1)).collect::<Vec<_>>());
+        g.remove_precedents(p(1, 1));
+        let order = g.cells_to_recalculate(&[p(0, 1)]);
+        assert!(order.cells.is_empty());
+    }
+
+    #[test]
+    fn test_circular_references() {
+        let mut g = DependencyGraph::new();
+        // A1 = C1; B1 = A1; C1 = B1; D1 = B1; E1 = Z1
+        g.set_precedents(p(0, 1), [p(2, 1)]);
+        g.set_precedents(p(1, 1), [p(0, 1)]);
+        g.set_precedents(p(2, 1), [p(1, 1)]);
+        g.set_precedents(p(3, 1), [p(1, 1)]);
+        g.set_precedents(p(4, 1), [p(25, 1)]);
+
+        let order = g.cells_to_recalculate(&[p(0, 1), p(25, 1)]);
+        assert_eq!(vec![p(4, 1)], order.cells);
+        assert_eq!(
+            vec![p(0, 1), p(1, 1), p(2, 1), p(3, 1)],
+            order.blocked_cells,
+        );
+        assert_eq!(
+            vec![vec![p(0, 1), p(1, 1), p(2, 1)]],
+            order.circular_references,
+        );
+
+        assert_eq!(vec![vec![p(0, 1), p(1, 1), p(2, 1)]], g.find_cycles());
+
+        // Breaking the cycle fixes it.
+        g.set_precedents(p(0, 1), []);
+        assert!(g.find_cycles().is_empty());
+        let order = g.cells_to_recalculate(&[p(0, 1)]);
+        assert_eq!(vec![p(1, 1), p(2, 1), p(3, 1)], order.cells);
+
+        // Replacing a formula in the cycle with a plain value fixes it too.
+        g.set_precedents(p(0, 1), [p(2, 1)]);
+        assert!(g.cycle_through(p(1, 1)).is_some());
+        g.remove_precedents(p(2, 1));
+        assert_eq!(None, g.cycle_through(p(1, 1)));
+        let order = g.cells_to_recalculate(&[p(2, 1)]);
+        assert_eq!(vec![p(0, 1), p(1, 1), p(3, 1)], order.cells);
+        assert!(order.circular_references.is_empty());
+
+        // Self-reference
+        g.set_precedents(p(5, 5), [p(5, 5)]);
+        assert_eq!(vec![vec![p(5, 5)]], g.find_cycles());
+        assert_eq!(Some(vec![p(5, 5)]), g.cycle_through(p(5, 5)));
+    }
+
+    #[test]
+    fn test_cycle_through() {
+        let mut g = DependencyGraph::new();
+        // A1 = B1; B1 = C1 + D1; C1 = A1; D1 = B1
+        g.set_precedents(p(0, 1), [p(1, 1)]);
+        g.set_precedents(p(1, 1), [p(2, 1), p(3, 1)]);
+        g.set_precedents(p(2, 1), [p(0, 1)]);
+        g.set_precedents(p(3, 1), [p(1, 1)]);
+
+        assert_eq!(
+            Some(vec![p(0, 1), p(2, 1), p(1, 1)]),
+            g.cycle_through(p(0, 1)),
+        );
+        assert_eq!(Some(vec![p(3, 1), p(1, 1)]), g.cycle_through(p(3, 1)));
+        assert_eq!(None, g.cycle_through(p(9, 9)));
+
+        g.remove_precedents(p(2, 1));
+        assert_eq!(None, g.cycle_through(p(0, 1)));
+        assert_eq!(Some(vec![p(1, 1), p(3, 1)]), g.cycle_through(p(1, 1)));
+    }
+
+    #[test]
+    fn test_cycle_across_sheets() {
+        let mut g = DependencyGraph::<(u32, Pos)>::new();
+        // Sheet1!A1 = Sheet2!A1; Sheet2!A1 = Sheet1!B1
+        g.set_precedents((1, p(0, 1)), [(2, p(0, 1))]);
+        g.set_precedents((2, p(0, 1)), [(1, p(1, 1))]);
+        assert_eq!(None, g.cycle_through((1, p(0, 1))));
+
+        // Sheet1!B1 = Sheet1!A1
+        g.set_precedents((1, p(1, 1)), [(1, p(0, 1))]);
+        assert_eq!(
+            Some(vec![(1, p(0, 1)), (1, p(1, 1)), (2, p(0, 1))]),
+            g.cycle_through((1, p(0, 1))),
+        );
+    }
+
+    #[test]
+    fn test_iterative_calculation() {
+        let settings = IterativeCalculation {
+            max_iterations: 100,
+            epsilon: 0.001,
+        };
+
+        // A1 = B1 / 2 + 1; B1 = A1 converges to 2.
+        let mut a1 = 0.0;
+        let outcome = pollster::block_on(settings.run(|| {
+            a1 = a1 / 2.0 + 1.0;
+            std::future::ready(vec![a1, a1])
+        }));
+        assert!(outcome.converged);
+        assert!(outcome.iterations < 100);
+        assert!((a1 - 2.0).abs() < 0.01);
+
+        // A1 = A1 + 1 never converges.
+        let mut a1 = 0.0;
+        let outcome = pollster::block_on(settings.run(|| {
+            a1 += 1.0;
+            std::future::ready(vec![a1])
+        }));
+        assert_eq!(
+            IterationOutcome {
+                iterations: 100,
+                converged: false,
+            },
+            outcome,
+        );
+        assert_eq!(100.0, a1);
+    }
+}
@@ -1,5 +1,6 @@
 use futures::future::{FutureExt, LocalBoxFuture};
 use itertools::Itertools;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use smallvec::smallvec;
 use std::fmt;
@@ -10,6 +11,12 @@ use super::*;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Formula {
     pub ast: AstNode,
+    /// Source code of the formula.
+    pub source: String,
+    /// Coordinates of the cell where the formula was entered.
+    pub loc: Pos,
+    /// Notation used for cell references in the source code.
+    pub notation: CellRefNotation,
 }
 impl fmt::Display for Formula {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -28,8 +35,11 @@ pub enum AstNodeContents {
     Paren(Box<AstNode>),
     Array(Vec<Vec<AstNode>>),
     CellRef(CellRef),
+    RangeRef(RangeRef),
     String(String),
     Number(f64),
+    Error(ErrorKind),
+    Identifier(String),
 }
 impl fmt::Display for AstNodeContents {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -52,31 +62,42 @@ impl fmt::Display for AstNodeContents {
                 a.iter().map(|row| row.iter().join(", ")).join("; "),
             ),
             AstNodeContents::CellRef(cellref) => write!(f, "{cellref}"),
+            AstNodeContents::RangeRef(rangeref) => write!(f, "{rangeref}"),
             AstNodeContents::String(s) => write!(f, "{s:?}"),
             AstNodeContents::Number(n) => write!(f, "{n:?}"),
+            AstNodeContents::Error(kind) => write!(f, "{kind}"),
+            AstNodeContents::Identifier(name) => write!(f, "{name}"),
         }
     }
 }
 impl AstNodeContents {
-    fn type_string(&self) -> &'static str {
+    pub(super) fn type_string(&self) -> &'static str {
         match self {
             AstNodeContents::FunctionCall { func, .. } => match func.inner.as_str() {
                 "=" | "==" | "<>" | "!=" | "<" | ">" | "<=" | ">=" => "comparison",
-                s if s.chars().all(|c| c.is_alphanumeric() || c == '_') => "function call",
+                s if s
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '.') =>
+                {
+                    "function call"
+                }
                 _ => "expression",
             },
             AstNodeContents::Paren(contents) => contents.inner.type_string(),
             AstNodeContents::Array(_) => "array literal",
             AstNodeContents::CellRef(_) => "cell reference",
+            AstNodeContents::RangeRef(_) => "range reference",
             AstNodeContents::String(_) => "string literal",
             AstNodeContents::Number(_) => "numeric literal",
+            AstNodeContents::Error(_) => "error literal",
+            AstNodeContents::Identifier(_) => "identifier",
         }
     }
 }
 impl Spanned<AstNodeContents> {
     pub fn to_cell_ref(&self) -> FormulaResult<CellRef> {
         match &self.inner {
-            AstNodeContents::CellRef(cellref) => Ok(*cellref),
+            AstNodeContents::CellRef(cellref) => Ok(cellref.clone()),
             AstNodeContents::Paren(contents) => contents.to_cell_ref(),
             _ => Err(FormulaErrorMsg::Expected {
                 expected: "cell reference".into(),
@@ -85,6 +106,16 @@ impl Spanned<AstNodeContents> {
             .with_span(self.span)),
         }
     }
+    pub fn to_identifier(&self) -> FormulaResult<&str> {
+        match &self.inner {
+            AstNodeContents::Identifier(name) => Ok(name),
+            _ => Err(FormulaErrorMsg::Expected {
+                expected: "name".into(),
+                got: Some(self.inner.type_string().into()),
+            }
+            .with_span(self.span)),
+        }
+    }
 }
 
 impl Formula {
@@ -95,61 +126,133 @@ impl Formula {
         pollster::block_on(self.eval(grid, pos))
     }
 
-    /// Evaluates a formula.
+    /// Evaluates a formula. If the result is a single error value, returns
+    /// that error. Arrays are returned even if some of their values are
+    /// errors.
     pub async fn eval(&self, grid: &mut impl GridProxy, pos: Pos) -> FormulaResult {
-        self.ast.eval(grid, pos).await
+        self.eval_in_ctx(&mut Ctx::new(grid, pos)).await
+    }
+
+    /// Evaluates a formula using an existing context. Returns a `#SPILL!`
+    /// error if the result is an array that would spill into occupied cells.
+    pub async fn eval_in_ctx(&self, ctx: &mut Ctx<'_>) -> FormulaResult {
+        let value = self.ast.eval(ctx).await;
+        match value.inner {
+            Value::Error(e) => Err(*e),
+            _ => {
+                if let Some(size) = value.inner.array_size() {
+                    self.check_spill(ctx, size).await?;
+                }
+                Ok(value)
+            }
+        }
+    }
+
+    /// Returns an error if an array of size `(rows, columns)` returned by the
+    /// formula at `ctx.pos` would spill into any occupied cells, reporting the
+    /// first one in reading order.
+    async fn check_spill(
+        &self,
+        ctx: &mut Ctx<'_>,
+        (rows, cols): (usize, usize),
+    ) -> FormulaResult<()> {
+        if rows * cols <= 1 {
+            return Ok(());
+        }
+        let far_corner = Pos {
+            x: ctx.pos.x + cols as i64 - 1,
+            y: ctx.pos.y + rows as i64 - 1,
+        };
+        let rect = Rect::new_span(ctx.pos, far_corner);
+        let blocked_by = ctx
+            .grid
+            .occupied_cells(None, rect)
+            .await
+            .into_iter()
+            .filter(|&pos| pos != ctx.pos && rect.contains(pos))
+            .min_by_key(|pos| (pos.y, pos.x));
+        match blocked_by {
+            Some(blocked_by) => Err(FormulaErrorMsg::Spill { blocked_by }.with_span(self.ast.span)),
+            None => Ok(()),
+        }
     }
 }
 
 impl AstNode {
-    fn eval<'a>(
-        &'a self,
-        grid: &'a mut impl GridProxy,
-        pos: Pos,
-    ) -> LocalBoxFuture<'a, FormulaResult<Spanned<Value>>> {
+    /// Evaluates an expression. Errors are returned as error values, so that
+    /// they can be handled by functions such as `IFERROR()`.
+    pub(super) fn eval<'a>(&'a self, ctx: &'a mut Ctx<'_>) -> LocalBoxFuture<'a, Spanned<Value>> {
         // See this link for why we need to box here:
         // https://rust-lang.github.io/async-book/07_workarounds/04_recursion.html
-        async move { self.eval_inner(grid, pos).await }.boxed_local()
+        async move {
+            self.eval_inner(ctx).await.unwrap_or_else(|e| Spanned {
+                span: self.span,
+                inner: Value::Error(Box::new(e.with_span(self.span))),
+            })
+        }
+        .boxed_local()
     }
 
-    async fn eval_inner(
-        &self,
-        grid: &mut impl GridProxy,
-        pos: Pos,
-    ) -> FormulaResult<Spanned<Value>> {
+    async fn eval_inner(&self, ctx: &mut Ctx<'_>) -> FormulaResult<Spanned<Value>> {
         let value = match &self.inner {
             // Cell range
             AstNodeContents::FunctionCall { func, args } if func.inner == ":" => {
                 if args.len() != 2 {
                     internal_error!("invalid arguments to cell range operator");
                 }
-                let corner1 = args[0].to_cell_ref()?.resolve_from(pos);
-                let corner2 = args[1].to_cell_ref()?.resolve_from(pos);
-
-                let x1 = std::cmp::min(corner1.x, corner2.x);
-                let y1 = std::cmp::min(corner1.y, corner2.y);
+                let corner1 = args[0].to_cell_ref()?;
+                let corner2 = args[1].to_cell_ref()?;
+                self.get_range_ref(ctx, &RangeRef::CellRange(corner1, corner2))
+                    .await?
+            }
 
-                let x2 = std::cmp::max(corner1.x, corner2.x);
-                let y2 = std::cmp::max(corner1.y, corner2.y);
+            // Spill range
+            AstNodeContents::FunctionCall { func, args } if func.inner == "#" => {
+                let [anchor] = &args[..] else {
+                    return Err(FormulaErrorMsg::InternalError(
+                        "invalid arguments to spill range operator".into(),
+                    )
+                    .with_span(self.span));
+                };
+                let anchor = anchor.to_cell_ref()?;
+                let sheet = anchor.sheet.as_deref();
+                let pos = anchor.resolve_from(ctx.pos);
+                let Some(rect) = ctx.grid.spill_rect(sheet, pos).await else {
+                    // The cell doesn't contain an array formula.
+                    return Err(FormulaErrorMsg::BadCellReference.with_span(self.span));
+                };
+                self.get_cell_range(ctx, sheet, rect).await?
+            }
 
-                let mut array = vec![];
-                for y in y1..=y2 {
-                    let mut row = smallvec![];
-                    for x in x1..=x2 {
-                        let cell_ref = CellRef::absolute(Pos { x, y });
-                        row.push(self.get_cell(grid, pos, cell_ref).await?);
-                    }
-                    array.push(row);
+            // Call to a function bound by `LET()`
+            AstNodeContents::FunctionCall { func, args } if ctx.env.get(&func.inner).is_some() => {
+                let mut arg_values = vec![];
+                for arg in args {
+                    arg_values.push(arg.eval(ctx).await);
                 }
+                let func_value = ctx.env.get(&func.inner).cloned().unwrap_or_default();
+                let Value::Lambda(lambda) = func_value else {
+                    return Err(FormulaErrorMsg::Expected {
+                        expected: "function".into(),
+                        got: Some(func_value.type_name().into()),
+                    }
+                    .with_span(func.span));
+                };
+                lambda.call(ctx, arg_values, self.span).await?
+            }
 
-                Value::Array(array)
+            // Function that only evaluates the arguments it needs
+            AstNodeContents::FunctionCall { func, args }
+                if special_forms::is_special_form(&func.inner) =>
+            {
+                self.eval_special_form(ctx, func, args).await?
             }
 
             // Other operator/function
             AstNodeContents::FunctionCall { func, args } => {
                 let mut arg_values = vec![];
                 for arg in args {
-                    arg_values.push(arg.eval(grid, pos).await?);
+                    arg_values.push(arg.eval(ctx).await);
                 }
                 let spanned_arg_values = Spanned {
                     span: self.span,
@@ -157,7 +260,26 @@ impl AstNode {
                 };
 
                 match func.inner.to_ascii_lowercase().as_str() {
-                    "cell" | "c" => self.array_mapped_get_cell(grid, pos, spanned_arg_values)?,
+                    "cell" | "c" => self.array_mapped_get_cell(ctx, spanned_arg_values)?,
+                    "today" => {
+                        Self::mark_volatile(ctx, &spanned_arg_values)?;
+                        Value::Date(ctx.now.date())
+                    }
+                    "now" => {
+                        Self::mark_volatile(ctx, &spanned_arg_values)?;
+                        Value::DateTime(ctx.now)
+                    }
+                    "rand" => {
+                        Self::mark_volatile(ctx, &spanned_arg_values)?;
+                        Value::Number(ctx.rng.gen())
+                    }
+                    "randbetween" => {
+                        ctx.volatile = true;
+                        let rng = &mut ctx.rng;
+                        functions::array_map(spanned_arg_values, |[low, high]| {
+                            functions::random_between(rng, low, high)
+                        })?
+                    }
                     _ => match functions::pure_function_from_name(&func.inner) {
                         Some(f) => f(spanned_arg_values)?,
                         None => return Err(FormulaErrorMsg::BadFunctionName.with_span(func.span)),
@@ -165,25 +287,45 @@ impl AstNode {
                 }
             }
 
-            AstNodeContents::Paren(expr) => expr.eval(grid, pos).await?.inner,
+            AstNodeContents::Paren(expr) => expr.eval(ctx).await.inner,
 
             AstNodeContents::Array(a) => {
                 let mut array_of_values = vec![];
                 for row in a {
                     let mut row_of_values = smallvec![];
                     for elem_expr in row {
-                        row_of_values.push(elem_expr.eval(grid, pos).await?.inner);
+                        row_of_values.push(elem_expr.eval(ctx).await.inner);
                     }
                     array_of_values.push(row_of_values);
                 }
                 Value::Array(array_of_values)
             }
 
-            AstNodeContents::CellRef(cell_ref) => self.get_cell(grid, pos, *cell_ref).await?,
+            AstNodeContents::CellRef(cell_ref) => self.get_cell(ctx, cell_ref).await?,
+
+            AstNodeContents::RangeRef(range_ref) => self.get_range_ref(ctx, range_ref).await?,
 
             AstNodeContents::String(s) => Value::String(s.clone()),
 
             AstNodeContents::Number(n) => Value::Number(*n),
+
+            AstNodeContents::Error(kind) => {
+                return Err(FormulaErrorMsg::ErrorValue(*kind).with_span(self.span))
+            }
+
+            AstNodeContents::Identifier(name) => {
+                if let Some(value) = ctx.env.get(name) {
+                    value.clone()
+                } else {
+                    match ctx.names.and_then(|names| names.get(name)) {
+                        Some(NameDefinition::Range(range_ref)) => {
+                            self.get_range_ref(ctx, range_ref).await?
+                        }
+                        Some(NameDefinition::Constant(value)) => value.clone(),
+                        None => return Err(FormulaErrorMsg::BadName.with_span(self.span)),
+                    }
+                }
+            }
         };
 
         Ok(Spanned {
@@ -192,27 +334,118 @@ impl AstNode {
         })
     }
 
-    /// Fetches the contents of the cell at `ref_pos` evaluated at `base_pos`,
-    /// or returns an error in the case of a circular reference.
-    async fn get_cell(
+    /// Fetches the contents of the cell at `cell_ref` evaluated at
+    /// `ctx.pos`, or returns an error in the case of a circular reference.
+    async fn get_cell(&self, ctx: &mut Ctx<'_>, cell_ref: &CellRef) -> FormulaResult<Value> {
+        let sheet = cell_ref.sheet.as_deref();
+        let ref_pos = cell_ref.resolve_from(ctx.pos);
+        if ctx.is_own_sheet(sheet) && ref_pos == ctx.pos && !ctx.allow_self_reference {
+            return Err(FormulaErrorMsg::CircularReference.with_span(self.span));
+        }
+        let cell = ctx.grid.get(sheet, ref_pos).await;
+        Ok(self.cell_value(cell))
+    }
+
+    /// Fetches the contents of every cell in `rect` on `sheet` evaluated at
+    /// `ctx.pos`, or returns an error in the case of a circular reference.
+    async fn get_cell_range(
         &self,
-        grid: &mut impl GridProxy,
-        base_pos: Pos,
-        ref_pos: CellRef,
+        ctx: &mut Ctx<'_>,
+        sheet: Option<&str>,
+        rect: Rect,
     ) -> FormulaResult<Value> {
-        let ref_pos = ref_pos.resolve_from(base_pos);
-        if ref_pos == base_pos {
+        if ctx.is_own_sheet(sheet) && rect.contains(ctx.pos) && !ctx.allow_self_reference {
             return Err(FormulaErrorMsg::CircularReference.with_span(self.span));
         }
-        Ok(Value::String(grid.get(ref_pos).await.unwrap_or_default()))
+        let rows = ctx.grid.get_range(sheet, rect).await;
+        Ok(Value::Array(
+            rows.into_iter()
+                .map(|row| row.into_iter().map(|cell| self.cell_value(cell)).collect())
+                .collect(),
+        ))
+    }
+
+    /// Fetches the contents of every cell in a range reference evaluated at
+    /// `ctx.pos`, or returns an error in the case of a circular reference.
+    /// Whole-row and whole-column ranges only include the part of the range
+    /// within the bounds of the grid.
+    async fn get_range_ref(&self, ctx: &mut Ctx<'_>, range_ref: &RangeRef) -> FormulaResult<Value> {
+        let sheet = range_ref.sheet();
+        let rect = match range_ref {
+            RangeRef::RowRange { start, end, .. } => {
+                let Some(bounds) = ctx.grid.bounds(sheet).await else {
+                    // The grid is empty.
+                    return Ok(Value::Array(vec![smallvec![Value::default()]]));
+                };
+                Rect::new_span(
+                    Pos::new(bounds.min.x, start.resolve_from(ctx.pos.y)),
+                    Pos::new(bounds.max.x, end.resolve_from(ctx.pos.y)),
+                )
+            }
+            RangeRef::ColRange { start, end, .. } => {
+                let Some(bounds) = ctx.grid.bounds(sheet).await else {
+                    // The grid is empty.
+                    return Ok(Value::Array(vec![smallvec![Value::default()]]));
+                };
+                Rect::new_span(
+                    Pos::new(start.resolve_from(ctx.pos.x), bounds.min.y),
+                    Pos::new(end.resolve_from(ctx.pos.x), bounds.max.y),
+                )
+            }
+            RangeRef::CellRange(corner1, corner2) => {
+                if let (Some(sheet1), Some(sheet2)) = (&corner1.sheet, &corner2.sheet) {
+                    if !sheet1.eq_ignore_ascii_case(sheet2) {
+                        // A range can't span multiple sheets.
+                        return Err(FormulaErrorMsg::BadCellReference.with_span(self.span));
+                    }
+                }
+                Rect::new_span(corner1.resolve_from(ctx.pos), corner2.resolve_from(ctx.pos))
+            }
+            RangeRef::Cell(cell_ref) => return self.get_cell(ctx, cell_ref).await,
+        };
+        self.get_cell_range(ctx, sheet, rect).await
+    }
+
+    /// Converts the contents of a cell to a value. Empty cells produce a blank
+    /// value, and text cells containing an error code such as `#N/A` produce
+    /// an error value. Other text stays text, and is only converted to a
+    /// number by operations that need one.
+    fn cell_value(&self, cell: Cell) -> Value {
+        let error = |kind| {
+            Value::Error(Box::new(
+                FormulaErrorMsg::ErrorValue(kind).with_span(self.span),
+            ))
+        };
+        match cell {
+            Cell::Empty => Value::Blank,
+            Cell::Int(i) => Value::Number(i as f64),
+            Cell::Number(n) => Value::Number(n),
+            Cell::Text(s) => match ErrorKind::from_code(s.trim()) {
+                Some(kind) => error(kind),
+                None => Value::String(s),
+            },
+            Cell::Bool(b) => Value::Bool(b),
+            Cell::Error(kind) => error(kind),
+            Cell::Date(d) => Value::Date(d),
+            Cell::DateTime(dt) => Value::DateTime(dt),
+        }
+    }
+
+    /// Checks that a volatile function such as `NOW()` has no arguments and
+    /// marks the formula as needing recalculation whenever anything changes.
+    fn mark_volatile(ctx: &mut Ctx<'_>, args: &Spanned<Vec<Spanned<Value>>>) -> FormulaResult<()> {
+        if !args.inner.is_empty() {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+        }
+        ctx.volatile = true;
+        Ok(())
     }
 
     /// Fetches the contents of the cell at `(x, y)`, but fetches an array of cells
     /// if either `x` or `y` is an array.
     fn array_mapped_get_cell(
         &self,
-        grid: &mut impl GridProxy,
-        base_pos: Pos,
+        ctx: &mut Ctx<'_>,
         args: Spanned<Vec<Spanned<Value>>>,
     ) -> FormulaResult<Value> {
         functions::array_map(args, move |[x, y]| {
@@ -223,7 +456,7 @@ impl AstNode {
             // Can't have this be async because it needs to mutate `grid` and
             // Rust isn't happy about moving a mutable reference to `grid` into
             // the closure.
-            pollster::block_on(self.get_cell(grid, base_pos, CellRef::absolute(pos)))
+            pollster::block_on(self.get_cell(ctx, &CellRef::absolute(pos)))
         })
     }
 }
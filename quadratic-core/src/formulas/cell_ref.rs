@@ -6,57 +6,283 @@ use std::str::FromStr;
 
 use super::Pos;
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Notation used for cell references in a formula.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CellRefNotation {
+    /// A1-style notation, such as `B3` or `$C$5`, where relative references
+    /// are relative to the cell containing the formula.
+    #[default]
+    A1,
+    /// R1C1-style notation, such as `R3C2` or `R[-1]C[0]`, where bracketed
+    /// coordinates are offsets from the cell containing the formula.
+    R1C1,
+}
+
+/// Optional coordinate in an R1C1-style reference, such as `[-1]` or `5`.
+///
+/// (\[[+-]?\d+\]|n?\d+)?
+/// (            |     )?    optional coordinate, which is EITHER
+///  \[[+-]?\d+\]             offset in brackets
+///               n?\d+       OR absolute coordinate
+const R1C1_COORD_PATTERN: &str = r#"(\[[+-]?\d+\]|n?\d+)?"#;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RangeRef {
-    RowRange(CellRefCoord, CellRefCoord),
-    ColRange(CellRefCoord, CellRefCoord),
+    RowRange {
+        sheet: Option<String>,
+        start: CellRefCoord,
+        end: CellRefCoord,
+    },
+    ColRange {
+        sheet: Option<String>,
+        start: CellRefCoord,
+        end: CellRefCoord,
+    },
     CellRange(CellRef, CellRef),
     Cell(CellRef),
 }
 impl fmt::Display for RangeRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            RangeRef::RowRange(start, end) => write!(f, "R{start}:R{end}"),
-            RangeRef::ColRange(start, end) => write!(f, "C{start}:C{end}"),
-            RangeRef::CellRange(start, end) => write!(f, "{start}:{end}"),
-            RangeRef::Cell(cell) => write!(f, "{cell}"),
-        }
+        write!(f, "{}", self.r1c1_string())
     }
 }
 impl RangeRef {
-    /// Returns the human-friendly string representing this range reference in
-    /// A1-style notation.
-    pub fn a1_string(self, base: Pos) -> String {
+    /// Parses a whole-row or whole-column range reference in the given
+    /// notation, relative to a given location. The reference may start with
+    /// a sheet name, such as `Sheet2!B:C`.
+    pub fn parse(s: &str, base: Pos, notation: CellRefNotation) -> Option<RangeRef> {
+        let (sheet, s) = split_sheet_prefix(s);
+        let range_ref = match notation {
+            CellRefNotation::A1 => Self::parse_a1(s, base),
+            CellRefNotation::R1C1 => Self::parse_r1c1(s),
+        }?;
+        Some(range_ref.with_sheet(sheet))
+    }
+    /// Returns the string representing this range reference in the given
+    /// notation.
+    pub fn notation_string(&self, base: Pos, notation: CellRefNotation) -> String {
+        match notation {
+            CellRefNotation::A1 => self.a1_string(base),
+            CellRefNotation::R1C1 => self.r1c1_string(),
+        }
+    }
+    /// Returns whether every coordinate in the reference can be resolved from
+    /// `base` without going outside the range of valid coordinates.
+    pub fn is_valid_from(&self, base: Pos) -> bool {
         match self {
-            RangeRef::RowRange(start, end) => {
-                format!("{}:{}", start.row_string(base.y), end.row_string(base.y))
+            RangeRef::RowRange { start, end, .. } => {
+                start.checked_resolve_from(base.y).is_some()
+                    && end.checked_resolve_from(base.y).is_some()
+            }
+            RangeRef::ColRange { start, end, .. } => {
+                start.checked_resolve_from(base.x).is_some()
+                    && end.checked_resolve_from(base.x).is_some()
             }
-            RangeRef::ColRange(start, end) => {
-                format!("{}:{}", start.col_string(base.x), end.col_string(base.x))
+            RangeRef::CellRange(corner1, corner2) => {
+                corner1.checked_resolve_from(base).is_some()
+                    && corner2.checked_resolve_from(base).is_some()
             }
+            RangeRef::Cell(cell_ref) => cell_ref.checked_resolve_from(base).is_some(),
+        }
+    }
+
+    /// Returns the name of the sheet that the reference points to, or `None`
+    /// if it points to the sheet containing the formula. A cell range takes
+    /// its sheet from whichever corner has one.
+    pub fn sheet(&self) -> Option<&str> {
+        match self {
+            RangeRef::RowRange { sheet, .. } | RangeRef::ColRange { sheet, .. } => sheet.as_deref(),
+            RangeRef::CellRange(corner1, corner2) => {
+                corner1.sheet.as_deref().or(corner2.sheet.as_deref())
+            }
+            RangeRef::Cell(cell_ref) => cell_ref.sheet.as_deref(),
+        }
+    }
+    /// Returns the same reference pointing to a different sheet.
+    pub fn with_sheet(self, sheet: Option<String>) -> Self {
+        match self {
+            RangeRef::RowRange { start, end, .. } => RangeRef::RowRange { sheet, start, end },
+            RangeRef::ColRange { start, end, .. } => RangeRef::ColRange { sheet, start, end },
+            RangeRef::CellRange(corner1, corner2) => RangeRef::CellRange(
+                CellRef { sheet, ..corner1 },
+                CellRef {
+                    sheet: None,
+                    ..corner2
+                },
+            ),
+            RangeRef::Cell(cell_ref) => RangeRef::Cell(CellRef { sheet, ..cell_ref }),
+        }
+    }
+
+    /// Returns the string representing this range reference in R1C1-style
+    /// notation.
+    pub fn r1c1_string(&self) -> String {
+        match self {
+            RangeRef::RowRange { sheet, start, end } => format!(
+                "{}R{}:R{}",
+                sheet_prefix(sheet),
+                start.r1c1_string(),
+                end.r1c1_string(),
+            ),
+            RangeRef::ColRange { sheet, start, end } => format!(
+                "{}C{}:C{}",
+                sheet_prefix(sheet),
+                start.r1c1_string(),
+                end.r1c1_string(),
+            ),
+            RangeRef::CellRange(start, end) => {
+                format!("{}:{}", start.r1c1_string(), end.r1c1_string())
+            }
+            RangeRef::Cell(cell) => cell.r1c1_string(),
+        }
+    }
+
+    /// Parses an R1C1-style whole-row or whole-column range reference, such
+    /// as `R2:R[3]` or `C[-1]:C[-1]`.
+    pub fn parse_r1c1(s: &str) -> Option<RangeRef> {
+        lazy_static! {
+            /// ^R(coord)?:R(coord)?$
+            pub static ref R1C1_ROW_RANGE_REGEX: Regex =
+                Regex::new(&format!("^R{R1C1_COORD_PATTERN}:R{R1C1_COORD_PATTERN}$")).unwrap();
+            /// ^C(coord)?:C(coord)?$
+            pub static ref R1C1_COLUMN_RANGE_REGEX: Regex =
+                Regex::new(&format!("^C{R1C1_COORD_PATTERN}:C{R1C1_COORD_PATTERN}$")).unwrap();
+        }
+
+        if let Some(captures) = R1C1_ROW_RANGE_REGEX.captures(s) {
+            Some(RangeRef::RowRange {
+                sheet: None,
+                start: CellRefCoord::parse_r1c1(captures.get(1))?,
+                end: CellRefCoord::parse_r1c1(captures.get(2))?,
+            })
+        } else if let Some(captures) = R1C1_COLUMN_RANGE_REGEX.captures(s) {
+            Some(RangeRef::ColRange {
+                sheet: None,
+                start: CellRefCoord::parse_r1c1(captures.get(1))?,
+                end: CellRefCoord::parse_r1c1(captures.get(2))?,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the human-friendly string representing this range reference in
+    /// A1-style notation.
+    pub fn a1_string(&self, base: Pos) -> String {
+        match self {
+            RangeRef::RowRange { sheet, start, end } => format!(
+                "{}{}:{}",
+                sheet_prefix(sheet),
+                start.row_string(base.y),
+                end.row_string(base.y),
+            ),
+            RangeRef::ColRange { sheet, start, end } => format!(
+                "{}{}:{}",
+                sheet_prefix(sheet),
+                start.col_string(base.x),
+                end.col_string(base.x),
+            ),
             RangeRef::CellRange(start, end) => {
                 format!("{}:{}", start.a1_string(base), end.a1_string(base))
             }
             RangeRef::Cell(cell) => cell.a1_string(base),
         }
     }
+
+    /// Parses an A1-style whole-row or whole-column range reference, such as
+    /// `B:D` or `$3:5`, relative to a given location.
+    pub fn parse_a1(s: &str, base: Pos) -> Option<RangeRef> {
+        lazy_static! {
+            /// ^(\$?)(n?[A-Z]+):(\$?)(n?[A-Z]+)$
+            /// ^                                $     match full string
+            ///  (\$?)                                 group 1: optional `$`
+            ///       (n?[A-Z]+)                       group 2: column name
+            ///                 :                      separator
+            ///                  (\$?)                 group 3: optional `$`
+            ///                       (n?[A-Z]+)       group 4: column name
+            pub static ref A1_COLUMN_RANGE_REGEX: Regex =
+                Regex::new(r#"^(\$?)(n?[A-Z]+):(\$?)(n?[A-Z]+)$"#).unwrap();
+
+            /// ^(\$?)(n?)(\d+):(\$?)(n?)(\d+)$
+            /// ^                              $     match full string
+            ///  (\$?)                               group 1: optional `$`
+            ///       (n?)                           group 2: optional `n`
+            ///           (\d+)                      group 3: row number
+            ///                :                     separator
+            ///                 (\$?)                group 4: optional `$`
+            ///                      (n?)            group 5: optional `n`
+            ///                          (\d+)       group 6: row number
+            pub static ref A1_ROW_RANGE_REGEX: Regex =
+                Regex::new(r#"^(\$?)(n?)(\d+):(\$?)(n?)(\d+)$"#).unwrap();
+        }
+
+        if let Some(captures) = A1_COLUMN_RANGE_REGEX.captures(s) {
+            let col_ref = |is_absolute: &str, name: &str| {
+                let col = crate::util::column_from_name(name)?;
+                Some(CellRefCoord::new(col, base.x, !is_absolute.is_empty()))
+            };
+            Some(RangeRef::ColRange {
+                sheet: None,
+                start: col_ref(&captures[1], &captures[2])?,
+                end: col_ref(&captures[3], &captures[4])?,
+            })
+        } else if let Some(captures) = A1_ROW_RANGE_REGEX.captures(s) {
+            let row_ref = |is_absolute: &str, is_negative: &str, number: &str| {
+                let mut row = number.parse::<i64>().ok()?;
+                if !is_negative.is_empty() {
+                    row = -row;
+                }
+                Some(CellRefCoord::new(row, base.y, !is_absolute.is_empty()))
+            };
+            Some(RangeRef::RowRange {
+                sheet: None,
+                start: row_ref(&captures[1], &captures[2], &captures[3])?,
+                end: row_ref(&captures[4], &captures[5], &captures[6])?,
+            })
+        } else {
+            None
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CellRef {
+    /// Name of the sheet containing the cell, or `None` if it is on the same
+    /// sheet as the formula.
+    pub sheet: Option<String>,
     pub x: CellRefCoord,
     pub y: CellRefCoord,
 }
 impl fmt::Display for CellRef {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self { x, y } = self;
-        write!(f, "R{y}C{x}")
+        write!(f, "{}", self.r1c1_string())
     }
 }
 impl CellRef {
+    /// Parses a cell reference in the given notation, relative to a given
+    /// location. The reference may start with a sheet name, such as
+    /// `Sheet2!B3` or `'My Sheet'!B3`.
+    pub fn parse(s: &str, base: Pos, notation: CellRefNotation) -> Option<CellRef> {
+        let (sheet, s) = split_sheet_prefix(s);
+        let cell_ref = match notation {
+            CellRefNotation::A1 => Self::parse_a1(s, base),
+            CellRefNotation::R1C1 => Self::parse_r1c1(s),
+        }?;
+        Some(CellRef { sheet, ..cell_ref })
+    }
+    /// Returns the string representing this cell reference in the given
+    /// notation.
+    pub fn notation_string(&self, base: Pos, notation: CellRefNotation) -> String {
+        match notation {
+            CellRefNotation::A1 => self.a1_string(base),
+            CellRefNotation::R1C1 => self.r1c1_string(),
+        }
+    }
+
     /// Constructs an absolute cell reference.
     pub fn absolute(pos: Pos) -> Self {
         Self {
+            sheet: None,
             x: CellRefCoord::Absolute(pos.x),
             y: CellRefCoord::Absolute(pos.y),
         }
@@ -64,18 +290,55 @@ impl CellRef {
 
     /// Resolves the reference to a absolute coordinates, given the cell
     /// coordinate where evaluation is taking place.
-    pub fn resolve_from(self, base: Pos) -> Pos {
+    pub fn resolve_from(&self, base: Pos) -> Pos {
         Pos {
             x: self.x.resolve_from(base.x),
             y: self.y.resolve_from(base.y),
         }
     }
+    /// Resolves the reference to absolute coordinates, or returns `None` if
+    /// they are outside the range of valid coordinates.
+    pub fn checked_resolve_from(&self, base: Pos) -> Option<Pos> {
+        Some(Pos {
+            x: self.x.checked_resolve_from(base.x)?,
+            y: self.y.checked_resolve_from(base.y)?,
+        })
+    }
     /// Returns the human-friendly string representing this cell reference in
     /// A1-style notation.
-    pub fn a1_string(self, base: Pos) -> String {
+    pub fn a1_string(&self, base: Pos) -> String {
+        let sheet = sheet_prefix(&self.sheet);
         let col = self.x.col_string(base.x);
-        let row = self.y.col_string(base.y);
-        format!("{col}{row}")
+        let row = self.y.row_string(base.y);
+        format!("{sheet}{col}{row}")
+    }
+    /// Returns the string representing this cell reference in R1C1-style
+    /// notation.
+    pub fn r1c1_string(&self) -> String {
+        format!(
+            "{}R{}C{}",
+            sheet_prefix(&self.sheet),
+            self.y.r1c1_string(),
+            self.x.r1c1_string(),
+        )
+    }
+
+    /// Parses an R1C1-style cell reference, such as `R3C2`, `R[-1]C[0]`, or
+    /// `RC[2]`. A missing coordinate refers to the same row or column as the
+    /// formula.
+    pub fn parse_r1c1(s: &str) -> Option<CellRef> {
+        lazy_static! {
+            /// ^R(coord)?C(coord)?$
+            pub static ref R1C1_CELL_REFERENCE_REGEX: Regex =
+                Regex::new(&format!("^R{R1C1_COORD_PATTERN}C{R1C1_COORD_PATTERN}$")).unwrap();
+        }
+
+        let captures = R1C1_CELL_REFERENCE_REGEX.captures(s)?;
+        Some(CellRef {
+            sheet: None,
+            y: CellRefCoord::parse_r1c1(captures.get(1))?,
+            x: CellRefCoord::parse_r1c1(captures.get(2))?,
+        })
     }
 
     /// Parses an A1-style cell reference relative to a given location.
@@ -118,12 +381,67 @@ impl CellRef {
         };
 
         Some(CellRef {
+            sheet: None,
             x: col_ref,
             y: row_ref,
         })
     }
 }
 
+/// Splits the sheet name off the start of a reference such as `Sheet2!B3` or
+/// `'My Sheet'!B3`, returning the unquoted sheet name (if any) and the rest of
+/// the reference.
+pub fn split_sheet_prefix(s: &str) -> (Option<String>, &str) {
+    lazy_static! {
+        /// ^(?:'((?:[^']|'')+)'|([A-Za-z_][A-Za-z_\d\.]*))!
+        /// ^                                             !     sheet name followed by `!`
+        ///     '((?:[^']|'')+)'                                group 1: quoted name, where `''` is a quote
+        ///                     |([A-Za-z_][A-Za-z_\d\.]*)     OR group 2: unquoted name
+        pub static ref SHEET_PREFIX_REGEX: Regex =
+            Regex::new(r#"^(?:'((?:[^']|'')+)'|([A-Za-z_][A-Za-z_\d\.]*))!"#).unwrap();
+    }
+
+    match SHEET_PREFIX_REGEX.captures(s) {
+        Some(captures) => {
+            let name = match captures.get(1) {
+                Some(quoted) => quoted.as_str().replace("''", "'"),
+                None => captures[2].to_string(),
+            };
+            (Some(name), &s[captures[0].len()..])
+        }
+        None => (None, s),
+    }
+}
+
+/// Returns a sheet name formatted for use in a reference, with quotes if it
+/// contains anything other than letters, digits, underscores, and periods
+/// or could be confused with a cell reference.
+pub fn quote_sheet_name(name: &str) -> String {
+    lazy_static! {
+        /// ^[A-Za-z_][A-Za-z_\d\.]*$
+        pub static ref UNQUOTED_SHEET_NAME_REGEX: Regex =
+            Regex::new(r#"^[A-Za-z_][A-Za-z_\d\.]*$"#).unwrap();
+    }
+
+    let needs_quotes = !UNQUOTED_SHEET_NAME_REGEX.is_match(name)
+        || CellRef::parse_a1(name, Pos::ORIGIN).is_some()
+        || CellRef::parse_r1c1(name).is_some();
+    if needs_quotes {
+        format!("'{}'", name.replace('\'', "''"))
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns the prefix for a reference to a cell on `sheet`, such as
+/// `Sheet2!`, or the empty string for the sheet containing the formula.
+fn sheet_prefix(sheet: &Option<String>) -> String {
+    match sheet {
+        Some(name) => format!("{}!", quote_sheet_name(name)),
+        None => String::new(),
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CellRefCoord {
     Relative(i64),
@@ -156,6 +474,9 @@ impl FromStr for CellRefCoord {
         let maybe_relative = (|| s.strip_prefix('[')?.strip_suffix(']')?.parse().ok())();
         if let Some(rel) = maybe_relative {
             Ok(Self::Relative(rel))
+        } else if let Some(abs) = s.strip_prefix('n') {
+            let abs: i64 = abs.parse().map_err(|_| ())?;
+            Ok(Self::Absolute(-abs))
         } else if let Ok(abs) = s.parse() {
             Ok(Self::Absolute(abs))
         } else {
@@ -164,6 +485,22 @@ impl FromStr for CellRefCoord {
     }
 }
 impl CellRefCoord {
+    /// Constructs a reference to the coordinate `coord`, which is either
+    /// absolute or relative to `base`.
+    pub fn new(coord: i64, base: i64, is_absolute: bool) -> Self {
+        if is_absolute {
+            CellRefCoord::Absolute(coord)
+        } else {
+            CellRefCoord::Relative(coord - base)
+        }
+    }
+
+    /// Returns a coordinate that resolves to `coord` from `base`, which is
+    /// absolute if this coordinate is absolute and relative otherwise.
+    pub fn with_resolved(self, coord: i64, base: i64) -> Self {
+        Self::new(coord, base, matches!(self, CellRefCoord::Absolute(_)))
+    }
+
     /// Resolves the reference to an absolute coordinate, given the cell
     /// coordinate where evaluation is taking place.
     pub fn resolve_from(self, base: i64) -> i64 {
@@ -172,6 +509,15 @@ impl CellRefCoord {
             CellRefCoord::Absolute(coord) => coord,
         }
     }
+    /// Resolves the reference to an absolute coordinate, or returns `None` if
+    /// it is outside the range of valid coordinates.
+    pub fn checked_resolve_from(self, base: i64) -> Option<i64> {
+        match self {
+            // Exclude `i64::MIN` so that the coordinate can always be negated.
+            CellRefCoord::Relative(delta) => base.checked_add(delta).filter(|&c| c != i64::MIN),
+            CellRefCoord::Absolute(coord) => Some(coord),
+        }
+    }
     /// Returns the `$` prefix if this is an absolute reference, or the empty
     /// string if it is a relative reference.
     fn prefix(self) -> &'static str {
@@ -190,6 +536,26 @@ impl CellRefCoord {
     /// a row coordinate.
     fn row_string(self, base: i64) -> String {
         let row = self.resolve_from(base);
-        format!("{}{row}", self.prefix())
+        if row < 0 {
+            format!("{}n{}", self.prefix(), -row)
+        } else {
+            format!("{}{row}", self.prefix())
+        }
+    }
+    /// Returns the string representing this coordinate in R1C1-style
+    /// notation, which is empty for an offset of zero.
+    fn r1c1_string(self) -> String {
+        match self {
+            CellRefCoord::Relative(0) => String::new(),
+            CellRefCoord::Relative(_) | CellRefCoord::Absolute(_) => self.to_string(),
+        }
+    }
+    /// Parses an optional coordinate from an R1C1-style reference, where a
+    /// missing coordinate is an offset of zero.
+    fn parse_r1c1(s: Option<regex::Match<'_>>) -> Option<Self> {
+        match s {
+            Some(m) => m.as_str().parse().ok(),
+            None => Some(CellRefCoord::Relative(0)),
+        }
     }
 }
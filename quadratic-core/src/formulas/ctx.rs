@@ -0,0 +1,100 @@
+use chrono::NaiveDateTime;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use std::rc::Rc;
+
+use super::*;
+
+/// Information used while evaluating a formula.
+pub struct Ctx<'ctx> {
+    /// Grid to fetch cell values from.
+    pub grid: &'ctx mut dyn GridProxy,
+    /// Position of the cell containing the formula.
+    pub pos: Pos,
+    /// Name of the sheet containing the formula, if known. References that
+    /// name this sheet are treated the same as references without a sheet
+    /// name when checking for circular references.
+    pub sheet: Option<String>,
+    /// Whether the formula may read its own cell, which normally is a
+    /// circular reference. This is allowed when calculating circular
+    /// references iteratively, in which case the cell's previous value is
+    /// used.
+    pub allow_self_reference: bool,
+    /// Names bound by `LET()` and `LAMBDA()` that are visible to the
+    /// expression being evaluated.
+    pub env: Env,
+    /// Number of lambda calls currently being evaluated, which is limited so
+    /// that recursive lambdas can't overflow the stack.
+    pub call_depth: usize,
+    /// Workbook-level names, which are used for identifiers that aren't bound
+    /// in `env`.
+    pub names: Option<&'ctx NameRegistry>,
+    /// Current local date and time, returned by `TODAY()` and `NOW()`.
+    pub now: NaiveDateTime,
+    /// Whether the formula called a volatile function such as `NOW()`, so it
+    /// must be recalculated whenever anything changes.
+    pub volatile: bool,
+    /// Random number generator used by `RAND()` and `RANDBETWEEN()`. Tests
+    /// replace this with a seeded generator to get repeatable results.
+    pub rng: SmallRng,
+}
+impl<'ctx> Ctx<'ctx> {
+    /// Constructs a context for evaluating a formula at `pos`.
+    pub fn new(grid: &'ctx mut dyn GridProxy, pos: Pos) -> Self {
+        Self {
+            grid,
+            pos,
+            sheet: None,
+            allow_self_reference: false,
+            env: Env::default(),
+            call_depth: 0,
+            names: None,
+            now: chrono::Local::now().naive_local(),
+            volatile: false,
+            rng: SmallRng::from_entropy(),
+        }
+    }
+
+    /// Returns whether a reference to `sheet` (where `None` is the sheet
+    /// containing the formula) refers to the sheet containing the formula.
+    pub fn is_own_sheet(&self, sheet: Option<&str>) -> bool {
+        match (sheet, &self.sheet) {
+            (None, _) => true,
+            (Some(sheet), Some(own_sheet)) => sheet.eq_ignore_ascii_case(own_sheet),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Lexically scoped names and their values. Cloning an environment is cheap,
+/// so lambdas can capture the environment where they were created.
+#[derive(Debug, Default, Clone)]
+pub struct Env(Option<Rc<Binding>>);
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    value: Value,
+    parent: Env,
+}
+impl Env {
+    /// Returns a new environment with `name` bound to `value`, shadowing any
+    /// existing binding with the same name.
+    pub fn bind(&self, name: &str, value: Value) -> Env {
+        Env(Some(Rc::new(Binding {
+            name: name.to_ascii_lowercase(),
+            value,
+            parent: self.clone(),
+        })))
+    }
+    /// Returns the value bound to `name`, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let mut env = self;
+        while let Some(binding) = &env.0 {
+            if binding.name.eq_ignore_ascii_case(name) {
+                return Some(&binding.value);
+            }
+            env = &binding.parent;
+        }
+        None
+    }
+}
@@ -1,13 +1,14 @@
 //! Error reporting functionality for compilation and runtime.
 
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
 
-use super::Span;
+use super::{Pos, Span};
 
 /// Error message and accompanying span.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FormulaError {
     /// Location of the source code where the error occurred (if any).
     pub span: Option<Span>,
@@ -54,6 +55,8 @@ pub enum FormulaErrorMsg {
     NonRectangularArray,
     BadArgumentCount,
     BadFunctionName,
+    BadName,
+    InvalidName(&'static str),
     BadCellReference,
     BadNumber,
 
@@ -63,6 +66,21 @@ pub enum FormulaErrorMsg {
     DivideByZero,
     NegativeExponent,
     IndexOutOfBounds,
+    NoMatch,
+    TextNotFound,
+    InvalidRegex(String),
+    InvalidArgument,
+    InvalidDate,
+    NoConvergence,
+    CallDepthExceeded,
+    EmptyArray,
+    /// Array can't spill because a cell it would spill into isn't empty.
+    Spill {
+        blocked_by: Pos,
+    },
+    /// Error value that came from a cell or was produced explicitly, such as
+    /// by `NA()`.
+    ErrorValue(ErrorKind),
 }
 impl fmt::Display for FormulaErrorMsg {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -98,6 +116,12 @@ impl fmt::Display for FormulaErrorMsg {
             Self::BadFunctionName => {
                 write!(f, "There is no function with this name")
             }
+            Self::BadName => {
+                write!(f, "There is no value with this name")
+            }
+            Self::InvalidName(reason) => {
+                write!(f, "Invalid name: {reason}")
+            }
             Self::BadCellReference => {
                 write!(f, "Bad cell reference")
             }
@@ -120,6 +144,40 @@ impl fmt::Display for FormulaErrorMsg {
             Self::IndexOutOfBounds => {
                 write!(f, "Index out of bounds")
             }
+            Self::NoMatch => {
+                write!(f, "No match found")
+            }
+            Self::TextNotFound => {
+                write!(f, "Text not found")
+            }
+            Self::InvalidRegex(e) => {
+                write!(f, "Invalid regular expression: {e}")
+            }
+            Self::InvalidArgument => {
+                write!(f, "Invalid argument")
+            }
+            Self::EmptyArray => {
+                write!(f, "Result is an empty array")
+            }
+            Self::NoConvergence => {
+                write!(f, "Calculation did not converge")
+            }
+            Self::CallDepthExceeded => {
+                write!(f, "Too many nested function calls")
+            }
+            Self::InvalidDate => {
+                write!(f, "Date or time is out of range")
+            }
+            Self::Spill { blocked_by } => {
+                write!(
+                    f,
+                    "Array can't spill because cell {} isn't empty",
+                    blocked_by.a1_string(),
+                )
+            }
+            Self::ErrorValue(kind) => {
+                write!(f, "{} ({kind})", kind.description())
+            }
         }
     }
 }
@@ -138,6 +196,118 @@ impl FormulaErrorMsg {
             msg: self,
         }
     }
+
+    /// Returns the kind of spreadsheet error value that this error produces.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Unimplemented | Self::UnknownError | Self::InternalError(_) => ErrorKind::Value,
+
+            Self::Unterminated(_)
+            | Self::Expected { .. }
+            | Self::ArraySizeMismatch { .. }
+            | Self::NonRectangularArray
+            | Self::BadArgumentCount
+            | Self::BadNumber => ErrorKind::Value,
+            Self::BadFunctionName | Self::BadName | Self::InvalidName(_) => ErrorKind::Name,
+            Self::BadCellReference => ErrorKind::Ref,
+
+            Self::CircularReference => ErrorKind::Ref,
+            Self::Overflow
+            | Self::NegativeExponent
+            | Self::InvalidArgument
+            | Self::InvalidDate
+            | Self::NoConvergence
+            | Self::CallDepthExceeded => ErrorKind::Num,
+            Self::DivideByZero => ErrorKind::DivideByZero,
+            Self::IndexOutOfBounds => ErrorKind::Ref,
+            Self::NoMatch => ErrorKind::NotAvailable,
+            Self::TextNotFound | Self::InvalidRegex(_) | Self::EmptyArray => ErrorKind::Value,
+            Self::Spill { .. } => ErrorKind::Spill,
+            Self::ErrorValue(kind) => *kind,
+        }
+    }
+}
+
+/// Kind of spreadsheet error value, such as `#DIV/0!` or `#N/A`.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// `#NULL!`
+    Null,
+    /// `#DIV/0!`
+    DivideByZero,
+    /// `#VALUE!`
+    Value,
+    /// `#REF!`
+    Ref,
+    /// `#NAME?`
+    Name,
+    /// `#NUM!`
+    Num,
+    /// `#N/A`
+    NotAvailable,
+    /// `#SPILL!`
+    Spill,
+}
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+impl ErrorKind {
+    /// List of all error kinds, in order of their `ERROR.TYPE()` number.
+    pub const ALL: [Self; 8] = [
+        Self::Null,
+        Self::DivideByZero,
+        Self::Value,
+        Self::Ref,
+        Self::Name,
+        Self::Num,
+        Self::NotAvailable,
+        Self::Spill,
+    ];
+
+    /// Returns the code that represents this error in a cell, such as
+    /// `#DIV/0!`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Null => "#NULL!",
+            Self::DivideByZero => "#DIV/0!",
+            Self::Value => "#VALUE!",
+            Self::Ref => "#REF!",
+            Self::Name => "#NAME?",
+            Self::Num => "#NUM!",
+            Self::NotAvailable => "#N/A",
+            Self::Spill => "#SPILL!",
+        }
+    }
+    /// Returns the error kind represented by a code such as `#DIV/0!`, ignoring
+    /// case.
+    pub fn from_code(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.code().eq_ignore_ascii_case(s))
+    }
+    /// Returns the number that `ERROR.TYPE()` returns for this error.
+    pub fn error_type_number(self) -> usize {
+        match self {
+            // Excel reserves 8 for `#GETTING_DATA`.
+            Self::Spill => 9,
+            _ => Self::ALL.iter().position(|&kind| kind == self).unwrap_or(0) + 1,
+        }
+    }
+    /// Returns a human-friendly description of this kind of error.
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Null => "Empty intersection",
+            Self::DivideByZero => "Divide by zero",
+            Self::Value => "Invalid value",
+            Self::Ref => "Invalid reference",
+            Self::Name => "Unknown name",
+            Self::Num => "Invalid number",
+            Self::NotAvailable => "Value not available",
+            Self::Spill => "Array can't spill",
+        }
+    }
 }
 
 impl<T: Into<FormulaErrorMsg>> From<T> for FormulaError {
@@ -0,0 +1,479 @@
+//! Spreadsheet format codes, such as `#,##0.00` or `yyyy-mm-dd`, used by
+//! `TEXT()`.
+
+use chrono::{Datelike, Timelike};
+use itertools::Itertools;
+
+use super::value::{parse_date_time, parse_number, serial_epoch, serial_to_datetime};
+use super::*;
+
+/// Part of one section of a format code.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    /// Text that is copied to the output, from quotes or escapes.
+    Literal(String),
+    /// Character that may have special meaning, such as `0` or `y`.
+    Code(char),
+    /// `AM/PM` or `A/P`, as written.
+    AmPm(String),
+    /// Elapsed time in brackets, such as `[h]`, as the lowercase unit and the
+    /// number of letters.
+    Elapsed(char, usize),
+}
+
+/// Formats a value using a spreadsheet format code.
+///
+/// A format code has up to four sections separated by `;`, which are used for
+/// positive numbers, negative numbers, zero, and text. A section may format
+/// numbers (`0`, `#`, `?`, `.`, `,`, `%`, `E+`) or dates and times (`y`, `m`,
+/// `d`, `h`, `s`, `AM/PM`, `[h]`).
+pub fn format_value(value: &Spanned<Value>, format: &str) -> FormulaResult<String> {
+    if format.trim().eq_ignore_ascii_case("general") {
+        return value.to_text();
+    }
+    let sections = split_sections(format)
+        .iter()
+        .map(|s| parse_section(s))
+        .collect_vec();
+
+    let n = match &value.inner {
+        Value::Bool(_) => None,
+        Value::String(s) if parse_number(s).is_none() && parse_date_time(s).is_none() => None,
+        _ => Some(value.to_number()?),
+    };
+    let Some(n) = n else {
+        // Text only uses the fourth section, or a section with `@`.
+        let text = value.to_text()?;
+        let text_section = sections
+            .get(3)
+            .or_else(|| sections.iter().find(|s| s.contains(&Part::Code('@'))));
+        return Ok(match text_section {
+            Some(section) => section
+                .iter()
+                .map(|part| match part {
+                    Part::Code('@') => text.clone(),
+                    other => part_to_literal(other),
+                })
+                .collect(),
+            None => text,
+        });
+    };
+
+    let section = if n < 0.0 && sections.len() >= 2 {
+        &sections[1]
+    } else if n == 0.0 && sections.len() >= 3 {
+        &sections[2]
+    } else {
+        &sections[0]
+    };
+    let show_minus = n < 0.0 && sections.len() < 2;
+
+    if is_date_format(section) {
+        format_date_time(n, section)
+            .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(value.span))
+    } else {
+        let formatted = format_number(n.abs(), section);
+        Ok(if show_minus {
+            format!("-{formatted}")
+        } else {
+            formatted
+        })
+    }
+}
+
+/// Splits a format code into sections at `;`, except inside quotes or after
+/// `\`.
+fn split_sections(format: &str) -> Vec<String> {
+    let mut sections = vec![String::new()];
+    let mut chars = format.chars();
+    let mut in_quotes = false;
+    while let Some(c) = chars.next() {
+        let current = sections.last_mut().expect("there is always a section");
+        match c {
+            ';' if !in_quotes => sections.push(String::new()),
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            '\\' if !in_quotes => {
+                current.push(c);
+                current.extend(chars.next());
+            }
+            _ => current.push(c),
+        }
+    }
+    sections
+}
+
+/// Parses one section of a format code.
+fn parse_section(section: &str) -> Vec<Part> {
+    let mut parts = vec![];
+    let mut chars = section.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => parts.push(Part::Literal(
+                chars.by_ref().take_while(|&c| c != '"').collect(),
+            )),
+            '\\' => parts.extend(chars.next().map(|c| Part::Literal(c.to_string()))),
+            // `_` leaves space for the next character.
+            '_' => {
+                chars.next();
+                parts.push(Part::Literal(" ".to_string()));
+            }
+            // `*` fills the cell with the next character, which doesn't mean
+            // anything outside a cell.
+            '*' => {
+                chars.next();
+            }
+            '[' => {
+                let contents: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                let lower = contents.to_ascii_lowercase();
+                // Anything else in brackets is a color or condition.
+                if let Some(unit) = lower.chars().next() {
+                    if "hms".contains(unit) && lower.chars().all(|c| c == unit) {
+                        parts.push(Part::Elapsed(unit, lower.len()));
+                    }
+                }
+            }
+            'a' | 'A' => {
+                let rest: String = std::iter::once(c).chain(chars.clone().take(4)).collect();
+                if rest.eq_ignore_ascii_case("am/pm") {
+                    chars.nth(3);
+                    parts.push(Part::AmPm(rest));
+                } else if rest.get(..3).is_some_and(|s| s.eq_ignore_ascii_case("a/p")) {
+                    chars.nth(1);
+                    parts.push(Part::AmPm(rest[..3].to_string()));
+                } else {
+                    parts.push(Part::Code(c));
+                }
+            }
+            _ => parts.push(Part::Code(c)),
+        }
+    }
+    parts
+}
+
+/// Returns the text that a part produces when it has no special meaning.
+fn part_to_literal(part: &Part) -> String {
+    match part {
+        Part::Literal(s) | Part::AmPm(s) => s.clone(),
+        Part::Code(c) => c.to_string(),
+        Part::Elapsed(..) => String::new(),
+    }
+}
+
+fn is_date_format(parts: &[Part]) -> bool {
+    parts.iter().any(|part| match part {
+        Part::Code(c) => "yYmMdDhHsS".contains(*c),
+        Part::AmPm(_) | Part::Elapsed(..) => true,
+        Part::Literal(_) => false,
+    })
+}
+
+fn is_digit_placeholder(part: &Part) -> bool {
+    matches!(part, Part::Code('0' | '#' | '?'))
+}
+
+/// Formats a non-negative number using a section of a format code that
+/// contains digit placeholders.
+fn format_number(mut n: f64, parts: &[Part]) -> String {
+    let exponent_index = (0..parts.len()).find(|&i| {
+        matches!(parts[i], Part::Code('E' | 'e'))
+            && matches!(parts.get(i + 1), Some(Part::Code('+' | '-')))
+    });
+    let mantissa_end = exponent_index.unwrap_or(parts.len());
+    let int_end = parts[..mantissa_end]
+        .iter()
+        .position(|p| *p == Part::Code('.'))
+        .unwrap_or(mantissa_end);
+
+    let placeholders = |parts: &[Part]| {
+        parts
+            .iter()
+            .filter_map(|p| match p {
+                Part::Code(c @ ('0' | '#' | '?')) => Some(*c),
+                _ => None,
+            })
+            .collect_vec()
+    };
+    let int_placeholders = placeholders(&parts[..int_end]);
+    let frac_placeholders = placeholders(&parts[int_end..mantissa_end]);
+    let exp_placeholders = exponent_index.map_or(vec![], |i| placeholders(&parts[i + 2..]));
+
+    // A comma between integer placeholders groups thousands, and each comma
+    // right after the last placeholder divides by 1000.
+    let first_int_placeholder = parts[..int_end].iter().position(is_digit_placeholder);
+    let last_int_placeholder = parts[..int_end].iter().rposition(is_digit_placeholder);
+    let grouping = match (first_int_placeholder, last_int_placeholder) {
+        (Some(first), Some(last)) => parts[first..last].contains(&Part::Code(',')),
+        _ => false,
+    };
+    let scaling_commas = match parts[..mantissa_end].iter().rposition(is_digit_placeholder) {
+        Some(last) => {
+            let count = parts[last + 1..mantissa_end]
+                .iter()
+                .take_while(|p| **p == Part::Code(','))
+                .count();
+            last + 1..last + 1 + count
+        }
+        None => 0..0,
+    };
+    let percent_signs = parts.iter().filter(|p| **p == Part::Code('%')).count();
+    n *= 100_f64.powi(percent_signs as i32);
+    n /= 1000_f64.powi(scaling_commas.len() as i32);
+
+    let mut exponent = 0;
+    if exponent_index.is_some() && n != 0.0 {
+        let int_digits = int_placeholders.len().max(1) as i32;
+        exponent = n.log10().floor() as i32 - (int_digits - 1);
+        n /= 10_f64.powi(exponent);
+        // Rounding may carry into another digit.
+        if format!("{:.*}", frac_placeholders.len(), n)
+            .split('.')
+            .next()
+            .map_or(0, str::len)
+            > int_digits as usize
+        {
+            n /= 10.0;
+            exponent += 1;
+        }
+    }
+
+    let rounded = format!("{:.*}", frac_placeholders.len(), n);
+    let (int_digits, frac_digits) = rounded.split_once('.').unwrap_or((&rounded, ""));
+    let int_digits = int_digits.trim_start_matches('0');
+
+    // Integer digits are right-aligned to the placeholders, and any extra
+    // digits go with the first one.
+    let mut int_outputs = vec![String::new(); int_placeholders.len()];
+    if grouping {
+        let min_digits = int_placeholders
+            .iter()
+            .position(|&c| c == '0')
+            .map_or(0, |i| int_placeholders.len() - i);
+        let padded = format!("{int_digits:0>min_digits$}");
+        if let Some(first) = int_outputs.first_mut() {
+            *first = group_thousands(&padded);
+        }
+    } else {
+        let digits = int_digits.chars().collect_vec();
+        let k = int_placeholders.len();
+        for (j, &placeholder) in int_placeholders.iter().enumerate() {
+            let digit_index = digits.len() as isize - k as isize + j as isize;
+            int_outputs[j] = if j == 0 && digit_index > 0 {
+                digits[..=digit_index as usize].iter().collect()
+            } else if digit_index >= 0 {
+                digits[digit_index as usize].to_string()
+            } else {
+                placeholder_padding(placeholder).to_string()
+            };
+        }
+    }
+
+    // Fraction digits are left-aligned, and trailing zeros are dropped for `#`
+    // and replaced with spaces for `?`.
+    let mut frac_outputs = frac_digits.chars().map(|c| c.to_string()).collect_vec();
+    for (output, &placeholder) in frac_outputs.iter_mut().zip(&frac_placeholders).rev() {
+        if placeholder == '0' || output != "0" {
+            break;
+        }
+        *output = placeholder_padding(placeholder).to_string();
+    }
+
+    let exp_digits = exponent.unsigned_abs().to_string();
+    let exp_min_digits = exp_placeholders.iter().filter(|&&c| c == '0').count();
+    let exp_output = format!("{exp_digits:0>exp_min_digits$}");
+
+    let mut output = String::new();
+    let (mut int_index, mut frac_index) = (0, 0);
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            _ if exponent_index.is_some_and(|e| i > e + 1) => {
+                // Exponent digits come right after the sign.
+                if !is_digit_placeholder(part) {
+                    output.push_str(&part_to_literal(part));
+                }
+            }
+            Part::Code(e @ ('E' | 'e')) if Some(i) == exponent_index => {
+                let sign = match (&parts[i + 1], exponent < 0) {
+                    (_, true) => "-",
+                    (Part::Code('+'), false) => "+",
+                    _ => "",
+                };
+                output.push_str(&format!("{e}{sign}{exp_output}"));
+            }
+            // The sign after `E` is written along with it.
+            _ if exponent_index == Some(i.wrapping_sub(1)) => (),
+            Part::Code(',') if i < int_end && first_int_placeholder.is_some_and(|f| i > f) => (),
+            _ if scaling_commas.contains(&i) => (),
+            part if is_digit_placeholder(part) && i < int_end => {
+                output.push_str(&int_outputs[int_index]);
+                int_index += 1;
+            }
+            part if is_digit_placeholder(part) => {
+                output.push_str(frac_outputs.get(frac_index).map_or("", |s| s));
+                frac_index += 1;
+            }
+            other => output.push_str(&part_to_literal(other)),
+        }
+    }
+    output
+}
+
+/// Returns what a digit placeholder shows when there is no digit for it.
+fn placeholder_padding(placeholder: char) -> &'static str {
+    match placeholder {
+        '0' => "0",
+        '?' => " ",
+        _ => "",
+    }
+}
+
+/// Inserts commas between groups of three digits.
+fn group_thousands(digits: &str) -> String {
+    let len = digits.len();
+    digits
+        .chars()
+        .enumerate()
+        .flat_map(|(i, c)| {
+            let comma = (i > 0 && (len - i).is_multiple_of(3)).then_some(',');
+            comma.into_iter().chain([c])
+        })
+        .collect()
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Formats a serial number as a date or time using a section of a format
+/// code, or returns `None` if it is out of range.
+fn format_date_time(serial: f64, parts: &[Part]) -> Option<String> {
+    // Group runs of the same letter, such as `yyyy`, or of `0`.
+    let mut runs: Vec<(Part, usize)> = vec![];
+    for part in parts {
+        match (runs.last_mut(), part) {
+            (Some((Part::Code(prev), count)), Part::Code(c))
+                if prev.eq_ignore_ascii_case(c) && (c.is_ascii_alphabetic() || *c == '0') =>
+            {
+                *count += 1
+            }
+            _ => runs.push((part.clone(), 1)),
+        }
+    }
+
+    // Fractional seconds, such as in `ss.00`.
+    let fraction_digits = runs
+        .iter()
+        .tuple_windows()
+        .find_map(|((a, _), (b, _), (c, n))| {
+            let is_fraction = matches!(a, Part::Code('s' | 'S'))
+                && *b == Part::Code('.')
+                && *c == Part::Code('0');
+            is_fraction.then_some((*n).min(3))
+        })
+        .unwrap_or(0);
+
+    // Round to the precision shown.
+    let units_per_day = 86400.0 * 10_f64.powi(fraction_digits as i32);
+    let dt = serial_to_datetime((serial * units_per_day).round() / units_per_day)?;
+    let twelve_hour = parts.iter().any(|p| matches!(p, Part::AmPm(_)));
+
+    let mut output = String::new();
+    let mut runs_iter = runs.iter().enumerate();
+    while let Some((i, (part, count))) = runs_iter.next() {
+        let count = *count;
+        let month = MONTH_NAMES[dt.month0() as usize];
+        let weekday = WEEKDAY_NAMES[dt.weekday().num_days_from_monday() as usize];
+        let text = match part {
+            Part::Code(c) => match c.to_ascii_lowercase() {
+                'y' if count <= 2 => pad(dt.year() as u32 % 100, 2),
+                'y' => pad(dt.year() as u32, 4),
+                'm' if is_minutes(&runs, i) => pad(dt.minute(), count),
+                'm' => match count {
+                    1 | 2 => pad(dt.month(), count),
+                    3 => month[..3].to_string(),
+                    4 => month.to_string(),
+                    _ => month[..1].to_string(),
+                },
+                'd' => match count {
+                    1 | 2 => pad(dt.day(), count),
+                    3 => weekday[..3].to_string(),
+                    _ => weekday.to_string(),
+                },
+                'h' if twelve_hour => pad(dt.hour12().1, count),
+                'h' => pad(dt.hour(), count),
+                's' if fraction_digits > 0 => {
+                    // Skip the `.` and `0`s.
+                    runs_iter.nth(1);
+                    let fraction = dt.nanosecond() / 10_u32.pow(9 - fraction_digits as u32);
+                    format!(
+                        "{}.{}",
+                        pad(dt.second(), count),
+                        pad(fraction, fraction_digits)
+                    )
+                }
+                's' => pad(dt.second(), count),
+                _ => c.to_string().repeat(count),
+            },
+            Part::AmPm(s) => {
+                // `AM/PM` or `A/P`, keeping the case as written.
+                let (am, pm) = s.split_once('/').unwrap_or((s, s));
+                match dt.hour12().0 {
+                    false => am.to_string(),
+                    true => pm.to_string(),
+                }
+            }
+            Part::Elapsed(unit, width) => {
+                let elapsed = dt - serial_epoch();
+                let total = match unit {
+                    'h' => elapsed.num_hours(),
+                    'm' => elapsed.num_minutes(),
+                    _ => elapsed.num_seconds(),
+                };
+                format!("{total:0width$}")
+            }
+            Part::Literal(s) => s.clone(),
+        };
+        output.push_str(&text);
+    }
+    Some(output)
+}
+
+/// Returns whether the run of `m` at index `i` means minutes rather than
+/// months, which is when it comes after hours or before seconds.
+fn is_minutes(runs: &[(Part, usize)], i: usize) -> bool {
+    let unit = |(part, _): &(Part, usize)| match part {
+        Part::Code(c) if "yYmMdDhHsS".contains(*c) => Some(c.to_ascii_lowercase()),
+        Part::Elapsed(unit, _) => Some(*unit),
+        _ => None,
+    };
+    let previous = runs[..i].iter().rev().find_map(unit);
+    let next = runs[i + 1..].iter().find_map(unit);
+    previous == Some('h') || next == Some('s')
+}
+
+/// Formats a number with at least `width` digits.
+fn pad(n: u32, width: usize) -> String {
+    format!("{n:0width$}")
+}
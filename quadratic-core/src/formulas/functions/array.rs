@@ -0,0 +1,642 @@
+//! Dynamic array functions, such as `FILTER` and `SORT`, which return arrays
+//! that spill into neighboring cells.
+//!
+//! A single value is treated as a 1x1 array.
+
+use smallvec::{smallvec, SmallVec};
+use std::cmp::Ordering;
+
+use super::lookup::LookupKey;
+use super::*;
+
+/// Rows of an array value.
+type Rows = Vec<SmallVec<[Value; 1]>>;
+
+/// Maximum number of values produced by `SEQUENCE()`.
+const MAX_SEQUENCE_LEN: f64 = 1_000_000.0;
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    // When adding new functions, also update the code editor completions list.
+    Some(match name {
+        "filter" => filter,
+        "sort" => sort,
+        "sortby" => sortby,
+        "unique" => unique,
+        "sequence" => sequence,
+        "transpose" => |args| {
+            let [array] = args_array::<1>(args)?;
+            Ok(Value::Array(transpose(into_rows(array))))
+        },
+        "vstack" => |args| stack(args, false),
+        "hstack" => |args| stack(args, true),
+        "take" => |args| take_or_drop(args, true),
+        "drop" => |args| take_or_drop(args, false),
+        "chooserows" => |args| choose(args, false),
+        "choosecols" => |args| choose(args, true),
+
+        _ => return None,
+    })
+}
+
+/// Evaluates `FILTER()`, which keeps the rows (or columns) of an array where
+/// `include` is true.
+fn filter(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=3)?;
+    let span = args.span;
+    let mut args = args.inner.into_iter();
+    let (array, include) = (args.next().unwrap(), args.next().unwrap());
+    let if_empty = args.next();
+
+    let (rows, cols) = size_of(&array);
+    let by_col = match size_of(&include) {
+        (r, 1) if r == rows => false,
+        (1, c) if c == cols => true,
+        got => {
+            return Err(FormulaErrorMsg::ArraySizeMismatch {
+                expected: (rows, 1),
+                got,
+            }
+            .with_span(include.span))
+        }
+    };
+    let keep = flat_values(&include)
+        .map(|v| element(v, include.span).to_condition())
+        .collect::<FormulaResult<Vec<_>>>()?;
+
+    let filtered = by_axis(into_rows(array), by_col, |rows| {
+        rows.into_iter()
+            .zip(&keep)
+            .filter(|(_, &keep)| keep)
+            .map(|(row, _)| row)
+            .collect()
+    });
+    match (filtered.is_empty(), if_empty) {
+        (true, Some(if_empty)) => Ok(if_empty.inner),
+        (true, None) => Err(FormulaErrorMsg::EmptyArray.with_span(span)),
+        (false, _) => Ok(Value::Array(filtered)),
+    }
+}
+
+/// Evaluates `SORT()`, which sorts the rows (or columns) of an array by one
+/// or more of its columns (or rows).
+fn sort(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 1..=4)?;
+    let mut args = args.inner.into_iter();
+    let array = args.next().unwrap();
+    let indices = args.next();
+    let orders = args.next();
+    let by_col = match args.next() {
+        Some(by_col) => by_col.into_single_value()?.to_condition()?,
+        None => false,
+    };
+
+    let rows = oriented(into_rows(array), by_col);
+    let width = rows.first().map_or(0, |row| row.len());
+    let indices = match &indices {
+        Some(indices) => flat_values(indices)
+            .map(|v| {
+                let i = element(v, indices.span).to_integer()?;
+                if i < 1 || i as usize > width {
+                    return Err(FormulaErrorMsg::IndexOutOfBounds.with_span(indices.span));
+                }
+                Ok(i as usize - 1)
+            })
+            .collect::<FormulaResult<Vec<_>>>()?,
+        None => vec![0],
+    };
+    let orders = match &orders {
+        Some(orders) => sort_orders(orders)?,
+        None => vec![false],
+    };
+
+    let keys = indices
+        .iter()
+        .enumerate()
+        .map(|(i, &col)| {
+            Ok(SortKey {
+                keys: rows.iter().map(|row| sort_key(&row[col])).try_collect()?,
+                descending: *orders.get(i).or(orders.first()).unwrap_or(&false),
+            })
+        })
+        .collect::<FormulaResult<Vec<_>>>()?;
+    Ok(Value::Array(oriented(sort_rows(rows, &keys), by_col)))
+}
+
+/// Evaluates `SORTBY()`, which sorts the rows (or columns) of an array by
+/// other arrays, each followed by an optional sort order.
+fn sortby(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    let Some((array, rest)) = args.inner.split_first() else {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+    };
+    if rest.is_empty() {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+    }
+
+    let (rows, cols) = size_of(array);
+    // The first array determines whether to sort rows or columns.
+    let first_size = size_of(&rest[0]);
+    let by_col = first_size != (rows, 1) && first_size == (1, cols);
+    let mut keys = vec![];
+    for pair in rest.chunks(2) {
+        let by = &pair[0];
+        let expected = if by_col { (1, cols) } else { (rows, 1) };
+        if size_of(by) != expected {
+            return Err(FormulaErrorMsg::ArraySizeMismatch {
+                expected,
+                got: size_of(by),
+            }
+            .with_span(by.span));
+        }
+        let descending = match pair.get(1) {
+            Some(order) => sort_orders(order)?.first().copied().unwrap_or(false),
+            None => false,
+        };
+        keys.push(SortKey {
+            keys: flat_values(by).map(sort_key).try_collect()?,
+            descending,
+        });
+    }
+
+    let rows = oriented(into_rows(array.clone()), by_col);
+    Ok(Value::Array(oriented(sort_rows(rows, &keys), by_col)))
+}
+
+/// Evaluates `UNIQUE()`, which removes duplicate rows (or columns) from an
+/// array, keeping the first of each. Comparison is case-insensitive.
+fn unique(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 1..=3)?;
+    let span = args.span;
+    let mut args = args.inner.into_iter();
+    let array = args.next().unwrap();
+    let mut flag = || match args.next() {
+        Some(v) => v.into_single_value()?.to_condition(),
+        None => Ok(false),
+    };
+    let (by_col, exactly_once) = (flag()?, flag()?);
+
+    let rows = oriented(into_rows(array), by_col);
+    let keys: Vec<Vec<LookupKey>> = rows
+        .iter()
+        .map(|row| row.iter().map(sort_key).try_collect())
+        .try_collect()?;
+    // Group equal rows together, keeping them in their original order.
+    let mut order = (0..rows.len()).collect_vec();
+    order.sort_by(|&a, &b| cmp_rows(&keys[a], &keys[b]).then(a.cmp(&b)));
+    let mut kept = order
+        .into_iter()
+        .group_by(|&i| &keys[i])
+        .into_iter()
+        .filter_map(|(_, group)| {
+            let group = group.collect_vec();
+            (!exactly_once || group.len() == 1).then(|| group[0])
+        })
+        .collect_vec();
+    kept.sort_unstable();
+    if kept.is_empty() {
+        return Err(FormulaErrorMsg::EmptyArray.with_span(span));
+    }
+    let mut rows = rows.into_iter().map(Some).collect_vec();
+    let result = kept.into_iter().filter_map(|i| rows[i].take()).collect();
+    Ok(Value::Array(oriented(result, by_col)))
+}
+
+/// Evaluates `SEQUENCE()`, which returns an array of evenly spaced numbers,
+/// filling each row before moving to the next.
+fn sequence(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 1..=4)?;
+    let span = args.span;
+    let mut args = args.inner.into_iter();
+    let mut number = |default: f64| -> FormulaResult<(f64, Span)> {
+        match args.next() {
+            Some(v) => Ok((v.clone().into_single_value()?.to_number()?, v.span)),
+            None => Ok((default, span)),
+        }
+    };
+    let (rows, rows_span) = number(1.0)?;
+    let (cols, cols_span) = number(1.0)?;
+    let (start, step) = (number(1.0)?.0, number(1.0)?.0);
+
+    let (rows, cols) = (rows.trunc(), cols.trunc());
+    if rows < 1.0 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(rows_span));
+    }
+    if cols < 1.0 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(cols_span));
+    }
+    if rows * cols > MAX_SEQUENCE_LEN {
+        return Err(FormulaErrorMsg::Overflow.with_span(span));
+    }
+    let (rows, cols) = (rows as usize, cols as usize);
+    Ok(Value::Array(
+        (0..rows)
+            .map(|r| {
+                (0..cols)
+                    .map(|c| Value::Number(start + step * (r * cols + c) as f64))
+                    .collect()
+            })
+            .collect(),
+    ))
+}
+
+/// Evaluates `VSTACK()`, or `HSTACK()` if `horizontal` is true. Arrays that
+/// are narrower (or shorter) than the others are padded with `#N/A`.
+fn stack(args: Spanned<Vec<Spanned<Value>>>, horizontal: bool) -> FormulaResult<Value> {
+    if args.inner.is_empty() {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+    }
+    let padding = Value::Error(Box::new(
+        FormulaErrorMsg::ErrorValue(ErrorKind::NotAvailable).with_span(args.span),
+    ));
+    let arrays = args
+        .inner
+        .into_iter()
+        .map(|array| oriented(into_rows(array), horizontal))
+        .collect_vec();
+    let width = arrays
+        .iter()
+        .flatten()
+        .map(|row| row.len())
+        .max()
+        .unwrap_or(0);
+    let rows = arrays
+        .into_iter()
+        .flatten()
+        .map(|mut row| {
+            row.resize(width, padding.clone());
+            row
+        })
+        .collect();
+    Ok(Value::Array(oriented(rows, horizontal)))
+}
+
+/// Evaluates `TAKE()`, or `DROP()` if `take` is false. Negative counts take
+/// or drop from the end.
+fn take_or_drop(args: Spanned<Vec<Spanned<Value>>>, take: bool) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=3)?;
+    let span = args.span;
+    let mut args = args.inner.into_iter();
+    let mut rows = into_rows(args.next().unwrap());
+    for (i, count) in args.enumerate() {
+        let count = count.into_single_value()?.to_integer()?;
+        let by_col = i == 1;
+        rows = by_axis(rows, by_col, |items| take_or_drop_items(items, count, take));
+    }
+    if rows.is_empty() || rows[0].is_empty() {
+        return Err(FormulaErrorMsg::EmptyArray.with_span(span));
+    }
+    Ok(Value::Array(rows))
+}
+
+fn take_or_drop_items<T>(mut items: Vec<T>, count: i64, take: bool) -> Vec<T> {
+    let n = (count.unsigned_abs() as usize).min(items.len());
+    match (take, count >= 0) {
+        (true, true) => items.truncate(n),
+        (true, false) => {
+            items.drain(..items.len() - n);
+        }
+        (false, true) => {
+            items.drain(..n);
+        }
+        (false, false) => items.truncate(items.len() - n),
+    }
+    items
+}
+
+/// Evaluates `CHOOSEROWS()`, or `CHOOSECOLS()` if `by_col` is true. Negative
+/// numbers count from the end.
+fn choose(args: Spanned<Vec<Spanned<Value>>>, by_col: bool) -> FormulaResult<Value> {
+    let Some((array, numbers)) = args.inner.split_first() else {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+    };
+    if numbers.is_empty() {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+    }
+    let rows = oriented(into_rows(array.clone()), by_col);
+    let len = rows.len() as i64;
+    let mut chosen = vec![];
+    for arg in numbers {
+        for v in flat_values(arg) {
+            let n = element(v, arg.span).to_integer()?;
+            let index = if n < 0 { len + n } else { n - 1 };
+            if n == 0 || !(0..len).contains(&index) {
+                return Err(FormulaErrorMsg::IndexOutOfBounds.with_span(arg.span));
+            }
+            chosen.push(rows[index as usize].clone());
+        }
+    }
+    Ok(Value::Array(oriented(chosen, by_col)))
+}
+
+/// Column of keys to sort by, with one key per row.
+struct SortKey {
+    keys: Vec<LookupKey>,
+    descending: bool,
+}
+
+/// Sorts rows by several keys, using later keys to break ties. The sort is
+/// stable, so rows with equal keys keep their order.
+fn sort_rows(rows: Rows, keys: &[SortKey]) -> Rows {
+    let mut order = (0..rows.len()).collect_vec();
+    order.sort_by(|&a, &b| {
+        keys.iter()
+            .map(|key| cmp_for_sort(&key.keys[a], &key.keys[b], key.descending))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    let mut rows = rows.into_iter().map(Some).collect_vec();
+    order.into_iter().filter_map(|i| rows[i].take()).collect()
+}
+
+/// Returns the key for sorting or removing duplicates of a value. Unlike
+/// lookups, empty text is compared as text rather than as a blank, and error
+/// values are returned as errors.
+fn sort_key(value: &Value) -> FormulaResult<LookupKey> {
+    match value {
+        Value::Error(e) => Err((**e).clone()),
+        Value::String(s) => Ok(LookupKey::Text(s.to_lowercase())),
+        other => Ok(LookupKey::new(other)),
+    }
+}
+
+/// Compares keys for sorting: numbers, then text, then booleans. Blanks come
+/// last regardless of the sort direction.
+fn cmp_for_sort(a: &LookupKey, b: &LookupKey, descending: bool) -> Ordering {
+    match (a, b) {
+        (LookupKey::Blank, LookupKey::Blank) => Ordering::Equal,
+        (LookupKey::Blank, _) => Ordering::Greater,
+        (_, LookupKey::Blank) => Ordering::Less,
+        _ if descending => b.total_cmp(a),
+        _ => a.total_cmp(b),
+    }
+}
+
+fn cmp_rows(a: &[LookupKey], b: &[LookupKey]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.total_cmp(b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Returns whether each sort order in an argument is descending. Orders
+/// must be 1 (ascending) or -1 (descending).
+fn sort_orders(orders: &Spanned<Value>) -> FormulaResult<Vec<bool>> {
+    flat_values(orders)
+        .map(|v| match element(v, orders.span).to_integer()? {
+            1 => Ok(false),
+            -1 => Ok(true),
+            _ => Err(FormulaErrorMsg::InvalidArgument.with_span(orders.span)),
+        })
+        .collect()
+}
+
+/// Returns the `(rows, cols)` size of a value.
+fn size_of(value: &Spanned<Value>) -> (usize, usize) {
+    value.inner.array_size().unwrap_or((1, 1))
+}
+
+fn element(value: &Value, span: Span) -> Spanned<Value> {
+    Spanned {
+        span,
+        inner: value.clone(),
+    }
+}
+
+fn into_rows(value: Spanned<Value>) -> Rows {
+    match value.inner {
+        Value::Array(a) => a,
+        other => vec![smallvec![other]],
+    }
+}
+
+fn transpose(rows: Rows) -> Rows {
+    let width = rows.first().map_or(0, |row| row.len());
+    let mut columns: Rows = (0..width)
+        .map(|_| SmallVec::with_capacity(rows.len()))
+        .collect();
+    for row in rows {
+        for (column, value) in columns.iter_mut().zip(row) {
+            column.push(value);
+        }
+    }
+    columns
+}
+
+/// Transposes the rows if `by_col` is true, so that columns can be handled
+/// the same way as rows.
+fn oriented(rows: Rows, by_col: bool) -> Rows {
+    if by_col {
+        transpose(rows)
+    } else {
+        rows
+    }
+}
+
+/// Applies an operation to the rows of an array, or to its columns if
+/// `by_col` is true.
+fn by_axis(rows: Rows, by_col: bool, op: impl FnOnce(Rows) -> Rows) -> Rows {
+    oriented(op(oriented(rows, by_col)), by_col)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_filter() {
+        let g = &mut PanicGridMock;
+        let data = "{1, 'a'; 2, 'b'; 3, 'c'}";
+        assert_eq!(
+            "{1, a; 3, c}",
+            eval_to_string(g, &format!("FILTER({data}, {{TRUE(); FALSE(); TRUE()}})")),
+        );
+        assert_eq!(
+            "{2, b; 3, c}",
+            eval_to_string(g, &format!("FILTER({data}, {{1; 2; 3}} > 1)")),
+        );
+        // Numbers are `TRUE` unless they are zero.
+        assert_eq!(
+            "{1, a; 3, c}",
+            eval_to_string(g, &format!("FILTER({data}, {{1; 0; -2}})")),
+        );
+        assert_eq!(
+            "{a; b; c}",
+            eval_to_string(g, &format!("FILTER({data}, {{FALSE(), TRUE()}})")),
+        );
+        assert_eq!(
+            "none",
+            eval_to_string(g, &format!("FILTER({data}, {{1; 2; 3}} > 5, 'none')")),
+        );
+        assert_eq!(
+            FormulaErrorMsg::EmptyArray,
+            eval(g, &format!("FILTER({data}, {{1; 2; 3}} > 5)"))
+                .unwrap_err()
+                .msg,
+        );
+        let error = eval(g, &format!("FILTER({data}, {{TRUE(); FALSE()}})")).unwrap_err();
+        assert!(matches!(
+            error.msg,
+            FormulaErrorMsg::ArraySizeMismatch { .. },
+        ));
+        assert_eq!(Some(Span { start: 33, end: 50 }), error.span);
+    }
+
+    #[test]
+    fn test_sort() {
+        let g = &mut PanicGridMock;
+        // Numbers, then text, then booleans. Text is sorted as text even if
+        // it looks like a number.
+        assert_eq!(
+            "{1; 10; ; 2; a; B; FALSE; TRUE}",
+            eval_to_string(g, "SORT({TRUE(); 'B'; ''; 10; FALSE(); 'a'; 1; '2'})"),
+        );
+        assert_eq!(
+            "{TRUE; FALSE; B; a; 2; ; 10; 1}",
+            eval_to_string(
+                g,
+                "SORT({TRUE(); 'B'; ''; 10; FALSE(); 'a'; 1; '2'}, 1, -1)"
+            ),
+        );
+        // Error values are propagated.
+        assert_eq!(
+            ErrorKind::DivideByZero,
+            eval(g, "SORT({2; 1 / 0; 1})").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "SORTBY({1; 2}, {NA(); 1})").unwrap_err().msg.kind(),
+        );
+
+        let data = "{'b', 2; 'a', 2; 'c', 1}";
+        assert_eq!(
+            "{c, 1; a, 2; b, 2}",
+            eval_to_string(g, &format!("SORT({data}, {{2, 1}})")),
+        );
+        assert_eq!(
+            "{b, 2; a, 2; c, 1}",
+            eval_to_string(g, &format!("SORT({data}, {{2, 1}}, {{-1, -1}})")),
+        );
+        assert_eq!(
+            "{2, 3, 1; a, b, c}",
+            eval_to_string(g, "SORT({3, 1, 2; 'b', 'c', 'a'}, 2, 1, TRUE())"),
+        );
+        assert_eq!(
+            ErrorKind::Ref,
+            eval(g, &format!("SORT({data}, 3)")).unwrap_err().msg.kind(),
+        );
+
+        assert_eq!(
+            "{c, 1; b, 2; a, 2}",
+            eval_to_string(g, &format!("SORTBY({data}, {{2; 2; 1}})")),
+        );
+        assert_eq!(
+            "{a, 2; b, 2; c, 1}",
+            eval_to_string(
+                g,
+                &format!("SORTBY({data}, {{2; 2; 1}}, -1, {{'b'; 'a'; 'c'}})")
+            ),
+        );
+        assert_eq!(
+            "{2, 3, 1}",
+            eval_to_string(g, "SORTBY({1, 2, 3}, {30, 10, 20})"),
+        );
+        let error = eval(g, &format!("SORTBY({data}, {{1; 2}})")).unwrap_err();
+        assert_eq!(Some(Span { start: 33, end: 39 }), error.span);
+    }
+
+    #[test]
+    fn test_unique() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            "{b; A; c}",
+            eval_to_string(g, "UNIQUE({'b'; 'A'; 'a'; 'c'; 'b'})"),
+        );
+        assert_eq!(
+            "{1, x; 2, y}",
+            eval_to_string(g, "UNIQUE({1, 'x'; 2, 'y'; 1, 'x'})"),
+        );
+        assert_eq!("{1, 2}", eval_to_string(g, "UNIQUE({1, 2, 1}, TRUE())"),);
+        assert_eq!("{1, 2}", eval_to_string(g, "UNIQUE({1, 2, 1}, 1)"));
+        assert_eq!("{1; 1}", eval_to_string(g, "UNIQUE({1; '1'; 1})"));
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "UNIQUE({1; NA()})").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            "{c}",
+            eval_to_string(g, "UNIQUE({'b'; 'A'; 'a'; 'c'; 'b'}, FALSE(), TRUE())"),
+        );
+        assert_eq!(
+            FormulaErrorMsg::EmptyArray,
+            eval(g, "UNIQUE({1; 1}, FALSE(), TRUE())").unwrap_err().msg,
+        );
+    }
+
+    #[test]
+    fn test_sequence_and_transpose() {
+        let g = &mut PanicGridMock;
+        assert_eq!("{1; 2; 3}", eval_to_string(g, "SEQUENCE(3)"));
+        assert_eq!(
+            "{0, 5, 10; 15, 20, 25}",
+            eval_to_string(g, "SEQUENCE(2, 3, 0, 5)"),
+        );
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "SEQUENCE(0)").unwrap_err().msg.kind()
+        );
+        assert_eq!(
+            FormulaErrorMsg::Overflow,
+            eval(g, "SEQUENCE(1e6, 2)").unwrap_err().msg,
+        );
+        assert_eq!("{1, 3; 2, 4}", eval_to_string(g, "TRANSPOSE({1, 2; 3, 4})"),);
+        assert_eq!("{5}", eval_to_string(g, "TRANSPOSE(5)"));
+    }
+
+    #[test]
+    fn test_reshaping() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            "{1, 2; 3, #N/A; 4, 5}",
+            eval_to_string(g, "VSTACK({1, 2}, 3, {4, 5})"),
+        );
+        assert_eq!(
+            "{1, 3, 4; 2, #N/A, 5}",
+            eval_to_string(g, "HSTACK({1; 2}, 3, {4; 5})"),
+        );
+
+        let data = "{1, 2, 3; 4, 5, 6; 7, 8, 9}";
+        assert_eq!(
+            "{1, 2, 3; 4, 5, 6}",
+            eval_to_string(g, &format!("TAKE({data}, 2)"))
+        );
+        assert_eq!(
+            "{8, 9}",
+            eval_to_string(g, &format!("TAKE({data}, -1, -2)"))
+        );
+        assert_eq!("{4; 7}", eval_to_string(g, &format!("DROP({data}, 1, -2)")));
+        assert_eq!(
+            "{1, 2, 3; 4, 5, 6; 7, 8, 9}",
+            eval_to_string(g, &format!("TAKE({data}, 10)")),
+        );
+        assert_eq!(
+            FormulaErrorMsg::EmptyArray,
+            eval(g, &format!("DROP({data}, 3)")).unwrap_err().msg,
+        );
+
+        assert_eq!(
+            "{7, 8, 9; 1, 2, 3; 7, 8, 9}",
+            eval_to_string(g, &format!("CHOOSEROWS({data}, -1, {{1, 3}})")),
+        );
+        assert_eq!(
+            "{2; 5; 8}",
+            eval_to_string(g, &format!("CHOOSECOLS({data}, 2)")),
+        );
+        assert_eq!(
+            ErrorKind::Ref,
+            eval(g, &format!("CHOOSECOLS({data}, 4)"))
+                .unwrap_err()
+                .msg
+                .kind(),
+        );
+    }
+}
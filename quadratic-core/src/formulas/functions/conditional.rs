@@ -0,0 +1,388 @@
+//! Conditional aggregation functions, such as `SUMIF` and `COUNTIFS`.
+//!
+//! Criteria are written the same way as in other spreadsheets: a value to
+//! compare against, optionally preceded by a comparison operator such as `>=`
+//! or `<>`. Text criteria may contain wildcards and are case-insensitive.
+
+use std::cmp::Ordering;
+
+use super::lookup::LookupKey;
+use super::statistics::mean;
+use super::*;
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    // When adding new functions, also update the code editor completions list.
+    Some(match name {
+        "countif" => |args| {
+            let [range, criterion] = args_array::<2>(args)?;
+            map_arg(criterion, |[criterion]| {
+                let count = matching_values(&range, &[(&range, criterion)])?.len();
+                Ok(Value::Number(count as f64))
+            })
+        },
+        "sumif" => |args| {
+            let (range, criterion, sum_range) = if_args(args)?;
+            map_arg(criterion, |[criterion]| {
+                let values = matching_values(&sum_range, &[(&range, criterion)])?;
+                Ok(Value::Number(
+                    numbers(&values, sum_range.span)?.iter().sum(),
+                ))
+            })
+        },
+        "averageif" => |args| {
+            let (range, criterion, average_range) = if_args(args)?;
+            map_arg(criterion, |[criterion]| {
+                let span = criterion.span;
+                let values = matching_values(&average_range, &[(&range, criterion)])?;
+                mean(&numbers(&values, average_range.span)?, span).map(Value::Number)
+            })
+        },
+        "countifs" => |args| {
+            let conditions = conditions(args.span, &args.inner)?;
+            let count = matching_values(conditions[0].0, &conditions)?.len();
+            Ok(Value::Number(count as f64))
+        },
+        "sumifs" => |args| {
+            let (values, conditions) = ifs_args(&args)?;
+            let values = matching_values(values, &conditions)?;
+            Ok(Value::Number(numbers(&values, args.span)?.iter().sum()))
+        },
+        "averageifs" => |args| {
+            let (values, conditions) = ifs_args(&args)?;
+            let values = matching_values(values, &conditions)?;
+            mean(&numbers(&values, args.span)?, args.span).map(Value::Number)
+        },
+        "maxifs" => |args| {
+            let (values, conditions) = ifs_args(&args)?;
+            let values = matching_values(values, &conditions)?;
+            let max = numbers(&values, args.span)?.into_iter().reduce(f64::max);
+            Ok(Value::Number(max.unwrap_or(0.0)))
+        },
+        "minifs" => |args| {
+            let (values, conditions) = ifs_args(&args)?;
+            let values = matching_values(values, &conditions)?;
+            let min = numbers(&values, args.span)?.into_iter().reduce(f64::min);
+            Ok(Value::Number(min.unwrap_or(0.0)))
+        },
+
+        _ => return None,
+    })
+}
+
+/// Range of values paired with a criterion that they must satisfy.
+type Condition<'a> = (&'a Spanned<Value>, Spanned<Value>);
+
+/// Comparison operator at the start of a criterion.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+impl Comparison {
+    /// Splits a comparison operator off the start of a criterion string. If
+    /// there is no operator, the comparison is equality.
+    fn parse(s: &str) -> (Self, &str) {
+        let operators = [
+            (">=", Self::Ge),
+            ("<=", Self::Le),
+            ("<>", Self::Ne),
+            ("=", Self::Eq),
+            (">", Self::Gt),
+            ("<", Self::Lt),
+        ];
+        for (prefix, comparison) in operators {
+            if let Some(rest) = s.strip_prefix(prefix) {
+                return (comparison, rest);
+            }
+        }
+        (Self::Eq, s)
+    }
+
+    fn accepts(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering == Ordering::Equal,
+            Self::Ne => ordering != Ordering::Equal,
+            Self::Lt => ordering == Ordering::Less,
+            Self::Gt => ordering == Ordering::Greater,
+            Self::Le => ordering != Ordering::Greater,
+            Self::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// Condition that a value must satisfy, such as `">=10"` or `"a*"`.
+#[derive(Debug, Clone)]
+struct Criterion {
+    comparison: Comparison,
+    key: LookupKey,
+    /// Pattern for text criteria that contain wildcards.
+    wildcard: Option<Regex>,
+}
+impl Criterion {
+    /// Parses a criterion. Numbers and booleans must equal the value, while
+    /// text may start with a comparison operator.
+    fn new(value: &Spanned<Value>) -> FormulaResult<Self> {
+        let (comparison, operand) = match &value.inner {
+            Value::Error(e) => return Err((**e).clone()),
+            Value::String(s) => {
+                let (comparison, operand) = Comparison::parse(s);
+                (comparison, Value::String(operand.to_string()))
+            }
+            other => (Comparison::Eq, other.clone()),
+        };
+        let key = criteria_key(&operand).unwrap_or(LookupKey::Blank);
+        let wildcard = match &key {
+            LookupKey::Text(s) if contains_wildcards(s) => Some(wildcard_pattern_regex(s)),
+            _ => None,
+        };
+        Ok(Self {
+            comparison,
+            key,
+            wildcard,
+        })
+    }
+
+    /// Returns whether a value satisfies the criterion. Error values never
+    /// do.
+    fn matches(&self, value: &Value) -> bool {
+        let Some(key) = criteria_key(value) else {
+            return false;
+        };
+        match self.comparison {
+            Comparison::Eq => self.equals(&key),
+            Comparison::Ne => !self.equals(&key),
+            comparison => key
+                .partial_cmp_same_type(&self.key)
+                .is_some_and(|ordering| comparison.accepts(ordering)),
+        }
+    }
+
+    fn equals(&self, key: &LookupKey) -> bool {
+        match (&self.wildcard, key) {
+            (Some(regex), LookupKey::Text(s)) => regex.is_match(s),
+            _ => self.key.total_cmp(key) == Ordering::Equal,
+        }
+    }
+}
+
+/// Returns the key used to compare a value against a criterion, or `None`
+/// for error values. Text such as `10` or `TRUE` compares equal to the number
+/// or boolean.
+fn criteria_key(value: &Value) -> Option<LookupKey> {
+    match LookupKey::parse(value) {
+        _ if matches!(value, Value::Error(_)) => None,
+        LookupKey::Text(s) if s == "true" => Some(LookupKey::Bool(true)),
+        LookupKey::Text(s) if s == "false" => Some(LookupKey::Bool(false)),
+        key => Some(key),
+    }
+}
+
+/// Returns the arguments to a function such as `SUMIF()`: a range to test, a
+/// criterion, and an optional range of values that defaults to the range to
+/// test. The two ranges must be the same size.
+fn if_args(
+    args: Spanned<Vec<Spanned<Value>>>,
+) -> FormulaResult<(Spanned<Value>, Spanned<Value>, Spanned<Value>)> {
+    check_arg_count(&args, 2..=3)?;
+    let mut args = args.inner.into_iter();
+    let range = args.next().unwrap();
+    let criterion = args.next().unwrap();
+    let values = args.next().unwrap_or_else(|| range.clone());
+    check_same_size(&range, &values)?;
+    Ok((range, criterion, values))
+}
+
+/// Returns the arguments to a function such as `SUMIFS()`: a range of values
+/// followed by pairs of ranges and criteria.
+fn ifs_args(
+    args: &Spanned<Vec<Spanned<Value>>>,
+) -> FormulaResult<(&Spanned<Value>, Vec<Condition<'_>>)> {
+    let Some((values, rest)) = args.inner.split_first() else {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+    };
+    Ok((values, conditions(args.span, rest)?))
+}
+
+/// Returns pairs of ranges and criteria.
+fn conditions(span: Span, args: &[Spanned<Value>]) -> FormulaResult<Vec<Condition<'_>>> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(span));
+    }
+    args.chunks_exact(2)
+        .map(|pair| Ok((&pair[0], pair[1].clone().into_single_value()?)))
+        .collect()
+}
+
+/// Returns an error pointing at `other` if it is a different size from
+/// `expected`. Single values are treated as 1x1 arrays.
+fn check_same_size(expected: &Spanned<Value>, other: &Spanned<Value>) -> FormulaResult<()> {
+    let expected_size = expected.inner.array_size().unwrap_or((1, 1));
+    let other_size = other.inner.array_size().unwrap_or((1, 1));
+    if expected_size != other_size {
+        return Err(FormulaErrorMsg::ArraySizeMismatch {
+            expected: expected_size,
+            got: other_size,
+        }
+        .with_span(other.span));
+    }
+    Ok(())
+}
+
+/// Returns the elements of `values` where the corresponding elements of
+/// every range satisfy its criterion. All ranges must be the same size as
+/// `values`.
+fn matching_values<'a>(
+    values: &'a Spanned<Value>,
+    conditions: &[Condition<'_>],
+) -> FormulaResult<Vec<&'a Value>> {
+    let mut keep = vec![true; flat_values(values).count()];
+    for (range, criterion) in conditions {
+        check_same_size(values, range)?;
+        let criterion = Criterion::new(criterion)?;
+        for (keep, value) in keep.iter_mut().zip(flat_values(range)) {
+            *keep &= criterion.matches(value);
+        }
+    }
+    Ok(flat_values(values)
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(value, _)| value)
+        .collect())
+}
+
+/// Returns the numbers among some values, skipping text and blanks.
+fn numbers(values: &[&Value], span: Span) -> FormulaResult<Vec<f64>> {
+    let mut ret = vec![];
+    for value in values {
+        ret.extend(array_element_number(value, span)?);
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_criteria() {
+        let g = &mut PanicGridMock;
+        let data = "{5, 10, 15, 'apple', 'Banana', '', 'avocado'}";
+        let count = |g: &mut PanicGridMock, criterion: &str| {
+            eval_to_string(g, &format!("COUNTIF({data}, '{criterion}')"))
+        };
+        assert_eq!("2", count(g, ">=10"));
+        assert_eq!("1", count(g, "<10"));
+        assert_eq!("1", count(g, "=10"));
+        assert_eq!("1", count(g, "10"));
+        assert_eq!("6", count(g, "<>10"));
+        assert_eq!("1", count(g, "APPLE"));
+        assert_eq!("2", count(g, "a*"));
+        assert_eq!("1", count(g, "?anana"));
+        assert_eq!("5", count(g, "<>a*"));
+        assert_eq!("1", count(g, ">b"));
+        assert_eq!("1", count(g, ""));
+        assert_eq!("6", count(g, "<>"));
+        assert_eq!("1", eval_to_string(g, &format!("COUNTIF({data}, 15)")));
+        assert_eq!(
+            "{1, 2}",
+            eval_to_string(g, &format!("COUNTIF({data}, {{'>12', 'a*'}})")),
+        );
+        assert_eq!(
+            "1",
+            eval_to_string(g, "COUNTIF({TRUE(), FALSE(), 'x'}, 'true')"),
+        );
+        assert_eq!(
+            "1",
+            eval_to_string(g, "COUNTIF({'a~*', 'a*', 'ab'}, 'a~*')")
+        );
+    }
+
+    #[test]
+    fn test_conditional_aggregation() {
+        let g = &mut PanicGridMock;
+        let regions = "{'east'; 'west'; 'east'; 'north'}";
+        let sales = "{100; 200; 300; 'n/a'}";
+        let units = "{1; 2; 3; 4}";
+
+        assert_eq!(
+            "400",
+            eval_to_string(g, &format!("SUMIF({regions}, 'east', {sales})")),
+        );
+        assert_eq!(
+            "300",
+            eval_to_string(g, &format!("SUMIF({units}, '<3', {sales})"))
+        );
+        assert_eq!("9", eval_to_string(g, &format!("SUMIF({units}, '>1')")));
+        assert_eq!(
+            "200",
+            eval_to_string(g, &format!("AVERAGEIF({regions}, 'east', {sales})")),
+        );
+        assert_eq!(
+            ErrorKind::DivideByZero,
+            eval(g, &format!("AVERAGEIF({regions}, 'south', {sales})"))
+                .unwrap_err()
+                .msg
+                .kind(),
+        );
+
+        let both = format!("{regions}, 'east', {units}, '>1'");
+        assert_eq!("1", eval_to_string(g, &format!("COUNTIFS({both})")));
+        assert_eq!(
+            "300",
+            eval_to_string(g, &format!("SUMIFS({sales}, {both})"))
+        );
+        assert_eq!(
+            "300",
+            eval_to_string(g, &format!("AVERAGEIFS({sales}, {both})")),
+        );
+        assert_eq!(
+            "300",
+            eval_to_string(g, &format!("MAXIFS({sales}, {regions}, 'east')")),
+        );
+        assert_eq!(
+            "100",
+            eval_to_string(g, &format!("MINIFS({sales}, {regions}, 'east')")),
+        );
+        assert_eq!(
+            "0",
+            eval_to_string(g, &format!("MAXIFS({sales}, {regions}, 'south')")),
+        );
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, &format!("SUMIFS({sales}, {regions})"))
+                .unwrap_err()
+                .msg,
+        );
+    }
+
+    #[test]
+    fn test_conditional_size_mismatch() {
+        let g = &mut PanicGridMock;
+        let error = eval(g, "SUMIF({1; 2; 3}, '>1', {1; 2})").unwrap_err();
+        assert_eq!(
+            FormulaErrorMsg::ArraySizeMismatch {
+                expected: (3, 1),
+                got: (2, 1),
+            },
+            error.msg,
+        );
+        assert_eq!(Some(Span { start: 23, end: 29 }), error.span);
+
+        let error = eval(g, "COUNTIFS({1; 2}, '>1', {1, 2}, '>1')").unwrap_err();
+        assert_eq!(
+            FormulaErrorMsg::ArraySizeMismatch {
+                expected: (2, 1),
+                got: (1, 2),
+            },
+            error.msg,
+        );
+        assert_eq!(Some(Span { start: 23, end: 29 }), error.span);
+
+        let error = eval(g, "MINIFS({1; 2}, {1; 2; 3}, '>1')").unwrap_err();
+        assert_eq!(Some(Span { start: 15, end: 24 }), error.span);
+    }
+}
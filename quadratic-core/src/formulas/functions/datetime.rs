@@ -0,0 +1,572 @@
+//! Date and time functions, such as `DATE` and `NETWORKDAYS`, and arithmetic
+//! on dates.
+//!
+//! `TODAY` and `NOW` depend on when the formula is evaluated, so they are
+//! evaluated in `ast.rs`.
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use std::collections::HashSet;
+
+use super::*;
+use crate::formulas::value::{
+    date_to_serial, datetime_to_serial, duration_to_serial, parse_date_time, parse_number,
+    serial_to_duration,
+};
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    Some(match name {
+        "date" => array_mapped!(|[year, month, day]| {
+            let span = Span::merge(&year, &day);
+            date_from_parts(year.to_number()?, month.to_number()?, day.to_number()?)
+                .map(Value::Date)
+                .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(span))
+        }),
+        "time" => array_mapped!(|[hour, minute, second]| {
+            let span = Span::merge(&hour, &second);
+            let seconds = hour.to_number()?.trunc() * 3600.0
+                + minute.to_number()?.trunc() * 60.0
+                + second.to_number()?.trunc();
+            if !(0.0..i64::MAX as f64).contains(&seconds) {
+                return Err(FormulaErrorMsg::InvalidDate.with_span(span));
+            }
+            // Times wrap around at midnight.
+            Ok(Value::Duration(Duration::seconds(seconds as i64 % 86400)))
+        }),
+        "year" => array_mapped!(|[date]| Ok(Value::Number(date.to_date()?.year() as f64))),
+        "month" => array_mapped!(|[date]| Ok(Value::Number(date.to_date()?.month() as f64))),
+        "day" => array_mapped!(|[date]| Ok(Value::Number(date.to_date()?.day() as f64))),
+        "hour" => array_mapped!(|[time]| Ok(Value::Number(time.to_time()?.hour() as f64))),
+        "minute" => array_mapped!(|[time]| Ok(Value::Number(time.to_time()?.minute() as f64))),
+        "second" => array_mapped!(|[time]| Ok(Value::Number(time.to_time()?.second() as f64))),
+        "weekday" => |args| match args.inner.len() {
+            1 => array_map(args, |[date]| weekday(date, None)),
+            _ => array_map(args, |[date, return_type]| weekday(date, Some(return_type))),
+        },
+        "weeknum" => |args| match args.inner.len() {
+            1 => array_map(args, |[date]| weeknum(date, None)),
+            _ => array_map(args, |[date, return_type]| weeknum(date, Some(return_type))),
+        },
+        "edate" => array_mapped!(|[start, months]| {
+            let span = Span::merge(&start, &months);
+            add_months(start.to_date()?, months.to_number()?.trunc() as i64)
+                .map(Value::Date)
+                .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(span))
+        }),
+        "eomonth" => array_mapped!(|[start, months]| {
+            let span = Span::merge(&start, &months);
+            let months = months.to_number()?.trunc() as i64;
+            // Find the first day of the following month, then go back a day.
+            (start.to_date()?.with_day(1))
+                .and_then(|d| add_months(d, months.checked_add(1)?))
+                .and_then(|d| d.pred_opt())
+                .map(Value::Date)
+                .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(span))
+        }),
+        "datedif" => array_mapped!(|[start, end, unit]| {
+            let start_date = start.to_date()?;
+            let end_date = end.to_date()?;
+            if start_date > end_date {
+                return Err(FormulaErrorMsg::InvalidArgument.with_span(Span::merge(&start, &end)));
+            }
+            datedif(start_date, end_date, &unit.to_text()?)
+                .map(|n| Value::Number(n as f64))
+                .ok_or_else(|| FormulaErrorMsg::InvalidArgument.with_span(unit.span))
+        }),
+        "networkdays" => networkdays,
+        "workday" => workday,
+        "datevalue" => array_mapped!(|[text]| match parse_date_time(&text.to_text()?) {
+            Some(Value::Date(d)) => Ok(Value::Date(d)),
+            Some(Value::DateTime(dt)) => Ok(Value::Date(dt.date())),
+            _ => Err(FormulaErrorMsg::Expected {
+                expected: "date".into(),
+                got: Some(format!("{:?}", text.inner.to_string()).into()),
+            }
+            .with_span(text.span)),
+        }),
+        _ => return None,
+    })
+}
+
+/// Returns the date for a year, month, and day, allowing the month and day to
+/// overflow into the next year or month. Years from 0 to 1899 are counted
+/// from 1900. Returns `None` if the date is out of range.
+fn date_from_parts(year: f64, month: f64, day: f64) -> Option<NaiveDate> {
+    let mut year = year.trunc();
+    if !(0.0..10000.0).contains(&year) || !month.is_finite() || !day.is_finite() {
+        return None;
+    }
+    if year < 1900.0 {
+        year += 1900.0;
+    }
+    let months_since_year_zero = year * 12.0 + month.trunc() - 1.0;
+    let first_of_month = NaiveDate::from_ymd_opt(
+        months_since_year_zero.div_euclid(12.0) as i32,
+        months_since_year_zero.rem_euclid(12.0) as u32 + 1,
+        1,
+    )?;
+    let date = first_of_month.checked_add_signed(Duration::try_days(day.trunc() as i64 - 1)?)?;
+    (date_to_serial(date) >= 0.0 && date.year() <= 9999).then_some(date)
+}
+
+/// Adds a number of months to a date, moving to the end of the month if the
+/// day doesn't exist in that month.
+fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let delta = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months >= 0 {
+        date.checked_add_months(delta)
+    } else {
+        date.checked_sub_months(delta)
+    }
+}
+
+/// Returns the day of the week as a number. `return_type` chooses which day
+/// the week starts on and whether it is numbered 0 or 1, as in other
+/// spreadsheets.
+fn weekday(date: Spanned<Value>, return_type: Option<Spanned<Value>>) -> FormulaResult<Value> {
+    let (week_start, first_number) = match &return_type {
+        None => (6, 1),
+        Some(return_type) => match return_type.to_integer()? {
+            1 => (6, 1),
+            2 => (0, 1),
+            3 => (0, 0),
+            n @ 11..=17 => (n as u32 - 11, 1),
+            _ => return Err(FormulaErrorMsg::InvalidArgument.with_span(return_type.span)),
+        },
+    };
+    let day = date.to_date()?.weekday().num_days_from_monday();
+    Ok(Value::Number(
+        ((day + 7 - week_start) % 7 + first_number) as f64,
+    ))
+}
+
+/// Returns the week of the year. The week containing January 1st is week 1,
+/// except for `return_type` 21, which uses ISO 8601 week numbers.
+fn weeknum(date: Spanned<Value>, return_type: Option<Spanned<Value>>) -> FormulaResult<Value> {
+    let week_start = match &return_type {
+        None => 6,
+        Some(return_type) => match return_type.to_integer()? {
+            1 => 6,
+            2 => 0,
+            n @ 11..=17 => n as u32 - 11,
+            21 => return Ok(Value::Number(date.to_date()?.iso_week().week() as f64)),
+            _ => return Err(FormulaErrorMsg::InvalidArgument.with_span(return_type.span)),
+        },
+    };
+    let date = date.to_date()?;
+    let jan_1 = date.with_ordinal(1).unwrap_or(date);
+    let days_before_jan_1 = (jan_1.weekday().num_days_from_monday() + 7 - week_start) % 7;
+    Ok(Value::Number(
+        ((date.ordinal0() + days_before_jan_1) / 7 + 1) as f64,
+    ))
+}
+
+/// Returns the number of whole months from `start` to `end`.
+fn whole_months_between(start: NaiveDate, end: NaiveDate) -> i64 {
+    let months =
+        (end.year() - start.year()) as i64 * 12 + end.month() as i64 - start.month() as i64;
+    if end.day() < start.day() {
+        months - 1
+    } else {
+        months
+    }
+}
+
+/// Returns the difference between two dates in the unit used by `DATEDIF()`,
+/// or `None` if the unit is invalid. `start` must not be after `end`.
+fn datedif(start: NaiveDate, end: NaiveDate, unit: &str) -> Option<i64> {
+    let months = whole_months_between(start, end);
+    let days_since = |date: NaiveDate| (end - date).num_days();
+    Some(match unit.trim().to_ascii_uppercase().as_str() {
+        "Y" => months / 12,
+        "M" => months,
+        "D" => days_since(start),
+        "MD" => days_since(add_months(start, months)?),
+        "YM" => months % 12,
+        "YD" => days_since(add_months(start, months / 12 * 12)?),
+        _ => return None,
+    })
+}
+
+/// Returns the set of holidays given as the optional argument to
+/// `NETWORKDAYS()` or `WORKDAY()`.
+fn holidays(arg: Option<&Spanned<Value>>) -> FormulaResult<HashSet<NaiveDate>> {
+    Ok(match arg {
+        Some(arg) => arg.to_dates()?.into_iter().collect(),
+        None => HashSet::new(),
+    })
+}
+
+/// Returns whether a date is a weekday that is not a holiday.
+fn is_workday(date: NaiveDate, holidays: &HashSet<NaiveDate>) -> bool {
+    date.weekday().num_days_from_monday() < 5 && !holidays.contains(&date)
+}
+
+fn networkdays(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=3)?;
+    let start = args.inner[0].clone().into_single_value()?.to_date()?;
+    let end = args.inner[1].clone().into_single_value()?.to_date()?;
+    let holidays = holidays(args.inner.get(2))?;
+
+    let (first, last, sign) = if start <= end {
+        (start, end, 1.0)
+    } else {
+        (end, start, -1.0)
+    };
+    let count = first
+        .iter_days()
+        .take_while(|&date| date <= last)
+        .filter(|&date| is_workday(date, &holidays))
+        .count();
+    Ok(Value::Number(sign * count as f64))
+}
+
+fn workday(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=3)?;
+    let start = args.inner[0].clone().into_single_value()?;
+    let days = args.inner[1]
+        .clone()
+        .into_single_value()?
+        .to_number()?
+        .trunc();
+    let holidays = holidays(args.inner.get(2))?;
+
+    let step = Duration::days(if days < 0.0 { -1 } else { 1 });
+    let mut date = start.to_date()?;
+    let mut remaining = days.abs();
+    while remaining > 0.0 {
+        date = date
+            .checked_add_signed(step)
+            .filter(|d| (0..=9999).contains(&d.year()))
+            .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(args.span))?;
+        if is_workday(date, &holidays) {
+            remaining -= 1.0;
+        }
+    }
+    Ok(Value::Date(date))
+}
+
+/// Value used in arithmetic, which may be a point in time, a length of time,
+/// or a plain number.
+#[derive(Debug, Copy, Clone)]
+enum Temporal {
+    /// Date or date-time, and whether it is a date without a time of day.
+    Point(NaiveDateTime, bool),
+    Span(Duration),
+    Number(f64),
+}
+impl Temporal {
+    /// Classifies a value for arithmetic. Text is parsed as a number if
+    /// possible, or else as a date or time.
+    fn new(value: &Spanned<Value>) -> FormulaResult<Self> {
+        Ok(match &value.inner {
+            Value::Date(d) => Self::Point(d.and_time(NaiveTime::MIN), true),
+            Value::DateTime(dt) => Self::Point(*dt, false),
+            Value::Duration(d) => Self::Span(*d),
+            Value::String(s) if parse_number(s).is_none() => match parse_date_time(s) {
+                Some(parsed) => Self::new(&Spanned {
+                    span: value.span,
+                    inner: parsed,
+                })?,
+                None => Self::Number(value.to_number()?),
+            },
+            _ => Self::Number(value.to_number()?),
+        })
+    }
+
+    fn to_serial(self) -> f64 {
+        match self {
+            Self::Point(dt, _) => datetime_to_serial(dt),
+            Self::Span(d) => duration_to_serial(d),
+            Self::Number(n) => n,
+        }
+    }
+    fn to_duration(self, span: Span) -> FormulaResult<Duration> {
+        match self {
+            Self::Span(d) => Ok(d),
+            other => serial_to_duration(other.to_serial())
+                .ok_or_else(|| FormulaErrorMsg::Overflow.with_span(span)),
+        }
+    }
+}
+
+/// Moves a date or date-time by `offset`. The result is a date if `point` is
+/// a date and `offset` is a whole number of days.
+fn offset_point(
+    point: NaiveDateTime,
+    is_date: bool,
+    offset: Duration,
+    span: Span,
+) -> FormulaResult<Value> {
+    let result = point
+        .checked_add_signed(offset)
+        .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(span))?;
+    let whole_days = (offset - Duration::days(offset.num_days())).is_zero();
+    Ok(if is_date && whole_days {
+        Value::Date(result.date())
+    } else {
+        Value::DateTime(result)
+    })
+}
+
+/// Adds two values, either of which may be a date or duration. Adding a
+/// number to a date adds that many days.
+pub fn add(a: Spanned<Value>, b: Spanned<Value>) -> FormulaResult<Value> {
+    let span = Span::merge(&a, &b);
+    match (Temporal::new(&a)?, Temporal::new(&b)?) {
+        (Temporal::Number(x), Temporal::Number(y)) => Ok(Value::Number(x + y)),
+        // Adding two dates doesn't mean much, so just add the serial numbers.
+        (x @ Temporal::Point(..), y @ Temporal::Point(..)) => {
+            Ok(Value::Number(x.to_serial() + y.to_serial()))
+        }
+        (Temporal::Point(point, is_date), other) | (other, Temporal::Point(point, is_date)) => {
+            offset_point(point, is_date, other.to_duration(span)?, span)
+        }
+        (x, y) => x
+            .to_duration(span)?
+            .checked_add(&y.to_duration(span)?)
+            .map(Value::Duration)
+            .ok_or_else(|| FormulaErrorMsg::Overflow.with_span(span)),
+    }
+}
+
+/// Subtracts two values, either of which may be a date or duration. The
+/// difference between two dates is a number of days, and the difference
+/// between two date-times is a duration.
+pub fn subtract(a: Spanned<Value>, b: Spanned<Value>) -> FormulaResult<Value> {
+    let span = Span::merge(&a, &b);
+    match (Temporal::new(&a)?, Temporal::new(&b)?) {
+        (Temporal::Number(x), Temporal::Number(y)) => Ok(Value::Number(x - y)),
+        (Temporal::Point(x, true), Temporal::Point(y, true)) => {
+            Ok(Value::Number((x - y).num_days() as f64))
+        }
+        (Temporal::Point(x, _), Temporal::Point(y, _)) => Ok(Value::Duration(x - y)),
+        (Temporal::Point(point, is_date), other) => {
+            offset_point(point, is_date, -other.to_duration(span)?, span)
+        }
+        (x, y @ Temporal::Point(..)) => Ok(Value::Number(x.to_serial() - y.to_serial())),
+        (x, y) => x
+            .to_duration(span)?
+            .checked_sub(&y.to_duration(span)?)
+            .map(Value::Duration)
+            .ok_or_else(|| FormulaErrorMsg::Overflow.with_span(span)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_date_parts() {
+        let g = &mut PanicGridMock;
+        assert_eq!("2024-01-15", eval_to_string(g, "DATE(2024, 1, 15)"));
+        // Months and days overflow into the next year or month.
+        assert_eq!("2025-02-01", eval_to_string(g, "DATE(2024, 14, 1)"));
+        assert_eq!("2024-03-01", eval_to_string(g, "DATE(2024, 2, 30)"));
+        assert_eq!("2023-12-31", eval_to_string(g, "DATE(2024, 1, 0)"));
+        assert_eq!("1999-05-01", eval_to_string(g, "DATE(99, 5, 1)"));
+        assert_eq!(
+            FormulaErrorMsg::InvalidDate,
+            eval(g, "DATE(10000, 1, 1)").unwrap_err().msg,
+        );
+
+        assert_eq!("13:45:30", eval_to_string(g, "TIME(13, 45, 30)"));
+        assert_eq!("1:00:00", eval_to_string(g, "TIME(25, 0, 0)"));
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "TIME(-1, 0, 0)").unwrap_err().msg.kind(),
+        );
+
+        assert_eq!("2024", eval_to_string(g, "YEAR('2024-01-15')"));
+        assert_eq!("1", eval_to_string(g, "MONTH('1/15/2024')"));
+        assert_eq!("15", eval_to_string(g, "DAY('15-Jan-2024')"));
+        assert_eq!("15", eval_to_string(g, "DAY('January 15, 2024')"));
+        assert_eq!("{2024, 2025}", eval_to_string(g, "YEAR({45306, 45700})"));
+        assert_eq!("13", eval_to_string(g, "HOUR('2024-01-15T13:45:30')"));
+        assert_eq!("13", eval_to_string(g, "HOUR('1:45 PM')"));
+        assert_eq!("45", eval_to_string(g, "MINUTE(TIME(13, 45, 30))"));
+        assert_eq!("30", eval_to_string(g, "SECOND('13:45:30')"));
+        assert_eq!("12", eval_to_string(g, "HOUR(0.5)"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "YEAR('soon')").unwrap_err().msg.kind()
+        );
+    }
+
+    #[test]
+    fn test_date_serial_numbers() {
+        let g = &mut PanicGridMock;
+        assert_eq!("45306", eval_to_string(g, "DATE(2024, 1, 15) * 1"));
+        assert_eq!("61", eval_to_string(g, "DATE(1900, 3, 1) * 1"));
+        assert_eq!("45306.5", eval_to_string(g, "'2024-01-15 12:00' * 1"));
+        assert_eq!("0.25", eval_to_string(g, "TIME(6, 0, 0) * 1"));
+        assert_eq!("45306", eval_to_string(g, "'2024-01-15' * 1"));
+        assert_eq!("2024-01-15", eval_to_string(g, "DATEVALUE('1/15/2024')"));
+        assert_eq!(
+            "2024-01-15",
+            eval_to_string(g, "DATEVALUE('2024-01-15 08:00')")
+        );
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "DATEVALUE('tomorrow')").unwrap_err().msg.kind(),
+        );
+    }
+
+    #[test]
+    fn test_date_arithmetic() {
+        let g = &mut PanicGridMock;
+        assert_eq!("2024-01-22", eval_to_string(g, "DATE(2024, 1, 15) + 7"));
+        assert_eq!("2024-01-08", eval_to_string(g, "'2024-01-15' - 7"));
+        assert_eq!(
+            "2024-01-15 12:00:00",
+            eval_to_string(g, "DATE(2024, 1, 15) + 0.5"),
+        );
+        assert_eq!(
+            "2024-01-15 13:45:00",
+            eval_to_string(g, "DATE(2024, 1, 15) + TIME(13, 45, 0)"),
+        );
+        assert_eq!(
+            "31",
+            eval_to_string(g, "DATE(2024, 3, 1) - DATE(2024, 1, 30)")
+        );
+        assert_eq!(
+            "26:30:00",
+            eval_to_string(g, "'2024-01-16 14:30' - '2024-01-15 12:00'"),
+        );
+        assert_eq!(
+            "2:30:00",
+            eval_to_string(g, "TIME(2, 0, 0) + TIME(0, 30, 0)")
+        );
+        assert_eq!("3", eval_to_string(g, "1 + 2"));
+    }
+
+    #[test]
+    fn test_weekday_and_weeknum() {
+        let g = &mut PanicGridMock;
+        // 2024-01-15 is a Monday.
+        assert_eq!("2", eval_to_string(g, "WEEKDAY(DATE(2024, 1, 15))"));
+        assert_eq!("1", eval_to_string(g, "WEEKDAY(DATE(2024, 1, 15), 2)"));
+        assert_eq!("0", eval_to_string(g, "WEEKDAY(DATE(2024, 1, 15), 3)"));
+        assert_eq!("7", eval_to_string(g, "WEEKDAY(DATE(2024, 1, 15), 12)"));
+        assert_eq!("2", eval_to_string(g, "WEEKDAY(DATE(2024, 1, 15), 17)"));
+        assert_eq!(
+            FormulaErrorMsg::InvalidArgument,
+            eval(g, "WEEKDAY(DATE(2024, 1, 15), 4)").unwrap_err().msg,
+        );
+
+        // 2023-01-01 is a Sunday.
+        assert_eq!("1", eval_to_string(g, "WEEKNUM(DATE(2023, 1, 1))"));
+        assert_eq!("1", eval_to_string(g, "WEEKNUM(DATE(2023, 1, 2))"));
+        assert_eq!("2", eval_to_string(g, "WEEKNUM(DATE(2023, 1, 2), 2)"));
+        assert_eq!("2", eval_to_string(g, "WEEKNUM(DATE(2023, 1, 8))"));
+        assert_eq!("52", eval_to_string(g, "WEEKNUM(DATE(2023, 1, 1), 21)"));
+        assert_eq!("53", eval_to_string(g, "WEEKNUM(DATE(2023, 12, 31))"));
+    }
+
+    #[test]
+    fn test_month_arithmetic() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            "2024-02-29",
+            eval_to_string(g, "EDATE(DATE(2024, 1, 31), 1)")
+        );
+        assert_eq!(
+            "2023-11-30",
+            eval_to_string(g, "EDATE(DATE(2024, 1, 30), -2)")
+        );
+        assert_eq!(
+            "2024-02-29",
+            eval_to_string(g, "EOMONTH(DATE(2024, 2, 10), 0)")
+        );
+        assert_eq!(
+            "2023-12-31",
+            eval_to_string(g, "EOMONTH(DATE(2024, 2, 10), -2)")
+        );
+
+        let datedif = |unit: &str| {
+            eval_to_string(
+                &mut PanicGridMock,
+                &format!("DATEDIF(DATE(2021, 3, 20), DATE(2024, 1, 15), '{unit}')"),
+            )
+        };
+        assert_eq!("2", datedif("Y"));
+        assert_eq!("33", datedif("M"));
+        assert_eq!("1031", datedif("D"));
+        assert_eq!("26", datedif("MD"));
+        assert_eq!("9", datedif("YM"));
+        assert_eq!("301", datedif("yd"));
+        assert_eq!(
+            FormulaErrorMsg::InvalidArgument,
+            eval(g, "DATEDIF(DATE(2024, 1, 15), DATE(2021, 3, 20), 'Y')")
+                .unwrap_err()
+                .msg,
+        );
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "DATEDIF(1, 2, 'W')").unwrap_err().msg.kind()
+        );
+    }
+
+    #[test]
+    fn test_workdays() {
+        let g = &mut PanicGridMock;
+        // 2024-01-01 is a Monday.
+        assert_eq!(
+            "23",
+            eval_to_string(g, "NETWORKDAYS(DATE(2024, 1, 1), DATE(2024, 1, 31))"),
+        );
+        assert_eq!(
+            "21",
+            eval_to_string(
+                g,
+                "NETWORKDAYS('2024-01-01', '2024-01-31', {'2024-01-01', '2024-01-15', '2024-01-20'})",
+            ),
+        );
+        assert_eq!(
+            "-5",
+            eval_to_string(g, "NETWORKDAYS(DATE(2024, 1, 7), DATE(2024, 1, 1))"),
+        );
+        assert_eq!(
+            "2024-01-08",
+            eval_to_string(g, "WORKDAY(DATE(2024, 1, 5), 1)")
+        );
+        assert_eq!(
+            "2024-01-09",
+            eval_to_string(g, "WORKDAY(DATE(2024, 1, 5), 1, DATE(2024, 1, 8))"),
+        );
+        assert_eq!(
+            "2024-01-05",
+            eval_to_string(g, "WORKDAY(DATE(2024, 1, 8), -1)")
+        );
+        assert_eq!(
+            "2024-01-05",
+            eval_to_string(g, "WORKDAY(DATE(2024, 1, 5), 0)")
+        );
+    }
+
+    #[test]
+    fn test_today_and_now() {
+        let g = &mut PanicGridMock;
+        let now = chrono::NaiveDate::from_ymd_opt(2024, 1, 15)
+            .and_then(|d| d.and_hms_opt(13, 45, 0))
+            .unwrap();
+        let mut eval_at_now = |s: &str| {
+            let mut ctx = Ctx::new(g, Pos::ORIGIN);
+            ctx.now = now;
+            let value =
+                pollster::block_on(parse_formula(s, Pos::ORIGIN).unwrap().eval_in_ctx(&mut ctx));
+            (value.unwrap().inner.to_string(), ctx.volatile)
+        };
+        assert_eq!(("2024-01-15".to_string(), true), eval_at_now("TODAY()"));
+        assert_eq!(
+            ("2024-01-15 13:45:00".to_string(), true),
+            eval_at_now("NOW()")
+        );
+        assert_eq!(("2024-01-22".to_string(), true), eval_at_now("TODAY() + 7"));
+        assert_eq!(("1".to_string(), false), eval_at_now("DAY(2)"));
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, "TODAY(1)").unwrap_err().msg,
+        );
+    }
+}
@@ -0,0 +1,610 @@
+//! Financial functions, such as `NPV` and `PMT`.
+//!
+//! Money paid out is negative and money received is positive. Functions that
+//! take a payment `type` treat 0 as payments at the end of each period and
+//! anything else as payments at the beginning.
+
+use super::statistics::numbers;
+use super::*;
+
+/// Number of days per year used by `XNPV()` and `XIRR()`.
+const DAYS_PER_YEAR: f64 = 365.0;
+
+/// Rate that `IRR()`, `XIRR()`, and `RATE()` start from if none is given.
+const DEFAULT_GUESS: f64 = 0.1;
+
+/// Number of steps that `IRR()`, `XIRR()`, and `RATE()` take with each
+/// method before giving up.
+const DEFAULT_MAX_ITERATIONS: usize = 100;
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    // When adding new functions, also update the code editor completions list.
+    Some(match name {
+        // Cash flows
+        "npv" => |args| {
+            let Some((rate, flows)) = args.inner.split_first() else {
+                return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+            };
+            let rate_num = rate.clone().into_single_value()?.to_number()?;
+            if rate_num == -1.0 {
+                return Err(FormulaErrorMsg::DivideByZero.with_span(rate.span));
+            }
+            let flows = numbers(flows)?;
+            // The first cash flow is discounted by one period.
+            Ok(Value::Number(npv(rate_num, &flows) / (1.0 + rate_num)))
+        },
+        "xnpv" => |args| {
+            let [rate, values, dates] = args_array::<3>(args)?;
+            let rate_num = rate.into_single_value()?.to_number()?;
+            let flows = dated_cash_flows(&values, &dates)?;
+            if rate_num <= -1.0 {
+                return Err(FormulaErrorMsg::InvalidArgument.with_span(values.span));
+            }
+            Ok(Value::Number(xnpv(rate_num, &flows)))
+        },
+        "irr" => |args| {
+            check_arg_count(&args, 1..=2)?;
+            let span = args.span;
+            let mut args = args.inner.into_iter();
+            let flows = numbers(std::slice::from_ref(&args.next().unwrap()))?;
+            check_sign_change(&flows, span)?;
+            RootFinder::with_guess(args.next())?
+                .solve(|rate| npv(rate, &flows), span)
+                .map(Value::Number)
+        },
+        "xirr" => |args| {
+            check_arg_count(&args, 2..=3)?;
+            let span = args.span;
+            let mut args = args.inner.into_iter();
+            let (values, dates) = (args.next().unwrap(), args.next().unwrap());
+            let flows = dated_cash_flows(&values, &dates)?;
+            let amounts = flows.iter().map(|&(amount, _)| amount).collect_vec();
+            check_sign_change(&amounts, span)?;
+            RootFinder::with_guess(args.next())?
+                .solve(|rate| xnpv(rate, &flows), span)
+                .map(Value::Number)
+        },
+
+        // Loans and annuities
+        "pmt" => |args| {
+            array_map(
+                with_defaults(args, 3, &[0.0, 0.0])?,
+                |[rate, nper, pv, fv, t]| {
+                    let (rate, nper) = (rate.to_number()?, nonzero_periods(&nper)?);
+                    let (pv, fv, t) = (pv.to_number()?, fv.to_number()?, payment_type(&t)?);
+                    Ok(Value::Number(pmt(rate, nper, pv, fv, t)))
+                },
+            )
+        },
+        "ipmt" => |args| {
+            array_map(
+                with_defaults(args, 4, &[0.0, 0.0])?,
+                |[rate, per, nper, pv, fv, t]| {
+                    let (rate, per, nper) =
+                        (rate.to_number()?, period(&per, &nper)?, nper.to_number()?);
+                    let (pv, fv, t) = (pv.to_number()?, fv.to_number()?, payment_type(&t)?);
+                    Ok(Value::Number(ipmt(rate, per, nper, pv, fv, t)))
+                },
+            )
+        },
+        "ppmt" => |args| {
+            array_map(
+                with_defaults(args, 4, &[0.0, 0.0])?,
+                |[rate, per, nper, pv, fv, t]| {
+                    let (rate, per, nper) =
+                        (rate.to_number()?, period(&per, &nper)?, nper.to_number()?);
+                    let (pv, fv, t) = (pv.to_number()?, fv.to_number()?, payment_type(&t)?);
+                    let payment = pmt(rate, nper, pv, fv, t);
+                    Ok(Value::Number(payment - ipmt(rate, per, nper, pv, fv, t)))
+                },
+            )
+        },
+        "pv" => |args| {
+            array_map(
+                with_defaults(args, 3, &[0.0, 0.0])?,
+                |[rate, nper, pmt, fv, t]| {
+                    let span = rate.span;
+                    let (rate, nper) = (rate.to_number()?, nper.to_number()?);
+                    let (pmt, fv, t) = (pmt.to_number()?, fv.to_number()?, payment_type(&t)?);
+                    let pv = if rate == 0.0 {
+                        -(fv + pmt * nper)
+                    } else {
+                        let growth = (1.0 + rate).powf(nper);
+                        -(fv + pmt * (1.0 + rate * t) * (growth - 1.0) / rate) / growth
+                    };
+                    checked_number(pv, span)
+                },
+            )
+        },
+        "fv" => |args| {
+            array_map(
+                with_defaults(args, 3, &[0.0, 0.0])?,
+                |[rate, nper, pmt, pv, t]| {
+                    let span = rate.span;
+                    let (rate, nper) = (rate.to_number()?, nper.to_number()?);
+                    let (pmt, pv, t) = (pmt.to_number()?, pv.to_number()?, payment_type(&t)?);
+                    checked_number(fv(rate, nper, pmt, pv, t), span)
+                },
+            )
+        },
+        "nper" => |args| {
+            array_map(
+                with_defaults(args, 3, &[0.0, 0.0])?,
+                |[rate, pmt, pv, fv, t]| {
+                    let span = Span::merge(&rate, &t);
+                    let (rate, pmt) = (rate.to_number()?, pmt.to_number()?);
+                    let (pv, fv, t) = (pv.to_number()?, fv.to_number()?, payment_type(&t)?);
+                    let nper = if rate == 0.0 {
+                        -(pv + fv) / pmt
+                    } else {
+                        let adjusted_pmt = pmt * (1.0 + rate * t);
+                        ((adjusted_pmt - fv * rate) / (adjusted_pmt + pv * rate)).ln()
+                            / (1.0 + rate).ln()
+                    };
+                    checked_number(nper, span)
+                },
+            )
+        },
+        "rate" => |args| {
+            check_arg_count(&args, 3..=6)?;
+            let span = args.span;
+            let mut args = args.inner;
+            let guess = (args.len() == 6).then(|| args.pop()).flatten();
+            let args = with_defaults(Spanned { span, inner: args }, 3, &[0.0, 0.0])?;
+            let finder = RootFinder::with_guess(guess)?;
+            array_map(args, |[nper, pmt, pv, fv, t]| {
+                let nper = nonzero_periods(&nper)?;
+                let (pmt, pv, fv, t) = (
+                    pmt.to_number()?,
+                    pv.to_number()?,
+                    fv.to_number()?,
+                    payment_type(&t)?,
+                );
+                finder
+                    .solve(|rate| fv_of_rate(rate, nper, pmt, pv, t) + fv, span)
+                    .map(Value::Number)
+            })
+        },
+
+        // Depreciation
+        "sln" => array_mapped!(|[cost, salvage, life]| {
+            let depreciable = cost.to_number()? - salvage.to_number()?;
+            Ok(Value::Number(depreciable / divisor(&life)?))
+        }),
+        "ddb" => |args| {
+            array_map(
+                with_defaults(args, 4, &[2.0])?,
+                |[cost, salvage, life, per, factor]| {
+                    let cost = non_negative(&cost)?;
+                    let salvage = non_negative(&salvage)?;
+                    let life_num = positive(&life)?;
+                    let per_num = positive(&per)?;
+                    if per_num > life_num {
+                        return Err(FormulaErrorMsg::InvalidArgument.with_span(per.span));
+                    }
+                    let rate = positive(&factor)? / life_num;
+                    let (old_value, new_value) = if rate >= 1.0 {
+                        (if per_num == 1.0 { cost } else { 0.0 }, 0.0)
+                    } else {
+                        (
+                            cost * (1.0 - rate).powf(per_num - 1.0),
+                            cost * (1.0 - rate).powf(per_num),
+                        )
+                    };
+                    // Don't depreciate below the salvage value.
+                    let depreciation = old_value - new_value.max(salvage);
+                    Ok(Value::Number(depreciation.max(0.0)))
+                },
+            )
+        },
+
+        // Interest rates
+        "effect" => array_mapped!(|[nominal, npery]| {
+            let (nominal, npery) = (positive(&nominal)?, compounding_periods(&npery)?);
+            Ok(Value::Number((1.0 + nominal / npery).powf(npery) - 1.0))
+        }),
+        "nominal" => array_mapped!(|[effect, npery]| {
+            let (effect, npery) = (positive(&effect)?, compounding_periods(&npery)?);
+            Ok(Value::Number(
+                npery * ((1.0 + effect).powf(1.0 / npery) - 1.0),
+            ))
+        }),
+
+        _ => return None,
+    })
+}
+
+/// Method for finding an interest rate at which a function of the rate is
+/// zero. Newton's method is tried first, starting from `guess`. If it fails,
+/// bisection is used over an interval where the function changes sign.
+#[derive(Debug, Copy, Clone)]
+struct RootFinder {
+    /// Rate to start Newton's method from.
+    guess: f64,
+    /// Maximum number of steps for each method.
+    max_iterations: usize,
+    /// Difference between steps at which the result is considered exact.
+    tolerance: f64,
+}
+impl Default for RootFinder {
+    fn default() -> Self {
+        Self {
+            guess: DEFAULT_GUESS,
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            tolerance: 1e-12,
+        }
+    }
+}
+impl RootFinder {
+    /// Returns a root finder starting from the guess in an optional
+    /// argument.
+    fn with_guess(guess: Option<Spanned<Value>>) -> FormulaResult<Self> {
+        let mut ret = Self::default();
+        if let Some(guess) = guess {
+            ret.guess = guess.into_single_value()?.to_number()?;
+        }
+        Ok(ret)
+    }
+
+    /// Returns a rate greater than -1 at which `f` is zero, or an error if
+    /// none can be found.
+    fn solve(&self, f: impl Fn(f64) -> f64, span: Span) -> FormulaResult<f64> {
+        self.newton(&f)
+            .or_else(|| self.bisect(&f))
+            .ok_or_else(|| FormulaErrorMsg::NoConvergence.with_span(span))
+    }
+
+    fn newton(&self, f: &impl Fn(f64) -> f64) -> Option<f64> {
+        let mut x = self.guess;
+        for _ in 0..self.max_iterations {
+            let h = 1e-7 * x.abs().max(1.0);
+            let y = f(x);
+            let slope = (f(x + h) - f(x - h)) / (2.0 * h);
+            let next = x - y / slope;
+            if !next.is_finite() || next <= -1.0 {
+                return None;
+            }
+            if (next - x).abs() <= self.tolerance * next.abs().max(1.0) {
+                return Some(next);
+            }
+            x = next;
+        }
+        None
+    }
+
+    fn bisect(&self, f: &impl Fn(f64) -> f64) -> Option<f64> {
+        // Look for an interval where `f` changes sign.
+        let candidates = [
+            -0.999999, -0.99, -0.9, -0.5, -0.1, 0.0, 0.1, 0.5, 1.0, 2.0, 10.0, 100.0, 1000.0,
+        ];
+        let (mut low, mut high) = candidates
+            .into_iter()
+            .tuple_windows()
+            .find(|&(a, b)| f(a).is_finite() && f(b).is_finite() && f(a) * f(b) <= 0.0)?;
+        let low_is_negative = f(low) < 0.0;
+        for _ in 0..self.max_iterations {
+            let mid = (low + high) / 2.0;
+            if (high - low).abs() <= self.tolerance * mid.abs().max(1.0) {
+                return Some(mid);
+            }
+            if (f(mid) < 0.0) == low_is_negative {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        None
+    }
+}
+
+/// Fills in omitted optional arguments with default values, so that all the
+/// arguments can be passed to `array_map()`.
+fn with_defaults(
+    mut args: Spanned<Vec<Spanned<Value>>>,
+    required: usize,
+    defaults: &[f64],
+) -> FormulaResult<Spanned<Vec<Spanned<Value>>>> {
+    check_arg_count(&args, required..=required + defaults.len())?;
+    let given_optional = args.inner.len() - required;
+    for &default in &defaults[given_optional..] {
+        args.inner.push(Spanned {
+            span: args.span,
+            inner: Value::Number(default),
+        });
+    }
+    Ok(args)
+}
+
+/// Returns the net present value of cash flows at the start of each period.
+fn npv(rate: f64, flows: &[f64]) -> f64 {
+    flows
+        .iter()
+        .enumerate()
+        .map(|(i, flow)| flow / (1.0 + rate).powi(i as i32))
+        .sum()
+}
+
+/// Returns the net present value of cash flows on specific days, discounted
+/// to the first day.
+fn xnpv(rate: f64, flows: &[(f64, f64)]) -> f64 {
+    let first_day = flows.first().map_or(0.0, |&(_, day)| day);
+    flows
+        .iter()
+        .map(|(flow, day)| flow / (1.0 + rate).powf((day - first_day) / DAYS_PER_YEAR))
+        .sum()
+}
+
+/// Returns pairs of cash flows and serial day numbers. Returns an error if
+/// the arrays are different sizes or any day is before the first one.
+fn dated_cash_flows(
+    values: &Spanned<Value>,
+    dates: &Spanned<Value>,
+) -> FormulaResult<Vec<(f64, f64)>> {
+    let values_size = values.inner.array_size().unwrap_or((1, 1));
+    let dates_size = dates.inner.array_size().unwrap_or((1, 1));
+    if values_size != dates_size {
+        return Err(FormulaErrorMsg::ArraySizeMismatch {
+            expected: values_size,
+            got: dates_size,
+        }
+        .with_span(dates.span));
+    }
+    let element = |value: &Value, span: Span| {
+        Spanned {
+            span,
+            inner: value.clone(),
+        }
+        .to_number()
+    };
+    let flows = flat_values(values)
+        .zip(flat_values(dates))
+        .map(|(value, date)| {
+            Ok((
+                element(value, values.span)?,
+                element(date, dates.span)?.trunc(),
+            ))
+        })
+        .collect::<FormulaResult<Vec<_>>>()?;
+    let first_day = flows.first().map_or(0.0, |&(_, day)| day);
+    if flows.iter().any(|&(_, day)| day < first_day) {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(dates.span));
+    }
+    Ok(flows)
+}
+
+/// Returns an error unless there is at least one positive and one negative
+/// cash flow, without which there is no rate of return.
+fn check_sign_change(flows: &[f64], span: Span) -> FormulaResult<()> {
+    if flows.iter().any(|&x| x > 0.0) && flows.iter().any(|&x| x < 0.0) {
+        Ok(())
+    } else {
+        Err(FormulaErrorMsg::InvalidArgument.with_span(span))
+    }
+}
+
+/// Returns the future value of a loan or investment.
+fn fv(rate: f64, nper: f64, pmt: f64, pv: f64, t: f64) -> f64 {
+    -fv_of_rate(rate, nper, pmt, pv, t)
+}
+
+/// Returns the negated future value of a loan or investment. `RATE()` solves
+/// for the rate at which this cancels out the target future value.
+fn fv_of_rate(rate: f64, nper: f64, pmt: f64, pv: f64, t: f64) -> f64 {
+    if rate == 0.0 {
+        return pv + pmt * nper;
+    }
+    let growth = (1.0 + rate).powf(nper);
+    pv * growth + pmt * (1.0 + rate * t) * (growth - 1.0) / rate
+}
+
+/// Returns the payment each period for a loan or investment.
+fn pmt(rate: f64, nper: f64, pv: f64, fv: f64, t: f64) -> f64 {
+    if rate == 0.0 {
+        return -(pv + fv) / nper;
+    }
+    let growth = (1.0 + rate).powf(nper);
+    -rate * (pv * growth + fv) / ((1.0 + rate * t) * (growth - 1.0))
+}
+
+/// Returns the interest portion of the payment in period `per`.
+fn ipmt(rate: f64, per: f64, nper: f64, pv: f64, fv_target: f64, t: f64) -> f64 {
+    let payment = pmt(rate, nper, pv, fv_target, t);
+    let interest = if per == 1.0 {
+        // Payments at the beginning of the first period pay no interest.
+        if t == 1.0 {
+            0.0
+        } else {
+            -pv
+        }
+    } else if t == 1.0 {
+        fv(rate, per - 2.0, payment, pv, 1.0) - payment
+    } else {
+        fv(rate, per - 1.0, payment, pv, 0.0)
+    };
+    interest * rate
+}
+
+/// Returns 1 for payments at the beginning of each period, or 0 for
+/// payments at the end.
+fn payment_type(value: &Spanned<Value>) -> FormulaResult<f64> {
+    Ok(if value.to_number()? == 0.0 { 0.0 } else { 1.0 })
+}
+
+/// Returns a number of periods, or an error if it is zero.
+fn nonzero_periods(value: &Spanned<Value>) -> FormulaResult<f64> {
+    let n = value.to_number()?;
+    if n == 0.0 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(value.span));
+    }
+    Ok(n)
+}
+
+/// Returns a period number, or an error if it is not between 1 and `nper`.
+fn period(per: &Spanned<Value>, nper: &Spanned<Value>) -> FormulaResult<f64> {
+    let per_num = per.to_number()?;
+    if per_num < 1.0 || per_num > nper.to_number()? {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(per.span));
+    }
+    Ok(per_num)
+}
+
+/// Returns the number of compounding periods per year, truncated to an
+/// integer, or an error if it is less than 1.
+fn compounding_periods(value: &Spanned<Value>) -> FormulaResult<f64> {
+    let n = value.to_number()?.trunc();
+    if n < 1.0 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(value.span));
+    }
+    Ok(n)
+}
+
+/// Returns a number, or an error if it is not positive.
+fn positive(value: &Spanned<Value>) -> FormulaResult<f64> {
+    let n = value.to_number()?;
+    if n <= 0.0 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(value.span));
+    }
+    Ok(n)
+}
+
+/// Returns a number, or an error if it is negative.
+fn non_negative(value: &Spanned<Value>) -> FormulaResult<f64> {
+    let n = value.to_number()?;
+    if n < 0.0 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(value.span));
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{npv, RootFinder};
+    use crate::formulas::tests::*;
+
+    fn eval_rounded(g: &mut PanicGridMock, s: &str) -> String {
+        eval_to_string(g, &format!("ROUND({s}, 6)"))
+    }
+
+    #[test]
+    fn test_cash_flows() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            "1188.443412",
+            eval_rounded(g, "NPV(0.1, -10000, 3000, 4200, 6800)"),
+        );
+        assert_eq!(
+            "1188.443412",
+            eval_rounded(g, "NPV(0.1, {-10000, 3000; 4200, 6800})"),
+        );
+        assert_eq!(
+            FormulaErrorMsg::DivideByZero,
+            eval(g, "NPV(-1, 100)").unwrap_err().msg,
+        );
+
+        assert_eq!("0.1", eval_rounded(g, "IRR({-100, 110})"));
+        assert_eq!("0.088963", eval_rounded(g, "IRR({-1000; 300; 400; 500})"));
+        assert_eq!(
+            "0.088963",
+            eval_rounded(g, "IRR({-1000; 300; 400; 500}, -0.5)")
+        );
+        assert_eq!(
+            FormulaErrorMsg::InvalidArgument,
+            eval(g, "IRR({100, 100})").unwrap_err().msg,
+        );
+
+        let values = "{-10000, 2750, 4250, 3250, 2750}";
+        let dates = "{'2008-01-01', '2008-03-01', '2008-10-30', '2009-02-15', '2009-04-01'}";
+        assert_eq!(
+            "2086.647602",
+            eval_rounded(g, &format!("XNPV(0.09, {values}, {dates})")),
+        );
+        assert_eq!(
+            "0.373363",
+            eval_rounded(g, &format!("XIRR({values}, {dates})")),
+        );
+        let error = eval(g, &format!("XIRR({values}, {{'2008-01-01'}})")).unwrap_err();
+        assert!(matches!(
+            error.msg,
+            FormulaErrorMsg::ArraySizeMismatch { .. },
+        ));
+    }
+
+    #[test]
+    fn test_loans() {
+        let g = &mut PanicGridMock;
+        assert_eq!("-1037.032089", eval_rounded(g, "PMT(0.08 / 12, 10, 10000)"));
+        assert_eq!("-1000", eval_rounded(g, "PMT(0, 10, 10000)"));
+        assert_eq!(
+            "{-1037.032089, -1030.164327}",
+            eval_rounded(g, "PMT(0.08 / 12, 10, 10000, 0, {0, 1})"),
+        );
+        assert_eq!("-66.666667", eval_rounded(g, "IPMT(0.1 / 12, 1, 36, 8000)"));
+        assert_eq!("-75.623186", eval_rounded(g, "PPMT(0.1 / 12, 1, 24, 2000)"));
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "IPMT(0.1, 5, 4, 100)").unwrap_err().msg.kind()
+        );
+        assert_eq!(
+            "-59777.145851",
+            eval_rounded(g, "PV(0.08 / 12, 12 * 20, 500)")
+        );
+        assert_eq!(
+            "2581.403374",
+            eval_rounded(g, "FV(0.06 / 12, 10, -200, -500, 1)"),
+        );
+        assert_eq!(
+            "59.673866",
+            eval_rounded(g, "NPER(0.12 / 12, -100, -1000, 10000, 1)"),
+        );
+        assert_eq!("0.007701", eval_rounded(g, "RATE(48, -200, 8000)"));
+        assert_eq!(
+            "0.007701",
+            eval_rounded(g, "RATE(48, -200, 8000, 0, 0, 0.5)")
+        );
+
+        // There is no rate at which these payments add up to nothing.
+        let error = eval(g, "RATE(10, 100, 100)").unwrap_err();
+        assert_eq!(FormulaErrorMsg::NoConvergence, error.msg);
+        assert_eq!(ErrorKind::Num, error.msg.kind());
+    }
+
+    #[test]
+    fn test_root_finder_iteration_cap() {
+        let flows = [-100.0, 60.0, 60.0];
+        let f = |rate| npv(rate, &flows);
+        let span = Span::empty(0);
+
+        let rate = RootFinder::default().solve(f, span).unwrap();
+        assert!((rate - 0.130662).abs() < 1e-6, "wrong rate {rate}");
+
+        // Neither method converges in only two steps.
+        let finder = RootFinder {
+            max_iterations: 2,
+            ..RootFinder::default()
+        };
+        assert_eq!(
+            FormulaErrorMsg::NoConvergence,
+            finder.solve(f, span).unwrap_err().msg,
+        );
+    }
+
+    #[test]
+    fn test_depreciation_and_rates() {
+        let g = &mut PanicGridMock;
+        assert_eq!("2250", eval_to_string(g, "SLN(30000, 7500, 10)"));
+        assert_eq!("1.315068", eval_rounded(g, "DDB(2400, 300, 10 * 365, 1)"));
+        assert_eq!("480", eval_rounded(g, "DDB(2400, 300, 10, 1, 2)"));
+        assert_eq!("22.122547", eval_rounded(g, "DDB(2400, 300, 10, 10)"));
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "DDB(2400, 300, 10, 11)").unwrap_err().msg.kind()
+        );
+
+        assert_eq!("0.053543", eval_rounded(g, "EFFECT(0.0525, 4)"));
+        assert_eq!("0.0525", eval_rounded(g, "NOMINAL(0.053543, 4)"));
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "EFFECT(0.05, 0)").unwrap_err().msg.kind()
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Information functions, such as `ISERROR` and `TYPE`.
+
+use super::*;
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    Some(match name {
+        "na" => constant_function!(Err(
+            FormulaErrorMsg::ErrorValue(ErrorKind::NotAvailable).without_span()
+        )),
+        "iserror" => array_mapped!(|[value]| Ok(Value::Bool(value.inner.error().is_some()))),
+        "iserr" => array_mapped!(|[value]| {
+            Ok(Value::Bool(
+                value
+                    .inner
+                    .error()
+                    .is_some_and(|e| e.msg.kind() != ErrorKind::NotAvailable),
+            ))
+        }),
+        "isna" => array_mapped!(|[value]| {
+            Ok(Value::Bool(
+                value
+                    .inner
+                    .error()
+                    .is_some_and(|e| e.msg.kind() == ErrorKind::NotAvailable),
+            ))
+        }),
+        "error.type" => array_mapped!(|[value]| match value.inner.error() {
+            Some(e) => Ok(Value::Number(e.msg.kind().error_type_number() as f64)),
+            None => Err(FormulaErrorMsg::ErrorValue(ErrorKind::NotAvailable).with_span(value.span)),
+        }),
+
+        // Value types
+        "isblank" => array_mapped!(|[value]| Ok(Value::Bool(is_blank(&value.inner)))),
+        "isnumber" => array_mapped!(|[value]| {
+            Ok(Value::Bool(matches!(
+                value.inner,
+                Value::Number(_) | Value::Date(_) | Value::DateTime(_) | Value::Duration(_),
+            )))
+        }),
+        "istext" => {
+            array_mapped!(|[value]| Ok(Value::Bool(matches!(value.inner, Value::String(_)))))
+        }
+        "islogical" => {
+            array_mapped!(|[value]| Ok(Value::Bool(matches!(value.inner, Value::Bool(_)))))
+        }
+        "iseven" => array_mapped!(|[n]| Ok(Value::Bool(n.to_number()?.trunc() % 2.0 == 0.0))),
+        "isodd" => array_mapped!(|[n]| Ok(Value::Bool(n.to_number()?.trunc() % 2.0 != 0.0))),
+        "type" => |args| {
+            let [value] = args_array(args)?;
+            Ok(Value::Number(type_number(&value.inner)))
+        },
+        "n" => array_mapped!(|[value]| match &value.inner {
+            Value::String(_) => Ok(Value::Number(0.0)),
+            _ => value.to_number().map(Value::Number),
+        }),
+        "t" => array_mapped!(|[value]| match value.inner {
+            Value::String(s) => Ok(Value::String(s)),
+            Value::Error(e) => Err(*e),
+            _ => Ok(Value::String(String::new())),
+        }),
+
+        _ => return None,
+    })
+}
+
+/// Returns the number that `TYPE()` returns for a value.
+fn type_number(value: &Value) -> f64 {
+    match value {
+        Value::Blank
+        | Value::Number(_)
+        | Value::Date(_)
+        | Value::DateTime(_)
+        | Value::Duration(_) => 1.0,
+        Value::String(_) => 2.0,
+        Value::Bool(_) => 4.0,
+        Value::Error(_) => 16.0,
+        Value::Array(_) => 64.0,
+        Value::Lambda(_) => 128.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_error_functions() {
+        let g = &mut PanicGridMock;
+        assert_eq!("TRUE", eval_to_string(g, "ISERROR(NA())"));
+        assert_eq!("TRUE", eval_to_string(g, "ISNA(NA())"));
+        assert_eq!("FALSE", eval_to_string(g, "ISERR(NA())"));
+        assert_eq!("TRUE", eval_to_string(g, "ISERR(1 + 'a')"));
+        assert_eq!("FALSE", eval_to_string(g, "ISNA(1 + 'a')"));
+        assert_eq!("FALSE", eval_to_string(g, "ISERROR(1)"));
+        assert_eq!("7", eval_to_string(g, "ERROR.TYPE(NA())"));
+        assert_eq!("3", eval_to_string(g, "ERROR.TYPE(1 + 'a')"));
+        assert_eq!("5", eval_to_string(g, "ERROR.TYPE(NOTAFUNCTION())"));
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "ERROR.TYPE(1)").unwrap_err().msg.kind(),
+        );
+        assert_eq!("fallback", eval_to_string(g, "IFNA(NA(), 'fallback')"));
+        assert_eq!("fallback", eval_to_string(g, "IFERROR(NA(), 'fallback')"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "IFNA(1 + 'a', 'fallback')").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            "{1, fallback}",
+            eval_to_string(g, "IFERROR({1, NA()}, 'fallback')")
+        );
+    }
+
+    #[test]
+    fn test_blank_values() {
+        // A1 contains a number, B1 contains text, A2 contains empty text, and
+        // B2 is empty.
+        make_stateless_grid_mock!(|pos| match (pos.x, pos.y) {
+            (0, 1) => Some("1".to_string()),
+            (1, 1) => Some("x".to_string()),
+            (0, 2) => Some(String::new()),
+            _ => None,
+        });
+        let g = &mut GridMock;
+
+        assert_eq!("TRUE", eval_to_string(g, "ISBLANK(B2)"));
+        assert_eq!("FALSE", eval_to_string(g, "ISBLANK(A2)"));
+        assert_eq!(
+            "{FALSE, FALSE; FALSE, TRUE}",
+            eval_to_string(g, "ISBLANK(A1:B2)")
+        );
+        assert_eq!("3", eval_to_string(g, "COUNTA(A1:B2)"));
+        assert_eq!("1", eval_to_string(g, "COUNTBLANK(A1:B2)"));
+
+        // Blank values only become `0` or `""` when needed.
+        assert_eq!("1", eval_to_string(g, "B2 + 1"));
+        assert_eq!("ab", eval_to_string(g, "'a' & B2 & 'b'"));
+        assert_eq!("TRUE", eval_to_string(g, "B2 = 0"));
+        assert_eq!("TRUE", eval_to_string(g, "B2 = ''"));
+        assert_eq!("TRUE", eval_to_string(g, "B2 = FALSE()"));
+        assert_eq!("FALSE", eval_to_string(g, "A2 = 0"));
+        assert_eq!("", eval_to_string(g, "B2"));
+    }
+
+    #[test]
+    fn test_type_functions() {
+        let g = &mut PanicGridMock;
+        assert_eq!("TRUE", eval_to_string(g, "ISNUMBER(3)"));
+        assert_eq!("TRUE", eval_to_string(g, "ISNUMBER(DATE(2024, 1, 1))"));
+        assert_eq!("FALSE", eval_to_string(g, "ISNUMBER('3')"));
+        assert_eq!("{TRUE, FALSE}", eval_to_string(g, "ISTEXT({'a', 1})"));
+        assert_eq!("TRUE", eval_to_string(g, "ISTEXT('')"));
+        assert_eq!("TRUE", eval_to_string(g, "ISLOGICAL(TRUE())"));
+        assert_eq!("FALSE", eval_to_string(g, "ISLOGICAL('TRUE')"));
+        assert_eq!(
+            "{TRUE, FALSE, TRUE}",
+            eval_to_string(g, "ISEVEN({-2, 3.5, 0})")
+        );
+        assert_eq!(
+            "{FALSE, TRUE, TRUE}",
+            eval_to_string(g, "ISODD({-2, 3.5, -1})")
+        );
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "ISEVEN('x')").unwrap_err().msg.kind(),
+        );
+
+        assert_eq!("1", eval_to_string(g, "TYPE(5)"));
+        assert_eq!("2", eval_to_string(g, "TYPE('5')"));
+        assert_eq!("4", eval_to_string(g, "TYPE(FALSE())"));
+        assert_eq!("16", eval_to_string(g, "TYPE(NA())"));
+        assert_eq!("64", eval_to_string(g, "TYPE({1, 2})"));
+        assert_eq!("128", eval_to_string(g, "TYPE(LAMBDA(x, x))"));
+
+        assert_eq!(
+            "{5, 0, 1, 0}",
+            eval_to_string(g, "N({5, 'x', TRUE(), FALSE()})")
+        );
+        assert_eq!("45292", eval_to_string(g, "N(DATE(2024, 1, 1))"));
+        assert_eq!("{x, , }", eval_to_string(g, "T({'x', 5, TRUE()})"));
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "T(NA())").unwrap_err().msg.kind(),
+        );
+    }
+
+    #[test]
+    fn test_typed_cells() {
+        /// Grid with a different type of value in each cell of row 1.
+        #[derive(Debug, Default, Copy, Clone)]
+        struct TypedGridMock;
+        #[async_trait(?Send)]
+        impl GridProxy for TypedGridMock {
+            async fn get(&mut self, _sheet: Option<&str>, pos: Pos) -> Cell {
+                match (pos.x, pos.y) {
+                    (0, 1) => Cell::Number(2.5),
+                    (1, 1) => Cell::Text("5".to_string()),
+                    (2, 1) => Cell::Bool(true),
+                    (3, 1) => Cell::Error(ErrorKind::NotAvailable),
+                    (4, 1) => Cell::Date(chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+                    _ => Cell::Empty,
+                }
+            }
+        }
+        let g = &mut TypedGridMock;
+
+        assert_eq!("TRUE", eval_to_string(g, "ISNUMBER(A1)"));
+        assert_eq!("3.5", eval_to_string(g, "A1 + 1"));
+
+        // Numeric text is still text, but is converted when a number is
+        // needed.
+        assert_eq!("FALSE", eval_to_string(g, "ISNUMBER(B1)"));
+        assert_eq!("TRUE", eval_to_string(g, "ISTEXT(B1)"));
+        assert_eq!("6", eval_to_string(g, "B1 + 1"));
+
+        assert_eq!("TRUE", eval_to_string(g, "ISLOGICAL(C1)"));
+        assert_eq!("TRUE", eval_to_string(g, "ISNA(D1)"));
+        assert_eq!("2024", eval_to_string(g, "YEAR(E1)"));
+        assert_eq!("TRUE", eval_to_string(g, "ISBLANK(F1)"));
+        assert_eq!(
+            "{TRUE, FALSE, FALSE, FALSE, TRUE, FALSE}",
+            eval_to_string(g, "ISNUMBER(A1:F1)"),
+        );
+    }
+
+    #[test]
+    fn test_isformula() {
+        /// Grid with a formula in B1, which records the cells whose values
+        /// are read.
+        #[derive(Debug, Default, Clone)]
+        struct FormulaGridMock {
+            cells_read: Vec<Pos>,
+        }
+        #[async_trait(?Send)]
+        impl GridProxy for FormulaGridMock {
+            async fn get(&mut self, _sheet: Option<&str>, pos: Pos) -> Cell {
+                self.cells_read.push(pos);
+                Cell::Empty
+            }
+            async fn is_formula(&mut self, _sheet: Option<&str>, pos: Pos) -> bool {
+                pos == Pos::new(1, 1)
+            }
+        }
+        let g = &mut FormulaGridMock::default();
+        assert_eq!("TRUE", eval_to_string(g, "ISFORMULA(B1)"));
+        assert_eq!("FALSE", eval_to_string(g, "ISFORMULA(A1)"));
+        assert_eq!(
+            FormulaErrorMsg::Expected {
+                expected: "cell reference".into(),
+                got: Some("numeric literal".into()),
+            },
+            eval(g, "ISFORMULA(1)").unwrap_err().msg,
+        );
+        assert_eq!(Vec::<Pos>::new(), g.cells_read);
+    }
+}
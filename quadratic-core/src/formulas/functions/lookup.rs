@@ -0,0 +1,735 @@
+//! Lookup functions, such as `VLOOKUP` and `INDEX`.
+
+use smallvec::smallvec;
+use std::cmp::Ordering;
+
+use super::*;
+use crate::formulas::value::{parse_date_time, parse_number};
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    Some(match name {
+        "vlookup" => |args| vlookup_or_hlookup(args, LookupDirection::Vertical),
+        "hlookup" => |args| vlookup_or_hlookup(args, LookupDirection::Horizontal),
+        "xlookup" => xlookup,
+        "index" => index,
+        "match" => match_,
+        "xmatch" => xmatch,
+        _ => return None,
+    })
+}
+
+/// Whether a lookup table is searched down its first column or across its
+/// first row.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum LookupDirection {
+    Vertical,
+    Horizontal,
+}
+
+/// How a lookup function decides whether a value matches.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum MatchMode {
+    /// Only an exact (case-insensitive) match.
+    Exact,
+    /// An exact match, or else the next smaller value.
+    ExactOrNextSmaller,
+    /// An exact match, or else the next larger value.
+    ExactOrNextLarger,
+    /// A match using `*`, `?`, and `~` wildcards.
+    Wildcard,
+}
+impl MatchMode {
+    /// Parses an `XLOOKUP`/`XMATCH` match mode argument.
+    fn from_arg(arg: Option<&Spanned<Value>>) -> FormulaResult<Self> {
+        let Some(arg) = arg else {
+            return Ok(Self::Exact);
+        };
+        match arg.to_integer()? {
+            0 => Ok(Self::Exact),
+            -1 => Ok(Self::ExactOrNextSmaller),
+            1 => Ok(Self::ExactOrNextLarger),
+            2 => Ok(Self::Wildcard),
+            _ => Err(FormulaErrorMsg::Expected {
+                expected: "match mode 0, -1, 1, or 2".into(),
+                got: Some(arg.inner.to_string().into()),
+            }
+            .with_span(arg.span)),
+        }
+    }
+}
+
+/// The order in which a lookup function searches.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SearchMode {
+    FirstToLast,
+    LastToFirst,
+    /// Binary search over values sorted in ascending order.
+    BinaryAscending,
+    /// Binary search over values sorted in descending order.
+    BinaryDescending,
+}
+impl SearchMode {
+    /// Parses an `XLOOKUP`/`XMATCH` search mode argument.
+    fn from_arg(arg: Option<&Spanned<Value>>) -> FormulaResult<Self> {
+        let Some(arg) = arg else {
+            return Ok(Self::FirstToLast);
+        };
+        match arg.to_integer()? {
+            1 => Ok(Self::FirstToLast),
+            -1 => Ok(Self::LastToFirst),
+            2 => Ok(Self::BinaryAscending),
+            -2 => Ok(Self::BinaryDescending),
+            _ => Err(FormulaErrorMsg::Expected {
+                expected: "search mode 1, -1, 2, or -2".into(),
+                got: Some(arg.inner.to_string().into()),
+            }
+            .with_span(arg.span)),
+        }
+    }
+}
+
+/// Value normalized for comparison by lookup functions.
+///
+/// Text only matches text, even if it looks like a number. Dates are compared
+/// by their serial numbers. Text comparison is case-insensitive.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum LookupKey {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Blank,
+}
+impl LookupKey {
+    pub(super) fn new(value: &Value) -> Self {
+        match value {
+            Value::Number(n) => Self::Number(*n),
+            Value::Bool(b) => Self::Bool(*b),
+            Value::String(s) if s.trim().is_empty() => Self::Blank,
+            Value::String(s) => Self::Text(s.to_lowercase()),
+            Value::Date(_) | Value::DateTime(_) | Value::Duration(_) => {
+                Self::Number(value.to_serial().unwrap_or_default())
+            }
+            _ => Self::Blank,
+        }
+    }
+
+    /// Returns the key for a value like `new()`, except that text that looks
+    /// like a number or date is treated as a number.
+    pub(super) fn parse(value: &Value) -> Self {
+        match value {
+            Value::String(s) => match parse_number(s).or_else(|| parse_date_time(s)?.to_serial()) {
+                Some(n) => Self::Number(n),
+                None => Self::new(value),
+            },
+            _ => Self::new(value),
+        }
+    }
+
+    /// Returns the rank of this key's type in the sort order used for binary
+    /// search: numbers, then text, then booleans, then blanks.
+    fn type_rank(&self) -> u8 {
+        match self {
+            Self::Number(_) => 0,
+            Self::Text(_) => 1,
+            Self::Bool(_) => 2,
+            Self::Blank => 3,
+        }
+    }
+
+    /// Compares two keys, ordering first by type and then by value.
+    pub(super) fn total_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Number(a), Self::Number(b)) => a.total_cmp(b),
+            (Self::Text(a), Self::Text(b)) => a.cmp(b),
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+
+    /// Compares two keys if they have the same type.
+    pub(super) fn partial_cmp_same_type(&self, other: &Self) -> Option<Ordering> {
+        (self.type_rank() == other.type_rank()).then(|| self.total_cmp(other))
+    }
+}
+
+/// Read-only view of a value as a rectangular array. A single value is
+/// treated as a 1x1 array.
+struct ArrayView<'a> {
+    rows: Vec<&'a [Value]>,
+    span: Span,
+}
+impl<'a> ArrayView<'a> {
+    fn new(value: &'a Spanned<Value>) -> Self {
+        let rows = match &value.inner {
+            Value::Array(a) => a.iter().map(|row| row.as_slice()).collect(),
+            other => vec![std::slice::from_ref(other)],
+        };
+        Self {
+            rows,
+            span: value.span,
+        }
+    }
+
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    fn row(&self, index: usize) -> Vec<&'a Value> {
+        self.rows[index].iter().collect()
+    }
+    fn col(&self, index: usize) -> Vec<&'a Value> {
+        self.rows.iter().map(|row| &row[index]).collect()
+    }
+
+    /// Returns the values of a single-row or single-column array, or an error
+    /// if the array has more than one row and more than one column.
+    fn to_vector(&self) -> FormulaResult<(LookupDirection, Vec<&'a Value>)> {
+        if self.width() == 1 {
+            Ok((LookupDirection::Vertical, self.col(0)))
+        } else if self.height() == 1 {
+            Ok((LookupDirection::Horizontal, self.row(0)))
+        } else {
+            Err(FormulaErrorMsg::Expected {
+                expected: "single row or column".into(),
+                got: Some(format!("{}x{} array", self.height(), self.width()).into()),
+            }
+            .with_span(self.span))
+        }
+    }
+}
+
+/// Returns a single-row array containing `values`.
+fn row_array(values: Vec<&Value>) -> Value {
+    Value::Array(vec![values.into_iter().cloned().collect()])
+}
+/// Returns a single-column array containing `values`.
+fn col_array(values: Vec<&Value>) -> Value {
+    Value::Array(values.into_iter().map(|v| smallvec![v.clone()]).collect())
+}
+
+/// Returns an error if `needle` is an array, since lookup functions search for
+/// a single value, or if `needle` is an error value.
+fn check_single_value(needle: &Spanned<Value>) -> FormulaResult<()> {
+    match &needle.inner {
+        Value::Array(_) => Err(FormulaErrorMsg::Expected {
+            expected: "single value".into(),
+            got: Some("array".into()),
+        }
+        .with_span(needle.span)),
+        Value::Error(e) => Err((**e).clone()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the 0-based index of the value in `haystack` that matches `needle`,
+/// or `None` if there is no match.
+fn find_match(
+    needle: &Value,
+    haystack: &[&Value],
+    match_mode: MatchMode,
+    search_mode: SearchMode,
+) -> Option<usize> {
+    let key = LookupKey::new(needle);
+    let keys = haystack.iter().map(|v| LookupKey::new(v)).collect_vec();
+
+    match search_mode {
+        SearchMode::FirstToLast => linear_search(&key, haystack, &keys, match_mode, 0..keys.len()),
+        SearchMode::LastToFirst => {
+            linear_search(&key, haystack, &keys, match_mode, (0..keys.len()).rev())
+        }
+        SearchMode::BinaryAscending => binary_search(&key, &keys, match_mode, false),
+        SearchMode::BinaryDescending => binary_search(&key, &keys, match_mode, true),
+    }
+}
+
+fn linear_search(
+    key: &LookupKey,
+    haystack: &[&Value],
+    keys: &[LookupKey],
+    match_mode: MatchMode,
+    indices: impl Clone + Iterator<Item = usize>,
+) -> Option<usize> {
+    if match_mode == MatchMode::Wildcard {
+        if let LookupKey::Text(pattern) = key {
+            let regex = wildcard_pattern_regex(pattern);
+            return indices.into_iter().find(|&i| {
+                keys[i] != LookupKey::Blank && regex.is_match(&haystack[i].to_string())
+            });
+        }
+    }
+
+    if let Some(i) = indices.clone().find(|&i| keys[i] == *key) {
+        return Some(i);
+    }
+
+    // Find the closest value in the right direction, preferring the first
+    // one encountered in search order.
+    let wanted = match match_mode {
+        MatchMode::ExactOrNextSmaller => Ordering::Less,
+        MatchMode::ExactOrNextLarger => Ordering::Greater,
+        MatchMode::Exact | MatchMode::Wildcard => return None,
+    };
+    let mut best: Option<usize> = None;
+    for i in indices {
+        if keys[i].partial_cmp_same_type(key) != Some(wanted) {
+            continue;
+        }
+        let is_closer = match best {
+            None => true,
+            Some(b) => keys[i].total_cmp(&keys[b]) == wanted.reverse(),
+        };
+        if is_closer {
+            best = Some(i);
+        }
+    }
+    best
+}
+
+fn binary_search(
+    key: &LookupKey,
+    keys: &[LookupKey],
+    match_mode: MatchMode,
+    descending: bool,
+) -> Option<usize> {
+    let cmp = |item: &LookupKey| {
+        let ordering = item.total_cmp(key);
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+
+    // Index of the first item that is not before `key` in sort order.
+    let i = keys.partition_point(|item| cmp(item) == Ordering::Less);
+    if keys.get(i).is_some_and(|item| cmp(item) == Ordering::Equal) {
+        return Some(i);
+    }
+
+    let candidate = match (match_mode, descending) {
+        (MatchMode::Exact | MatchMode::Wildcard, _) => None,
+        // The item just before `i` in sort order.
+        (MatchMode::ExactOrNextSmaller, false) | (MatchMode::ExactOrNextLarger, true) => {
+            i.checked_sub(1)
+        }
+        // The item at `i` in sort order.
+        (MatchMode::ExactOrNextLarger, false) | (MatchMode::ExactOrNextSmaller, true) => {
+            (i < keys.len()).then_some(i)
+        }
+    };
+    candidate.filter(|&i| keys[i].partial_cmp_same_type(key).is_some())
+}
+
+/// Returns the index of the last value that is not after `needle` in sorted
+/// data, as used by `MATCH` and approximate `VLOOKUP`/`HLOOKUP`.
+fn approximate_match(needle: &Value, haystack: &[&Value], descending: bool) -> Option<usize> {
+    let key = LookupKey::new(needle);
+    let keys = haystack.iter().map(|v| LookupKey::new(v)).collect_vec();
+    let i = keys.partition_point(|item| {
+        let ordering = item.total_cmp(&key);
+        let ordering = if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        ordering != Ordering::Greater
+    });
+    i.checked_sub(1)
+        .filter(|&i| keys[i].partial_cmp_same_type(&key).is_some())
+}
+
+/// Returns the index of the first exact match for `needle`, allowing
+/// wildcards if `needle` is text.
+fn exact_match(needle: &Value, haystack: &[&Value]) -> Option<usize> {
+    let match_mode = match needle {
+        Value::String(s) if contains_wildcards(s) => MatchMode::Wildcard,
+        _ => MatchMode::Exact,
+    };
+    find_match(needle, haystack, match_mode, SearchMode::FirstToLast)
+}
+
+fn no_match(args: &Spanned<Vec<Spanned<Value>>>) -> FormulaError {
+    FormulaErrorMsg::NoMatch.with_span(args.span)
+}
+
+/// Converts a 1-based index argument into a 0-based index less than `len`.
+fn index_arg(arg: &Spanned<Value>, len: usize) -> FormulaResult<usize> {
+    let i = arg.to_integer()?;
+    if 1 <= i && i <= len as i64 {
+        Ok(i as usize - 1)
+    } else {
+        Err(FormulaErrorMsg::IndexOutOfBounds.with_span(arg.span))
+    }
+}
+
+/// Implements `VLOOKUP(value, table, index, [is_sorted])` and
+/// `HLOOKUP(value, table, index, [is_sorted])`.
+fn vlookup_or_hlookup(
+    args: Spanned<Vec<Spanned<Value>>>,
+    direction: LookupDirection,
+) -> FormulaResult<Value> {
+    check_arg_count(&args, 3..=4)?;
+    let needle = &args.inner[0];
+    check_single_value(needle)?;
+    let table = ArrayView::new(&args.inner[1]);
+    let is_sorted = match args.inner.get(3) {
+        Some(arg) => arg.to_condition()?,
+        None => true,
+    };
+
+    let (haystack, result_count) = match direction {
+        LookupDirection::Vertical => (table.col(0), table.width()),
+        LookupDirection::Horizontal => (table.row(0), table.height()),
+    };
+    let result_index = index_arg(&args.inner[2], result_count)?;
+
+    let found = if is_sorted {
+        approximate_match(&needle.inner, &haystack, false)
+    } else {
+        exact_match(&needle.inner, &haystack)
+    };
+    let i = found.ok_or_else(|| no_match(&args))?;
+
+    Ok(match direction {
+        LookupDirection::Vertical => table.rows[i][result_index].clone(),
+        LookupDirection::Horizontal => table.rows[result_index][i].clone(),
+    })
+}
+
+/// Implements `XLOOKUP(value, lookup_array, return_array, [if_not_found],
+/// [match_mode], [search_mode])`.
+fn xlookup(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 3..=6)?;
+    let needle = &args.inner[0];
+    check_single_value(needle)?;
+    let (direction, haystack) = ArrayView::new(&args.inner[1]).to_vector()?;
+    let return_array = ArrayView::new(&args.inner[2]);
+    let if_not_found = args.inner.get(3);
+    let match_mode = MatchMode::from_arg(args.inner.get(4))?;
+    let search_mode = SearchMode::from_arg(args.inner.get(5))?;
+
+    // The return array must line up with the lookup array.
+    let (expected_size, got_size) = match direction {
+        LookupDirection::Vertical => (
+            (haystack.len(), return_array.width()),
+            (return_array.height(), return_array.width()),
+        ),
+        LookupDirection::Horizontal => (
+            (return_array.height(), haystack.len()),
+            (return_array.height(), return_array.width()),
+        ),
+    };
+    if expected_size != got_size {
+        return Err(FormulaErrorMsg::ArraySizeMismatch {
+            expected: expected_size,
+            got: got_size,
+        }
+        .with_span(return_array.span));
+    }
+
+    match find_match(&needle.inner, &haystack, match_mode, search_mode) {
+        Some(i) => Ok(match direction {
+            LookupDirection::Vertical if return_array.width() == 1 => {
+                return_array.rows[i][0].clone()
+            }
+            LookupDirection::Vertical => row_array(return_array.row(i)),
+            LookupDirection::Horizontal if return_array.height() == 1 => {
+                return_array.rows[0][i].clone()
+            }
+            LookupDirection::Horizontal => col_array(return_array.col(i)),
+        }),
+        None => match if_not_found {
+            Some(value) => Ok(value.inner.clone()),
+            None => Err(no_match(&args)),
+        },
+    }
+}
+
+/// Implements `INDEX(array, row, [column])`.
+fn index(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=3)?;
+    let array = ArrayView::new(&args.inner[0]);
+
+    // With only one index into a single row, the index selects a column.
+    let (row_arg, col_arg) = match args.inner.get(2) {
+        Some(col_arg) => (Some(&args.inner[1]), Some(col_arg)),
+        None if array.height() == 1 && array.width() > 1 => (None, Some(&args.inner[1])),
+        None => (Some(&args.inner[1]), None),
+    };
+
+    // An index of zero (or a missing index) selects the entire row or column.
+    let row = match row_arg {
+        Some(arg) if arg.to_integer()? != 0 => Some(index_arg(arg, array.height())?),
+        _ => None,
+    };
+    let col = match col_arg {
+        Some(arg) if arg.to_integer()? != 0 => Some(index_arg(arg, array.width())?),
+        _ => None,
+    };
+
+    Ok(match (row, col) {
+        (Some(row), Some(col)) => array.rows[row][col].clone(),
+        (Some(row), None) if array.width() == 1 => array.rows[row][0].clone(),
+        (Some(row), None) => row_array(array.row(row)),
+        (None, Some(col)) if array.height() == 1 => array.rows[0][col].clone(),
+        (None, Some(col)) => col_array(array.col(col)),
+        (None, None) => args.inner[0].inner.clone(),
+    })
+}
+
+/// Implements `MATCH(value, array, [match_type])`.
+fn match_(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=3)?;
+    let needle = &args.inner[0];
+    check_single_value(needle)?;
+    let (_, haystack) = ArrayView::new(&args.inner[1]).to_vector()?;
+
+    let match_type = match args.inner.get(2) {
+        Some(arg) => arg.to_number()?,
+        None => 1.0,
+    };
+
+    let found = if match_type > 0.0 {
+        approximate_match(&needle.inner, &haystack, false)
+    } else if match_type < 0.0 {
+        approximate_match(&needle.inner, &haystack, true)
+    } else {
+        exact_match(&needle.inner, &haystack)
+    };
+    let i = found.ok_or_else(|| no_match(&args))?;
+    Ok(Value::Number((i + 1) as f64))
+}
+
+/// Implements `XMATCH(value, array, [match_mode], [search_mode])`.
+fn xmatch(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=4)?;
+    let needle = &args.inner[0];
+    check_single_value(needle)?;
+    let (_, haystack) = ArrayView::new(&args.inner[1]).to_vector()?;
+    let match_mode = MatchMode::from_arg(args.inner.get(2))?;
+    let search_mode = SearchMode::from_arg(args.inner.get(3))?;
+
+    let i = find_match(&needle.inner, &haystack, match_mode, search_mode)
+        .ok_or_else(|| no_match(&args))?;
+    Ok(Value::Number((i + 1) as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    const FRUITS: &str = "{'apple', 3, 0.5; 'banana', 6, 0.25; 'cherry', 12, 2; 'date', 20, 4}";
+
+    #[test]
+    fn test_vlookup() {
+        let f = |s: &str| eval_to_string(&mut PanicGridMock, &s.replace("FRUITS", FRUITS));
+
+        assert_eq!("6", f("VLOOKUP('banana', FRUITS, 2, FALSE())"));
+        assert_eq!("6", f("VLOOKUP('BANANA', FRUITS, 2, FALSE())"));
+        assert_eq!("2", f("VLOOKUP('ch*', FRUITS, 3, FALSE())"));
+        assert_eq!("date", f("VLOOKUP('d?te', FRUITS, 1, FALSE())"));
+
+        // Approximate match finds the largest value less than or equal to the
+        // lookup value.
+        assert_eq!("cherry", f("VLOOKUP('coconut', FRUITS, 1)"));
+        assert_eq!("cherry", f("VLOOKUP('coconut', FRUITS, 1, 1)"));
+        assert_eq!("date", f("VLOOKUP('zucchini', FRUITS, 1)"));
+
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, &format!("VLOOKUP('aardvark', {FRUITS}, 1)"))
+                .unwrap_err()
+                .msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, &format!("VLOOKUP('coconut', {FRUITS}, 1, FALSE())"))
+                .unwrap_err()
+                .msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::IndexOutOfBounds,
+            eval(g, &format!("VLOOKUP('apple', {FRUITS}, 4, FALSE())"))
+                .unwrap_err()
+                .msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, &format!("VLOOKUP('apple', {FRUITS})"))
+                .unwrap_err()
+                .msg,
+        );
+    }
+
+    #[test]
+    fn test_hlookup() {
+        let g = &mut PanicGridMock;
+        let table = "{10, 20, 30; 'a', 'b', 'c'}";
+        assert_eq!("b", eval_to_string(g, &format!("HLOOKUP(20, {table}, 2)")));
+        assert_eq!("b", eval_to_string(g, &format!("HLOOKUP(25, {table}, 2)")));
+        assert_eq!(
+            "c",
+            eval_to_string(g, &format!("HLOOKUP(30, {table}, 2, FALSE())")),
+        );
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, &format!("HLOOKUP(5, {table}, 2)")).unwrap_err().msg,
+        );
+    }
+
+    #[test]
+    fn test_vlookup_cell_range() {
+        make_stateless_grid_mock!(|pos| Some(match (pos.x, pos.y) {
+            (0, y) => format!("key{y}"),
+            (1, y) => (y * 100).to_string(),
+            _ => panic!("cell {pos} shouldn't be accessed"),
+        }));
+        assert_eq!(
+            "300",
+            eval_to_string(&mut GridMock, "VLOOKUP('KEY3', A1:B5, 2, 0)"),
+        );
+    }
+
+    #[test]
+    fn test_index() {
+        let g = &mut PanicGridMock;
+        let table = "{1, 2, 3; 4, 5, 6}";
+        assert_eq!("6", eval_to_string(g, &format!("INDEX({table}, 2, 3)")));
+        assert_eq!(
+            "{4, 5, 6}",
+            eval_to_string(g, &format!("INDEX({table}, 2)"))
+        );
+        assert_eq!(
+            "{4, 5, 6}",
+            eval_to_string(g, &format!("INDEX({table}, 2, 0)"))
+        );
+        assert_eq!(
+            "{2; 5}",
+            eval_to_string(g, &format!("INDEX({table}, 0, 2)"))
+        );
+        assert_eq!("20", eval_to_string(g, "INDEX({10, 20, 30}, 2)"));
+        assert_eq!("20", eval_to_string(g, "INDEX({10; 20; 30}, 2)"));
+        assert_eq!(
+            FormulaErrorMsg::IndexOutOfBounds,
+            eval(g, &format!("INDEX({table}, 3, 1)")).unwrap_err().msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::IndexOutOfBounds,
+            eval(g, &format!("INDEX({table}, 1, -1)")).unwrap_err().msg,
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        let g = &mut PanicGridMock;
+        assert_eq!("2", eval_to_string(g, "MATCH(25, {10, 20, 30, 40})"));
+        assert_eq!("4", eval_to_string(g, "MATCH(99, {10, 20, 30, 40}, 1)"));
+        assert_eq!("3", eval_to_string(g, "MATCH(30, {10, 20, 30, 40}, 0)"));
+        assert_eq!("2", eval_to_string(g, "MATCH(25, {40; 30; 20; 10}, -1)"));
+        assert_eq!("2", eval_to_string(g, "MATCH('b*', {'a', 'Bee', 'b'}, 0)"));
+        assert_eq!("2", eval_to_string(g, "MATCH('~*', {'a', '*', 'b'}, 0)"));
+        // Text never matches numbers, even if it looks like one.
+        assert_eq!("2", eval_to_string(g, "MATCH(1, {'1', 1}, 0)"));
+        assert_eq!("1", eval_to_string(g, "MATCH('1', {'1', 1}, 0)"));
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, "MATCH('20', {10, 20, 30}, 0)").unwrap_err().msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, "MATCH(5, {10, 20, 30})").unwrap_err().msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, "MATCH(25, {10, 20, 30}, 0)").unwrap_err().msg,
+        );
+        assert!(eval(g, "MATCH(1, {1, 2; 3, 4}, 0)").is_err());
+    }
+
+    #[test]
+    fn test_xmatch() {
+        let g = &mut PanicGridMock;
+        let values = "{5, 10, 10, 20}";
+        assert_eq!("2", eval_to_string(g, &format!("XMATCH(10, {values})")));
+        assert_eq!(
+            "3",
+            eval_to_string(g, &format!("XMATCH(10, {values}, 0, -1)"))
+        );
+        assert_eq!(
+            "3",
+            eval_to_string(g, &format!("XMATCH(15, {values}, -1, -1)"))
+        );
+        assert_eq!("4", eval_to_string(g, &format!("XMATCH(15, {values}, 1)")));
+        assert_eq!("1", eval_to_string(g, &format!("XMATCH(1, {values}, 1)")));
+        assert_eq!(
+            "4",
+            eval_to_string(g, &format!("XMATCH(20, {values}, 0, 2)"))
+        );
+        assert_eq!(
+            "4",
+            eval_to_string(g, &format!("XMATCH(99, {values}, -1, 2)"))
+        );
+        assert_eq!("2", eval_to_string(g, "XMATCH(8, {20, 10, 5}, 1, -2)"));
+        assert_eq!("3", eval_to_string(g, "XMATCH(8, {20, 10, 5}, -1, -2)"));
+        assert_eq!("2", eval_to_string(g, "XMATCH('b?', {'a', 'bc', 'b'}, 2)"));
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, &format!("XMATCH(99, {values}, 1)"))
+                .unwrap_err()
+                .msg,
+        );
+        assert!(eval(g, &format!("XMATCH(10, {values}, 3)")).is_err());
+        assert!(eval(g, &format!("XMATCH(10, {values}, 0, 0)")).is_err());
+    }
+
+    #[test]
+    fn test_xlookup() {
+        let f = |s: &str| eval_to_string(&mut PanicGridMock, &s.replace("FRUITS", FRUITS));
+
+        assert_eq!(
+            "12",
+            f("XLOOKUP('cherry', {'apple'; 'banana'; 'cherry'}, {3; 6; 12})"),
+        );
+        assert_eq!(
+            "{12, 2}",
+            f("XLOOKUP('cherry', {'apple'; 'banana'; 'cherry'}, {3, 0.5; 6, 0.25; 12, 2})"),
+        );
+        assert_eq!("y", f("XLOOKUP(2, {1, 2, 3}, {'x', 'y', 'z'})"));
+        assert_eq!("text", f("XLOOKUP('2', {2, '2'}, {'number', 'text'})"));
+        assert_eq!(
+            "{b; e}",
+            f("XLOOKUP(2, {1, 2, 3}, {'a', 'b', 'c'; 'd', 'e', 'f'})")
+        );
+        assert_eq!(
+            "none",
+            f("XLOOKUP('fig', {'apple'; 'banana'}, {1; 2}, 'none')"),
+        );
+        assert_eq!(
+            "b",
+            f("XLOOKUP('ban*', {'apple', 'banana'}, {'a', 'b'}, '', 2)")
+        );
+        assert_eq!("b", f("XLOOKUP(15, {10, 20}, {'a', 'b'}, '', 1)"));
+        assert_eq!("a", f("XLOOKUP(15, {10, 20}, {'a', 'b'}, '', -1)"));
+
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, "XLOOKUP('fig', {'apple'; 'banana'}, {1; 2})")
+                .unwrap_err()
+                .msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::ArraySizeMismatch {
+                expected: (2, 1),
+                got: (3, 1),
+            },
+            eval(g, "XLOOKUP('fig', {'apple'; 'banana'}, {1; 2; 3})")
+                .unwrap_err()
+                .msg,
+        );
+    }
+}
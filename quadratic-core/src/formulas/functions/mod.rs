@@ -0,0 +1,693 @@
+use itertools::Itertools;
+use rand::Rng;
+use regex::Regex;
+use smallvec::SmallVec;
+use std::ops::RangeInclusive;
+
+use super::*;
+
+/// Produces a constant function that takes no arguments.
+macro_rules! constant_function {
+    ($value:expr) => {
+        |args| {
+            if !args.inner.is_empty() {
+                return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+            }
+            $value
+        }
+    };
+}
+
+/// Constructs a thunk that calls `array_mapped()`.
+macro_rules! array_mapped {
+    ($closure:expr) => {
+        |args| array_map(args, $closure)
+    };
+}
+
+mod array;
+mod conditional;
+mod datetime;
+mod financial;
+mod information;
+mod lookup;
+mod statistics;
+mod text;
+
+/// Function that takes a list of evaluated arguments and returns a value.
+pub type PureFunction = fn(Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value>;
+
+pub fn pure_function_from_name(s: &str) -> Option<PureFunction> {
+    let name = s.to_ascii_lowercase();
+    basic_function_from_name(&name)
+        .or_else(|| lookup::function_from_name(&name))
+        .or_else(|| information::function_from_name(&name))
+        .or_else(|| datetime::function_from_name(&name))
+        .or_else(|| text::function_from_name(&name))
+        .or_else(|| statistics::function_from_name(&name))
+        .or_else(|| conditional::function_from_name(&name))
+        .or_else(|| financial::function_from_name(&name))
+        .or_else(|| array::function_from_name(&name))
+}
+
+fn basic_function_from_name(name: &str) -> Option<PureFunction> {
+    // When adding new functions, also update the code editor completions list.
+    Some(match name {
+        // Comparison operators
+        "=" | "==" => array_mapped!(|[a, b]| Ok(Value::Bool(values_equal(&a, &b)?))),
+        "<>" | "!=" => array_mapped!(|[a, b]| Ok(Value::Bool(!values_equal(&a, &b)?))),
+        "<" => array_mapped!(|[a, b]| Ok(Value::Bool(a.to_number()? < b.to_number()?))),
+        ">" => array_mapped!(|[a, b]| Ok(Value::Bool(a.to_number()? > b.to_number()?))),
+        "<=" => array_mapped!(|[a, b]| Ok(Value::Bool(a.to_number()? <= b.to_number()?))),
+        ">=" => array_mapped!(|[a, b]| Ok(Value::Bool(a.to_number()? >= b.to_number()?))),
+
+        // Mathematical operators
+        "+" => |args| match args.inner.len() {
+            1 => array_map(args, |[a]| Ok(Value::Number(a.to_number()?))),
+            _ => array_map(args, |[a, b]| datetime::add(a, b)),
+        },
+        "-" => |args| match args.inner.len() {
+            1 => array_map(args, |[a]| Ok(Value::Number(-a.to_number()?))),
+            _ => array_map(args, |[a, b]| datetime::subtract(a, b)),
+        },
+        "*" => array_mapped!(|[a, b]| Ok(Value::Number(a.to_number()? * b.to_number()?))),
+        "/" => array_mapped!(|[a, b]| Ok(Value::Number(a.to_number()? / divisor(&b)?))),
+        "^" | "**" => array_mapped!(|[base, exponent]| {
+            let span = Span::merge(&base, &exponent);
+            let (base, exponent_num) = (base.to_number()?, exponent.to_number()?);
+            if base == 0.0 && exponent_num < 0.0 {
+                return Err(FormulaErrorMsg::NegativeExponent.with_span(exponent.span));
+            }
+            checked_number(base.powf(exponent_num), span)
+        }),
+        "%" => array_mapped!(|[n]| Ok(Value::Number(n.to_number()? / 100.0))),
+        "<<" => array_mapped!(|[n, shift]| {
+            let span = Span::merge(&n, &shift);
+            bitshift_left(n.to_integer()?, shift.to_integer()?, span)
+                .map(|n| Value::Number(n as f64))
+        }),
+        ">>" => array_mapped!(|[n, shift]| {
+            let span = Span::merge(&n, &shift);
+            let shift = shift
+                .to_integer()?
+                .checked_neg()
+                .ok_or_else(|| FormulaErrorMsg::Overflow.with_span(span))?;
+            bitshift_left(n.to_integer()?, shift, span).map(|n| Value::Number(n as f64))
+        }),
+        ".." => |args| {
+            let span = args.span;
+            let ranges = array_map(args, |[start, end]| {
+                numeric_range(
+                    start.to_integer()?,
+                    end.to_integer()?,
+                    Span::merge(&start, &end),
+                )
+            })?;
+            Ok(join_numeric_ranges(ranges, span))
+        },
+
+        // Mathematical functions
+        "sum" => |args| sum(&args.inner).map(Value::Number),
+        "product" => |args| product(&args.inner).map(Value::Number),
+        "abs" => array_mapped!(|[n]| Ok(Value::Number(n.to_number()?.abs()))),
+        "sign" => array_mapped!(|[n]| {
+            let n = n.to_number()?;
+            Ok(Value::Number(if n == 0.0 { 0.0 } else { n.signum() }))
+        }),
+        "round" => |args| round_function(args, f64::round),
+        "roundup" => |args| round_function(args, round_away_from_zero),
+        "rounddown" | "trunc" => |args| round_function(args, f64::trunc),
+        "mround" => array_mapped!(|[n, multiple]| {
+            if n.to_number()? * multiple.to_number()? < 0.0 {
+                return Err(FormulaErrorMsg::InvalidArgument.with_span(multiple.span));
+            }
+            round_to_multiple(&n, &multiple, f64::round)
+        }),
+        "ceiling" => |args| match args.inner.len() {
+            1 => array_map(args, |[n]| Ok(Value::Number(n.to_number()?.ceil()))),
+            _ => array_map(args, |[n, significance]| {
+                round_to_multiple(&n, &significance, f64::ceil)
+            }),
+        },
+        "floor" => |args| match args.inner.len() {
+            1 => array_map(args, |[n]| Ok(Value::Number(n.to_number()?.floor()))),
+            _ => array_map(args, |[n, significance]| {
+                divisor(&significance)?;
+                round_to_multiple(&n, &significance, f64::floor)
+            }),
+        },
+        "int" => array_mapped!(|[n]| Ok(Value::Number(n.to_number()?.floor()))),
+        "mod" => array_mapped!(|[n, d]| {
+            let (n, d) = (n.to_number()?, divisor(&d)?);
+            Ok(Value::Number(n - d * (n / d).floor()))
+        }),
+        "quotient" => {
+            array_mapped!(|[n, d]| { Ok(Value::Number((n.to_number()? / divisor(&d)?).trunc())) })
+        }
+        "sqrt" => array_mapped!(|[n]| checked_number(n.to_number()?.sqrt(), n.span)),
+        "exp" => array_mapped!(|[n]| checked_number(n.to_number()?.exp(), n.span)),
+        "ln" => array_mapped!(|[n]| checked_number(n.to_number()?.ln(), n.span)),
+        "log" => |args| match args.inner.len() {
+            1 => array_map(args, |[n]| checked_number(n.to_number()?.log10(), n.span)),
+            _ => array_map(args, |[n, base]| {
+                let span = Span::merge(&n, &base);
+                let base_num = base.to_number()?;
+                if base_num == 1.0 {
+                    return Err(FormulaErrorMsg::DivideByZero.with_span(base.span));
+                }
+                checked_number(n.to_number()?.log(base_num), span)
+            }),
+        },
+        "log10" => array_mapped!(|[n]| checked_number(n.to_number()?.log10(), n.span)),
+        "pi" => constant_function!(Ok(Value::Number(std::f64::consts::PI))),
+        "sin" => array_mapped!(|[n]| checked_number(n.to_number()?.sin(), n.span)),
+        "cos" => array_mapped!(|[n]| checked_number(n.to_number()?.cos(), n.span)),
+        "tan" => array_mapped!(|[n]| checked_number(n.to_number()?.tan(), n.span)),
+        "asin" => array_mapped!(|[n]| checked_number(n.to_number()?.asin(), n.span)),
+        "acos" => array_mapped!(|[n]| checked_number(n.to_number()?.acos(), n.span)),
+        "atan" => array_mapped!(|[n]| checked_number(n.to_number()?.atan(), n.span)),
+        "atan2" => array_mapped!(|[x, y]| {
+            let span = Span::merge(&x, &y);
+            let (x, y) = (x.to_number()?, y.to_number()?);
+            if x == 0.0 && y == 0.0 {
+                return Err(FormulaErrorMsg::DivideByZero.with_span(span));
+            }
+            Ok(Value::Number(y.atan2(x)))
+        }),
+        "degrees" => array_mapped!(|[n]| Ok(Value::Number(n.to_number()?.to_degrees()))),
+        "radians" => array_mapped!(|[n]| Ok(Value::Number(n.to_number()?.to_radians()))),
+        "fact" => array_mapped!(|[n]| {
+            let n_num = non_negative_integer(&n)?;
+            if n_num > MAX_FACT_ARG {
+                return Err(FormulaErrorMsg::Overflow.with_span(n.span));
+            }
+            Ok(Value::Number(
+                (1..=n_num as u64).map(|i| i as f64).product(),
+            ))
+        }),
+        "combin" => array_mapped!(|[n, k]| {
+            let span = Span::merge(&n, &k);
+            let (n_num, k_num) = (non_negative_integer(&n)?, non_negative_integer(&k)?);
+            if k_num > n_num {
+                return Err(FormulaErrorMsg::InvalidArgument.with_span(k.span));
+            }
+            // The result at least doubles for each factor, so this overflows
+            // quickly for large `k`.
+            let k_num = k_num.min(n_num - k_num);
+            let mut result = 1.0;
+            for i in 0..k_num as u64 {
+                result = result * (n_num - i as f64) / (i + 1) as f64;
+                if !result.is_finite() {
+                    break;
+                }
+            }
+            checked_number(result.round(), span)
+        }),
+        "permut" => array_mapped!(|[n, k]| {
+            let span = Span::merge(&n, &k);
+            let (n_num, k_num) = (non_negative_integer(&n)?, non_negative_integer(&k)?);
+            if k_num > n_num {
+                return Err(FormulaErrorMsg::InvalidArgument.with_span(k.span));
+            }
+            let mut result = 1.0;
+            for i in 0..k_num as u64 {
+                result *= n_num - i as f64;
+                if !result.is_finite() {
+                    break;
+                }
+            }
+            checked_number(result, span)
+        }),
+        "gcd" => |args| {
+            let mut result = 0;
+            for n in flat_iter_integers(&args)? {
+                result = gcd(result, n);
+            }
+            Ok(Value::Number(result as f64))
+        },
+        "lcm" => |args| {
+            let mut result = 1;
+            for n in flat_iter_integers(&args)? {
+                if n == 0 {
+                    return Ok(Value::Number(0.0));
+                }
+                result = (result / gcd(result, n))
+                    .checked_mul(n)
+                    .filter(|&lcm| lcm <= MAX_EXACT_INTEGER as u64)
+                    .ok_or_else(|| FormulaErrorMsg::Overflow.with_span(args.span))?;
+            }
+            Ok(Value::Number(result as f64))
+        },
+
+        // Logic functions (non-short-circuiting). Short-circuiting functions
+        // such as `IF()` and `AND()` are special forms.
+        "true" => constant_function!(Ok(Value::Bool(true))),
+        "false" => constant_function!(Ok(Value::Bool(false))),
+        "not" => array_mapped!(|[a]| Ok(Value::Bool(!a.to_bool()?))),
+        "xor" => |args| {
+            flat_iter_bools(&args.inner)
+                .try_fold(false, |ret, next| FormulaResult::Ok(ret ^ next?))
+                .map(Value::Bool)
+        },
+
+        // String functions
+        "&" => {
+            array_mapped!(|[a, b]| Ok(Value::String(a.to_text()? + &b.to_text()?)))
+        }
+        "concat" => |args| {
+            Ok(Value::String(
+                flat_iter_strings(&args.inner)
+                    .try_fold(String::new(), |ret, next| FormulaResult::Ok(ret + &next?))?,
+            ))
+        },
+
+        _ => return None,
+    })
+}
+
+/// Largest integer magnitude that can be represented exactly as a number.
+const MAX_EXACT_INTEGER: i64 = 1 << f64::MANTISSA_DIGITS;
+
+/// Maximum number of values produced by the `..` operator.
+const MAX_NUMERIC_RANGE_LEN: u64 = 1_000_000;
+
+/// Largest number whose factorial can be represented.
+const MAX_FACT_ARG: f64 = 170.0;
+
+/// Returns the number as a value, or an error if it is infinite or not a
+/// number.
+fn checked_number(n: f64, span: Span) -> FormulaResult<Value> {
+    if n.is_nan() {
+        Err(FormulaErrorMsg::InvalidArgument.with_span(span))
+    } else if n.is_infinite() {
+        Err(FormulaErrorMsg::Overflow.with_span(span))
+    } else {
+        Ok(Value::Number(n))
+    }
+}
+
+/// Returns a divisor as a number, or an error if it is zero.
+fn divisor(value: &Spanned<Value>) -> FormulaResult<f64> {
+    let n = value.to_number()?;
+    if n == 0.0 {
+        return Err(FormulaErrorMsg::DivideByZero.with_span(value.span));
+    }
+    Ok(n)
+}
+
+/// Returns a number truncated to an integer, or an error if it is negative.
+fn non_negative_integer(value: &Spanned<Value>) -> FormulaResult<f64> {
+    match value.to_number()?.trunc() {
+        n if n < 0.0 => Err(FormulaErrorMsg::InvalidArgument.with_span(value.span)),
+        n => Ok(n),
+    }
+}
+
+/// Returns every number in the arguments, truncated to an integer. Returns
+/// an error if any are negative or too large to represent exactly.
+fn flat_iter_integers(args: &Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Vec<u64>> {
+    flat_iter_numbers(&args.inner)
+        .map(|n| {
+            let n = n?.trunc();
+            if n < 0.0 || n > MAX_EXACT_INTEGER as f64 {
+                return Err(FormulaErrorMsg::InvalidArgument.with_span(args.span));
+            }
+            Ok(n as u64)
+        })
+        .collect()
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Evaluates a rounding function such as `ROUND()`, which takes a number
+/// and an optional number of decimal places.
+fn round_function(
+    args: Spanned<Vec<Spanned<Value>>>,
+    round_fn: fn(f64) -> f64,
+) -> FormulaResult<Value> {
+    match args.inner.len() {
+        1 => array_map(args, |[n]| {
+            Ok(Value::Number(round_to_digits(n.to_number()?, 0, round_fn)))
+        }),
+        _ => array_map(args, |[n, digits]| {
+            let digits = digits.to_number()?.trunc() as i32;
+            Ok(Value::Number(round_to_digits(
+                n.to_number()?,
+                digits,
+                round_fn,
+            )))
+        }),
+    }
+}
+
+/// Rounds `n` to `digits` decimal places using `round_fn`. If `digits` is
+/// negative, rounds to the left of the decimal point instead.
+fn round_to_digits(n: f64, digits: i32, round_fn: fn(f64) -> f64) -> f64 {
+    let digits = digits.clamp(-308, 308);
+    let scale = 10_f64.powi(digits.abs());
+    let result = if digits >= 0 {
+        round_fn(remove_float_noise(n * scale)) / scale
+    } else {
+        round_fn(remove_float_noise(n / scale)) * scale
+    };
+    // Rounding to more digits than a number has leaves it unchanged.
+    if result.is_finite() {
+        result
+    } else {
+        n
+    }
+}
+
+/// Rounds `n` to a multiple of `significance` using `round_fn`. Returns an
+/// error if `n` is positive and `significance` is negative.
+fn round_to_multiple(
+    n: &Spanned<Value>,
+    significance: &Spanned<Value>,
+    round_fn: fn(f64) -> f64,
+) -> FormulaResult<Value> {
+    let (n, significance_num) = (n.to_number()?, significance.to_number()?);
+    if significance_num == 0.0 {
+        return Ok(Value::Number(0.0));
+    }
+    if n > 0.0 && significance_num < 0.0 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(significance.span));
+    }
+    let multiples = round_fn(remove_float_noise(n / significance_num));
+    Ok(Value::Number(remove_float_noise(
+        multiples * significance_num,
+    )))
+}
+
+fn round_away_from_zero(n: f64) -> f64 {
+    n.abs().ceil().copysign(n)
+}
+
+/// Rounds `n` to 15 significant digits, which removes error introduced by
+/// floating-point arithmetic so that `0.1 * 3` rounds up to `0.3` and not
+/// `0.4`.
+fn remove_float_noise(n: f64) -> f64 {
+    if n == 0.0 || !n.is_finite() {
+        return n;
+    }
+    format!("{n:.14e}").parse().unwrap_or(n)
+}
+
+/// Returns a random integer between `low` and `high`, inclusive.
+pub fn random_between(
+    rng: &mut impl Rng,
+    low: Spanned<Value>,
+    high: Spanned<Value>,
+) -> FormulaResult<Value> {
+    let (low_num, high_num) = (low.to_number()?.ceil(), high.to_number()?.floor());
+    if !low_num.is_finite() || !high_num.is_finite() || low_num > high_num {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(Span::merge(&low, &high)));
+    }
+    Ok(Value::Number(
+        rng.gen_range(low_num as i64..=high_num as i64) as f64,
+    ))
+}
+
+/// Shifts the bits of an integer left by `shift` (or right, if `shift` is
+/// negative), returning an overflow error if the result cannot be represented
+/// exactly.
+fn bitshift_left(n: i64, shift: i64, span: Span) -> FormulaResult<i64> {
+    let overflow = || FormulaErrorMsg::Overflow.with_span(span);
+    let result = if shift >= 0 {
+        let factor = u32::try_from(shift)
+            .ok()
+            .and_then(|shift| 2_i64.checked_pow(shift))
+            .ok_or_else(overflow)?;
+        n.checked_mul(factor).ok_or_else(overflow)?
+    } else {
+        // Shifting right by 63 or more bits leaves only the sign.
+        n >> shift.unsigned_abs().min(63)
+    };
+    if result.abs() > MAX_EXACT_INTEGER {
+        return Err(overflow());
+    }
+    Ok(result)
+}
+
+/// Returns a column of consecutive integers from `start` to `end`,
+/// inclusive. If `end` is less than `start`, the column counts down.
+fn numeric_range(start: i64, end: i64, span: Span) -> FormulaResult<Value> {
+    if start.abs_diff(end) >= MAX_NUMERIC_RANGE_LEN {
+        return Err(FormulaErrorMsg::Overflow.with_span(span));
+    }
+    let column = |n: i64| smallvec::smallvec![Value::Number(n as f64)];
+    Ok(Value::Array(if start <= end {
+        (start..=end).map(column).collect()
+    } else {
+        (end..=start).rev().map(column).collect()
+    }))
+}
+
+/// Lays out the columns produced by `..` with array operands. The columns
+/// for each row of operands are placed side by side, padding shorter columns
+/// with `#N/A`, and the rows of operands are stacked vertically.
+fn join_numeric_ranges(ranges: Value, span: Span) -> Value {
+    let Value::Array(ranges) = ranges else {
+        return ranges;
+    };
+    let padding = Value::Error(Box::new(
+        FormulaErrorMsg::ErrorValue(ErrorKind::NotAvailable).with_span(span),
+    ));
+    let mut rows = vec![];
+    for ranges_row in ranges {
+        let columns = ranges_row
+            .into_iter()
+            .map(|range| match range {
+                Value::Array(column) => column.into_iter().flatten().collect_vec(),
+                other => vec![other],
+            })
+            .collect_vec();
+        let height = columns.iter().map(|column| column.len()).max().unwrap_or(0);
+        rows.extend((0..height).map(|i| {
+            columns
+                .iter()
+                .map(|column| column.get(i).unwrap_or(&padding).clone())
+                .collect()
+        }));
+    }
+    Value::Array(rows)
+}
+
+fn sum(args: &[Spanned<Value>]) -> FormulaResult<f64> {
+    flat_iter_numbers(args).try_fold(0.0, |sum, next| FormulaResult::Ok(sum + next?))
+}
+fn product(args: &[Spanned<Value>]) -> FormulaResult<f64> {
+    flat_iter_numbers(args).try_fold(1.0, |prod, next| FormulaResult::Ok(prod * next?))
+}
+
+fn flat_iter_numbers<'a>(
+    args: &'a [Spanned<Value>],
+) -> impl 'a + Iterator<Item = FormulaResult<f64>> {
+    args.iter().map(|v| v.to_numbers()).flatten_ok()
+}
+fn flat_iter_bools<'a>(
+    args: &'a [Spanned<Value>],
+) -> impl 'a + Iterator<Item = FormulaResult<bool>> {
+    args.iter().map(|v| v.to_bools()).flatten_ok()
+}
+fn flat_iter_strings<'a>(
+    args: &'a [Spanned<Value>],
+) -> impl 'a + Iterator<Item = FormulaResult<String>> {
+    args.iter().map(|v| v.to_strings()).flatten_ok()
+}
+
+/// Returns the arguments as an array, checking the argument count.
+fn args_array<const N: usize>(
+    args: Spanned<Vec<Spanned<Value>>>,
+) -> FormulaResult<[Spanned<Value>; N]> {
+    args.inner
+        .try_into()
+        .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(args.span))
+}
+
+/// Iterates over the elements of an array, or yields a single value.
+fn flat_values(value: &Spanned<Value>) -> Box<dyn '_ + Iterator<Item = &Value>> {
+    match &value.inner {
+        Value::Array(a) => Box::new(a.iter().flatten()),
+        v => Box::new(std::iter::once(v)),
+    }
+}
+
+/// Returns whether two values are equal according to `=`. Values are compared
+/// as text, except that a blank value also equals `0` and `FALSE`.
+fn values_equal(a: &Spanned<Value>, b: &Spanned<Value>) -> FormulaResult<bool> {
+    match (&a.inner, &b.inner) {
+        (Value::Blank, Value::Number(_) | Value::Bool(_)) => Ok(b.to_number()? == 0.0),
+        (Value::Number(_) | Value::Bool(_), Value::Blank) => Ok(a.to_number()? == 0.0),
+        _ => Ok(a.to_text()? == b.to_text()?),
+    }
+}
+
+/// Returns whether a value is blank, meaning that it came from an empty cell.
+fn is_blank(value: &Value) -> bool {
+    matches!(value, Value::Blank)
+}
+
+/// Returns whether a value is blank or empty text, which functions that take
+/// numbers skip.
+fn is_blank_or_empty(value: &Value) -> bool {
+    match value {
+        Value::Blank => true,
+        Value::String(s) => s.is_empty(),
+        _ => false,
+    }
+}
+
+/// Returns an element of an array as a number, or `None` if it is blank,
+/// text, or a boolean. Errors are propagated.
+fn array_element_number(value: &Value, span: Span) -> FormulaResult<Option<f64>> {
+    match value {
+        Value::Error(e) => Err((**e).clone()),
+        Value::Bool(_) | Value::Lambda(_) | Value::Array(_) => Ok(None),
+        v if is_blank_or_empty(v) => Ok(None),
+        v => Ok(Spanned {
+            span,
+            inner: v.clone(),
+        }
+        .to_number()
+        .ok()),
+    }
+}
+
+/// Maps a function over a single argument that may be an array.
+fn map_arg(
+    arg: Spanned<Value>,
+    op: impl FnMut([Spanned<Value>; 1]) -> FormulaResult<Value>,
+) -> FormulaResult<Value> {
+    let span = arg.span;
+    array_map(
+        Spanned {
+            span,
+            inner: vec![arg],
+        },
+        op,
+    )
+}
+
+/// Returns an error if the number of arguments is outside `allowed`.
+fn check_arg_count(
+    args: &Spanned<Vec<Spanned<Value>>>,
+    allowed: RangeInclusive<usize>,
+) -> FormulaResult<()> {
+    if allowed.contains(&args.inner.len()) {
+        Ok(())
+    } else {
+        Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span))
+    }
+}
+
+/// Returns whether a string contains any unescaped or escaped wildcard
+/// characters (`*`, `?`, or `~`).
+fn contains_wildcards(s: &str) -> bool {
+    s.contains(['*', '?', '~'])
+}
+
+/// Compiles a wildcard pattern into a case-insensitive regex that must match
+/// an entire string. `*` matches any sequence of characters, `?` matches any
+/// single character, and `~` escapes the character after it.
+fn wildcard_pattern_regex(pattern: &str) -> Regex {
+    let regex_string = format!("(?is)^{}$", wildcard_regex_body(pattern));
+    Regex::new(&regex_string).expect("escaped wildcard pattern should be a valid regex")
+}
+
+/// Translates a wildcard pattern into regex syntax, without anchors or flags.
+fn wildcard_regex_body(pattern: &str) -> String {
+    let mut regex_string = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex_string.push_str(".*"),
+            '?' => regex_string.push('.'),
+            '~' => match chars.next() {
+                Some(escaped) => regex_string.push_str(&regex::escape(&escaped.to_string())),
+                None => regex_string.push_str(&regex::escape("~")),
+            },
+            other => regex_string.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    regex_string
+}
+
+/// Maps a fixed-argument-count function over arguments that may be arrays.
+///
+/// If the operation fails for an element of the array, that element becomes
+/// an error value.
+pub fn array_map<const N: usize>(
+    args: Spanned<Vec<Spanned<Value>>>,
+    mut op: impl FnMut([Spanned<Value>; N]) -> FormulaResult<Value>,
+) -> FormulaResult<Value> {
+    let (args, array_size) = args_with_common_array_size(args)?;
+    match array_size {
+        // Compute the results. If any argument is not an array, pretend it's an
+        // array of one element repeated with the right size.
+        Some((rows, cols)) => {
+            let mut output_array = Vec::with_capacity(rows);
+            for row in 0..rows {
+                let mut output_row = SmallVec::with_capacity(cols);
+                for col in 0..cols {
+                    let output_value = op(args
+                        .iter()
+                        .map(|arg| arg.get_array_value(row, col))
+                        .collect::<FormulaResult<Vec<_>>>()?
+                        .try_into()
+                        .unwrap());
+                    // An error in one element doesn't affect the others.
+                    output_row.push(output_value.unwrap_or_else(|e| Value::Error(Box::new(e))));
+                }
+                output_array.push(output_row);
+            }
+            Ok(Value::Array(output_array))
+        }
+
+        // No operands are arrays, so just do the operation once.
+        None => op(args),
+    }
+}
+
+/// Returns the common `(rows, cols)` of several arguments, or `None` if no
+/// arguments are arrays.
+pub fn args_with_common_array_size<const N: usize>(
+    args: Spanned<Vec<Spanned<Value>>>,
+) -> FormulaResult<([Spanned<Value>; N], Option<(usize, usize)>)> {
+    // Check argument count.
+    let args: [Spanned<Value>; N] = args
+        .inner
+        .try_into()
+        .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(args.span))?;
+
+    let array_size = common_array_size(&args)?;
+    Ok((args, array_size))
+}
+
+/// Returns the common `(rows, cols)` of any number of arguments, or `None` if
+/// no arguments are arrays. Returns an error if the arrays are different
+/// sizes.
+pub fn common_array_size(args: &[Spanned<Value>]) -> FormulaResult<Option<(usize, usize)>> {
+    let mut array_sizes_iter = args
+        .iter()
+        .filter_map(|arg| Some((arg.span, arg.inner.array_size()?)));
+
+    let Some((_span, array_size)) = array_sizes_iter.next() else {
+        return Ok(None);
+    };
+    // Check that all the arrays are the same size.
+    for (error_span, other_array_size) in array_sizes_iter {
+        if array_size != other_array_size {
+            return Err(FormulaErrorMsg::ArraySizeMismatch {
+                expected: array_size,
+                got: other_array_size,
+            }
+            .with_span(error_span));
+        }
+    }
+    Ok(Some(array_size))
+}
@@ -0,0 +1,571 @@
+//! Statistics functions, such as `AVERAGE` and `STDEV.S`.
+//!
+//! Blank cells, text, and booleans inside ranges and arrays are skipped, but
+//! text given directly as an argument must be numeric. When there are no
+//! numbers, functions that need some return an error value such as `#DIV/0!`
+//! instead of `NaN` or infinity.
+
+use super::*;
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    // When adding new functions, also update the code editor completions list.
+    Some(match name {
+        // Averages
+        "average" => |args| mean(&numbers(&args.inner)?, args.span).map(Value::Number),
+        "averagea" => |args| mean(&numbers_a(&args.inner)?, args.span).map(Value::Number),
+        "median" => |args| {
+            let mut values = numbers(&args.inner)?;
+            values.sort_by(f64::total_cmp);
+            percentile_inclusive(&values, 0.5, args.span).map(Value::Number)
+        },
+        "mode" | "mode.sngl" => |args| {
+            let values = numbers(&args.inner)?;
+            let mut best = None;
+            let mut best_count = 1;
+            for (i, &n) in values.iter().enumerate() {
+                // The first value with the highest count wins ties.
+                let count = values[i..].iter().filter(|&&other| other == n).count();
+                if count > best_count {
+                    best = Some(n);
+                    best_count = count;
+                }
+            }
+            best.map(Value::Number)
+                .ok_or_else(|| FormulaErrorMsg::NoMatch.with_span(args.span))
+        },
+
+        // Counting
+        "count" => |args| Ok(Value::Number(count_numbers(&args.inner) as f64)),
+        "counta" => |args| {
+            let count: usize = args.inner.iter().map(|v| v.inner.count()).sum();
+            Ok(Value::Number(count as f64))
+        },
+        "countblank" => |args| {
+            let count: usize = args
+                .inner
+                .iter()
+                .map(|arg| match &arg.inner {
+                    Value::Array(a) => a.iter().flatten().filter(|v| is_blank(v)).count(),
+                    v => is_blank(v) as usize,
+                })
+                .sum();
+            Ok(Value::Number(count as f64))
+        },
+
+        // Extremes
+        "min" => |args| {
+            let values = numbers(&args.inner)?;
+            Ok(Value::Number(
+                values.into_iter().reduce(f64::min).unwrap_or(0.0),
+            ))
+        },
+        "max" => |args| {
+            let values = numbers(&args.inner)?;
+            Ok(Value::Number(
+                values.into_iter().reduce(f64::max).unwrap_or(0.0),
+            ))
+        },
+        "large" => |args| {
+            let [array, k] = args_array::<2>(args)?;
+            let mut values = numbers(&[array])?;
+            values.sort_by(|a, b| b.total_cmp(a));
+            map_arg(k, |[k]| nth(&values, &k).map(Value::Number))
+        },
+        "small" => |args| {
+            let [array, k] = args_array::<2>(args)?;
+            let mut values = numbers(&[array])?;
+            values.sort_by(f64::total_cmp);
+            map_arg(k, |[k]| nth(&values, &k).map(Value::Number))
+        },
+        "rank" | "rank.eq" => |args| {
+            check_arg_count(&args, 2..=3)?;
+            let mut args = args.inner.into_iter();
+            let (n, array) = (args.next().unwrap(), args.next().unwrap());
+            let ascending = match args.next() {
+                Some(order) => order.into_single_value()?.to_number()? != 0.0,
+                None => false,
+            };
+            let values = numbers(&[array])?;
+            map_arg(n, |[n]| {
+                let n_num = n.to_number()?;
+                if !values.contains(&n_num) {
+                    return Err(FormulaErrorMsg::NoMatch.with_span(n.span));
+                }
+                let better = values
+                    .iter()
+                    .filter(|&&v| if ascending { v < n_num } else { v > n_num })
+                    .count();
+                Ok(Value::Number((better + 1) as f64))
+            })
+        },
+
+        // Percentiles
+        "percentile" | "percentile.inc" => |args| {
+            let [array, k] = args_array::<2>(args)?;
+            let values = sorted_numbers(&array)?;
+            map_arg(k, |[k]| {
+                let k_num = k.to_number()?;
+                if !(0.0..=1.0).contains(&k_num) {
+                    return Err(FormulaErrorMsg::InvalidArgument.with_span(k.span));
+                }
+                percentile_inclusive(&values, k_num, k.span).map(Value::Number)
+            })
+        },
+        "percentile.exc" => |args| {
+            let [array, k] = args_array::<2>(args)?;
+            let values = sorted_numbers(&array)?;
+            map_arg(k, |[k]| {
+                percentile_exclusive(&values, k.to_number()?, k.span).map(Value::Number)
+            })
+        },
+        "quartile" | "quartile.inc" => |args| {
+            let [array, quart] = args_array::<2>(args)?;
+            let values = sorted_numbers(&array)?;
+            map_arg(quart, |[quart]| {
+                let q = quart.to_number()?.trunc();
+                if !(0.0..=4.0).contains(&q) {
+                    return Err(FormulaErrorMsg::InvalidArgument.with_span(quart.span));
+                }
+                percentile_inclusive(&values, q / 4.0, quart.span).map(Value::Number)
+            })
+        },
+        "quartile.exc" => |args| {
+            let [array, quart] = args_array::<2>(args)?;
+            let values = sorted_numbers(&array)?;
+            map_arg(quart, |[quart]| {
+                let q = quart.to_number()?.trunc();
+                if !(1.0..=3.0).contains(&q) {
+                    return Err(FormulaErrorMsg::InvalidArgument.with_span(quart.span));
+                }
+                percentile_exclusive(&values, q / 4.0, quart.span).map(Value::Number)
+            })
+        },
+
+        // Spread
+        "var" | "var.s" => |args| variance(&numbers(&args.inner)?, 1, args.span).map(Value::Number),
+        "var.p" | "varp" => {
+            |args| variance(&numbers(&args.inner)?, 0, args.span).map(Value::Number)
+        }
+        "stdev" | "stdev.s" => |args| {
+            let var = variance(&numbers(&args.inner)?, 1, args.span)?;
+            Ok(Value::Number(var.sqrt()))
+        },
+        "stdev.p" | "stdevp" => |args| {
+            let var = variance(&numbers(&args.inner)?, 0, args.span)?;
+            Ok(Value::Number(var.sqrt()))
+        },
+
+        // Relationships between two sets of values
+        "correl" => |args| {
+            let span = args.span;
+            let [xs, ys] = args_array::<2>(args)?;
+            let stats = PairedStats::new(&xs, &ys)?;
+            let denominator = (stats.sxx * stats.syy).sqrt();
+            Ok(Value::Number(stats.sxy / nonzero(denominator, span)?))
+        },
+        "covariance.p" | "covar" => |args| {
+            let span = args.span;
+            let [xs, ys] = args_array::<2>(args)?;
+            let stats = PairedStats::new(&xs, &ys)?;
+            Ok(Value::Number(stats.sxy / nonzero(stats.n, span)?))
+        },
+        "covariance.s" => |args| {
+            let span = args.span;
+            let [xs, ys] = args_array::<2>(args)?;
+            let stats = PairedStats::new(&xs, &ys)?;
+            Ok(Value::Number(stats.sxy / nonzero(stats.n - 1.0, span)?))
+        },
+        "slope" => |args| {
+            let span = args.span;
+            let [ys, xs] = args_array::<2>(args)?;
+            PairedStats::new(&xs, &ys)?.slope(span).map(Value::Number)
+        },
+        "intercept" => |args| {
+            let span = args.span;
+            let [ys, xs] = args_array::<2>(args)?;
+            let stats = PairedStats::new(&xs, &ys)?;
+            Ok(Value::Number(stats.predict(0.0, span)?))
+        },
+        "rsq" => |args| {
+            let span = args.span;
+            let [ys, xs] = args_array::<2>(args)?;
+            let stats = PairedStats::new(&xs, &ys)?;
+            let denominator = nonzero(stats.sxx * stats.syy, span)?;
+            Ok(Value::Number(stats.sxy * stats.sxy / denominator))
+        },
+        "forecast" | "forecast.linear" => |args| {
+            let span = args.span;
+            let [x, ys, xs] = args_array::<3>(args)?;
+            let stats = PairedStats::new(&xs, &ys)?;
+            map_arg(x, |[x]| {
+                stats.predict(x.to_number()?, span).map(Value::Number)
+            })
+        },
+
+        _ => return None,
+    })
+}
+
+/// Returns the numbers in the arguments. Blank values are skipped, as are
+/// text and booleans inside arrays.
+pub(super) fn numbers(args: &[Spanned<Value>]) -> FormulaResult<Vec<f64>> {
+    flat_numbers(args, |_| None)
+}
+
+/// Returns the numbers in the arguments like `numbers()`, except that text
+/// inside arrays counts as zero and booleans count as zero or one.
+fn numbers_a(args: &[Spanned<Value>]) -> FormulaResult<Vec<f64>> {
+    flat_numbers(args, |v| match v {
+        Value::Bool(b) => Some(*b as u8 as f64),
+        _ => Some(0.0),
+    })
+}
+
+fn flat_numbers(
+    args: &[Spanned<Value>],
+    non_numeric: fn(&Value) -> Option<f64>,
+) -> FormulaResult<Vec<f64>> {
+    let mut ret = vec![];
+    for arg in args {
+        match &arg.inner {
+            Value::Array(a) => {
+                for v in a.iter().flatten().filter(|v| !is_blank_or_empty(v)) {
+                    let n = array_element_number(v, arg.span)?;
+                    ret.extend(n.or_else(|| non_numeric(v)));
+                }
+            }
+            v if is_blank_or_empty(v) => (),
+            _ => ret.push(arg.to_number()?),
+        }
+    }
+    Ok(ret)
+}
+
+/// Returns how many of the arguments and the values in them are numbers.
+/// Unlike `numbers()`, errors and non-numeric text are skipped, even when
+/// given directly as an argument.
+fn count_numbers(args: &[Spanned<Value>]) -> usize {
+    let is_number = |v: &Value, span: Span| matches!(array_element_number(v, span), Ok(Some(_)));
+    args.iter()
+        .map(|arg| match &arg.inner {
+            Value::Array(a) => a
+                .iter()
+                .flatten()
+                .filter(|v| is_number(v, arg.span))
+                .count(),
+            v => is_number(v, arg.span) as usize,
+        })
+        .sum()
+}
+
+fn sorted_numbers(array: &Spanned<Value>) -> FormulaResult<Vec<f64>> {
+    let mut values = numbers(std::slice::from_ref(array))?;
+    values.sort_by(f64::total_cmp);
+    Ok(values)
+}
+
+/// Returns `n`, or an error if it is zero.
+fn nonzero(n: f64, span: Span) -> FormulaResult<f64> {
+    if n == 0.0 || n.is_nan() {
+        return Err(FormulaErrorMsg::DivideByZero.with_span(span));
+    }
+    Ok(n)
+}
+
+pub(super) fn mean(values: &[f64], span: Span) -> FormulaResult<f64> {
+    Ok(values.iter().sum::<f64>() / nonzero(values.len() as f64, span)?)
+}
+
+/// Returns the variance of some values, dividing by the number of values
+/// minus `ddof` (so 0 for a population and 1 for a sample).
+fn variance(values: &[f64], ddof: usize, span: Span) -> FormulaResult<f64> {
+    let m = mean(values, span)?;
+    let divisor = values.len().saturating_sub(ddof) as f64;
+    let sum_of_squares: f64 = values.iter().map(|x| (x - m).powi(2)).sum();
+    Ok(sum_of_squares / nonzero(divisor, span)?)
+}
+
+/// Returns the `k`th value (starting from 1) of `values`, or an error if `k`
+/// is out of range.
+fn nth(values: &[f64], k: &Spanned<Value>) -> FormulaResult<f64> {
+    let k_num = k.to_number()?.ceil();
+    if k_num < 1.0 || k_num > values.len() as f64 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(k.span));
+    }
+    Ok(values[k_num as usize - 1])
+}
+
+/// Interpolates between sorted values at a fractional index.
+fn interpolate(sorted: &[f64], index: f64) -> f64 {
+    let lower = index.floor() as usize;
+    let upper = (lower + 1).min(sorted.len() - 1);
+    let fraction = index - lower as f64;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+/// Returns the `k`th percentile of sorted values, where the lowest value is
+/// the 0th percentile and the highest value is the 100th.
+fn percentile_inclusive(sorted: &[f64], k: f64, span: Span) -> FormulaResult<f64> {
+    if sorted.is_empty() {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(span));
+    }
+    Ok(interpolate(sorted, k * (sorted.len() - 1) as f64))
+}
+
+/// Returns the `k`th percentile of sorted values, excluding 0 and 1. Returns
+/// an error if there aren't enough values to determine the percentile.
+fn percentile_exclusive(sorted: &[f64], k: f64, span: Span) -> FormulaResult<f64> {
+    let rank = k * (sorted.len() + 1) as f64;
+    if rank < 1.0 || rank > sorted.len() as f64 {
+        return Err(FormulaErrorMsg::InvalidArgument.with_span(span));
+    }
+    Ok(interpolate(sorted, rank - 1.0))
+}
+
+/// Sums used by functions that compare two sets of values, such as `CORREL`.
+struct PairedStats {
+    /// Number of pairs.
+    n: f64,
+    mean_x: f64,
+    mean_y: f64,
+    /// Sum of squared deviations of `x` from its mean.
+    sxx: f64,
+    /// Sum of squared deviations of `y` from its mean.
+    syy: f64,
+    /// Sum of products of deviations of `x` and `y` from their means.
+    sxy: f64,
+}
+impl PairedStats {
+    /// Computes statistics for pairs of numbers from two arrays of the same
+    /// size. Pairs where either value is not a number are skipped.
+    fn new(xs: &Spanned<Value>, ys: &Spanned<Value>) -> FormulaResult<Self> {
+        let x_size = xs.inner.array_size().unwrap_or((1, 1));
+        let y_size = ys.inner.array_size().unwrap_or((1, 1));
+        if x_size != y_size {
+            return Err(FormulaErrorMsg::ArraySizeMismatch {
+                expected: x_size,
+                got: y_size,
+            }
+            .with_span(ys.span));
+        }
+
+        let mut pairs = vec![];
+        for (x, y) in flat_values(xs).zip(flat_values(ys)) {
+            let x = array_element_number(x, xs.span)?;
+            let y = array_element_number(y, ys.span)?;
+            pairs.extend(x.zip(y));
+        }
+
+        let span = Span::merge(xs, ys);
+        let (xs, ys): (Vec<f64>, Vec<f64>) = pairs.into_iter().unzip();
+        let mean_x = mean(&xs, span)?;
+        let mean_y = mean(&ys, span)?;
+        Ok(Self {
+            n: xs.len() as f64,
+            mean_x,
+            mean_y,
+            sxx: xs.iter().map(|x| (x - mean_x).powi(2)).sum(),
+            syy: ys.iter().map(|y| (y - mean_y).powi(2)).sum(),
+            sxy: xs
+                .iter()
+                .zip(&ys)
+                .map(|(x, y)| (x - mean_x) * (y - mean_y))
+                .sum(),
+        })
+    }
+
+    /// Returns the slope of the least-squares regression line.
+    fn slope(&self, span: Span) -> FormulaResult<f64> {
+        Ok(self.sxy / nonzero(self.sxx, span)?)
+    }
+
+    /// Returns the `y` value on the least-squares regression line at `x`.
+    fn predict(&self, x: f64, span: Span) -> FormulaResult<f64> {
+        Ok(self.mean_y + (x - self.mean_x) * self.slope(span)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_zero_argument_semantics() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            FormulaErrorMsg::DivideByZero,
+            eval(g, "AVERAGE({''})").unwrap_err().msg,
+        );
+        assert_eq!(
+            ErrorKind::DivideByZero,
+            eval(g, "AVERAGE()").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            ErrorKind::DivideByZero,
+            eval(g, "STDEV.S(5)").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            ErrorKind::DivideByZero,
+            eval(g, "VAR.P({'a', ''})").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "MEDIAN({''})").unwrap_err().msg.kind()
+        );
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "MODE(1, 2, 3)").unwrap_err().msg.kind(),
+        );
+        assert_eq!("0", eval_to_string(g, "MIN()"));
+        assert_eq!("0", eval_to_string(g, "MAX({'a'})"));
+        assert_eq!("0", eval_to_string(g, "COUNT()"));
+    }
+
+    #[test]
+    fn test_averages_and_counts() {
+        let g = &mut PanicGridMock;
+        // Text and booleans in arrays are skipped, but not direct arguments.
+        assert_eq!("2", eval_to_string(g, "AVERAGE({1, 'x', TRUE(), ''}, 3)"));
+        assert_eq!(
+            "1.25",
+            eval_to_string(g, "AVERAGEA({1, 'x', TRUE(), ''}, 3)")
+        );
+        assert_eq!("1", eval_to_string(g, "AVERAGE(TRUE())"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "AVERAGE('x')").unwrap_err().msg.kind()
+        );
+
+        assert_eq!("3", eval_to_string(g, "MEDIAN({5, 1, 3})"));
+        assert_eq!("2.5", eval_to_string(g, "MEDIAN({4, 1, 3, 2})"));
+        assert_eq!("3", eval_to_string(g, "MODE({1, 3, 2, 3, 2})"));
+
+        assert_eq!("2", eval_to_string(g, "COUNT({1, 'x', ''}, 2)"));
+        // Errors and non-numeric text aren't counted, even as arguments.
+        assert_eq!("2", eval_to_string(g, "COUNT({1, 'x', NA()}, 2)"));
+        assert_eq!("0", eval_to_string(g, "COUNT('abc')"));
+        // Empty text isn't blank.
+        assert_eq!("4", eval_to_string(g, "COUNTA({1, 'x', ''}, 2)"));
+        // Errors aren't blank either.
+        assert_eq!("2", eval_to_string(g, "COUNTA({1, NA()})"));
+        assert_eq!("0", eval_to_string(g, "COUNTBLANK({1, ''; '', 'x'})"));
+
+        assert_eq!("-1", eval_to_string(g, "MIN({3, 'x'}, -1)"));
+        assert_eq!("3", eval_to_string(g, "MAX({3, 'x'}, -1)"));
+    }
+
+    #[test]
+    fn test_spread_and_ranking() {
+        let g = &mut PanicGridMock;
+        let data = "{2, 4, 4, 4, 5, 5, 7, 9}";
+        assert_eq!("4", eval_to_string(g, &format!("VAR.P({data})")));
+        assert_eq!("2", eval_to_string(g, &format!("STDEV.P({data})")));
+        assert_eq!(
+            "4.571428571428571",
+            eval_to_string(g, &format!("VAR.S({data})"))
+        );
+        assert_eq!(
+            "2.138089935299395",
+            eval_to_string(g, &format!("STDEV.S({data})")),
+        );
+
+        assert_eq!(
+            "{9, 7}",
+            eval_to_string(g, &format!("LARGE({data}, {{1, 2}})"))
+        );
+        assert_eq!("4", eval_to_string(g, &format!("SMALL({data}, 3)")));
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, "SMALL({1, 2}, 3)").unwrap_err().msg.kind()
+        );
+        assert_eq!(
+            "{2, 1, 4}",
+            eval_to_string(g, "RANK({5, 9, 2}, {9, 5, 2, 5})")
+        );
+        assert_eq!("1", eval_to_string(g, "RANK(2, {9, 5, 2, 5}, 1)"));
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "RANK(3, {9, 5, 2})").unwrap_err().msg.kind(),
+        );
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let g = &mut PanicGridMock;
+        let data = "{1, 2, 3, 4}";
+        assert_eq!(
+            "1.75",
+            eval_to_string(g, &format!("PERCENTILE({data}, 0.25)"))
+        );
+        assert_eq!(
+            "{1, 4}",
+            eval_to_string(g, &format!("PERCENTILE.INC({data}, {{0, 1}})")),
+        );
+        assert_eq!(
+            "1.25",
+            eval_to_string(g, &format!("PERCENTILE.EXC({data}, 0.25)"))
+        );
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, &format!("PERCENTILE.EXC({data}, 0.1)"))
+                .unwrap_err()
+                .msg
+                .kind(),
+        );
+        assert_eq!("3.25", eval_to_string(g, &format!("QUARTILE({data}, 3)")));
+        assert_eq!(
+            "3.75",
+            eval_to_string(g, &format!("QUARTILE.EXC({data}, 3)"))
+        );
+        assert_eq!(
+            ErrorKind::Num,
+            eval(g, &format!("QUARTILE.EXC({data}, 4)"))
+                .unwrap_err()
+                .msg
+                .kind(),
+        );
+    }
+
+    #[test]
+    fn test_paired_statistics() {
+        let g = &mut PanicGridMock;
+        let ys = "{2, 4, 5, 4, 5}";
+        let xs = "{1, 2, 3, 4, 5}";
+        assert_eq!("0.6", eval_to_string(g, &format!("SLOPE({ys}, {xs})")));
+        assert_eq!("2.2", eval_to_string(g, &format!("INTERCEPT({ys}, {xs})")));
+        assert_eq!(
+            "{2.8, 5.8}",
+            eval_to_string(g, &format!("FORECAST.LINEAR({{1, 6}}, {ys}, {xs})")),
+        );
+        assert_eq!(
+            "0.7745966692414834",
+            eval_to_string(g, &format!("CORREL({xs}, {ys})")),
+        );
+        assert_eq!(
+            "0.6",
+            eval_to_string(g, &format!("ROUND(RSQ({ys}, {xs}), 10)"))
+        );
+        assert_eq!(
+            "1.2",
+            eval_to_string(g, &format!("COVARIANCE.P({xs}, {ys})"))
+        );
+        assert_eq!(
+            "1.5",
+            eval_to_string(g, &format!("COVARIANCE.S({xs}, {ys})"))
+        );
+
+        // Pairs with a non-numeric value are skipped.
+        assert_eq!("1", eval_to_string(g, "SLOPE({1, 'x', 3}, {1, 2, 3})"));
+
+        let error = eval(g, "CORREL({1, 2}, {1, 2, 3})").unwrap_err();
+        assert!(matches!(
+            error.msg,
+            FormulaErrorMsg::ArraySizeMismatch { .. },
+        ));
+        assert_eq!(Some(Span { start: 15, end: 24 }), error.span);
+        assert_eq!(
+            FormulaErrorMsg::DivideByZero,
+            eval(g, "SLOPE({1, 2}, {3, 3})").unwrap_err().msg,
+        );
+    }
+}
@@ -0,0 +1,811 @@
+//! Text functions, such as `LEN` and `SUBSTITUTE`, and regular expression
+//! functions, such as `REGEXMATCH`.
+//!
+//! Positions and lengths count graphemes (characters as the user sees them),
+//! so an emoji or a letter with a combining accent counts as one character.
+
+use smallvec::smallvec;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::*;
+use crate::formulas::format::format_value;
+
+/// Maximum length in bytes of text produced by `REPT()`.
+const MAX_REPT_LEN: usize = 1_000_000;
+
+/// Maximum number of compiled patterns kept by `cached_regex()`.
+const REGEX_CACHE_SIZE: usize = 64;
+
+thread_local! {
+    /// Compiled regular expressions, keyed by pattern, so that a formula
+    /// filled down a column doesn't recompile its pattern for every cell.
+    static REGEX_CACHE: RefCell<HashMap<String, Regex>> = RefCell::default();
+}
+
+pub fn function_from_name(name: &str) -> Option<PureFunction> {
+    Some(match name {
+        "len" => {
+            array_mapped!(|[s]| Ok(Value::Number(s.to_text()?.graphemes(true).count() as f64)))
+        }
+        "left" => |args| match args.inner.len() {
+            1 => array_map(args, |[s]| Ok(Value::String(left(&s.to_text()?, 1)))),
+            _ => array_map(args, |[s, n]| {
+                Ok(Value::String(left(&s.to_text()?, to_count(&n)?)))
+            }),
+        },
+        "right" => |args| match args.inner.len() {
+            1 => array_map(args, |[s]| Ok(Value::String(right(&s.to_text()?, 1)))),
+            _ => array_map(args, |[s, n]| {
+                Ok(Value::String(right(&s.to_text()?, to_count(&n)?)))
+            }),
+        },
+        "mid" => array_mapped!(|[s, start, n]| {
+            let text = s.to_text()?;
+            let start = to_position(&start)?;
+            let n = to_count(&n)?;
+            Ok(Value::String(
+                text.graphemes(true).skip(start - 1).take(n).collect(),
+            ))
+        }),
+        "upper" => array_mapped!(|[s]| Ok(Value::String(s.to_text()?.to_uppercase()))),
+        "lower" => array_mapped!(|[s]| Ok(Value::String(s.to_text()?.to_lowercase()))),
+        "proper" => array_mapped!(|[s]| Ok(Value::String(proper(&s.to_text()?)))),
+        "trim" => array_mapped!(|[s]| {
+            // Only spaces are removed, and runs of them between words become
+            // a single space.
+            Ok(Value::String(
+                s.to_text()?.split(' ').filter(|w| !w.is_empty()).join(" "),
+            ))
+        }),
+        "substitute" => |args| match args.inner.len() {
+            3 => array_map(args, |[s, old, new]| {
+                substitute(&s.to_text()?, &old.to_text()?, &new.to_text()?, None)
+            }),
+            _ => array_map(args, |[s, old, new, instance]| {
+                let instance = to_position(&instance)?;
+                substitute(
+                    &s.to_text()?,
+                    &old.to_text()?,
+                    &new.to_text()?,
+                    Some(instance),
+                )
+            }),
+        },
+        "replace" => array_mapped!(|[s, start, n, new]| {
+            let text = s.to_text()?;
+            let start = to_position(&start)?;
+            let n = to_count(&n)?;
+            let graphemes = text.graphemes(true);
+            Ok(Value::String(
+                graphemes
+                    .clone()
+                    .take(start - 1)
+                    .chain([new.to_text()?.as_str()])
+                    .chain(graphemes.skip((start - 1).saturating_add(n)))
+                    .collect(),
+            ))
+        }),
+        "find" => |args| match args.inner.len() {
+            2 => array_map(args, |[needle, haystack]| {
+                find(needle, haystack, None, SearchKind::Find)
+            }),
+            _ => array_map(args, |[needle, haystack, start]| {
+                find(needle, haystack, Some(start), SearchKind::Find)
+            }),
+        },
+        "search" => |args| match args.inner.len() {
+            2 => array_map(args, |[needle, haystack]| {
+                find(needle, haystack, None, SearchKind::Search)
+            }),
+            _ => array_map(args, |[needle, haystack, start]| {
+                find(needle, haystack, Some(start), SearchKind::Search)
+            }),
+        },
+        "textjoin" => textjoin,
+        "split" => split,
+        "textsplit" => textsplit,
+        "rept" => array_mapped!(|[s, n]| {
+            let text = s.to_text()?;
+            let count = to_count(&n)?;
+            if text.len().saturating_mul(count) > MAX_REPT_LEN {
+                return Err(FormulaErrorMsg::Overflow.with_span(n.span));
+            }
+            Ok(Value::String(text.repeat(count)))
+        }),
+        "exact" => array_mapped!(|[a, b]| Ok(Value::Bool(a.to_text()? == b.to_text()?))),
+        "value" => array_mapped!(|[s]| {
+            // Percentages are allowed here, but not in arithmetic.
+            if let Value::String(text) = &s.inner {
+                if let Some(percent) = text.trim().strip_suffix('%') {
+                    let percent = Spanned {
+                        span: s.span,
+                        inner: Value::String(percent.to_string()),
+                    };
+                    return Ok(Value::Number(percent.to_number()? / 100.0));
+                }
+            }
+            Ok(Value::Number(s.to_number()?))
+        }),
+        "text" => array_mapped!(|[value, format]| {
+            Ok(Value::String(format_value(&value, &format.to_text()?)?))
+        }),
+        "char" => array_mapped!(|[n]| {
+            let code = n.to_number()?.trunc();
+            (1.0..=u32::MAX as f64)
+                .contains(&code)
+                .then(|| char::from_u32(code as u32))
+                .flatten()
+                .map(|c| Value::String(c.to_string()))
+                .ok_or_else(|| {
+                    FormulaErrorMsg::Expected {
+                        expected: "character code".into(),
+                        got: Some(code.to_string().into()),
+                    }
+                    .with_span(n.span)
+                })
+        }),
+        // Both of these return the Unicode code point of the first
+        // character.
+        "code" | "unicode" => array_mapped!(|[s]| {
+            match s.to_text()?.chars().next() {
+                Some(c) => Ok(Value::Number(c as u32 as f64)),
+                None => Err(FormulaErrorMsg::Expected {
+                    expected: "text".into(),
+                    got: Some("empty string".into()),
+                }
+                .with_span(s.span)),
+            }
+        }),
+        "clean" => array_mapped!(|[s]| {
+            Ok(Value::String(
+                s.to_text()?.chars().filter(|c| !c.is_control()).collect(),
+            ))
+        }),
+
+        // Regular expression functions
+        "regexmatch" => array_mapped!(|[s, pattern]| {
+            Ok(Value::Bool(cached_regex(&pattern)?.is_match(&s.to_text()?)))
+        }),
+        "regexextract" => regexextract,
+        "regexreplace" => array_mapped!(|[s, pattern, replacement]| {
+            let regex = cached_regex(&pattern)?;
+            Ok(Value::String(
+                regex
+                    .replace_all(&s.to_text()?, replacement.to_text()?.as_str())
+                    .into_owned(),
+            ))
+        }),
+
+        _ => return None,
+    })
+}
+
+/// Returns a number of characters, such as the length argument of `LEFT()`,
+/// which must not be negative.
+fn to_count(n: &Spanned<Value>) -> FormulaResult<usize> {
+    let count = n.to_number()?.trunc();
+    if count >= 0.0 {
+        Ok(count as usize)
+    } else {
+        Err(FormulaErrorMsg::Expected {
+            expected: "non-negative number".into(),
+            got: Some(count.to_string().into()),
+        }
+        .with_span(n.span))
+    }
+}
+
+/// Returns a 1-based position, such as the start argument of `MID()`, which
+/// must be at least 1.
+fn to_position(n: &Spanned<Value>) -> FormulaResult<usize> {
+    let position = n.to_number()?.trunc();
+    if position >= 1.0 {
+        Ok(position as usize)
+    } else {
+        Err(FormulaErrorMsg::Expected {
+            expected: "positive number".into(),
+            got: Some(position.to_string().into()),
+        }
+        .with_span(n.span))
+    }
+}
+
+/// Returns the first `n` characters of a string.
+fn left(s: &str, n: usize) -> String {
+    s.graphemes(true).take(n).collect()
+}
+/// Returns the last `n` characters of a string.
+fn right(s: &str, n: usize) -> String {
+    let graphemes = s.graphemes(true).collect_vec();
+    graphemes[graphemes.len().saturating_sub(n)..].concat()
+}
+
+/// Capitalizes the first letter of each word and lowercases the other
+/// letters. A word starts at any letter that comes after a non-letter.
+fn proper(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut after_letter = false;
+    for c in s.chars() {
+        if after_letter {
+            ret.extend(c.to_lowercase());
+        } else {
+            ret.extend(c.to_uppercase());
+        }
+        after_letter = c.is_alphabetic();
+    }
+    ret
+}
+
+/// Replaces every occurrence of `old` in `s` with `new`, or only the
+/// occurrence numbered `instance` (starting from 1).
+fn substitute(s: &str, old: &str, new: &str, instance: Option<usize>) -> FormulaResult<Value> {
+    if old.is_empty() {
+        return Ok(Value::String(s.to_string()));
+    }
+    Ok(Value::String(match instance {
+        None => s.replace(old, new),
+        Some(instance) => match s.match_indices(old).nth(instance - 1) {
+            Some((i, _)) => format!("{}{new}{}", &s[..i], &s[i + old.len()..]),
+            None => s.to_string(),
+        },
+    }))
+}
+
+/// Whether `FIND()` or `SEARCH()` is being evaluated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SearchKind {
+    /// Case-sensitive search for exact text.
+    Find,
+    /// Case-insensitive search that supports wildcards.
+    Search,
+}
+
+/// Returns the 1-based position of `needle` in `haystack`, starting the
+/// search at the position `start`.
+fn find(
+    needle: Spanned<Value>,
+    haystack: Spanned<Value>,
+    start: Option<Spanned<Value>>,
+    kind: SearchKind,
+) -> FormulaResult<Value> {
+    let needle_text = needle.to_text()?;
+    let haystack_text = haystack.to_text()?;
+    let start_position = match &start {
+        Some(start) => to_position(start)?,
+        None => 1,
+    };
+
+    // Convert the start position to a byte index. Starting just past the end
+    // is allowed, and finds only empty text.
+    let start_byte = haystack_text
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .chain([haystack_text.len()])
+        .nth(start_position - 1);
+    let span = start.map_or(haystack.span, |start| start.span);
+    let start_byte = start_byte.ok_or_else(|| FormulaErrorMsg::IndexOutOfBounds.with_span(span))?;
+
+    let found = match kind {
+        SearchKind::Find => haystack_text[start_byte..]
+            .find(&needle_text)
+            .map(|i| start_byte + i),
+        SearchKind::Search => {
+            let regex_string = format!("(?is){}", wildcard_regex_body(&needle_text));
+            let regex = Regex::new(&regex_string)
+                .map_err(|e| FormulaErrorMsg::InvalidRegex(e.to_string()).with_span(needle.span))?;
+            regex.find_at(&haystack_text, start_byte).map(|m| m.start())
+        }
+    };
+    match found {
+        Some(i) => Ok(Value::Number(
+            (haystack_text[..i].graphemes(true).count() + 1) as f64,
+        )),
+        None => Err(FormulaErrorMsg::TextNotFound.with_span(needle.span)),
+    }
+}
+
+/// Returns the text of every value in the arguments, including empty ones,
+/// flattening arrays.
+fn flat_texts(args: &[Spanned<Value>]) -> FormulaResult<Vec<String>> {
+    let mut ret = vec![];
+    for arg in args {
+        match &arg.inner {
+            Value::Array(a) => {
+                for v in a.iter().flatten() {
+                    ret.push(
+                        Spanned {
+                            span: arg.span,
+                            inner: v.clone(),
+                        }
+                        .to_text()?,
+                    );
+                }
+            }
+            _ => ret.push(arg.to_text()?),
+        }
+    }
+    Ok(ret)
+}
+
+fn textjoin(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    if args.inner.len() < 3 {
+        return Err(FormulaErrorMsg::BadArgumentCount.with_span(args.span));
+    }
+    // Multiple delimiters are used in turn.
+    let delimiters = flat_texts(&args.inner[..1])?;
+    let ignore_empty = args.inner[1].clone().into_single_value()?.to_condition()?;
+    let texts = flat_texts(&args.inner[2..])?
+        .into_iter()
+        .filter(|s| !(ignore_empty && s.is_empty()));
+
+    let mut ret = String::new();
+    for (i, text) in texts.enumerate() {
+        if i > 0 && !delimiters.is_empty() {
+            ret.push_str(&delimiters[(i - 1) % delimiters.len()]);
+        }
+        ret.push_str(&text);
+    }
+    Ok(Value::String(ret))
+}
+
+/// Compiles a regex that matches any of `delimiters`, preferring longer
+/// ones. Returns `None` if there are no non-empty delimiters.
+fn delimiter_regex(
+    delimiters: &[String],
+    case_insensitive: bool,
+    span: Span,
+) -> FormulaResult<Option<Regex>> {
+    let alternatives = delimiters
+        .iter()
+        .filter(|d| !d.is_empty())
+        .sorted_by_key(|d| std::cmp::Reverse(d.len()))
+        .map(|d| regex::escape(d))
+        .join("|");
+    if alternatives.is_empty() {
+        return Ok(None);
+    }
+    let flags = if case_insensitive { "(?i)" } else { "" };
+    Regex::new(&format!("{flags}{alternatives}"))
+        .map(Some)
+        .map_err(|e| FormulaErrorMsg::InvalidRegex(e.to_string()).with_span(span))
+}
+
+/// Splits text at each match of a regex, or returns it whole if there is no
+/// regex.
+fn split_text<'a>(text: &'a str, regex: &Option<Regex>) -> Vec<&'a str> {
+    match regex {
+        Some(regex) => regex.split(text).collect(),
+        None => vec![text],
+    }
+}
+
+fn split(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=4)?;
+    let text = args.inner[0].clone().into_single_value()?.to_text()?;
+    let delimiter = args.inner[1].clone().into_single_value()?;
+    let split_by_each = match args.inner.get(2) {
+        Some(arg) => arg.clone().into_single_value()?.to_condition()?,
+        None => true,
+    };
+    let remove_empty = match args.inner.get(3) {
+        Some(arg) => arg.clone().into_single_value()?.to_condition()?,
+        None => true,
+    };
+
+    let delimiter_text = delimiter.to_text()?;
+    let delimiters = if split_by_each {
+        delimiter_text.chars().map(String::from).collect_vec()
+    } else {
+        vec![delimiter_text]
+    };
+    let regex = delimiter_regex(&delimiters, false, delimiter.span)?;
+    let mut row: SmallVec<[Value; 1]> = split_text(&text, &regex)
+        .into_iter()
+        .filter(|s| !(remove_empty && s.is_empty()))
+        .map(|s| Value::String(s.to_string()))
+        .collect();
+    if row.is_empty() {
+        row.push(Value::String(String::new()));
+    }
+    Ok(Value::Array(vec![row]))
+}
+
+fn textsplit(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    check_arg_count(&args, 2..=6)?;
+    let text = args.inner[0].clone().into_single_value()?.to_text()?;
+    let ignore_empty = match args.inner.get(3) {
+        Some(arg) => arg.clone().into_single_value()?.to_condition()?,
+        None => false,
+    };
+    let case_insensitive = match args.inner.get(4) {
+        Some(arg) => match arg.clone().into_single_value()?.to_integer()? {
+            0 => false,
+            1 => true,
+            _ => return Err(FormulaErrorMsg::InvalidArgument.with_span(arg.span)),
+        },
+        None => false,
+    };
+    let pad_with = match args.inner.get(5) {
+        Some(arg) => arg.clone().into_single_value()?.inner,
+        None => Value::Error(Box::new(
+            FormulaErrorMsg::ErrorValue(ErrorKind::NotAvailable).without_span(),
+        )),
+    };
+
+    let col_delimiter = &args.inner[1];
+    let col_regex = delimiter_regex(
+        &flat_texts(std::slice::from_ref(col_delimiter))?,
+        case_insensitive,
+        col_delimiter.span,
+    )?;
+    let row_regex = match args.inner.get(2) {
+        Some(row_delimiter) => delimiter_regex(
+            &flat_texts(std::slice::from_ref(row_delimiter))?,
+            case_insensitive,
+            row_delimiter.span,
+        )?,
+        None => None,
+    };
+
+    let mut rows = split_text(&text, &row_regex)
+        .into_iter()
+        .filter(|row| !(ignore_empty && row.is_empty()))
+        .map(|row| {
+            split_text(row, &col_regex)
+                .into_iter()
+                .filter(|s| !(ignore_empty && s.is_empty()))
+                .map(|s| Value::String(s.to_string()))
+                .collect::<SmallVec<[Value; 1]>>()
+        })
+        .filter(|row| !row.is_empty())
+        .collect_vec();
+    if rows.is_empty() {
+        rows.push(smallvec![Value::String(String::new())]);
+    }
+
+    // Pad rows to the same width.
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(1);
+    for row in &mut rows {
+        row.resize(width, pad_with.clone());
+    }
+    Ok(Value::Array(rows))
+}
+
+/// Compiles a regular expression, reusing the compiled pattern from an
+/// earlier call if possible. Returns an error pointing at `pattern` if it is
+/// invalid.
+fn cached_regex(pattern: &Spanned<Value>) -> FormulaResult<Regex> {
+    let pattern_text = pattern.to_text()?;
+    if let Some(regex) = REGEX_CACHE.with(|cache| cache.borrow().get(&pattern_text).cloned()) {
+        return Ok(regex);
+    }
+    let regex = Regex::new(&pattern_text)
+        .map_err(|e| FormulaErrorMsg::InvalidRegex(e.to_string()).with_span(pattern.span))?;
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= REGEX_CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(pattern_text, regex.clone());
+    });
+    Ok(regex)
+}
+
+/// Returns the capture groups of the first match of `regex` in `s`, or the
+/// whole match if there are no capture groups.
+fn regex_captures(regex: &Regex, s: &Spanned<Value>) -> FormulaResult<Vec<String>> {
+    let text = s.to_text()?;
+    let captures = regex
+        .captures(&text)
+        .ok_or_else(|| FormulaErrorMsg::NoMatch.with_span(s.span))?;
+    let first_group = if captures.len() > 1 { 1 } else { 0 };
+    Ok((first_group..captures.len())
+        .map(|i| captures.get(i).map_or("", |m| m.as_str()).to_string())
+        .collect())
+}
+
+/// Evaluates `REGEXEXTRACT()`. Each capture group gets its own column, so
+/// text with several groups spills to the right.
+fn regexextract(args: Spanned<Vec<Spanned<Value>>>) -> FormulaResult<Value> {
+    let [text, pattern] = <[Spanned<Value>; 2]>::try_from(args.inner)
+        .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(args.span))?;
+    let pattern = pattern.into_single_value()?;
+    let regex = cached_regex(&pattern)?;
+    let to_value = |result: FormulaResult<Vec<String>>| match result {
+        Ok(groups) => groups.into_iter().map(Value::String).collect(),
+        Err(e) => {
+            let width = regex.captures_len().saturating_sub(1).max(1);
+            vec![Value::Error(Box::new(e)); width]
+        }
+    };
+
+    match &text.inner {
+        Value::Array(rows) => Ok(Value::Array(
+            rows.iter()
+                .map(|row| {
+                    row.iter()
+                        .flat_map(|v| {
+                            to_value(regex_captures(
+                                &regex,
+                                &Spanned {
+                                    span: text.span,
+                                    inner: v.clone(),
+                                },
+                            ))
+                        })
+                        .collect()
+                })
+                .collect(),
+        )),
+        _ => {
+            let groups = regex_captures(&regex, &text)?;
+            Ok(match &groups[..] {
+                [single] => Value::String(single.clone()),
+                _ => Value::Array(vec![groups.into_iter().map(Value::String).collect()]),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_text_length_and_slicing() {
+        let g = &mut PanicGridMock;
+        assert_eq!("5", eval_to_string(g, "LEN('hello')"));
+        // A family emoji and an accented letter are one character each.
+        assert_eq!("3", eval_to_string(g, "LEN('a👨‍👩‍👧e\u{301}')"));
+        assert_eq!("{1, 2, 0}", eval_to_string(g, "LEN({'a', 'bc', ''})"));
+        assert_eq!("h", eval_to_string(g, "LEFT('hello')"));
+        assert_eq!("hel", eval_to_string(g, "LEFT('hello', 3)"));
+        assert_eq!("hello", eval_to_string(g, "LEFT('hello', 10)"));
+        assert_eq!("lo", eval_to_string(g, "RIGHT('hello', 2)"));
+        assert_eq!("👍🏽!", eval_to_string(g, "RIGHT('ok 👍🏽!', 2)"));
+        assert_eq!("ell", eval_to_string(g, "MID('hello', 2, 3)"));
+        assert_eq!("", eval_to_string(g, "MID('hello', 9, 3)"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "LEFT('hello', -1)").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "MID('hello', 0, 1)").unwrap_err().msg.kind(),
+        );
+    }
+
+    #[test]
+    fn test_text_case_and_whitespace() {
+        let g = &mut PanicGridMock;
+        assert_eq!("ÉCOLE", eval_to_string(g, "UPPER('école')"));
+        assert_eq!("straße", eval_to_string(g, "LOWER('STRAßE')"));
+        assert_eq!(
+            "Hello World O'Neil 2Nd",
+            eval_to_string(g, "PROPER(\"hELLO wORLD o'neil 2nd\")"),
+        );
+        assert_eq!("a b c", eval_to_string(g, "TRIM('  a   b c ')"));
+        assert_eq!("ab", eval_to_string(g, "CLEAN('a' & CHAR(7) & 'b')"));
+        assert_eq!("TRUE", eval_to_string(g, "EXACT('abc', 'abc')"));
+        assert_eq!("FALSE", eval_to_string(g, "EXACT('abc', 'ABC')"));
+        assert_eq!("ababab", eval_to_string(g, "REPT('ab', 3)"));
+        assert_eq!(
+            FormulaErrorMsg::Overflow,
+            eval(g, "REPT('ab', 1000000)").unwrap_err().msg,
+        );
+    }
+
+    #[test]
+    fn test_text_substitution() {
+        let g = &mut PanicGridMock;
+        assert_eq!("a-b-c", eval_to_string(g, "SUBSTITUTE('a b c', ' ', '-')"));
+        assert_eq!(
+            "a b-c",
+            eval_to_string(g, "SUBSTITUTE('a b c', ' ', '-', 2)")
+        );
+        assert_eq!(
+            "a b c",
+            eval_to_string(g, "SUBSTITUTE('a b c', ' ', '-', 3)")
+        );
+        assert_eq!("a b c", eval_to_string(g, "SUBSTITUTE('a b c', '', '-')"));
+        assert_eq!("h👋lo", eval_to_string(g, "REPLACE('hello', 2, 2, '👋')"));
+        assert_eq!("héllo!", eval_to_string(g, "REPLACE('héllo', 6, 0, '!')"));
+    }
+
+    #[test]
+    fn test_find_and_search() {
+        let g = &mut PanicGridMock;
+        assert_eq!("3", eval_to_string(g, "FIND('l', 'hello')"));
+        assert_eq!("4", eval_to_string(g, "FIND('l', 'hello', 4)"));
+        assert_eq!("3", eval_to_string(g, "FIND('c', '👍🏽bc')"));
+        assert_eq!(
+            FormulaErrorMsg::TextNotFound,
+            eval(g, "FIND('L', 'hello')").unwrap_err().msg,
+        );
+        assert_eq!("3", eval_to_string(g, "SEARCH('L', 'hello')"));
+        assert_eq!("2", eval_to_string(g, "SEARCH('e?l', 'hello')"));
+        assert_eq!("1", eval_to_string(g, "SEARCH('h*o', 'hello')"));
+        assert_eq!("2", eval_to_string(g, "SEARCH('~*', 'a**')"));
+        assert_eq!("{1, 2}", eval_to_string(g, "SEARCH('x', {'xa', 'ax'})"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "SEARCH('z', 'hello')").unwrap_err().msg.kind(),
+        );
+        // Patterns too big to compile point at the pattern.
+        let error = eval(g, "SEARCH(REPT('?', 100000), 'a')").unwrap_err();
+        assert!(matches!(error.msg, FormulaErrorMsg::InvalidRegex(_)));
+        assert_eq!(Some(Span { start: 7, end: 24 }), error.span);
+    }
+
+    #[test]
+    fn test_textjoin_and_split() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            "a, b, , c",
+            eval_to_string(g, "TEXTJOIN(', ', FALSE(), {'a', 'b'; '', 'c'})"),
+        );
+        assert_eq!(
+            "a, b, c",
+            eval_to_string(g, "TEXTJOIN(', ', TRUE(), {'a', 'b'; '', 'c'})"),
+        );
+        assert_eq!(
+            "1-2/3",
+            eval_to_string(g, "TEXTJOIN({'-', '/'}, TRUE(), 1, 2, 3)")
+        );
+        // Flags can be numbers.
+        assert_eq!(
+            "a, c",
+            eval_to_string(g, "TEXTJOIN(', ', 1, {'a', ''; 'c', ''})")
+        );
+        assert_eq!("{a, , c}", eval_to_string(g, "SPLIT('a,,c', ',', 0, 0)"));
+
+        assert_eq!("{a, b, c}", eval_to_string(g, "SPLIT('a,b;;c', ',;')"));
+        assert_eq!(
+            "{a, b, , c}",
+            eval_to_string(g, "SPLIT('a,b;;c', ',;', TRUE(), FALSE())")
+        );
+        assert_eq!(
+            "{a, b;;c}",
+            eval_to_string(g, "SPLIT('a,;b;;c', ',;', FALSE())")
+        );
+
+        assert_eq!("{a, b, c}", eval_to_string(g, "TEXTSPLIT('a,b,c', ',')"));
+        assert_eq!(
+            "{a, b; c, #N/A}",
+            eval_to_string(g, "TEXTSPLIT('a,b;c', ',', ';')"),
+        );
+        assert_eq!(
+            "{a, b; c, -}",
+            eval_to_string(g, "TEXTSPLIT('a,b;c', ',', ';', FALSE(), 0, '-')"),
+        );
+        assert_eq!(
+            "{a, b, c}",
+            eval_to_string(g, "TEXTSPLIT('aXbxc', 'x', '', FALSE(), 1)"),
+        );
+        assert_eq!(
+            "{a, b, c}",
+            eval_to_string(g, "TEXTSPLIT('a, b;c', {', ', ';'})"),
+        );
+        assert_eq!("{a, , b}", eval_to_string(g, "TEXTSPLIT('a,,b', ',')"),);
+        assert_eq!(
+            "{a, b}",
+            eval_to_string(g, "TEXTSPLIT('a,,b', ',', '', TRUE())"),
+        );
+    }
+
+    #[test]
+    fn test_regex_functions() {
+        let g = &mut PanicGridMock;
+        assert_eq!("TRUE", eval_to_string(g, r#"REGEXMATCH('abc123', '\\d+')"#));
+        assert_eq!(
+            "{TRUE, FALSE}",
+            eval_to_string(g, "REGEXMATCH({'cat', 'dog'}, '^c')"),
+        );
+        assert_eq!(
+            "123",
+            eval_to_string(g, r#"REGEXEXTRACT('abc123', '\\d+')"#)
+        );
+        assert_eq!(
+            "{2024, 01}",
+            eval_to_string(g, r#"REGEXEXTRACT('2024-01-15', '(\\d+)-(\\d+)')"#),
+        );
+        // Each element of an array spills its groups to the right.
+        assert_eq!(
+            "{a, 1; b, 2}",
+            eval_to_string(g, "REGEXEXTRACT({'a=1'; 'b=2'}, '(.)=(.)')"),
+        );
+        assert_eq!(
+            "{a; #N/A}",
+            eval_to_string(g, "REGEXEXTRACT({'a1'; '22'}, '[a-z]')"),
+        );
+        assert_eq!(
+            FormulaErrorMsg::NoMatch,
+            eval(g, "REGEXEXTRACT('abc', 'x')").unwrap_err().msg,
+        );
+        assert_eq!(
+            "2024/01/15",
+            eval_to_string(g, "REGEXREPLACE('2024-01-15', '-', '/')"),
+        );
+        assert_eq!(
+            "15.01.2024",
+            eval_to_string(
+                g,
+                r#"REGEXREPLACE('2024-01-15', '(\\d+)-(\\d+)-(\\d+)', '$3.$2.$1')"#
+            ),
+        );
+
+        // Invalid patterns point at the pattern.
+        let error = eval(g, "REGEXMATCH('abc', 'a(')").unwrap_err();
+        assert!(matches!(error.msg, FormulaErrorMsg::InvalidRegex(_)));
+        assert_eq!(Some(Span { start: 18, end: 22 }), error.span);
+        assert_eq!(
+            "{#VALUE!; #VALUE!}",
+            eval_to_string(g, "REGEXREPLACE({'a'; 'b'}, '[', '')"),
+        );
+    }
+
+    #[test]
+    fn test_character_codes() {
+        let g = &mut PanicGridMock;
+        assert_eq!("A", eval_to_string(g, "CHAR(65)"));
+        assert_eq!("€", eval_to_string(g, "CHAR(8364)"));
+        assert_eq!("65", eval_to_string(g, "CODE('ABC')"));
+        assert_eq!("128077", eval_to_string(g, "UNICODE('👍')"));
+        assert_eq!(ErrorKind::Value, eval(g, "CHAR(0)").unwrap_err().msg.kind());
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "CODE('')").unwrap_err().msg.kind()
+        );
+    }
+
+    #[test]
+    fn test_value_and_text() {
+        let g = &mut PanicGridMock;
+        assert_eq!("1234.5", eval_to_string(g, "VALUE('$1234.5')"));
+        assert_eq!("0.25", eval_to_string(g, "VALUE('25%')"));
+        assert_eq!("45306", eval_to_string(g, "VALUE('2024-01-15')"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "VALUE('abc')").unwrap_err().msg.kind()
+        );
+
+        let text = |value: &str, format: &str| {
+            eval_to_string(&mut PanicGridMock, &format!("TEXT({value}, '{format}')"))
+        };
+        assert_eq!("1,234.57", text("1234.567", "#,##0.00"));
+        assert_eq!("-1,234.57", text("-1234.567", "#,##0.00"));
+        assert_eq!("(1,234.57)", text("-1234.567", "#,##0.00;(#,##0.00)"));
+        assert_eq!("zero", text("0", "0;-0;\"zero\""));
+        assert_eq!("0042", text("42", "0000"));
+        assert_eq!(".5", text("0.5", "#.##"));
+        assert_eq!("3.1", text("3.14159", "0.0"));
+        assert_eq!("12.5%", text("0.125", "0.0%"));
+        assert_eq!("$1,000", text("1000", "$#,##0"));
+        assert_eq!("1.23E+04", text("12345", "0.00E+00"));
+        assert_eq!("1.5E-03", text("0.0015", "0.0E+00"));
+        assert_eq!("555-1234", text("5551234", "000-0000"));
+        assert_eq!("1.2", text("1200", "0.0,"));
+        assert_eq!("42", text("42", "General"));
+
+        assert_eq!("2024-01-15", text("DATE(2024, 1, 15)", "yyyy-mm-dd"));
+        assert_eq!("01/15/24", text("DATE(2024, 1, 15)", "mm/dd/yy"));
+        assert_eq!(
+            "Monday, January 15, 2024",
+            text("DATE(2024, 1, 15)", "dddd, mmmm d, yyyy"),
+        );
+        assert_eq!("Jan 15", text("'2024-01-15'", "mmm d"));
+        assert_eq!("1:45 PM", text("TIME(13, 45, 0)", "h:mm AM/PM"));
+        assert_eq!("13:45:30", text("TIME(13, 45, 30)", "hh:mm:ss"));
+        assert_eq!(
+            "05:07.50",
+            text("TIME(0, 5, 7.5) + 0.5 / 86400", "mm:ss.00")
+        );
+        assert_eq!("26:30", text("1 + TIME(2, 30, 0)", "[h]:mm"));
+
+        assert_eq!("abc", text("'abc'", "0.00"));
+        assert_eq!("[abc]", text("'abc'", "\"[\"@\"]\""));
+        assert_eq!("TRUE", text("TRUE()", "0"));
+    }
+}
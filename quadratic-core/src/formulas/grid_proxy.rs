@@ -1,16 +1,72 @@
 use async_trait::async_trait;
 
-use crate::Pos;
+use crate::{Cell, Pos, Rect};
 
 /// Something that acts like a read-only spreadsheet grid.
 ///
+/// Every method takes the name of a sheet, or `None` for the sheet containing
+/// the formula being evaluated. Sheet names are case-insensitive.
+///
 /// Implement this using `#[async_trait(?Send)]`; see this link for why:
 /// https://rust-lang.github.io/async-book/07_workarounds/05_async_in_traits.html
 ///
 /// `?Send` is necessary because `JsValue` can't be sent between threads.
 #[async_trait(?Send)]
 pub trait GridProxy {
-    /// Fetches the contents of the cell at `pos`, not checking whether it
-    /// results in a circular reference.
-    async fn get(&mut self, pos: Pos) -> Option<String>;
+    /// Fetches the contents of the cell at `pos` on `sheet`, not checking
+    /// whether it results in a circular reference. Empty cells return
+    /// `Cell::Empty`.
+    async fn get(&mut self, sheet: Option<&str>, pos: Pos) -> Cell;
+
+    /// Fetches the contents of every cell in `rect` on `sheet`, as a list of
+    /// rows, not checking whether it results in a circular reference.
+    ///
+    /// The default implementation calls `get()` once for each cell; override
+    /// this if the grid can fetch a whole region at once.
+    async fn get_range(&mut self, sheet: Option<&str>, rect: Rect) -> Vec<Vec<Cell>> {
+        let mut rows = vec![];
+        for y in rect.y_range() {
+            let mut row = vec![];
+            for x in rect.x_range() {
+                row.push(self.get(sheet, Pos { x, y }).await);
+            }
+            rows.push(row);
+        }
+        rows
+    }
+
+    /// Returns the smallest rectangle containing every non-empty cell on
+    /// `sheet`, or `None` if the sheet is empty. This is used to evaluate
+    /// whole-row and whole-column references such as `A:A`.
+    ///
+    /// The default implementation returns `None`.
+    async fn bounds(&mut self, _sheet: Option<&str>) -> Option<Rect> {
+        None
+    }
+
+    /// Returns the cells in `rect` on `sheet` that contain something, so that
+    /// an array can't spill into them. Cells that only hold values spilled
+    /// from the formula being evaluated don't count.
+    ///
+    /// The default implementation returns no cells, so arrays always spill.
+    async fn occupied_cells(&mut self, _sheet: Option<&str>, _rect: Rect) -> Vec<Pos> {
+        vec![]
+    }
+
+    /// Returns whether the cell at `pos` on `sheet` contains a formula.
+    ///
+    /// The default implementation returns `false`.
+    async fn is_formula(&mut self, _sheet: Option<&str>, _pos: Pos) -> bool {
+        false
+    }
+
+    /// Returns the region that the array returned by the formula at `pos` on
+    /// `sheet` spilled into, including `pos` itself, or `None` if the cell
+    /// doesn't contain an array formula. This is used to evaluate spill range
+    /// references such as `A1#`.
+    ///
+    /// The default implementation returns `None`.
+    async fn spill_rect(&mut self, _sheet: Option<&str>, _pos: Pos) -> Option<Rect> {
+        None
+    }
 }
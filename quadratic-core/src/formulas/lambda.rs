@@ -0,0 +1,501 @@
+//! User-defined names and functions, created using `LET()` and `LAMBDA()`,
+//! and the functions that call lambdas, such as `MAP()` and `REDUCE()`.
+
+use smallvec::{smallvec, SmallVec};
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use super::*;
+
+/// Maximum number of values produced by `MAKEARRAY()`.
+const MAX_MAKEARRAY_LEN: u64 = 1_000_000;
+
+/// Maximum number of nested lambda calls.
+const MAX_CALL_DEPTH: usize = 32;
+
+/// User-defined function created by `LAMBDA()`.
+#[derive(Debug)]
+pub struct Lambda {
+    /// Names of the parameters, in lowercase.
+    pub params: Vec<String>,
+    /// Expression that computes the result.
+    pub body: AstNode,
+    /// Environment where the lambda was created.
+    pub env: Env,
+}
+impl PartialEq for Lambda {
+    fn eq(&self, other: &Self) -> bool {
+        // Lambdas are only equal to themselves.
+        std::ptr::eq(self, other)
+    }
+}
+impl fmt::Display for Lambda {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LAMBDA(")?;
+        for param in &self.params {
+            write!(f, "{param}, ")?;
+        }
+        write!(f, "{})", self.body)
+    }
+}
+impl Lambda {
+    /// Calls the lambda with evaluated arguments. `span` is the span of the
+    /// call, which is used for argument count and call depth errors.
+    pub async fn call(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: Vec<Spanned<Value>>,
+        span: Span,
+    ) -> FormulaResult<Value> {
+        if args.len() != self.params.len() {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(span));
+        }
+        if ctx.call_depth >= MAX_CALL_DEPTH {
+            return Err(FormulaErrorMsg::CallDepthExceeded.with_span(span));
+        }
+        let mut env = self.env.clone();
+        for (param, arg) in self.params.iter().zip(args) {
+            env = env.bind(param, arg.inner);
+        }
+        let outer_env = std::mem::replace(&mut ctx.env, env);
+        ctx.call_depth += 1;
+        let result = self.body.eval(ctx).await;
+        ctx.call_depth -= 1;
+        ctx.env = outer_env;
+        Ok(result.inner)
+    }
+
+    /// Calls the lambda and returns a single value, or an error value if the
+    /// lambda returns an array with more than one element.
+    async fn call_for_single_value(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: Vec<Spanned<Value>>,
+        span: Span,
+    ) -> FormulaResult<Value> {
+        let inner = self.call(ctx, args, span).await?;
+        Ok(Spanned { span, inner }
+            .into_single_value()
+            .map(|v| v.inner)
+            .unwrap_or_else(|e| Value::Error(Box::new(e))))
+    }
+}
+
+impl AstNode {
+    /// Evaluates `LET(name1, value1, name2, value2, ..., body)`. Each value
+    /// can refer to the names before it.
+    pub(super) async fn eval_let(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+    ) -> FormulaResult<Value> {
+        let [bindings @ .., body] = args else {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        };
+        if bindings.is_empty() || !bindings.len().is_multiple_of(2) {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        }
+        let names: Vec<&str> = bindings
+            .iter()
+            .step_by(2)
+            .map(|name| name.to_identifier())
+            .try_collect()?;
+
+        let outer_env = ctx.env.clone();
+        for (name, value) in names.into_iter().zip(bindings.iter().skip(1).step_by(2)) {
+            let value = value.eval(ctx).await;
+            ctx.env = ctx.env.bind(name, value.inner);
+        }
+        let result = body.eval(ctx).await;
+        ctx.env = outer_env;
+        Ok(result.inner)
+    }
+
+    /// Evaluates `LAMBDA(param1, param2, ..., body)`, which captures the
+    /// names currently in scope.
+    pub(super) fn eval_lambda(&self, ctx: &Ctx<'_>, args: &[AstNode]) -> FormulaResult<Value> {
+        let [params @ .., body] = args else {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        };
+        let mut seen = HashSet::new();
+        let mut param_names = vec![];
+        for param in params {
+            let name = param.to_identifier()?.to_ascii_lowercase();
+            if !seen.insert(name.clone()) {
+                return Err(FormulaErrorMsg::Expected {
+                    expected: "unique parameter name".into(),
+                    got: Some(format!("duplicate {name:?}").into()),
+                }
+                .with_span(param.span));
+            }
+            param_names.push(name);
+        }
+        Ok(Value::Lambda(Rc::new(Lambda {
+            params: param_names,
+            body: body.clone(),
+            env: ctx.env.clone(),
+        })))
+    }
+
+    /// Evaluates `MAP(array1, array2, ..., lambda)`, which calls the lambda
+    /// on corresponding elements of each array.
+    pub(super) async fn eval_map(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+    ) -> FormulaResult<Value> {
+        let (arrays, lambda) = self.eval_args_and_lambda(ctx, args).await?;
+        if arrays.is_empty() {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        }
+        let Some((rows, cols)) = functions::common_array_size(&arrays)? else {
+            return lambda.call_for_single_value(ctx, arrays, self.span).await;
+        };
+        let mut output_array = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let mut output_row = SmallVec::with_capacity(cols);
+            for col in 0..cols {
+                let lambda_args: Vec<_> = arrays
+                    .iter()
+                    .map(|array| array.get_array_value(row, col))
+                    .try_collect()?;
+                output_row.push(
+                    lambda
+                        .call_for_single_value(ctx, lambda_args, self.span)
+                        .await?,
+                );
+            }
+            output_array.push(output_row);
+        }
+        Ok(Value::Array(output_array))
+    }
+
+    /// Evaluates `REDUCE(initial, array, lambda)`, which calls the lambda
+    /// with an accumulator and each element of the array in turn and returns
+    /// the final accumulator. If `scan` is true, evaluates `SCAN()` instead,
+    /// which returns every intermediate accumulator.
+    pub(super) async fn eval_reduce(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+        scan: bool,
+    ) -> FormulaResult<Value> {
+        let (values, lambda) = self.eval_args_and_lambda(ctx, args).await?;
+        let [initial, array]: [Spanned<Value>; 2] = values
+            .try_into()
+            .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(self.span))?;
+        let array_span = array.span;
+
+        let mut accumulator = initial.into_single_value()?.inner;
+        let mut output_array = vec![];
+        for row in into_rows(array.inner) {
+            let mut output_row = SmallVec::with_capacity(row.len());
+            for value in row {
+                let lambda_args = vec![
+                    Spanned {
+                        span: self.span,
+                        inner: accumulator,
+                    },
+                    Spanned {
+                        span: array_span,
+                        inner: value,
+                    },
+                ];
+                accumulator = lambda
+                    .call_for_single_value(ctx, lambda_args, self.span)
+                    .await?;
+                if scan {
+                    output_row.push(accumulator.clone());
+                }
+            }
+            output_array.push(output_row);
+        }
+
+        if scan {
+            Ok(Value::Array(output_array))
+        } else {
+            Ok(accumulator)
+        }
+    }
+
+    /// Evaluates `BYROW(array, lambda)`, which calls the lambda on each row
+    /// of the array and returns a column of the results.
+    pub(super) async fn eval_byrow(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+    ) -> FormulaResult<Value> {
+        let (values, lambda) = self.eval_args_and_lambda(ctx, args).await?;
+        let [array]: [Spanned<Value>; 1] = values
+            .try_into()
+            .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(self.span))?;
+
+        let mut output_array = vec![];
+        for row in into_rows(array.inner) {
+            let lambda_args = vec![Spanned {
+                span: array.span,
+                inner: Value::Array(vec![row]),
+            }];
+            let result = lambda
+                .call_for_single_value(ctx, lambda_args, self.span)
+                .await?;
+            output_array.push(smallvec![result]);
+        }
+        Ok(Value::Array(output_array))
+    }
+
+    /// Evaluates `BYCOL(array, lambda)`, which calls the lambda on each
+    /// column of the array and returns a row of the results.
+    pub(super) async fn eval_bycol(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+    ) -> FormulaResult<Value> {
+        let (values, lambda) = self.eval_args_and_lambda(ctx, args).await?;
+        let [array]: [Spanned<Value>; 1] = values
+            .try_into()
+            .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(self.span))?;
+
+        let rows = into_rows(array.inner);
+        let cols = rows.first().map_or(0, |row| row.len());
+        let mut output_row = SmallVec::with_capacity(cols);
+        for col in 0..cols {
+            let column = rows.iter().map(|row| smallvec![row[col].clone()]).collect();
+            let lambda_args = vec![Spanned {
+                span: array.span,
+                inner: Value::Array(column),
+            }];
+            output_row.push(
+                lambda
+                    .call_for_single_value(ctx, lambda_args, self.span)
+                    .await?,
+            );
+        }
+        Ok(Value::Array(vec![output_row]))
+    }
+
+    /// Evaluates `MAKEARRAY(rows, cols, lambda)`, which calls the lambda with
+    /// the 1-based row and column of each element of a new array.
+    pub(super) async fn eval_makearray(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+    ) -> FormulaResult<Value> {
+        let (values, lambda) = self.eval_args_and_lambda(ctx, args).await?;
+        let [rows, cols]: [Spanned<Value>; 2] = values
+            .try_into()
+            .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(self.span))?;
+        let rows = positive_dimension(rows)?;
+        let cols = positive_dimension(cols)?;
+        if rows.saturating_mul(cols) > MAX_MAKEARRAY_LEN {
+            return Err(FormulaErrorMsg::Overflow.with_span(self.span));
+        }
+
+        let mut output_array = vec![];
+        for row in 1..=rows {
+            let mut output_row = SmallVec::new();
+            for col in 1..=cols {
+                let lambda_args = [row, col]
+                    .map(|n| Spanned {
+                        span: self.span,
+                        inner: Value::Number(n as f64),
+                    })
+                    .to_vec();
+                output_row.push(
+                    lambda
+                        .call_for_single_value(ctx, lambda_args, self.span)
+                        .await?,
+                );
+            }
+            output_array.push(output_row);
+        }
+        Ok(Value::Array(output_array))
+    }
+
+    /// Evaluates all the arguments to a function whose last argument is a
+    /// lambda, returning the other arguments and the lambda.
+    async fn eval_args_and_lambda(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+    ) -> FormulaResult<(Vec<Spanned<Value>>, Rc<Lambda>)> {
+        let mut values = vec![];
+        for arg in args {
+            values.push(arg.eval(ctx).await);
+        }
+        let Some(last) = values.pop() else {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        };
+        match last.inner {
+            Value::Lambda(lambda) => Ok((values, lambda)),
+            Value::Error(e) => Err(*e),
+            other => Err(FormulaErrorMsg::Expected {
+                expected: "lambda".into(),
+                got: Some(other.type_name().into()),
+            }
+            .with_span(last.span)),
+        }
+    }
+}
+
+/// Returns the rows of an array, treating any other value as an array with
+/// one element.
+fn into_rows(value: Value) -> Vec<SmallVec<[Value; 1]>> {
+    match value {
+        Value::Array(a) => a,
+        other => vec![smallvec![other]],
+    }
+}
+
+/// Returns the number of rows or columns for `MAKEARRAY()`, which must be
+/// positive.
+fn positive_dimension(value: Spanned<Value>) -> FormulaResult<u64> {
+    let n = value.into_single_value()?;
+    u64::try_from(n.to_integer()?)
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or_else(|| {
+            FormulaErrorMsg::Expected {
+                expected: "positive number".into(),
+                got: Some(n.inner.to_string().into()),
+            }
+            .with_span(n.span)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    #[test]
+    fn test_formula_let() {
+        let g = &mut PanicGridMock;
+        assert_eq!("3", eval_to_string(g, "LET(x, 1, x + 2)"));
+        assert_eq!("12", eval_to_string(g, "LET(x, 2, y, x * 3, x * y)"));
+        // Names are case-insensitive and can be shadowed.
+        assert_eq!("7", eval_to_string(g, "LET(x, 2, X + LET(x, 5, x))"));
+        // Bindings are lexically scoped.
+        assert_eq!(
+            ErrorKind::Name,
+            eval(g, "LET(x, 1, x) + x").unwrap_err().msg.kind(),
+        );
+
+        let e = eval(g, "LET(x, 1, y)").unwrap_err();
+        assert_eq!(FormulaErrorMsg::BadName, e.msg);
+        assert_eq!(Some(Span { start: 10, end: 11 }), e.span);
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, "LET(x, 1)").unwrap_err().msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::Expected {
+                expected: "name".into(),
+                got: Some("numeric literal".into()),
+            },
+            eval(g, "LET(1, 1, 2)").unwrap_err().msg,
+        );
+    }
+
+    #[test]
+    fn test_formula_lambda() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            "10",
+            eval_to_string(g, "LET(double, LAMBDA(x, x * 2), double(5))")
+        );
+        assert_eq!(
+            "7",
+            eval_to_string(g, "LET(add, LAMBDA(a, b, a + b), ADD(3, 4))"),
+        );
+        // Lambdas capture the names in scope where they are created.
+        assert_eq!(
+            "15",
+            eval_to_string(g, "LET(n, 10, addn, LAMBDA(x, x + n), n, 100, addn(5))",),
+        );
+        // Lambdas that take no arguments.
+        assert_eq!("42", eval_to_string(g, "LET(f, LAMBDA(42), f())"));
+
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, "LET(f, LAMBDA(x, x), f(1, 2))").unwrap_err().msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::Expected {
+                expected: "function".into(),
+                got: Some("number".into()),
+            },
+            eval(g, "LET(f, 3, f(1))").unwrap_err().msg,
+        );
+        assert!(matches!(
+            eval(g, "LAMBDA(x, x, x + 1)").unwrap_err().msg,
+            FormulaErrorMsg::Expected { .. },
+        ));
+
+        // Recursion gives up instead of overflowing the stack.
+        assert_eq!(
+            FormulaErrorMsg::CallDepthExceeded,
+            eval(g, "LET(f, LAMBDA(g, g(g)), f(f))").unwrap_err().msg,
+        );
+        let countdown = "LET(f, LAMBDA(g, n, IF(n = 0, 0, 1 + g(g, n - 1))), f(f, {n}))";
+        assert_eq!("20", eval_to_string(g, &countdown.replace("{n}", "20")));
+        assert_eq!(
+            FormulaErrorMsg::CallDepthExceeded,
+            eval(g, &countdown.replace("{n}", "1000")).unwrap_err().msg,
+        );
+    }
+
+    #[test]
+    fn test_formula_lambda_helpers() {
+        let g = &mut PanicGridMock;
+        assert_eq!(
+            "{2, 4; 6, 8}",
+            eval_to_string(g, "MAP({1, 2; 3, 4}, LAMBDA(x, x * 2))"),
+        );
+        assert_eq!(
+            "{11, 22}",
+            eval_to_string(g, "MAP({1, 2}, {10, 20}, LAMBDA(a, b, a + b))"),
+        );
+        assert_eq!(
+            "10",
+            eval_to_string(g, "REDUCE(0, {1, 2; 3, 4}, LAMBDA(acc, x, acc + x))"),
+        );
+        assert_eq!(
+            "{1, 3; 6, 10}",
+            eval_to_string(g, "SCAN(0, {1, 2; 3, 4}, LAMBDA(acc, x, acc + x))"),
+        );
+        assert_eq!(
+            "{3; 7}",
+            eval_to_string(g, "BYROW({1, 2; 3, 4}, LAMBDA(row, SUM(row)))"),
+        );
+        assert_eq!(
+            "{4, 6}",
+            eval_to_string(g, "BYCOL({1, 2; 3, 4}, LAMBDA(col, SUM(col)))"),
+        );
+        assert_eq!(
+            "{11, 12, 13; 21, 22, 23}",
+            eval_to_string(g, "MAKEARRAY(2, 3, LAMBDA(r, c, r * 10 + c))"),
+        );
+
+        assert_eq!(
+            FormulaErrorMsg::Expected {
+                expected: "lambda".into(),
+                got: Some("number".into()),
+            },
+            eval(g, "MAP({1, 2}, 3)").unwrap_err().msg,
+        );
+        assert!(matches!(
+            eval(g, "MAP({1, 2}, {1, 2, 3}, LAMBDA(a, b, a + b))")
+                .unwrap_err()
+                .msg,
+            FormulaErrorMsg::ArraySizeMismatch { .. },
+        ));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "MAKEARRAY(0, 3, LAMBDA(r, c, 1))")
+                .unwrap_err()
+                .msg
+                .kind(),
+        );
+    }
+}
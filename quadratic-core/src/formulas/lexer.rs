@@ -4,12 +4,15 @@ use lazy_static::lazy_static;
 use regex::Regex;
 use strum_macros::Display;
 
-use super::{Span, Spanned};
+use super::{CellRefNotation, Span, Spanned};
 
-pub fn tokenize<'a>(input_str: &'a str) -> impl 'a + Iterator<Item = Spanned<Token>> {
+pub fn tokenize(
+    input_str: &str,
+    notation: CellRefNotation,
+) -> impl '_ + Iterator<Item = Spanned<Token>> {
     let mut token_start = 0;
     std::iter::from_fn(move || {
-        Token::consume_from_input(input_str, token_start).map(|(token, token_end)| {
+        Token::consume_from_input(input_str, token_start, notation).map(|(token, token_end)| {
             let span = Span {
                 start: token_start,
                 end: token_end,
@@ -25,8 +28,21 @@ fn new_fullmatch_regex(s: &str) -> Regex {
 }
 
 /// Function call consisting of a letter or underscore followed by any letters,
-/// digits, and/or underscores terminated with a `(`.
-const FUNCTION_CALL_PATTERN: &str = r#"[A-Za-z_][A-Za-z_\d]*\("#;
+/// digits, underscores, and/or periods terminated with a `(`.
+const FUNCTION_CALL_PATTERN: &str = r#"[A-Za-z_][A-Za-z_\d\.]*\("#;
+
+/// Name consisting of a letter or underscore followed by any letters, digits,
+/// underscores, and/or periods, such as a name bound by `LET()`.
+const IDENTIFIER_PATTERN: &str = r#"[A-Za-z_][A-Za-z_\d\.]*"#;
+
+/// Sheet name followed by `!` at the start of a reference, such as `Sheet2!`
+/// or `'My Sheet'!`. Quotes in a quoted sheet name are written as `''`.
+///
+/// ('([^']|'')+'|[A-Za-z_][A-Za-z_\d\.]*)!
+/// ('([^']|'')+'|                     )     EITHER a quoted name
+///               [A-Za-z_][A-Za-z_\d\.]*      OR an unquoted name
+///                                      !    followed by `!`
+const SHEET_PREFIX_PATTERN: &str = r#"('([^']|'')+'|[A-Za-z_][A-Za-z_\d\.]*)!"#;
 
 /// A1-style cell reference.
 ///
@@ -37,6 +53,37 @@ const FUNCTION_CALL_PATTERN: &str = r#"[A-Za-z_][A-Za-z_\d]*\("#;
 ///                 \d+       digits
 const A1_CELL_REFERENCE_PATTERN: &str = r#"\$?n?[A-Z]+\$?n?\d+"#;
 
+/// A1-style whole-column range reference, such as `B:D`.
+///
+/// \$?n?[A-Z]+:\$?n?[A-Z]+
+/// \$?n?[A-Z]+              first column, with optional `$` and `n`
+///            :             separator
+///             \$?n?[A-Z]+  last column, with optional `$` and `n`
+const A1_COLUMN_RANGE_PATTERN: &str = r#"\$?n?[A-Z]+:\$?n?[A-Z]+"#;
+
+/// A1-style whole-row range reference, such as `3:5`.
+///
+/// \$?n?\d+:\$?n?\d+
+/// \$?n?\d+             first row, with optional `$` and `n`
+///         :            separator
+///          \$?n?\d+    last row, with optional `$` and `n`
+const A1_ROW_RANGE_PATTERN: &str = r#"\$?n?\d+:\$?n?\d+"#;
+
+/// R1C1-style cell reference.
+///
+/// R(\[[+-]?\d+\]|n?\d+)?C(\[[+-]?\d+\]|n?\d+)?
+/// R                                        row
+///  (\[[+-]?\d+\]|n?\d+)?                    optional offset or coordinate
+///                     C                    column
+///                      (\[[+-]?\d+\]|n?\d+)? optional offset or coordinate
+const R1C1_CELL_REFERENCE_PATTERN: &str = r#"R(\[[+-]?\d+\]|n?\d+)?C(\[[+-]?\d+\]|n?\d+)?"#;
+
+/// R1C1-style whole-column range reference, such as `C2:C[1]`.
+const R1C1_COLUMN_RANGE_PATTERN: &str = r#"C(\[[+-]?\d+\]|n?\d+)?:C(\[[+-]?\d+\]|n?\d+)?"#;
+
+/// R1C1-style whole-row range reference, such as `R3:R[-1]`.
+const R1C1_ROW_RANGE_PATTERN: &str = r#"R(\[[+-]?\d+\]|n?\d+)?:R(\[[+-]?\d+\]|n?\d+)?"#;
+
 /// Floating-point or integer number, without leading sign.
 ///
 /// (\d+(\.\d*)?|\.\d+)([eE][+-]?\d+)?
@@ -56,27 +103,80 @@ const SINGLE_QUOTE_STRING_LITERAL_PATTERN: &str = r#"'([^'\\]|\\[\s\S])*'"#;
 /// Double-quoted string. Note that like Rust strings, this can span multiple
 /// lines.
 const DOUBLE_QUOTE_STRING_LITERAL_PATTERN: &str = r#""([^"\\]|\\[\s\S])*""#;
+/// Spreadsheet error value, such as `#REF!` or `#N/A`.
+const ERROR_LITERAL_PATTERN: &str = r#"#(?i:NULL!|DIV/0!|VALUE!|REF!|NAME\?|NUM!|N/A|SPILL!)"#;
+
 /// Unterminated string literal.
 const UNTERMINATED_STRING_LITERAL_PATTERN: &str = r#"["']"#;
 
+/// Returns the list of token patterns for a reference notation, arranged
+/// roughly from least to most general.
+fn token_patterns(notation: CellRefNotation) -> Vec<String> {
+    let (range_reference_patterns, cell_reference_pattern) = match notation {
+        CellRefNotation::A1 => (
+            [A1_COLUMN_RANGE_PATTERN, A1_ROW_RANGE_PATTERN],
+            A1_CELL_REFERENCE_PATTERN,
+        ),
+        CellRefNotation::R1C1 => (
+            [R1C1_COLUMN_RANGE_PATTERN, R1C1_ROW_RANGE_PATTERN],
+            R1C1_CELL_REFERENCE_PATTERN,
+        ),
+    };
+    let mut reference_patterns = range_reference_patterns.to_vec();
+    reference_patterns.push(cell_reference_pattern);
+    let reference_patterns = reference_patterns.join("|");
+    TOKEN_PATTERNS
+        .iter()
+        .flat_map(|&pattern| match pattern {
+            SHEET_REFERENCE_PLACEHOLDER => {
+                vec![format!("{SHEET_PREFIX_PATTERN}({reference_patterns})")]
+            }
+            RANGE_REFERENCE_PLACEHOLDER => range_reference_patterns.map(|p| p.to_string()).to_vec(),
+            CELL_REFERENCE_PLACEHOLDER => vec![cell_reference_pattern.to_string()],
+            _ => vec![pattern.to_string()],
+        })
+        .collect()
+}
+
+/// Placeholder in `TOKEN_PATTERNS` for references that start with a sheet
+/// name, which depend on the notation.
+const SHEET_REFERENCE_PLACEHOLDER: &str = "<sheet reference>";
+/// Placeholder in `TOKEN_PATTERNS` for whole-row and whole-column range
+/// reference patterns, which depend on the notation.
+const RANGE_REFERENCE_PLACEHOLDER: &str = "<range reference>";
+/// Placeholder in `TOKEN_PATTERNS` for the cell reference pattern, which
+/// depends on the notation.
+const CELL_REFERENCE_PLACEHOLDER: &str = "<cell reference>";
+
 /// List of token patterns, arranged roughly from least to most general.
 const TOKEN_PATTERNS: &[&str] = &[
     // Comparison operators `==`, `!=`, `<=`, and `>=`.
     r#"[=!<>]="#,
+    // Operators `<<`, `>>`, `**`, and `..`.
+    r#"<<|>>|\*\*|\.\."#,
     // Line comment.
     r#"//[^\n]*"#,
     // Start of a block comment (block comment has special handling).
     r#"/\*"#,
+    // Reference to a cell or range on another sheet. This comes before string
+    // literals because sheet names may be quoted.
+    SHEET_REFERENCE_PLACEHOLDER,
     // String literal.
     SINGLE_QUOTE_STRING_LITERAL_PATTERN,
     DOUBLE_QUOTE_STRING_LITERAL_PATTERN,
     UNTERMINATED_STRING_LITERAL_PATTERN,
+    // Error literal.
+    ERROR_LITERAL_PATTERN,
+    // Reference to a whole row or column range.
+    RANGE_REFERENCE_PLACEHOLDER,
     // Numeric literal.
     NUMERIC_LITERAL_PATTERN,
     // Function call.
     FUNCTION_CALL_PATTERN,
     // Reference to a cell.
-    A1_CELL_REFERENCE_PATTERN,
+    CELL_REFERENCE_PLACEHOLDER,
+    // Identifier.
+    IDENTIFIER_PATTERN,
     // Whitespace.
     r#"\s+"#,
     // Any other single Unicode character.
@@ -84,19 +184,48 @@ const TOKEN_PATTERNS: &[&str] = &[
 ];
 
 lazy_static! {
-    /// Single regex that matches any token, including comments and strings,
-    /// by joining each member of `TOKEN_PATTERNS` with "|".
-    pub static ref TOKEN_REGEX: Regex =
-        Regex::new(&TOKEN_PATTERNS.join("|")).unwrap();
+    /// Single regex that matches any token in A1 notation, including comments
+    /// and strings, by joining each token pattern with "|".
+    pub static ref A1_TOKEN_REGEX: Regex =
+        Regex::new(&token_patterns(CellRefNotation::A1).join("|")).unwrap();
+
+    /// Single regex that matches any token in R1C1 notation, including
+    /// comments and strings, by joining each token pattern with "|".
+    pub static ref R1C1_TOKEN_REGEX: Regex =
+        Regex::new(&token_patterns(CellRefNotation::R1C1).join("|")).unwrap();
 
     /// Regex that matches a valid function call.
     pub static ref FUNCTION_CALL_REGEX: Regex =
         new_fullmatch_regex(FUNCTION_CALL_PATTERN);
 
+    /// Regex that matches a valid identifier.
+    pub static ref IDENTIFIER_REGEX: Regex =
+        new_fullmatch_regex(IDENTIFIER_PATTERN);
+
     /// Regex that matches a valid A1-style cell reference.
     pub static ref A1_CELL_REFERENCE_REGEX: Regex =
         new_fullmatch_regex(A1_CELL_REFERENCE_PATTERN);
 
+    /// Regex that matches a valid R1C1-style cell reference.
+    pub static ref R1C1_CELL_REFERENCE_REGEX: Regex =
+        new_fullmatch_regex(R1C1_CELL_REFERENCE_PATTERN);
+
+    /// Regex that matches the sheet name at the start of a reference.
+    pub static ref SHEET_PREFIX_REGEX: Regex =
+        new_fullmatch_regex(SHEET_PREFIX_PATTERN);
+
+    /// Regex that matches a valid whole-row or whole-column range reference in
+    /// either notation.
+    pub static ref ROW_OR_COLUMN_RANGE_REGEX: Regex = new_fullmatch_regex(
+        &[
+            A1_COLUMN_RANGE_PATTERN,
+            A1_ROW_RANGE_PATTERN,
+            R1C1_COLUMN_RANGE_PATTERN,
+            R1C1_ROW_RANGE_PATTERN,
+        ]
+        .join("|"),
+    );
+
     /// Regex that matches all valid numeric literals and some invalid ones.
     pub static ref NUMERIC_LITERAL_REGEX: Regex =
         new_fullmatch_regex(NUMERIC_LITERAL_PATTERN);
@@ -108,6 +237,10 @@ lazy_static! {
             DOUBLE_QUOTE_STRING_LITERAL_PATTERN,
         ].join("|"));
 
+    /// Regex that matches a valid error literal.
+    pub static ref ERROR_LITERAL_REGEX: Regex =
+        new_fullmatch_regex(ERROR_LITERAL_PATTERN);
+
     /// Regex that matches an unterminated string literal.
     pub static ref UNTERMINATED_STRING_LITERAL_REGEX: Regex =
         new_fullmatch_regex(UNTERMINATED_STRING_LITERAL_PATTERN);
@@ -174,6 +307,8 @@ pub enum Token {
     Percent, // %
     #[strum(to_string = "cell range operator")]
     CellRangeOp, // :
+    #[strum(to_string = "spill range operator")]
+    SpillOp, // #
 
     // Comments
     #[strum(to_string = "comment")]
@@ -190,8 +325,14 @@ pub enum Token {
     UnterminatedStringLiteral,
     #[strum(to_string = "numeric literal")]
     NumericLiteral,
-    #[strum(to_string = "RC-style cell reference")]
+    #[strum(to_string = "error literal")]
+    ErrorLiteral,
+    #[strum(to_string = "cell reference")]
     CellRef,
+    #[strum(to_string = "whole-row or whole-column range reference")]
+    RangeRef,
+    #[strum(to_string = "identifier")]
+    Identifier,
     #[strum(to_string = "whitespace")]
     Whitespace,
     #[strum(to_string = "unknown symbol")]
@@ -200,9 +341,18 @@ pub enum Token {
 impl Token {
     /// Consumes a token from a given starting index and returns the index of
     /// the next character after the token.
-    fn consume_from_input(input_str: &str, start: usize) -> Option<(Self, usize)> {
+    fn consume_from_input(
+        input_str: &str,
+        start: usize,
+        notation: CellRefNotation,
+    ) -> Option<(Self, usize)> {
+        let token_regex: &Regex = match notation {
+            CellRefNotation::A1 => &A1_TOKEN_REGEX,
+            CellRefNotation::R1C1 => &R1C1_TOKEN_REGEX,
+        };
+
         // Find next token.
-        TOKEN_REGEX.find_at(input_str, start).map(|m| {
+        token_regex.find_at(input_str, start).map(|m| {
             let mut end = m.end();
 
             let token = match m.as_str() {
@@ -231,6 +381,7 @@ impl Token {
                 ".." => Self::RangeOp,
                 "%" => Self::Percent,
                 ":" => Self::CellRangeOp,
+                "#" => Self::SpillOp,
 
                 // Match a line comment.
                 s if s.starts_with("//") => Self::Comment,
@@ -262,12 +413,36 @@ impl Token {
                     }
                 }
 
+                // Match a reference to another sheet.
+                s if SHEET_PREFIX_REGEX.is_match(s) => {
+                    let (_sheet, reference) = super::split_sheet_prefix(s);
+                    if ROW_OR_COLUMN_RANGE_REGEX.is_match(reference) {
+                        Self::RangeRef
+                    } else {
+                        Self::CellRef
+                    }
+                }
+
                 // Match anything else.
                 s if FUNCTION_CALL_REGEX.is_match(s) => Self::FunctionCall,
                 s if STRING_LITERAL_REGEX.is_match(s) => Self::StringLiteral,
+                s if ERROR_LITERAL_REGEX.is_match(s) => Self::ErrorLiteral,
+                s if ROW_OR_COLUMN_RANGE_REGEX.is_match(s) => Self::RangeRef,
                 s if UNTERMINATED_STRING_LITERAL_REGEX.is_match(s) => Self::StringLiteral,
-                s if NUMERIC_LITERAL_REGEX.is_match(s) => Self::NumericLiteral,
-                s if A1_CELL_REFERENCE_REGEX.is_match(s) => Self::CellRef,
+                s if NUMERIC_LITERAL_REGEX.is_match(s) => {
+                    // `1..10` is a range, not `1.` followed by `.10`.
+                    if s.ends_with('.') && input_str[end..].starts_with('.') {
+                        end -= 1;
+                    }
+                    Self::NumericLiteral
+                }
+                s if notation == CellRefNotation::A1 && A1_CELL_REFERENCE_REGEX.is_match(s) => {
+                    Self::CellRef
+                }
+                s if notation == CellRefNotation::R1C1 && R1C1_CELL_REFERENCE_REGEX.is_match(s) => {
+                    Self::CellRef
+                }
+                s if IDENTIFIER_REGEX.is_match(s) => Self::Identifier,
                 s if s.trim().is_empty() => Self::Whitespace,
 
                 // Give up.
@@ -305,8 +480,150 @@ mod tests {
         test_block_comment(false, "/* /*");
         test_block_comment(false, "/*/");
     }
+    #[test]
+    fn test_lex_multi_char_operators() {
+        let tokens = |s| {
+            tokenize(s, CellRefNotation::A1)
+                .map(|t| t.inner)
+                .collect_vec()
+        };
+        assert_eq!(
+            vec![
+                Token::NumericLiteral,
+                Token::ShiftLeft,
+                Token::NumericLiteral
+            ],
+            tokens("1<<2"),
+        );
+        assert_eq!(
+            vec![
+                Token::NumericLiteral,
+                Token::ShiftRight,
+                Token::NumericLiteral
+            ],
+            tokens("1>>2"),
+        );
+        assert_eq!(
+            vec![Token::NumericLiteral, Token::Power, Token::NumericLiteral],
+            tokens("1**2"),
+        );
+        assert_eq!(
+            vec![Token::NumericLiteral, Token::RangeOp, Token::NumericLiteral],
+            tokens("1..10"),
+        );
+        assert_eq!(
+            vec![Token::NumericLiteral, Token::RangeOp, Token::NumericLiteral],
+            tokens("1.5...5"),
+        );
+        assert_eq!(vec![Token::NumericLiteral], tokens("1."));
+    }
+
+    #[test]
+    fn test_lex_row_and_column_ranges() {
+        let tokens = |s| {
+            tokenize(s, CellRefNotation::A1)
+                .map(|t| t.inner)
+                .collect_vec()
+        };
+        assert_eq!(vec![Token::RangeRef], tokens("A:A"));
+        assert_eq!(vec![Token::RangeRef], tokens("$B:nD"));
+        assert_eq!(vec![Token::RangeRef], tokens("3:3"));
+        assert_eq!(vec![Token::RangeRef], tokens("$2:n5"));
+        assert_eq!(
+            vec![Token::CellRef, Token::CellRangeOp, Token::CellRef],
+            tokens("A1:B2"),
+        );
+        assert_eq!(vec![Token::CellRef, Token::SpillOp], tokens("A1#"));
+        assert_eq!(vec![Token::ErrorLiteral], tokens("#SPILL!"));
+    }
+
+    #[test]
+    fn test_lex_r1c1_references() {
+        let tokens = |s| {
+            tokenize(s, CellRefNotation::R1C1)
+                .map(|t| t.inner)
+                .collect_vec()
+        };
+        for s in ["R1C1", "RC", "R[-1]C[0]", "R5C[+2]", "Rn3Cn4"] {
+            assert_eq!(vec![Token::CellRef], tokens(s), "{s}");
+        }
+        for s in ["R1:R3", "R:R[2]", "C2:C2", "C[-1]:C[1]"] {
+            assert_eq!(vec![Token::RangeRef], tokens(s), "{s}");
+        }
+        assert_eq!(
+            vec![Token::CellRef, Token::CellRangeOp, Token::CellRef],
+            tokens("R1C1:R[2]C[2]"),
+        );
+        // A1-style references aren't recognized in R1C1 notation.
+        assert_ne!(vec![Token::CellRef], tokens("B2"));
+        // R1C1-style references aren't recognized in A1 notation.
+        assert_ne!(
+            vec![Token::CellRef],
+            tokenize("R[1]C[1]", CellRefNotation::A1)
+                .map(|t| t.inner)
+                .collect_vec(),
+        );
+    }
+
+    #[test]
+    fn test_lex_identifiers() {
+        let tokens = |s, notation| {
+            tokenize(s, notation)
+                .filter(|t| !t.inner.is_skip())
+                .map(|t| t.inner)
+                .collect_vec()
+        };
+        for s in ["x", "total", "_tmp", "rate.2", "Rocket", "RCOUNT"] {
+            assert_eq!(
+                vec![Token::Identifier],
+                tokens(s, CellRefNotation::A1),
+                "{s}"
+            );
+        }
+        assert_eq!(
+            vec![Token::Identifier, Token::Plus, Token::CellRef],
+            tokens("x + A1", CellRefNotation::A1),
+        );
+        assert_eq!(vec![Token::CellRef], tokens("ZZ99", CellRefNotation::A1));
+        // In R1C1 notation, A1-style references are identifiers.
+        assert_eq!(vec![Token::Identifier], tokens("B2", CellRefNotation::R1C1));
+        assert_eq!(vec![Token::CellRef], tokens("RC", CellRefNotation::R1C1));
+    }
+
+    #[test]
+    fn test_lex_sheet_references() {
+        let tokens = |s, notation| {
+            tokenize(s, notation)
+                .filter(|t| !t.inner.is_skip())
+                .map(|t| t.inner)
+                .collect_vec()
+        };
+        let a1 = CellRefNotation::A1;
+        for s in ["Sheet2!A1", "'My Sheet'!$B$2", "'It''s'!nC3"] {
+            assert_eq!(vec![Token::CellRef], tokens(s, a1), "{s}");
+        }
+        assert_eq!(vec![Token::RangeRef], tokens("Data!B:D", a1));
+        assert_eq!(
+            vec![Token::CellRef, Token::CellRangeOp, Token::CellRef],
+            tokens("'My Sheet'!B2:C9", a1),
+        );
+        assert_eq!(
+            vec![Token::CellRef],
+            tokens("Sheet2!R[1]C", CellRefNotation::R1C1),
+        );
+        // Without a reference after the `!`, quotes are a string.
+        assert_eq!(
+            vec![Token::StringLiteral, Token::Unknown, Token::NumericLiteral],
+            tokens("'My Sheet'!3", a1),
+        );
+        assert_eq!(
+            vec![Token::Identifier, Token::Neq, Token::CellRef],
+            tokens("x != A1", a1),
+        );
+    }
+
     fn test_block_comment(expected_to_end: bool, s: &str) {
-        let tokens = tokenize(s).collect_vec();
+        let tokens = tokenize(s, CellRefNotation::A1).collect_vec();
         if expected_to_end {
             assert_eq!(1, tokens.len(), "Too many tokens: {:?}", tokens);
         }
@@ -1,4 +1,6 @@
-use crate::Pos;
+use itertools::Itertools;
+
+use crate::{Cell, Pos, Rect};
 use ast::AstNode;
 use lexer::Token;
 
@@ -6,18 +8,28 @@ use lexer::Token;
 mod errors;
 mod ast;
 mod cell_ref;
+mod ctx;
+mod format;
 mod functions;
 mod grid_proxy;
+mod lambda;
 mod lexer;
+mod names;
 mod parser;
+mod rewrite;
 mod span;
+mod special_forms;
 mod value;
 
 pub use ast::Formula;
 pub use cell_ref::*;
-pub use errors::{FormulaError, FormulaErrorMsg};
+pub use ctx::{Ctx, Env};
+pub use errors::{ErrorKind, FormulaError, FormulaErrorMsg};
 pub use grid_proxy::GridProxy;
-pub use parser::parse_formula;
+pub use lambda::Lambda;
+pub use names::{NameDefinition, NameRegistry};
+pub use parser::{parse_formula, parse_formula_with_notation};
+pub use rewrite::{convert_notation, rename_name, rename_sheet, translate_formula, GridEdit};
 pub use span::{Span, Spanned};
 pub use value::Value;
 
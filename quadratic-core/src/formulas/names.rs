@@ -0,0 +1,253 @@
+//! Workbook-level names that refer to ranges or constants, such as
+//! `TaxRate = $B$2`.
+
+use std::collections::HashMap;
+
+use super::ast::AstNodeContents;
+use super::lexer::{self, Token};
+use super::*;
+
+/// What a name refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameDefinition {
+    /// Cell or range of cells. Relative coordinates are relative to the cell
+    /// containing the formula that uses the name.
+    Range(RangeRef),
+    /// Constant value, such as a number or string.
+    Constant(Value),
+}
+impl NameDefinition {
+    /// Parses the definition of a name, which must be a cell reference, a
+    /// range reference, or a numeric or string literal.
+    pub fn parse(source: &str, notation: CellRefNotation) -> FormulaResult<Self> {
+        let formula = parse_formula_with_notation(source, Pos::ORIGIN, notation)?;
+        let ast = &formula.ast;
+        match &ast.inner {
+            AstNodeContents::CellRef(cell_ref) => Ok(Self::Range(RangeRef::Cell(cell_ref.clone()))),
+            AstNodeContents::RangeRef(range_ref) => Ok(Self::Range(range_ref.clone())),
+            AstNodeContents::FunctionCall { func, args }
+                if func.inner == ":" && args.len() == 2 =>
+            {
+                Ok(Self::Range(RangeRef::CellRange(
+                    args[0].to_cell_ref()?,
+                    args[1].to_cell_ref()?,
+                )))
+            }
+            AstNodeContents::Number(n) => Ok(Self::Constant(Value::Number(*n))),
+            AstNodeContents::String(s) => Ok(Self::Constant(Value::String(s.clone()))),
+            _ => Err(FormulaErrorMsg::Expected {
+                expected: "cell reference, range, or constant".into(),
+                got: Some(ast.inner.type_string().into()),
+            }
+            .with_span(ast.span)),
+        }
+    }
+}
+
+/// Set of names defined for a workbook. Names are case-insensitive.
+#[derive(Debug, Default, Clone)]
+pub struct NameRegistry {
+    /// Map from lowercase name to the name as it was defined and its
+    /// definition.
+    names: HashMap<String, (String, NameDefinition)>,
+}
+impl NameRegistry {
+    /// Constructs an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines a name, replacing any existing definition. Returns an error if
+    /// the name is not a valid identifier or could be confused with a cell
+    /// reference.
+    pub fn define(&mut self, name: &str, definition: NameDefinition) -> FormulaResult<()> {
+        check_name(name)?;
+        self.names
+            .insert(name.to_ascii_lowercase(), (name.to_string(), definition));
+        Ok(())
+    }
+    /// Removes a name, returning its definition if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<NameDefinition> {
+        self.names
+            .remove(&name.to_ascii_lowercase())
+            .map(|(_, definition)| definition)
+    }
+    /// Returns the definition of a name, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&NameDefinition> {
+        self.names
+            .get(&name.to_ascii_lowercase())
+            .map(|(_, definition)| definition)
+    }
+    /// Renames a name, keeping its definition. Use `rename_name()` to update
+    /// the formulas that use it.
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> FormulaResult<()> {
+        check_name(new_name)?;
+        let new_key = new_name.to_ascii_lowercase();
+        let old_key = old_name.to_ascii_lowercase();
+        if new_key != old_key && self.names.contains_key(&new_key) {
+            return Err(FormulaErrorMsg::InvalidName("name is already defined").without_span());
+        }
+        let (_, definition) = self
+            .names
+            .remove(&old_key)
+            .ok_or_else(|| FormulaErrorMsg::BadName.without_span())?;
+        self.names
+            .insert(new_key, (new_name.to_string(), definition));
+        Ok(())
+    }
+    /// Iterates over the names, as they were defined, and their definitions.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = (&str, &NameDefinition)> {
+        self.names
+            .values()
+            .map(|(name, definition)| (name.as_str(), definition))
+    }
+}
+
+/// Returns an error if a string cannot be used as a name in either
+/// notation.
+fn check_name(name: &str) -> FormulaResult<()> {
+    for notation in [CellRefNotation::A1, CellRefNotation::R1C1] {
+        let tokens = lexer::tokenize(name, notation)
+            .map(|t| t.inner)
+            .collect_vec();
+        match tokens[..] {
+            [Token::Identifier] => (),
+            [Token::CellRef] | [Token::RangeRef] => {
+                return Err(
+                    FormulaErrorMsg::InvalidName("name looks like a cell reference")
+                        .without_span(),
+                )
+            }
+            _ => {
+                return Err(FormulaErrorMsg::InvalidName(
+                    "name must start with a letter or underscore and contain only letters, digits, underscores, and periods",
+                )
+                .without_span())
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formulas::tests::*;
+
+    fn eval_with_names(grid: &mut impl GridProxy, names: &NameRegistry, s: &str) -> String {
+        let pos = Pos::new(10, 10);
+        let mut ctx = Ctx::new(grid, pos);
+        ctx.names = Some(names);
+        match pollster::block_on(parse_formula(s, pos).unwrap().eval_in_ctx(&mut ctx)) {
+            Ok(value) => value.inner.to_string(),
+            Err(e) => e.msg.kind().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_name_validation() {
+        let mut names = NameRegistry::new();
+        let constant = NameDefinition::Constant(Value::Number(1.0));
+        for name in ["TaxRate", "tax_rate", "_x", "Rate.2023", "Rocket"] {
+            assert_eq!(Ok(()), names.define(name, constant.clone()), "{name}");
+        }
+        for name in [
+            "ABC1", "A1", "RC", "R1C1", "R[1]C", "B:C", "1x", "tax rate", "",
+        ] {
+            assert!(
+                matches!(
+                    names.define(name, constant.clone()).unwrap_err().msg,
+                    FormulaErrorMsg::InvalidName(_),
+                ),
+                "{name}",
+            );
+        }
+    }
+
+    #[test]
+    fn test_name_definition_parse() {
+        let a1 = CellRefNotation::A1;
+        assert_eq!(
+            Ok(NameDefinition::Range(RangeRef::Cell(CellRef::absolute(
+                Pos::new(1, 2)
+            )))),
+            NameDefinition::parse("$B$2", a1),
+        );
+        assert!(matches!(
+            NameDefinition::parse("$C$2:$C$500", a1),
+            Ok(NameDefinition::Range(RangeRef::CellRange(_, _))),
+        ));
+        assert!(matches!(
+            NameDefinition::parse("C:D", a1),
+            Ok(NameDefinition::Range(RangeRef::ColRange { .. })),
+        ));
+        assert_eq!(
+            Ok(NameDefinition::Constant(Value::Number(0.07))),
+            NameDefinition::parse("0.07", a1),
+        );
+        assert!(NameDefinition::parse("1 + 2", a1).is_err());
+    }
+
+    #[test]
+    fn test_formula_names() {
+        make_stateless_grid_mock!(|pos| Some((pos.x * 100 + pos.y).to_string()));
+        let g = &mut GridMock;
+
+        let mut names = NameRegistry::new();
+        names
+            .define(
+                "TaxRate",
+                NameDefinition::parse("$B$2", CellRefNotation::A1).unwrap(),
+            )
+            .unwrap();
+        names
+            .define(
+                "Revenue",
+                NameDefinition::parse("$C$2:$C$4", CellRefNotation::A1).unwrap(),
+            )
+            .unwrap();
+        names
+            .define("Bonus", NameDefinition::Constant(Value::Number(5.0)))
+            .unwrap();
+
+        assert_eq!("102", eval_with_names(g, &names, "TaxRate"));
+        assert_eq!("102", eval_with_names(g, &names, "taxrate"));
+        assert_eq!("609", eval_with_names(g, &names, "SUM(REVENUE)"));
+        assert_eq!("107", eval_with_names(g, &names, "TaxRate + Bonus"));
+        // `LET()` shadows names.
+        assert_eq!("1", eval_with_names(g, &names, "LET(Bonus, 1, Bonus)"));
+        assert_eq!("#NAME?", eval_with_names(g, &names, "Undefined"));
+        assert_eq!(
+            "#NAME?",
+            eval_with_names(g, &NameRegistry::new(), "TaxRate")
+        );
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut names = NameRegistry::new();
+        let constant = NameDefinition::Constant(Value::Number(1.0));
+        names.define("TaxRate", constant.clone()).unwrap();
+        names.define("Other", constant.clone()).unwrap();
+
+        names.rename("taxrate", "SalesTax").unwrap();
+        assert_eq!(None, names.get("TaxRate"));
+        assert_eq!(Some(&constant), names.get("salestax"));
+        assert_eq!(
+            vec!["Other", "SalesTax"],
+            names.iter().map(|(name, _)| name).sorted().collect_vec(),
+        );
+
+        assert!(names.rename("SalesTax", "OTHER").is_err());
+        assert!(names.rename("SalesTax", "A1").is_err());
+        assert_eq!(
+            FormulaErrorMsg::BadName,
+            names.rename("Missing", "Found").unwrap_err().msg,
+        );
+        // Changing only the case is allowed.
+        names.rename("SalesTax", "SALESTAX").unwrap();
+        assert_eq!(
+            vec!["Other", "SALESTAX"],
+            names.iter().map(|(name, _)| name).sorted().collect_vec(),
+        );
+    }
+}
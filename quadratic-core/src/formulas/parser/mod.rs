@@ -10,16 +10,33 @@ use super::*;
 use rules::SyntaxRule;
 
 pub fn parse_formula(source: &str, loc: Pos) -> FormulaResult<ast::Formula> {
+    parse_formula_with_notation(source, loc, CellRefNotation::default())
+}
+
+/// Parses a formula that uses the given notation for cell references.
+pub fn parse_formula_with_notation(
+    source: &str,
+    loc: Pos,
+    notation: CellRefNotation,
+) -> FormulaResult<ast::Formula> {
     Ok(Formula {
-        ast: parse_exactly_one(source, loc, rules::Expression)?,
+        ast: parse_exactly_one(source, loc, notation, rules::Expression)?,
+        source: source.to_string(),
+        loc,
+        notation,
     })
 }
 
-fn parse_exactly_one<R: SyntaxRule>(source: &str, loc: Pos, rule: R) -> FormulaResult<R::Output> {
-    let tokens = lexer::tokenize(source)
+fn parse_exactly_one<R: SyntaxRule>(
+    source: &str,
+    loc: Pos,
+    notation: CellRefNotation,
+    rule: R,
+) -> FormulaResult<R::Output> {
+    let tokens = lexer::tokenize(source, notation)
         .filter(|t| !t.inner.is_skip())
         .collect_vec();
-    let mut p = Parser::new(source, &tokens, loc);
+    let mut p = Parser::new(source, &tokens, loc, notation);
     match p.parse(rule) {
         Ok(_) if p.next().is_some() => p.expected("end of formula"),
         result => result,
@@ -38,16 +55,24 @@ pub struct Parser<'a> {
 
     /// Coordinates of the cell where this formula was entered.
     pub loc: Pos,
+    /// Notation used for cell references.
+    pub notation: CellRefNotation,
 }
 impl<'a> Parser<'a> {
     /// Constructs a parser for a file.
-    pub fn new(source_str: &'a str, tokens: &'a [Spanned<Token>], loc: Pos) -> Self {
+    pub fn new(
+        source_str: &'a str,
+        tokens: &'a [Spanned<Token>],
+        loc: Pos,
+        notation: CellRefNotation,
+    ) -> Self {
         let mut ret = Self {
             source_str,
             tokens,
             cursor: None,
 
             loc,
+            notation,
         };
 
         // Skip leading `=`
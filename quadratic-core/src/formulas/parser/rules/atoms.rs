@@ -69,6 +69,32 @@ impl SyntaxRule for NumericLiteral {
     }
 }
 
+/// Matches an error literal.
+#[derive(Debug, Copy, Clone)]
+pub struct ErrorLiteral;
+impl_display!(for ErrorLiteral, "error literal, such as '#REF!' or '#N/A'");
+impl SyntaxRule for ErrorLiteral {
+    type Output = AstNode;
+
+    fn prefix_matches(&self, mut p: Parser<'_>) -> bool {
+        p.next() == Some(Token::ErrorLiteral)
+    }
+    fn consume_match(&self, p: &mut Parser<'_>) -> FormulaResult<Self::Output> {
+        if p.next() != Some(Token::ErrorLiteral) {
+            return p.expected(self);
+        }
+        let Some(kind) = ErrorKind::from_code(p.token_str()) else {
+            return Err(
+                FormulaErrorMsg::InternalError("invalid error literal".into()).with_span(p.span()),
+            );
+        };
+        Ok(AstNode {
+            span: p.span(),
+            inner: ast::AstNodeContents::Error(kind),
+        })
+    }
+}
+
 /// Matches a cell reference.
 pub struct CellReference;
 impl_display!(for CellReference, "cell reference, such as 'A6' or '$ZB$3'");
@@ -80,7 +106,7 @@ impl SyntaxRule for CellReference {
     }
     fn consume_match(&self, p: &mut Parser<'_>) -> FormulaResult<Self::Output> {
         p.next();
-        let Some(cell_ref) = CellRef::parse_a1(p.token_str(), p.loc) else {
+        let Some(cell_ref) = CellRef::parse(p.token_str(), p.loc, p.notation) else {
             return Err(FormulaErrorMsg::BadCellReference.with_span(p.span()));
         };
         Ok(AstNode {
@@ -89,3 +115,45 @@ impl SyntaxRule for CellReference {
         })
     }
 }
+
+/// Matches a whole-row or whole-column range reference.
+pub struct RangeReference;
+impl_display!(for RangeReference, "row or column range, such as 'A:C' or '$3:5'");
+impl SyntaxRule for RangeReference {
+    type Output = AstNode;
+
+    fn prefix_matches(&self, mut p: Parser<'_>) -> bool {
+        p.next() == Some(Token::RangeRef)
+    }
+    fn consume_match(&self, p: &mut Parser<'_>) -> FormulaResult<Self::Output> {
+        p.next();
+        let Some(range_ref) = RangeRef::parse(p.token_str(), p.loc, p.notation) else {
+            return Err(FormulaErrorMsg::BadCellReference.with_span(p.span()));
+        };
+        Ok(AstNode {
+            span: p.span(),
+            inner: ast::AstNodeContents::RangeRef(range_ref),
+        })
+    }
+}
+
+/// Matches an identifier, such as a name bound by `LET()`.
+#[derive(Debug, Copy, Clone)]
+pub struct Identifier;
+impl_display!(for Identifier, "name, such as 'total' or 'x'");
+impl SyntaxRule for Identifier {
+    type Output = AstNode;
+
+    fn prefix_matches(&self, mut p: Parser<'_>) -> bool {
+        p.next() == Some(Token::Identifier)
+    }
+    fn consume_match(&self, p: &mut Parser<'_>) -> FormulaResult<Self::Output> {
+        if p.next() != Some(Token::Identifier) {
+            return p.expected(self);
+        }
+        Ok(AstNode {
+            span: p.span(),
+            inner: ast::AstNodeContents::Identifier(p.token_str().to_string()),
+        })
+    }
+}
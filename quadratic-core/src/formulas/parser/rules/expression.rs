@@ -74,7 +74,7 @@ impl OpPrecedence {
     pub fn suffix_ops(self) -> &'static [Token] {
         use Token::*;
         match self {
-            Self::Suffix => &[Percent],
+            Self::Suffix => &[Percent, SpillOp],
             _ => &[],
         }
     }
@@ -136,7 +136,8 @@ impl SyntaxRule for ExpressionWithPrecedence {
                 | Token::Concat
                 | Token::RangeOp
                 | Token::Percent
-                | Token::CellRangeOp => false,
+                | Token::CellRangeOp
+                | Token::SpillOp => false,
 
                 Token::Comment | Token::UnterminatedBlockComment => false,
 
@@ -144,7 +145,10 @@ impl SyntaxRule for ExpressionWithPrecedence {
                 | Token::StringLiteral
                 | Token::UnterminatedStringLiteral
                 | Token::NumericLiteral
-                | Token::CellRef => true,
+                | Token::ErrorLiteral
+                | Token::CellRef
+                | Token::RangeRef
+                | Token::Identifier => true,
 
                 Token::Whitespace => false,
                 Token::Unknown => false,
@@ -163,8 +167,11 @@ impl SyntaxRule for ExpressionWithPrecedence {
                     FunctionCall.map(Some),
                     StringLiteral.map(Some),
                     NumericLiteral.map(Some),
+                    ErrorLiteral.map(Some),
                     ArrayLiteral.map(Some),
                     CellReference.map(Some),
+                    RangeReference.map(Some),
+                    Identifier.map(Some),
                     ParenExpression.map(Some),
                     Epsilon.map(|_| None),
                 ],
@@ -0,0 +1,679 @@
+//! Functions for rewriting the references in a formula while leaving the rest
+//! of the formula, including whitespace and comments, unchanged.
+
+use serde::{Deserialize, Serialize};
+
+use super::lexer::{self, Token};
+use super::*;
+
+/// Structural edit to the grid that moves cells around.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum GridEdit {
+    /// Inserts `count` rows before row `index`.
+    InsertRows { index: i64, count: i64 },
+    /// Deletes `count` rows starting at row `index`.
+    DeleteRows { index: i64, count: i64 },
+    /// Inserts `count` columns before column `index`.
+    InsertColumns { index: i64, count: i64 },
+    /// Deletes `count` columns starting at column `index`.
+    DeleteColumns { index: i64, count: i64 },
+    /// Moves the cells in `source` so that its upper-left corner is at
+    /// `dest`, overwriting any cells already there.
+    MoveRect { source: Rect, dest: Pos },
+}
+impl GridEdit {
+    /// Edit that doesn't move any cells.
+    const NO_OP: GridEdit = GridEdit::InsertRows { index: 0, count: 0 };
+
+    /// Returns the new position of a cell after the edit, or `None` if it was
+    /// deleted or overwritten.
+    pub fn transform_pos(self, pos: Pos) -> Option<Pos> {
+        match self {
+            GridEdit::InsertRows { .. } | GridEdit::DeleteRows { .. } => Some(Pos {
+                x: pos.x,
+                y: self.transform_row(pos.y)?,
+            }),
+            GridEdit::InsertColumns { .. } | GridEdit::DeleteColumns { .. } => Some(Pos {
+                x: self.transform_col(pos.x)?,
+                y: pos.y,
+            }),
+            GridEdit::MoveRect { source, dest } => {
+                if source.contains(pos) {
+                    Some(Pos {
+                        x: pos.x - source.min.x + dest.x,
+                        y: pos.y - source.min.y + dest.y,
+                    })
+                } else if self.move_dest_rect().is_some_and(|r| r.contains(pos)) {
+                    None
+                } else {
+                    Some(pos)
+                }
+            }
+        }
+    }
+
+    /// Returns the new row of a cell after the edit, or `None` if it was
+    /// deleted. Moving cells doesn't affect whole rows.
+    fn transform_row(self, y: i64) -> Option<i64> {
+        match self {
+            GridEdit::InsertRows { index, count } => Some(insert_coord(y, index, count)),
+            GridEdit::DeleteRows { index, count } => delete_coord(y, index, count),
+            _ => Some(y),
+        }
+    }
+    /// Returns the new column of a cell after the edit, or `None` if it was
+    /// deleted. Moving cells doesn't affect whole columns.
+    fn transform_col(self, x: i64) -> Option<i64> {
+        match self {
+            GridEdit::InsertColumns { index, count } => Some(insert_coord(x, index, count)),
+            GridEdit::DeleteColumns { index, count } => delete_coord(x, index, count),
+            _ => Some(x),
+        }
+    }
+
+    /// Returns the new range of rows after the edit, or `None` if they were
+    /// all deleted. If only some of the rows are deleted, the range shrinks.
+    fn transform_row_range(self, start: i64, end: i64) -> Option<(i64, i64)> {
+        match self {
+            GridEdit::DeleteRows { index, count } => delete_range(start, end, index, count),
+            _ => Some((self.transform_row(start)?, self.transform_row(end)?)),
+        }
+    }
+    /// Returns the new range of columns after the edit, or `None` if they
+    /// were all deleted. If only some of the columns are deleted, the range
+    /// shrinks.
+    fn transform_col_range(self, start: i64, end: i64) -> Option<(i64, i64)> {
+        match self {
+            GridEdit::DeleteColumns { index, count } => delete_range(start, end, index, count),
+            _ => Some((self.transform_col(start)?, self.transform_col(end)?)),
+        }
+    }
+
+    /// Returns the new rectangle of cells after the edit, or `None` if they
+    /// were all deleted or overwritten.
+    fn transform_rect(self, rect: Rect) -> Option<Rect> {
+        match self {
+            GridEdit::MoveRect { source, .. } => {
+                if source.contains(rect.min) && source.contains(rect.max) {
+                    // The whole rectangle moves.
+                    Some(Rect {
+                        min: self.transform_pos(rect.min)?,
+                        max: self.transform_pos(rect.max)?,
+                    })
+                } else {
+                    // The rectangle stays, unless one of its corners is
+                    // overwritten.
+                    let is_overwritten = |pos| self.transform_pos(pos).is_none();
+                    (!is_overwritten(rect.min) && !is_overwritten(rect.max)).then_some(rect)
+                }
+            }
+            _ => {
+                let (min_x, max_x) = self.transform_col_range(rect.min.x, rect.max.x)?;
+                let (min_y, max_y) = self.transform_row_range(rect.min.y, rect.max.y)?;
+                Some(Rect {
+                    min: Pos { x: min_x, y: min_y },
+                    max: Pos { x: max_x, y: max_y },
+                })
+            }
+        }
+    }
+
+    /// Returns the region that cells are moved into, if this is a move.
+    fn move_dest_rect(self) -> Option<Rect> {
+        match self {
+            GridEdit::MoveRect { source, dest } => Some(Rect {
+                min: dest,
+                max: Pos {
+                    x: dest.x + source.max.x - source.min.x,
+                    y: dest.y + source.max.y - source.min.y,
+                },
+            }),
+            _ => None,
+        }
+    }
+
+    /// Returns the new version of a reference from a formula at `old_pos`
+    /// that is moving to `new_pos`, or `None` if the reference is no longer
+    /// valid. Absolute and relative coordinates stay absolute and relative.
+    fn transform_range_ref(
+        self,
+        range_ref: RangeRef,
+        old_pos: Pos,
+        new_pos: Pos,
+    ) -> Option<RangeRef> {
+        let x = |coord: CellRefCoord, new_x: i64| coord.with_resolved(new_x, new_pos.x);
+        let y = |coord: CellRefCoord, new_y: i64| coord.with_resolved(new_y, new_pos.y);
+        match range_ref {
+            RangeRef::RowRange { sheet, start, end } => {
+                let (new_start, new_end) = self.transform_row_range(
+                    start.resolve_from(old_pos.y),
+                    end.resolve_from(old_pos.y),
+                )?;
+                Some(RangeRef::RowRange {
+                    sheet,
+                    start: y(start, new_start),
+                    end: y(end, new_end),
+                })
+            }
+            RangeRef::ColRange { sheet, start, end } => {
+                let (new_start, new_end) = self.transform_col_range(
+                    start.resolve_from(old_pos.x),
+                    end.resolve_from(old_pos.x),
+                )?;
+                Some(RangeRef::ColRange {
+                    sheet,
+                    start: x(start, new_start),
+                    end: x(end, new_end),
+                })
+            }
+            RangeRef::CellRange(corner1, corner2) => {
+                let old_corner1 = corner1.resolve_from(old_pos);
+                let old_corner2 = corner2.resolve_from(old_pos);
+                let new_rect = self.transform_rect(Rect::new_span(old_corner1, old_corner2))?;
+                // Keep the corners in the same order that the user wrote them.
+                let pick =
+                    |old: i64, other: i64, min: i64, max: i64| if old <= other { min } else { max };
+                let new_corner = |cell_ref: CellRef, old: Pos, other: Pos| CellRef {
+                    sheet: cell_ref.sheet,
+                    x: x(
+                        cell_ref.x,
+                        pick(old.x, other.x, new_rect.min.x, new_rect.max.x),
+                    ),
+                    y: y(
+                        cell_ref.y,
+                        pick(old.y, other.y, new_rect.min.y, new_rect.max.y),
+                    ),
+                };
+                Some(RangeRef::CellRange(
+                    new_corner(corner1, old_corner1, old_corner2),
+                    new_corner(corner2, old_corner2, old_corner1),
+                ))
+            }
+            RangeRef::Cell(cell_ref) => {
+                let new_cell = self.transform_pos(cell_ref.resolve_from(old_pos))?;
+                Some(RangeRef::Cell(CellRef {
+                    sheet: cell_ref.sheet,
+                    x: x(cell_ref.x, new_cell.x),
+                    y: y(cell_ref.y, new_cell.y),
+                }))
+            }
+        }
+    }
+}
+
+/// Returns the new coordinate after inserting `count` rows or columns before
+/// `index`.
+fn insert_coord(coord: i64, index: i64, count: i64) -> i64 {
+    if coord >= index {
+        coord + count
+    } else {
+        coord
+    }
+}
+/// Returns the new coordinate after deleting `count` rows or columns starting
+/// at `index`, or `None` if it was deleted.
+fn delete_coord(coord: i64, index: i64, count: i64) -> Option<i64> {
+    if coord < index {
+        Some(coord)
+    } else if coord >= index + count {
+        Some(coord - count)
+    } else {
+        None
+    }
+}
+/// Returns the new range of coordinates after deleting `count` rows or
+/// columns starting at `index`, or `None` if they were all deleted.
+fn delete_range(start: i64, end: i64, index: i64, count: i64) -> Option<(i64, i64)> {
+    let (start, end) = (start.min(end), start.max(end));
+    let new_start = delete_coord(start, index, count).unwrap_or(index);
+    let new_end = delete_coord(end, index, count).unwrap_or(index - 1);
+    (new_start <= new_end).then_some((new_start, new_end))
+}
+
+impl Formula {
+    /// Returns the source code of the formula after a structural edit to the
+    /// grid, with its references updated to point to the same cells as
+    /// before. References to cells that were deleted become `#REF!`.
+    ///
+    /// `pos` is the position of the formula before the edit and `sheet` is
+    /// the name of the sheet containing it. `edited_sheet` is the name of the
+    /// sheet that was edited. For both, `None` is the default sheet. Only
+    /// references to cells on the edited sheet are changed, whether or not
+    /// they name it explicitly. Returns `None` if the formula's own cell is
+    /// deleted.
+    pub fn source_after_edit(
+        &self,
+        pos: Pos,
+        sheet: Option<&str>,
+        edit: GridEdit,
+        edited_sheet: Option<&str>,
+    ) -> Option<String> {
+        let is_edited = |s: Option<&str>| match (s, edited_sheet) {
+            (None, None) => true,
+            (Some(s), Some(edited)) => s.eq_ignore_ascii_case(edited),
+            _ => false,
+        };
+        let new_pos = match is_edited(sheet) {
+            true => edit.transform_pos(pos)?,
+            false => pos,
+        };
+        Some(rewrite_references(
+            &self.source,
+            pos,
+            new_pos,
+            self.notation,
+            self.notation,
+            |range_ref| {
+                let edit = match is_edited(range_ref.sheet().or(sheet)) {
+                    true => edit,
+                    false => GridEdit::NO_OP,
+                };
+                edit.transform_range_ref(range_ref, pos, new_pos)
+            },
+        ))
+    }
+}
+
+/// Converts the cell references in a formula from one notation to another.
+/// References that can't be parsed are left unchanged.
+pub fn convert_notation(
+    source: &str,
+    pos: Pos,
+    from: CellRefNotation,
+    to: CellRefNotation,
+) -> String {
+    rewrite_references(source, pos, pos, from, to, Some)
+}
+
+/// Translates the relative references in a formula that is copied from
+/// `from` to `to`, leaving absolute references unchanged. References that
+/// would go outside the range of valid coordinates become `#REF!`.
+pub fn translate_formula(source: &str, from: Pos, to: Pos, notation: CellRefNotation) -> String {
+    rewrite_references(source, from, to, notation, notation, |range_ref| {
+        range_ref.is_valid_from(to).then_some(range_ref)
+    })
+}
+
+/// Replaces every use of the name `old_name` in a formula with `new_name`,
+/// ignoring case. Everything else in the formula is left unchanged.
+pub fn rename_name(
+    source: &str,
+    notation: CellRefNotation,
+    old_name: &str,
+    new_name: &str,
+) -> String {
+    lexer::tokenize(source, notation)
+        .map(|token| {
+            let token_str = token.span.of_str(source);
+            if token.inner == Token::Identifier && token_str.eq_ignore_ascii_case(old_name) {
+                new_name
+            } else {
+                token_str
+            }
+        })
+        .collect()
+}
+
+/// Replaces the sheet name in every reference to the sheet `old_name` with
+/// `new_name`, ignoring case. Everything else in the formula is left
+/// unchanged.
+pub fn rename_sheet(
+    source: &str,
+    notation: CellRefNotation,
+    old_name: &str,
+    new_name: &str,
+) -> String {
+    lexer::tokenize(source, notation)
+        .map(|token| {
+            let token_str = token.span.of_str(source);
+            if !matches!(token.inner, Token::CellRef | Token::RangeRef) {
+                return token_str.to_string();
+            }
+            match split_sheet_prefix(token_str) {
+                (Some(sheet), reference) if sheet.eq_ignore_ascii_case(old_name) => {
+                    format!("{}!{reference}", quote_sheet_name(new_name))
+                }
+                _ => token_str.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Calls `f` on each reference in a formula at `old_pos`, and replaces it
+/// with the returned reference formatted for a formula at `new_pos`. If `f`
+/// returns `None`, the reference is replaced with `#REF!`.
+///
+/// Cell ranges such as `A1:B2` are passed to `f` as a whole, and any
+/// whitespace or comments around the `:` are preserved.
+fn rewrite_references(
+    source: &str,
+    old_pos: Pos,
+    new_pos: Pos,
+    from: CellRefNotation,
+    to: CellRefNotation,
+    mut f: impl FnMut(RangeRef) -> Option<RangeRef>,
+) -> String {
+    let tokens = lexer::tokenize(source, from).collect_vec();
+    let token_str = |i: usize| tokens[i].span.of_str(source);
+    let parse_cell_ref = |i: usize| match tokens.get(i)?.inner {
+        Token::CellRef => CellRef::parse(token_str(i), old_pos, from),
+        _ => None,
+    };
+    let next_non_skip = |i: usize| (i..tokens.len()).find(|&j| !tokens[j].inner.is_skip());
+
+    let mut ret = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        // Cell range, such as `A1:B2`
+        if let Some(corner1) = parse_cell_ref(i) {
+            let colon = next_non_skip(i + 1).filter(|&j| tokens[j].inner == Token::CellRangeOp);
+            let corner2_index = colon.and_then(|j| next_non_skip(j + 1));
+            let corner2 = corner2_index.and_then(parse_cell_ref);
+            if let (Some(j), Some(corner2)) = (corner2_index, corner2) {
+                match f(RangeRef::CellRange(corner1, corner2)) {
+                    Some(RangeRef::CellRange(new_corner1, new_corner2)) => {
+                        ret.push_str(&new_corner1.notation_string(new_pos, to));
+                        for k in i + 1..j {
+                            ret.push_str(token_str(k));
+                        }
+                        ret.push_str(&new_corner2.notation_string(new_pos, to));
+                    }
+                    Some(other) => ret.push_str(&other.notation_string(new_pos, to)),
+                    None => ret.push_str(ErrorKind::Ref.code()),
+                }
+                i = j + 1;
+                continue;
+            }
+        }
+
+        let range_ref = match tokens[i].inner {
+            Token::CellRef => CellRef::parse(token_str(i), old_pos, from).map(RangeRef::Cell),
+            Token::RangeRef => RangeRef::parse(token_str(i), old_pos, from),
+            _ => None,
+        };
+        match range_ref {
+            Some(range_ref) => match f(range_ref) {
+                Some(new_range_ref) => ret.push_str(&new_range_ref.notation_string(new_pos, to)),
+                None => ret.push_str(ErrorKind::Ref.code()),
+            },
+            None => ret.push_str(token_str(i)),
+        }
+        i += 1;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_r1c1(source: &str, pos: Pos) -> String {
+        convert_notation(source, pos, CellRefNotation::A1, CellRefNotation::R1C1)
+    }
+    fn to_a1(source: &str, pos: Pos) -> String {
+        convert_notation(source, pos, CellRefNotation::R1C1, CellRefNotation::A1)
+    }
+
+    #[test]
+    fn test_convert_notation() {
+        let pos = Pos::new(2, 3); // C3
+
+        assert_eq!("=R[-2]C[-1] + RC", to_r1c1("=B1 + C3", pos));
+        assert_eq!("R3C1", to_r1c1("$B$3", pos));
+        assert_eq!("R[1]C1 * R2C[1]", to_r1c1("$B4 * D$2", pos));
+        assert_eq!(
+            "SUM(R1C0:R[2]C[1], C:C[2], R3:R)",
+            to_r1c1("SUM($A$1:D5, C:E, $3:3)", pos),
+        );
+        assert_eq!("'B2' /* A1 */ & RCn1", to_r1c1("'B2' /* A1 */ & $nA3", pos),);
+
+        assert_eq!("=B1 + C3", to_a1("=R[-2]C[-1] + RC", pos));
+        assert_eq!(
+            "SUM($A$1:D5, C:E, $3:3)",
+            to_a1("SUM(R1C0:R[2]C[1], C:C[2], R3:R)", pos),
+        );
+        assert_eq!("An1", to_a1("R[-4]C[-2]", pos));
+    }
+
+    #[test]
+    fn test_convert_notation_round_trip() {
+        let pos = Pos::new(5, 8);
+        for source in [
+            "A1",
+            "$A1 + A$1 - $A$1",
+            "SUM(nB2:$C$n3) / COUNT(A:A)",
+            "IF(ZZ100 > 3, B:$D, $2:n2)",
+            "  =  B2  // comment",
+        ] {
+            let r1c1 = to_r1c1(source, pos);
+            assert_eq!(source, to_a1(&r1c1, pos), "round trip through {r1c1:?}");
+
+            // The formula means the same thing either way.
+            assert_eq!(
+                parse_formula_with_notation(source, pos, CellRefNotation::A1)
+                    .unwrap()
+                    .to_string(),
+                parse_formula_with_notation(&r1c1, pos, CellRefNotation::R1C1)
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+    }
+
+    fn after_edit(source: &str, pos: Pos, edit: GridEdit) -> Option<String> {
+        parse_formula(source, pos)
+            .unwrap()
+            .source_after_edit(pos, None, edit, None)
+    }
+
+    #[test]
+    fn test_insert_and_delete_rows() {
+        let pos = Pos::new(4, 5); // E5
+        let insert = GridEdit::InsertRows { index: 3, count: 2 };
+        let delete = GridEdit::DeleteRows { index: 3, count: 2 };
+
+        // References above the edit don't move; references below it do.
+        assert_eq!(
+            Some("=A1 +  $B$2  /* hi */ + C5 * $D$6".to_string()),
+            after_edit("=A1 +  $B$2  /* hi */ + C3 * $D$4", pos, insert),
+        );
+        assert_eq!(
+            Some("=A1 + $B$2 + C3 * $D$4".to_string()),
+            after_edit("=A1 + $B$2 + C5 * $D$6", pos, delete),
+        );
+        // The formula's own cell moves too.
+        assert_eq!(Some("E7".to_string()), after_edit("E5", pos, insert));
+        assert_eq!(
+            Some("E3 + E1".to_string()),
+            after_edit("E5 + E1", Pos::new(4, 7), delete),
+        );
+
+        // Ranges grow and shrink.
+        assert_eq!(
+            Some("SUM(A2 : B10)".to_string()),
+            after_edit("SUM(A2 : B8)", pos, insert),
+        );
+        assert_eq!(
+            Some("SUM(A2:B6, B2:A1)".to_string()),
+            after_edit("SUM(A2:B8, B4:A1)", pos, delete),
+        );
+        assert_eq!(
+            Some("COUNT(2:3)".to_string()),
+            after_edit("COUNT(2:5)", pos, delete)
+        );
+        assert_eq!(
+            Some("COUNT(C:C)".to_string()),
+            after_edit("COUNT(C:C)", pos, delete)
+        );
+
+        // References into deleted rows become `#REF!`.
+        assert_eq!(
+            Some("#REF! + SUM(#REF!) + A2".to_string()),
+            after_edit("A3 + SUM(A3:B4) + A2", pos, delete),
+        );
+        assert_eq!(
+            Some("COUNT(#REF!)".to_string()),
+            after_edit("COUNT(3:4)", pos, delete)
+        );
+
+        // The formula itself is deleted.
+        assert_eq!(None, after_edit("A1", Pos::new(0, 4), delete));
+    }
+
+    #[test]
+    fn test_insert_and_delete_columns() {
+        let pos = Pos::new(0, 0);
+        let insert = GridEdit::InsertColumns { index: 1, count: 1 };
+        let delete = GridEdit::DeleteColumns { index: 1, count: 2 };
+
+        assert_eq!(
+            Some("A1 + C1 + $D$2 + SUM(C:$E)".to_string()),
+            after_edit("A1 + B1 + $C$2 + SUM(B:$D)", pos, insert),
+        );
+        assert_eq!(
+            Some("A1 + #REF! + B2 + SUM(B:B)".to_string()),
+            after_edit("A1 + B1 + D2 + SUM(B:D)", pos, delete),
+        );
+        assert_eq!(Some("#REF!".to_string()), after_edit("B:C", pos, delete));
+    }
+
+    #[test]
+    fn test_move_rect() {
+        // Move B2:C3 to E5:F6.
+        let edit = GridEdit::MoveRect {
+            source: Rect::new_span(Pos::new(1, 2), Pos::new(2, 3)),
+            dest: Pos::new(4, 5),
+        };
+        let pos = Pos::new(0, 0);
+
+        // References into the moved cells follow them.
+        assert_eq!(
+            Some("E5 + $F$6 + SUM(E5:F6) + SUM(A1:C3)".to_string()),
+            after_edit("B2 + $C$3 + SUM(B2:C3) + SUM(A1:C3)", pos, edit),
+        );
+        // References to cells that were overwritten become `#REF!`.
+        assert_eq!(
+            Some("#REF! + G7".to_string()),
+            after_edit("F5 + G7", pos, edit),
+        );
+        // A moved formula still refers to the same cells.
+        assert_eq!(
+            Some("A1 + E5".to_string()),
+            after_edit("A1 + B2", Pos::new(2, 2), edit),
+        );
+        assert_eq!(
+            Some("R[-7]C[-7]".to_string()),
+            parse_formula_with_notation("R[-4]C[-4]", Pos::new(2, 2), CellRefNotation::R1C1)
+                .unwrap()
+                .source_after_edit(Pos::new(2, 2), None, edit, None),
+        );
+    }
+
+    #[test]
+    fn test_translate_formula() {
+        let translate = |source, from, to| translate_formula(source, from, to, CellRefNotation::A1);
+        let c4 = Pos::new(2, 4);
+        let e9 = Pos::new(4, 9);
+
+        assert_eq!(
+            "=C6 + $A$1 + $A6 + C$1 + E9",
+            translate("=A1 + $A$1 + $A1 + A$1 + C4", c4, e9),
+        );
+        assert_eq!(
+            "SUM(C7 : /* x */ $D$5, E:$F, $n1:6)",
+            translate("SUM(A2 : /* x */ $D$5, C:$F, $n1:1)", c4, e9),
+        );
+        assert_eq!("A1", translate("A1", c4, c4));
+        assert_eq!("SUM(C6#)", translate("SUM(A1#)", c4, e9));
+        // Negative coordinates are valid.
+        assert_eq!("nAn4 + Cn4", translate("A1 + D1", c4, Pos::new(1, -1)));
+        // Relative references in R1C1 notation don't change.
+        assert_eq!(
+            "R[-1]C + R1C1",
+            translate_formula("R[-1]C + R1C1", c4, e9, CellRefNotation::R1C1),
+        );
+
+        // References that leave the valid range become `#REF!`.
+        let far = Pos::new(i64::MAX, 0);
+        assert_eq!(
+            "#REF! + $B$1 + #REF!",
+            translate("D4 + $B$1 + D4:E5", c4, far)
+        );
+        assert_eq!(
+            "SUM(#REF!) + SUM($1:$2)",
+            translate("SUM(D:E) + SUM($1:$2)", c4, far)
+        );
+    }
+
+    #[test]
+    fn test_rename_name() {
+        let rename = |source| rename_name(source, CellRefNotation::A1, "TaxRate", "SalesTax");
+        assert_eq!(
+            "SUM(A1:A5) * SalesTax /* TaxRate */ + 'TaxRate' & SalesTax",
+            rename("SUM(A1:A5) * TaxRate /* TaxRate */ + 'TaxRate' & taxrate"),
+        );
+        // Other names and functions with the same name are unchanged.
+        assert_eq!("TaxRates + TaxRate(1)", rename("TaxRates + TaxRate(1)"));
+    }
+
+    #[test]
+    fn test_rename_sheet() {
+        let a1 = CellRefNotation::A1;
+        assert_eq!(
+            "'Q1 Data'!A1 + 'Q1 Data'!$B:C * Sheet3!A1 - 'Sheet2'",
+            rename_sheet(
+                "Sheet2!A1 + 'sheet2'!$B:C * Sheet3!A1 - 'Sheet2'",
+                a1,
+                "Sheet2",
+                "Q1 Data",
+            ),
+        );
+        assert_eq!(
+            "SUM('It''s'!B2:C9)",
+            rename_sheet("SUM('My Sheet'!B2:C9)", a1, "My Sheet", "It's"),
+        );
+        assert_eq!(
+            "Data!R[1]C",
+            rename_sheet("'Old'!R[1]C", CellRefNotation::R1C1, "old", "Data"),
+        );
+        // Names that look like cell references are quoted.
+        assert_eq!("'B2'!A1", rename_sheet("Sheet2!A1", a1, "Sheet2", "B2"));
+    }
+
+    #[test]
+    fn test_sheet_references_after_edit() {
+        let pos = Pos::new(4, 5); // E5
+        let insert = GridEdit::InsertRows { index: 3, count: 2 };
+
+        // References to other sheets point to the same cells, even though the
+        // formula moves.
+        assert_eq!(
+            Some("=C5 + Sheet2!C3 + SUM('My Sheet'!$A$1:A3)".to_string()),
+            after_edit("=C3 + Sheet2!C3 + SUM('My Sheet'!$A$1:A3)", pos, insert),
+        );
+        assert_eq!(
+            "Sheet2!C4",
+            translate_formula("Sheet2!C3", pos, Pos::new(4, 6), CellRefNotation::A1),
+        );
+        assert_eq!("Sheet2!R[-2]C[-2]", to_r1c1("Sheet2!C3", pos),);
+
+        // References that name the formula's own sheet move with the edit.
+        let source = "=C3 + Sheet1!C3 + 'sheet1'!A:A + Sheet2!C3";
+        let formula = parse_formula(source, pos).unwrap();
+        assert_eq!(
+            Some("=C5 + Sheet1!C5 + sheet1!A:A + Sheet2!C3".to_string()),
+            formula.source_after_edit(pos, Some("Sheet1"), insert, Some("Sheet1")),
+        );
+
+        // Editing another sheet only changes references to that sheet.
+        assert_eq!(
+            Some("=C3 + Sheet1!C3 + sheet1!A:A + Sheet2!C5".to_string()),
+            formula.source_after_edit(pos, Some("Sheet1"), insert, Some("Sheet2")),
+        );
+        assert_eq!(
+            Some("=C3 + Sheet1!C3 + sheet1!A:A + Sheet2!C5".to_string()),
+            formula.source_after_edit(pos, None, insert, Some("Sheet2")),
+        );
+    }
+}
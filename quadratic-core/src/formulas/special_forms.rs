@@ -0,0 +1,383 @@
+//! Special forms are functions that receive their arguments unevaluated, so
+//! that they only evaluate (and only read cells for) the arguments they need.
+
+use super::*;
+
+/// Returns whether `name` is the name of a special form.
+pub fn is_special_form(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "if" | "ifs"
+            | "switch"
+            | "choose"
+            | "iferror"
+            | "ifna"
+            | "and"
+            | "or"
+            | "let"
+            | "lambda"
+            | "map"
+            | "reduce"
+            | "scan"
+            | "byrow"
+            | "bycol"
+            | "makearray"
+            | "isformula",
+    )
+}
+
+impl AstNode {
+    /// Evaluates a call to a special form, evaluating only the arguments
+    /// needed to produce the result.
+    pub(super) async fn eval_special_form(
+        &self,
+        ctx: &mut Ctx<'_>,
+        func: &Spanned<String>,
+        args: &[AstNode],
+    ) -> FormulaResult<Value> {
+        match func.inner.to_ascii_lowercase().as_str() {
+            "if" => self.eval_if(ctx, args).await,
+            "ifs" => self.eval_ifs(ctx, args).await,
+            "switch" => self.eval_switch(ctx, args).await,
+            "choose" => self.eval_choose(ctx, args).await,
+            "iferror" => self.eval_iferror(ctx, args, |_| true).await,
+            "ifna" => {
+                self.eval_iferror(ctx, args, |e| e.msg.kind() == ErrorKind::NotAvailable)
+                    .await
+            }
+            // `AND()` stops at the first false value and `OR()` stops at the
+            // first true value.
+            "and" => self.eval_and_or(ctx, args, false).await,
+            "or" => self.eval_and_or(ctx, args, true).await,
+            "let" => self.eval_let(ctx, args).await,
+            "lambda" => self.eval_lambda(ctx, args),
+            "map" => self.eval_map(ctx, args).await,
+            "reduce" => self.eval_reduce(ctx, args, false).await,
+            "scan" => self.eval_reduce(ctx, args, true).await,
+            "byrow" => self.eval_byrow(ctx, args).await,
+            "bycol" => self.eval_bycol(ctx, args).await,
+            "makearray" => self.eval_makearray(ctx, args).await,
+            "isformula" => self.eval_isformula(ctx, args).await,
+            _ => Err(FormulaErrorMsg::BadFunctionName.with_span(func.span)),
+        }
+    }
+
+    /// Evaluates `IF(cond, if_true, if_false)`. If the condition is an array,
+    /// both branches are evaluated and the result is chosen for each element.
+    async fn eval_if(&self, ctx: &mut Ctx<'_>, args: &[AstNode]) -> FormulaResult<Value> {
+        let [cond, if_true, if_false] = self.fixed_args(args)?;
+        let cond = cond.eval(ctx).await;
+        if cond.inner.array_size().is_some() {
+            let if_true = if_true.eval(ctx).await;
+            let if_false = if_false.eval(ctx).await;
+            return functions::array_map(
+                self.spanned_args(vec![cond, if_true, if_false]),
+                |[c, t, f]| Ok(if c.to_condition()? { t.inner } else { f.inner }),
+            );
+        }
+        let branch = if cond.to_condition()? {
+            if_true
+        } else {
+            if_false
+        };
+        Ok(branch.eval(ctx).await.inner)
+    }
+
+    /// Evaluates `IFS(cond1, value1, cond2, value2, ...)`, returning the value
+    /// for the first true condition.
+    async fn eval_ifs(&self, ctx: &mut Ctx<'_>, args: &[AstNode]) -> FormulaResult<Value> {
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        }
+        for (cond, value) in args.iter().tuples() {
+            if cond.eval(ctx).await.into_single_value()?.to_condition()? {
+                return Ok(value.eval(ctx).await.inner);
+            }
+        }
+        Err(FormulaErrorMsg::NoMatch.with_span(self.span))
+    }
+
+    /// Evaluates `SWITCH(expr, case1, value1, case2, value2, ..., [default])`,
+    /// returning the value for the first case equal to `expr`.
+    async fn eval_switch(&self, ctx: &mut Ctx<'_>, args: &[AstNode]) -> FormulaResult<Value> {
+        let [expr, rest @ ..] = args else {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        };
+        if rest.len() < 2 {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        }
+        let expr = expr.eval(ctx).await.into_single_value()?.to_text()?;
+        let mut cases = rest.chunks_exact(2);
+        for case in &mut cases {
+            if case[0].eval(ctx).await.into_single_value()?.to_text()? == expr {
+                return Ok(case[1].eval(ctx).await.inner);
+            }
+        }
+        match cases.remainder() {
+            [default] => Ok(default.eval(ctx).await.inner),
+            _ => Err(FormulaErrorMsg::NoMatch.with_span(self.span)),
+        }
+    }
+
+    /// Evaluates `CHOOSE(index, value1, value2, ...)`, returning the value at
+    /// the 1-based `index`.
+    async fn eval_choose(&self, ctx: &mut Ctx<'_>, args: &[AstNode]) -> FormulaResult<Value> {
+        let [index, choices @ ..] = args else {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        };
+        if choices.is_empty() {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        }
+        let index = index.eval(ctx).await.into_single_value()?;
+        let choice = usize::try_from(index.to_integer()?)
+            .ok()
+            .and_then(|i| choices.get(i.checked_sub(1)?))
+            .ok_or_else(|| FormulaErrorMsg::IndexOutOfBounds.with_span(index.span))?;
+        Ok(choice.eval(ctx).await.inner)
+    }
+
+    /// Evaluates `IFERROR(value, fallback)` or `IFNA(value, fallback)`. The
+    /// fallback is only evaluated if `value` contains an error for which
+    /// `is_caught` returns true.
+    async fn eval_iferror(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+        is_caught: fn(&FormulaError) -> bool,
+    ) -> FormulaResult<Value> {
+        let [value, fallback] = self.fixed_args(args)?;
+        let value = value.eval(ctx).await;
+        let needs_fallback = match &value.inner {
+            Value::Array(a) => a.iter().flatten().any(|v| v.error().is_some_and(is_caught)),
+            other => other.error().is_some_and(is_caught),
+        };
+        if !needs_fallback {
+            return Ok(value.inner);
+        }
+        let fallback = fallback.eval(ctx).await;
+        functions::array_map(
+            self.spanned_args(vec![value, fallback]),
+            |[value, fallback]| {
+                Ok(match value.inner {
+                    Value::Error(e) if is_caught(&e) => fallback.inner,
+                    other => other,
+                })
+            },
+        )
+    }
+
+    /// Evaluates `AND(...)` or `OR(...)`, stopping at the first argument that
+    /// contains `short_circuit_value`. At least one argument is required.
+    async fn eval_and_or(
+        &self,
+        ctx: &mut Ctx<'_>,
+        args: &[AstNode],
+        short_circuit_value: bool,
+    ) -> FormulaResult<Value> {
+        if args.is_empty() {
+            return Err(FormulaErrorMsg::BadArgumentCount.with_span(self.span));
+        }
+        for arg in args {
+            let bools = arg.eval(ctx).await.to_conditions()?;
+            if bools.contains(&short_circuit_value) {
+                return Ok(Value::Bool(short_circuit_value));
+            }
+        }
+        Ok(Value::Bool(!short_circuit_value))
+    }
+
+    /// Evaluates `ISFORMULA(reference)`, which returns whether a cell
+    /// contains a formula without reading its value.
+    async fn eval_isformula(&self, ctx: &mut Ctx<'_>, args: &[AstNode]) -> FormulaResult<Value> {
+        let [reference] = self.fixed_args(args)?;
+        let cell_ref = reference.to_cell_ref()?;
+        let pos = cell_ref.resolve_from(ctx.pos);
+        let sheet = cell_ref.sheet.as_deref();
+        Ok(Value::Bool(ctx.grid.is_formula(sheet, pos).await))
+    }
+
+    /// Checks the argument count of a special form.
+    pub(super) fn fixed_args<'a, const N: usize>(
+        &self,
+        args: &'a [AstNode],
+    ) -> FormulaResult<&'a [AstNode; N]> {
+        args.try_into()
+            .map_err(|_| FormulaErrorMsg::BadArgumentCount.with_span(self.span))
+    }
+
+    fn spanned_args(&self, args: Vec<Spanned<Value>>) -> Spanned<Vec<Spanned<Value>>> {
+        Spanned {
+            span: self.span,
+            inner: args,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::formulas::tests::*;
+
+    /// `GridProxy` implementation that records which cells are accessed.
+    #[derive(Debug, Default, Clone)]
+    struct RecordingGridMock {
+        cells_accessed: HashSet<Pos>,
+    }
+    #[async_trait(?Send)]
+    impl GridProxy for RecordingGridMock {
+        async fn get(&mut self, _sheet: Option<&str>, pos: Pos) -> Cell {
+            self.cells_accessed.insert(pos);
+            Cell::Int(pos.x + 10 * pos.y)
+        }
+    }
+
+    /// Evaluates a formula and returns its result along with the cells that
+    /// were read, in A1 notation.
+    fn eval_recording(s: &str) -> (String, Vec<String>) {
+        let mut g = RecordingGridMock::default();
+        let result = match eval(&mut g, s) {
+            Ok(value) => value.to_string(),
+            Err(e) => e.msg.kind().to_string(),
+        };
+        let cells = g
+            .cells_accessed
+            .into_iter()
+            .sorted_by_key(|pos| (pos.y, pos.x))
+            .map(|pos| {
+                CellRef::absolute(pos)
+                    .a1_string(Pos::ORIGIN)
+                    .replace('$', "")
+            })
+            .collect();
+        (result, cells)
+    }
+
+    #[test]
+    fn test_formula_if_is_lazy() {
+        let g = &mut PanicGridMock;
+        assert_eq!("0", eval_to_string(g, "IF(0 = 0, 0, 1/A1)"));
+        assert_eq!("yes", eval_to_string(g, "IF(1, 'yes', C5)"));
+        assert_eq!("no", eval_to_string(g, "IF(FALSE(), B2:C8, 'no')"));
+        assert_eq!(
+            "{a, 2; 3, b}",
+            eval_to_string(g, "IF({TRUE(), 0; 0, 1}, {'a', 1; 2, 'b'}, {1, 2; 3, 4})"),
+        );
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, "IF(1, 2)").unwrap_err().msg,
+        );
+
+        assert_eq!(
+            ("12".into(), vec!["B1".into(), "C1".into()]),
+            eval_recording("IF(B1 > 5, C1, D1)")
+        );
+        assert_eq!(
+            ("13".into(), vec!["D1".into(), "A3".into()]),
+            eval_recording("IF(A3 > 50, C1, D1)")
+        );
+    }
+
+    #[test]
+    fn test_formula_ifs_and_switch() {
+        let g = &mut PanicGridMock;
+        assert_eq!("b", eval_to_string(g, "IFS(0, 'a', 1, 'b', A1, 'c')"));
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "IFS(0, A1, FALSE(), A2)").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, "IFS(0, 'a', 1)").unwrap_err().msg,
+        );
+
+        assert_eq!("two", eval_to_string(g, "SWITCH(1+1, 1, A1, 2, 'two', B1)"));
+        assert_eq!("other", eval_to_string(g, "SWITCH('x', 'y', A1, 'other')"));
+        assert_eq!(
+            ErrorKind::NotAvailable,
+            eval(g, "SWITCH(3, 1, A1, 2, A2)").unwrap_err().msg.kind(),
+        );
+
+        assert_eq!(
+            ("21".into(), vec!["B1".into(), "B2".into(), "A3".into()]),
+            eval_recording("IFS(A3 > 50, A1, B1 > 5, B2, C1 > 5, C2)"),
+        );
+        assert_eq!(
+            ("20".into(), vec!["A1".into(), "A2".into()]),
+            eval_recording("SWITCH(10, A1, A2, B1, B2)"),
+        );
+    }
+
+    #[test]
+    fn test_formula_choose() {
+        let g = &mut PanicGridMock;
+        assert_eq!("b", eval_to_string(g, "CHOOSE(2, A1, 'b', C3)"));
+        let e = eval(g, "CHOOSE(4, A1, 'b', C3)").unwrap_err();
+        assert_eq!(FormulaErrorMsg::IndexOutOfBounds, e.msg);
+        assert_eq!(Some(Span { start: 7, end: 8 }), e.span);
+        assert_eq!(
+            FormulaErrorMsg::IndexOutOfBounds,
+            eval(g, "CHOOSE(0, A1)").unwrap_err().msg,
+        );
+
+        assert_eq!(
+            ("11".into(), vec!["B1".into()]),
+            eval_recording("CHOOSE(3, A1, A2, B1, B2)"),
+        );
+    }
+
+    #[test]
+    fn test_formula_iferror_is_lazy() {
+        let g = &mut PanicGridMock;
+        assert_eq!("5", eval_to_string(g, "IFERROR(5, A1)"));
+        assert_eq!(
+            "fallback",
+            eval_to_string(g, "IFERROR(1 + 'a', 'fallback')")
+        );
+        assert_eq!("{1, 2}", eval_to_string(g, "IFERROR({1, 2}, A1)"));
+        assert_eq!("{1, x}", eval_to_string(g, "IFERROR({1, NA()}, 'x')"));
+        assert_eq!("5", eval_to_string(g, "IFNA(5, A1)"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "IFNA(1 + 'a', A1)").unwrap_err().msg.kind(),
+        );
+
+        assert_eq!(
+            ("31".into(), vec!["B3".into()]),
+            eval_recording("IFERROR(B3, C3)")
+        );
+    }
+
+    #[test]
+    fn test_formula_and_or_are_lazy() {
+        let g = &mut PanicGridMock;
+        assert_eq!("FALSE", eval_to_string(g, "AND(1, 0, A1)"));
+        assert_eq!("TRUE", eval_to_string(g, "OR(0, {0, 1}, A1)"));
+        assert_eq!("TRUE", eval_to_string(g, "AND(1, -0.5)"));
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "OR('yes', 0)").unwrap_err().msg.kind(),
+        );
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, "AND()").unwrap_err().msg,
+        );
+        assert_eq!(
+            FormulaErrorMsg::BadArgumentCount,
+            eval(g, "OR()").unwrap_err().msg,
+        );
+        assert_eq!(
+            ErrorKind::Value,
+            eval(g, "AND(1 + 'a', 0)").unwrap_err().msg.kind(),
+        );
+
+        assert_eq!(
+            ("FALSE".into(), vec!["A3".into(), "B3".into()]),
+            eval_recording("AND(B3 = 31, A3 = 1, C3)"),
+        );
+        assert_eq!(
+            ("TRUE".into(), vec!["A3".into(), "B3".into()]),
+            eval_recording("OR(A3 = 1, B3 = 31, C3)"),
+        );
+    }
+}
@@ -1,28 +1,33 @@
-use async_trait::async_trait;
+pub(crate) use async_trait::async_trait;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
 use smallvec::smallvec;
 
-use super::*;
+pub(crate) use super::*;
 
+/// Defines `GridMock`, a `GridProxy` implementation whose cells contain the
+/// text returned by the function, or are empty if it returns `None`.
 macro_rules! make_stateless_grid_mock {
     ($function_body:expr) => {
         #[derive(Debug, Default, Copy, Clone)]
         struct GridMock;
         #[async_trait(?Send)]
         impl GridProxy for GridMock {
-            async fn get(&mut self, pos: Pos) -> Option<String> {
+            async fn get(&mut self, _sheet: Option<&str>, pos: Pos) -> Cell {
                 let f: fn(Pos) -> Option<String> = $function_body;
-                f(pos)
+                f(pos).map(Cell::Text).unwrap_or_default()
             }
         }
     };
 }
+pub(crate) use make_stateless_grid_mock;
 
 /// `GridProxy` implementation that just panics whenever a cell is accessed.
 #[derive(Debug, Default, Copy, Clone)]
-struct PanicGridMock;
+pub(crate) struct PanicGridMock;
 #[async_trait(?Send)]
 impl GridProxy for PanicGridMock {
-    async fn get(&mut self, _pos: Pos) -> Option<String> {
+    async fn get(&mut self, _sheet: Option<&str>, _pos: Pos) -> Cell {
         panic!("no cell should be accessed")
     }
 }
@@ -58,6 +63,9 @@ fn test_formula_cell_ref() {
         (2, -6) => "100".to_string(),   // D$n6 -> Bn6
         (-1, -2) => "1000".to_string(), // A0   -> nAn2
         (-4, 0) => "10000".to_string(), // nB2  -> nD0
+        // Errors don't stop evaluation, so evaluating at C4 still reads the
+        // other cells.
+        (1, 0) | (4, -6) | (-2, 2) => "0".to_string(),
         _ => panic!("cell {pos} shouldn't be accessed"),
     }));
 
@@ -78,6 +86,56 @@ fn test_formula_cell_ref() {
     );
 }
 
+#[test]
+fn test_formula_iterative_self_reference() {
+    let form = parse_formula("A1 / 2 + 1", Pos::new(0, 1)).unwrap();
+
+    make_stateless_grid_mock!(|pos| Some(match (pos.x, pos.y) {
+        (0, 1) => "6".to_string(),
+        _ => panic!("cell {pos} shouldn't be accessed"),
+    }));
+
+    // When iterating, the formula sees its cell's previous value.
+    let mut grid = GridMock;
+    let mut ctx = Ctx::new(&mut grid, Pos::new(0, 1));
+    ctx.allow_self_reference = true;
+    assert_eq!(
+        "4",
+        pollster::block_on(form.eval_in_ctx(&mut ctx))
+            .unwrap()
+            .to_string(),
+    );
+}
+
+#[test]
+fn test_formula_r1c1_notation() {
+    make_stateless_grid_mock!(|pos| Some((pos.x * 10 + pos.y).to_string()));
+
+    let eval_r1c1 = |s: &str, pos: Pos| {
+        parse_formula_with_notation(s, pos, CellRefNotation::R1C1)
+            .unwrap()
+            .eval_blocking(&mut GridMock, pos)
+            .unwrap()
+            .to_string()
+    };
+    let pos = Pos::new(2, 3);
+    assert_eq!("22", eval_r1c1("R[-1]C[0]", pos));
+    assert_eq!("35", eval_r1c1("R5C3", pos));
+    assert_eq!("43", eval_r1c1("RC[2]", pos));
+    assert_eq!("11", eval_r1c1("R[-2]C1", pos));
+    assert_eq!("{11, 21; 12, 22}", eval_r1c1("R1C1:R[-1]C[0]", pos));
+
+    // A1-style references are not cell references in R1C1 notation.
+    assert_eq!(
+        FormulaErrorMsg::BadName,
+        parse_formula_with_notation("B2", pos, CellRefNotation::R1C1)
+            .unwrap()
+            .eval_blocking(&mut GridMock, pos)
+            .unwrap_err()
+            .msg,
+    );
+}
+
 #[test]
 fn test_formula_circular_array_ref() {
     let form = parse_formula("$B$0:$C$4", Pos::new(0, 0)).unwrap();
@@ -98,6 +156,161 @@ fn test_formula_circular_array_ref() {
     )
 }
 
+#[test]
+fn test_formula_range_uses_get_range() {
+    /// Grid that records whether cells are fetched individually or as part
+    /// of a range.
+    #[derive(Debug, Default)]
+    struct RangeGridMock {
+        cells_fetched: Vec<Pos>,
+        ranges_fetched: Vec<Rect>,
+    }
+    #[async_trait(?Send)]
+    impl GridProxy for RangeGridMock {
+        async fn get(&mut self, _sheet: Option<&str>, pos: Pos) -> Cell {
+            self.cells_fetched.push(pos);
+            Cell::Empty
+        }
+        async fn get_range(&mut self, _sheet: Option<&str>, rect: Rect) -> Vec<Vec<Cell>> {
+            self.ranges_fetched.push(rect);
+            rect.y_range()
+                .map(|y| rect.x_range().map(|x| Cell::Int(x * y)).collect())
+                .collect()
+        }
+    }
+
+    let mut g = RangeGridMock::default();
+    assert_eq!("165", eval_to_string(&mut g, "SUM(B1:C10)"));
+    assert_eq!("{1, 2; 2, 4}", eval_to_string(&mut g, "C2:B1"),);
+    assert_eq!(
+        vec![
+            Rect::new_span(Pos::new(1, 1), Pos::new(2, 10)),
+            Rect::new_span(Pos::new(1, 1), Pos::new(2, 2)),
+        ],
+        g.ranges_fetched,
+    );
+    assert_eq!(Vec::<Pos>::new(), g.cells_fetched);
+}
+
+#[test]
+fn test_formula_row_and_column_ranges() {
+    /// Grid with values in B2:D5.
+    #[derive(Debug, Default, Copy, Clone)]
+    struct BoundedGridMock;
+    #[async_trait(?Send)]
+    impl GridProxy for BoundedGridMock {
+        async fn get(&mut self, _sheet: Option<&str>, pos: Pos) -> Cell {
+            assert!(
+                (1..=3).contains(&pos.x) && (2..=5).contains(&pos.y),
+                "cell {pos} shouldn't be accessed",
+            );
+            Cell::Int(pos.x * 10 + pos.y)
+        }
+        async fn bounds(&mut self, _sheet: Option<&str>) -> Option<Rect> {
+            Some(Rect::new_span(Pos::new(1, 2), Pos::new(3, 5)))
+        }
+    }
+
+    let g = &mut BoundedGridMock;
+    assert_eq!("{12; 13; 14; 15}", eval_to_string(g, "B:B"));
+    assert_eq!("54", eval_to_string(g, "SUM(B:B)"));
+    assert_eq!("{12, 22, 32; 13, 23, 33}", eval_to_string(g, "2:3"));
+    assert_eq!("8", eval_to_string(g, "COUNT(C:$D)"));
+    assert_eq!("6", eval_to_string(g, "COUNT($4:5)"));
+
+    // Relative references move with the formula.
+    let form = parse_formula("SUM(B:B)", Pos::new(0, 0)).unwrap();
+    assert_eq!(
+        "134",
+        form.eval_blocking(g, Pos::new(2, 0)).unwrap().to_string(),
+    );
+
+    // Referencing the formula's own row or column is circular.
+    let form = parse_formula("SUM(3:3)", Pos::new(1, 3)).unwrap();
+    assert_eq!(
+        FormulaErrorMsg::CircularReference,
+        form.eval_blocking(g, Pos::new(1, 3)).unwrap_err().msg,
+    );
+
+    // Without any bounds, the range is empty.
+    assert_eq!("0", eval_to_string(&mut PanicGridMock, "SUM(A:A)"));
+}
+
+#[test]
+fn test_formula_spill() {
+    /// Grid with a value in C3 and an array formula in A5 that spilled into
+    /// A5:B6.
+    #[derive(Debug, Default, Copy, Clone)]
+    struct SpillGridMock;
+    #[async_trait(?Send)]
+    impl GridProxy for SpillGridMock {
+        async fn get(&mut self, _sheet: Option<&str>, pos: Pos) -> Cell {
+            Cell::Int(pos.x * 10 + pos.y)
+        }
+        async fn occupied_cells(&mut self, _sheet: Option<&str>, rect: Rect) -> Vec<Pos> {
+            [Pos::new(2, 3), Pos::new(3, 1)]
+                .into_iter()
+                .filter(|&pos| rect.contains(pos))
+                .collect()
+        }
+        async fn spill_rect(&mut self, _sheet: Option<&str>, pos: Pos) -> Option<Rect> {
+            (pos == Pos::new(0, 5)).then(|| Rect::new_span(pos, Pos::new(1, 6)))
+        }
+    }
+    let g = &mut SpillGridMock;
+
+    let eval_at = |s: &str, pos: Pos| {
+        parse_formula(s, pos)
+            .unwrap()
+            .eval_blocking(&mut SpillGridMock, pos)
+    };
+
+    // The array fits.
+    assert_eq!(
+        "{1, 2; 3, 4}",
+        eval_at("{1, 2; 3, 4}", Pos::new(0, 0)).unwrap().to_string(),
+    );
+    // The array would spill into C3, and also D1 when starting further up.
+    let error = eval_at("{1, 2; 3, 4}", Pos::new(1, 2)).unwrap_err();
+    assert_eq!(
+        FormulaErrorMsg::Spill {
+            blocked_by: Pos::new(2, 3),
+        },
+        error.msg,
+    );
+    assert_eq!(
+        "Array can't spill because cell C3 isn't empty",
+        error.msg.to_string()
+    );
+    assert_eq!(ErrorKind::Spill, error.msg.kind());
+    assert_eq!(Some(Span { start: 0, end: 12 }), error.span);
+    assert_eq!(
+        FormulaErrorMsg::Spill {
+            blocked_by: Pos::new(3, 1),
+        },
+        eval_at("SEQUENCE(3, 3)", Pos::new(1, 1)).unwrap_err().msg,
+    );
+    // A single value never spills.
+    assert_eq!("7", eval_at("7", Pos::new(2, 2)).unwrap().to_string());
+
+    // `A5#` refers to the whole spilled array.
+    assert_eq!("{5, 15; 6, 16}", eval_to_string(g, "A5#"));
+    assert_eq!("42", eval_to_string(g, "SUM($A$5#)"));
+    assert_eq!(
+        FormulaErrorMsg::BadCellReference,
+        eval(g, "B2#").unwrap_err().msg,
+    );
+    assert_eq!(
+        FormulaErrorMsg::CircularReference,
+        eval_at("A5#", Pos::new(1, 6)).unwrap_err().msg,
+    );
+    assert_eq!(
+        FormulaErrorMsg::ErrorValue(ErrorKind::Spill),
+        eval(g, "#SPILL!").unwrap_err().msg,
+    );
+    assert_eq!("9", eval_to_string(g, "ERROR.TYPE(#SPILL!)"));
+}
+
 #[test]
 fn test_formula_math_operators() {
     assert_eq!(
@@ -106,6 +319,229 @@ fn test_formula_math_operators() {
     );
 }
 
+#[test]
+fn test_formula_arithmetic_errors() {
+    let g = &mut PanicGridMock;
+    let error = eval(g, "1 / (2 - 2)").unwrap_err();
+    assert_eq!(FormulaErrorMsg::DivideByZero, error.msg);
+    assert_eq!(Some(Span { start: 4, end: 11 }), error.span);
+    assert_eq!("{0.5, #DIV/0!}", eval_to_string(g, "1 / {2, 0}"));
+
+    let error = eval(g, "0 ^ -1").unwrap_err();
+    assert_eq!(FormulaErrorMsg::NegativeExponent, error.msg);
+    assert_eq!(Some(Span { start: 4, end: 6 }), error.span);
+    assert_eq!("0.25", eval_to_string(g, "2 ^ -2"));
+    assert_eq!("1", eval_to_string(g, "0 ^ 0"));
+    assert_eq!(
+        ErrorKind::Num,
+        eval(g, "(-8) ^ 0.5").unwrap_err().msg.kind()
+    );
+    assert_eq!(
+        FormulaErrorMsg::Overflow,
+        eval(g, "10 ^ 400").unwrap_err().msg
+    );
+}
+
+#[test]
+fn test_formula_math_functions() {
+    let g = &mut PanicGridMock;
+    assert_eq!("{3, 0, 1}", eval_to_string(g, "ABS({-3, 0, 1})"));
+    assert_eq!("{-1, 0, 1}", eval_to_string(g, "SIGN({-3, 0, 0.5})"));
+
+    // Rounding
+    assert_eq!("2.68", eval_to_string(g, "ROUND(2.675, 2)"));
+    assert_eq!("{-3, 3}", eval_to_string(g, "ROUND({-2.5, 2.5})"));
+    assert_eq!("1200", eval_to_string(g, "ROUND(1234.5, -2)"));
+    assert_eq!("0.3", eval_to_string(g, "ROUNDUP(0.1 * 3, 1)"));
+    assert_eq!(
+        "{-3.2, 3.2}",
+        eval_to_string(g, "ROUNDUP({-3.14, 3.14}, 1)")
+    );
+    assert_eq!(
+        "{-3.1, 3.1}",
+        eval_to_string(g, "ROUNDDOWN({-3.19, 3.19}, 1)")
+    );
+    assert_eq!("-3", eval_to_string(g, "TRUNC(-3.9)"));
+    assert_eq!("-4", eval_to_string(g, "INT(-3.1)"));
+    assert_eq!("10", eval_to_string(g, "MROUND(11, 5)"));
+    assert_eq!("0.3", eval_to_string(g, "MROUND(0.32, 0.1)"));
+    assert_eq!(
+        ErrorKind::Num,
+        eval(g, "MROUND(-11, 5)").unwrap_err().msg.kind()
+    );
+    assert_eq!(
+        "{3, -2, -4}",
+        eval_to_string(g, "CEILING({2.5, -2.5, -2.5}, {1, 2, -2})")
+    );
+    assert_eq!(
+        "{2, -4, -2}",
+        eval_to_string(g, "FLOOR({2.5, -2.5, -2.5}, {1, 2, -2})")
+    );
+    assert_eq!("0.35", eval_to_string(g, "CEILING(0.31, 0.05)"));
+    assert_eq!(
+        ErrorKind::Num,
+        eval(g, "CEILING(2.5, -1)").unwrap_err().msg.kind()
+    );
+    assert_eq!(
+        FormulaErrorMsg::DivideByZero,
+        eval(g, "FLOOR(2.5, 0)").unwrap_err().msg,
+    );
+
+    // Division
+    assert_eq!(
+        "{1, 2, -1}",
+        eval_to_string(g, "MOD({7, -7, 7}, {3, 3, -4})")
+    );
+    assert_eq!("{2, -2}", eval_to_string(g, "QUOTIENT({7, -7}, 3)"));
+    let error = eval(g, "MOD(1, 0)").unwrap_err();
+    assert_eq!(FormulaErrorMsg::DivideByZero, error.msg);
+    assert_eq!(Some(Span { start: 7, end: 8 }), error.span);
+
+    // Powers and logarithms
+    assert_eq!("3", eval_to_string(g, "SQRT(9)"));
+    assert_eq!(ErrorKind::Num, eval(g, "SQRT(-1)").unwrap_err().msg.kind());
+    assert_eq!("1", eval_to_string(g, "LN(EXP(1))"));
+    assert_eq!("{2, 3}", eval_to_string(g, "LOG({100, 8}, {10, 2})"));
+    assert_eq!("3", eval_to_string(g, "LOG10(1000)"));
+    assert_eq!(ErrorKind::Num, eval(g, "LN(0)").unwrap_err().msg.kind());
+    assert_eq!(
+        FormulaErrorMsg::DivideByZero,
+        eval(g, "LOG(5, 1)").unwrap_err().msg,
+    );
+
+    // Trigonometry
+    assert_eq!("180", eval_to_string(g, "DEGREES(PI())"));
+    assert_eq!("1", eval_to_string(g, "ROUND(SIN(RADIANS(90)), 10)"));
+    assert_eq!("0", eval_to_string(g, "ROUND(COS(PI() / 2), 10)"));
+    assert_eq!("1", eval_to_string(g, "ROUND(TAN(PI() / 4), 10)"));
+    assert_eq!("90", eval_to_string(g, "DEGREES(ASIN(1))"));
+    assert_eq!("180", eval_to_string(g, "DEGREES(ACOS(-1))"));
+    assert_eq!("45", eval_to_string(g, "DEGREES(ATAN(1))"));
+    assert_eq!("135", eval_to_string(g, "DEGREES(ATAN2(-1, 1))"));
+    assert_eq!(ErrorKind::Num, eval(g, "ASIN(2)").unwrap_err().msg.kind());
+    assert_eq!(
+        FormulaErrorMsg::DivideByZero,
+        eval(g, "ATAN2(0, 0)").unwrap_err().msg,
+    );
+
+    // Combinatorics
+    assert_eq!("{1, 1, 120}", eval_to_string(g, "FACT({0, 1, 5.9})"));
+    assert_eq!(ErrorKind::Num, eval(g, "FACT(-1)").unwrap_err().msg.kind());
+    assert_eq!(
+        FormulaErrorMsg::Overflow,
+        eval(g, "FACT(171)").unwrap_err().msg
+    );
+    assert_eq!("{10, 1, 1}", eval_to_string(g, "COMBIN(5, {2, 0, 5})"));
+    assert_eq!("{20, 1, 120}", eval_to_string(g, "PERMUT(5, {2, 0, 5})"));
+    assert_eq!(
+        ErrorKind::Num,
+        eval(g, "COMBIN(2, 3)").unwrap_err().msg.kind()
+    );
+    assert_eq!(
+        FormulaErrorMsg::Overflow,
+        eval(g, "COMBIN(1e9, 5e8)").unwrap_err().msg
+    );
+    assert_eq!("6", eval_to_string(g, "GCD(12, {18, 24})"));
+    assert_eq!("0", eval_to_string(g, "GCD(0)"));
+    assert_eq!("72", eval_to_string(g, "LCM(8, {9, 12})"));
+    assert_eq!("0", eval_to_string(g, "LCM(8, 0)"));
+    assert_eq!(
+        ErrorKind::Num,
+        eval(g, "GCD(-4, 2)").unwrap_err().msg.kind()
+    );
+}
+
+#[test]
+fn test_formula_rand() {
+    let g = &mut PanicGridMock;
+    let mut eval_seeded = |seed: u64, s: &str| {
+        let mut ctx = Ctx::new(g, Pos::ORIGIN);
+        ctx.rng = SmallRng::seed_from_u64(seed);
+        let value =
+            pollster::block_on(parse_formula(s, Pos::ORIGIN).unwrap().eval_in_ctx(&mut ctx));
+        (value.map(|v| v.inner), ctx.volatile)
+    };
+
+    let (value, volatile) = eval_seeded(0, "RAND()");
+    let n = value.unwrap().to_string().parse::<f64>().unwrap();
+    assert!((0.0..1.0).contains(&n));
+    assert!(volatile);
+    // The same seed gives the same results.
+    assert_eq!(
+        eval_seeded(1, "{RAND(), RAND()}").0.unwrap().to_string(),
+        eval_seeded(1, "{RAND(), RAND()}").0.unwrap().to_string(),
+    );
+
+    for seed in 0..20 {
+        let (value, volatile) = eval_seeded(seed, "RANDBETWEEN(1.5, {3, 2})");
+        let Ok(Value::Array(a)) = value else {
+            panic!("expected array");
+        };
+        assert!(["2", "3"].contains(&a[0][0].to_string().as_str()));
+        assert_eq!("2", a[0][1].to_string());
+        assert!(volatile);
+    }
+    assert_eq!(
+        ErrorKind::Num,
+        eval_seeded(0, "RANDBETWEEN(3, 1)")
+            .0
+            .unwrap_err()
+            .msg
+            .kind(),
+    );
+    assert_eq!(
+        FormulaErrorMsg::BadArgumentCount,
+        eval_seeded(0, "RAND(1)").0.unwrap_err().msg,
+    );
+}
+
+#[test]
+fn test_formula_bitshift_operators() {
+    let g = &mut PanicGridMock;
+    assert_eq!("40", eval_to_string(g, "5 << 3"));
+    assert_eq!("5", eval_to_string(g, "40 >> 3"));
+    assert_eq!("1", eval_to_string(g, "3 << -1"));
+    assert_eq!("-2", eval_to_string(g, "-3 >> 1"));
+    assert_eq!("0", eval_to_string(g, "8 >> 100"));
+    assert_eq!("{2, 4, 8}", eval_to_string(g, "1 << {1, 2, 3}"));
+    assert_eq!("9007199254740992", eval_to_string(g, "1 << 53"));
+
+    // Shifts bind more loosely than addition.
+    assert_eq!("16", eval_to_string(g, "1 + 1 << 3"));
+
+    let error = eval(g, "1 << 54").unwrap_err();
+    assert_eq!(FormulaErrorMsg::Overflow, error.msg);
+    assert_eq!(Some(Span { start: 0, end: 7 }), error.span);
+    assert_eq!(
+        FormulaErrorMsg::Overflow,
+        eval(g, "1 << 100").unwrap_err().msg,
+    );
+    assert_eq!("{8; #NUM!}", eval_to_string(g, "{1; 3} << {3; 53}"),);
+}
+
+#[test]
+fn test_formula_numeric_range_operator() {
+    let g = &mut PanicGridMock;
+    assert_eq!("{1; 2; 3; 4; 5}", eval_to_string(g, "1..5"));
+    assert_eq!("{3; 2; 1}", eval_to_string(g, "3..1"));
+    assert_eq!("{-1; 0; 1}", eval_to_string(g, "-1..1"));
+    assert_eq!("{7}", eval_to_string(g, "7..7"));
+    assert_eq!("55", eval_to_string(g, "SUM(1..10)"));
+    assert_eq!("{2; 4; 6}", eval_to_string(g, "(1..3) * 2"));
+    assert_eq!("{1; 2; 3}", eval_to_string(g, "{1}..{3}"));
+
+    assert_eq!(
+        FormulaErrorMsg::Overflow,
+        eval(g, "1..1e9").unwrap_err().msg,
+    );
+
+    // Array operands produce a column for each element, side by side.
+    assert_eq!("{1, 4; 2, 5; 3, 6}", eval_to_string(g, "{1, 4}..{3, 6}"));
+    assert_eq!("{1, 1; 2, 2; #N/A, 3}", eval_to_string(g, "1..{2, 3}"));
+    assert_eq!("{1; 2; 5; 6}", eval_to_string(g, "{1; 5}..{2; 6}"));
+    assert_eq!("{1, #NUM!; 2, #N/A}", eval_to_string(g, "1..{2, 1e9}"));
+}
+
 #[test]
 fn test_formula_concat() {
     assert_eq!(
@@ -258,10 +694,10 @@ fn test_hyphen_after_cell_ref() {
     assert_eq!("25", eval_to_string(&mut GridMock, "Z1-5"));
 }
 
-fn eval_to_string(grid: &mut impl GridProxy, s: &str) -> String {
+pub(crate) fn eval_to_string(grid: &mut impl GridProxy, s: &str) -> String {
     eval(grid, s).unwrap().to_string()
 }
-fn eval(grid: &mut impl GridProxy, s: &str) -> FormulaResult<Value> {
+pub(crate) fn eval(grid: &mut impl GridProxy, s: &str) -> FormulaResult<Value> {
     parse_formula(s, Pos::ORIGIN)?
         .eval_blocking(grid, Pos::ORIGIN)
         .map(|value| value.inner)
@@ -272,3 +708,149 @@ fn eval(grid: &mut impl GridProxy, s: &str) -> FormulaResult<Value> {
 fn test_currency_string() {
     assert_eq!("30", eval_to_string(&mut PanicGridMock, "\"$10\" + 20"));
 }
+
+#[test]
+fn test_error_values_in_arrays() {
+    make_stateless_grid_mock!(|pos| Some(match (pos.x, pos.y) {
+        (0, 2) => "#DIV/0!".to_string(),
+        (0, y) => y.to_string(),
+        _ => panic!("cell {pos} shouldn't be accessed"),
+    }));
+
+    // One bad element doesn't affect the others.
+    let value = eval(&mut GridMock, "A1:A3 * 10").unwrap();
+    assert_eq!("{10; #DIV/0!; 30}", value.to_string());
+    let Value::Array(a) = value else {
+        panic!("expected array");
+    };
+    let error = a[1][0].error().unwrap();
+    assert_eq!(
+        FormulaErrorMsg::ErrorValue(ErrorKind::DivideByZero),
+        error.msg,
+    );
+    assert_eq!(Some(Span { start: 0, end: 5 }), error.span);
+
+    // Aggregate functions propagate the error.
+    assert_eq!(
+        FormulaErrorMsg::ErrorValue(ErrorKind::DivideByZero),
+        eval(&mut GridMock, "SUM(A1:A3)").unwrap_err().msg,
+    );
+    assert_eq!(
+        ErrorKind::DivideByZero,
+        eval(&mut GridMock, "A2 & 'x'").unwrap_err().msg.kind(),
+    );
+
+    // Errors can be caught.
+    assert_eq!(
+        "{1; 0; 3}",
+        eval_to_string(&mut GridMock, "IFERROR(A1:A3, 0)"),
+    );
+    assert_eq!("4", eval_to_string(&mut GridMock, "SUM(IFERROR(A1:A3, 0))"));
+    assert_eq!(
+        "{1; #DIV/0!; 3}",
+        eval_to_string(&mut GridMock, "IFNA(A1:A3, 0)"),
+    );
+    assert_eq!(
+        "{FALSE; TRUE; FALSE}",
+        eval_to_string(&mut GridMock, "ISERROR(A1:A3)"),
+    );
+}
+
+#[test]
+fn test_error_value_result() {
+    // A single error value is returned as an error.
+    let error = eval(&mut PanicGridMock, "NA()").unwrap_err();
+    assert_eq!(ErrorKind::NotAvailable, error.msg.kind());
+    assert_eq!(Some(Span { start: 0, end: 4 }), error.span);
+
+    let error = eval(&mut PanicGridMock, "1 + 'abc'").unwrap_err();
+    assert_eq!(ErrorKind::Value, error.msg.kind());
+    assert_eq!(Some(Span { start: 4, end: 9 }), error.span);
+
+    assert_eq!(
+        ErrorKind::Name,
+        eval(&mut PanicGridMock, "NOTAFUNCTION(1)")
+            .unwrap_err()
+            .msg
+            .kind(),
+    );
+}
+
+#[test]
+fn test_ref_error_literal() {
+    let error = eval(&mut PanicGridMock, "#REF! + 1").unwrap_err();
+    assert_eq!(FormulaErrorMsg::ErrorValue(ErrorKind::Ref), error.msg);
+    assert_eq!(Some(Span { start: 0, end: 5 }), error.span);
+}
+
+#[test]
+fn test_sheet_references() {
+    /// Grid with multiple sheets, where each cell contains its sheet name
+    /// and position.
+    #[derive(Debug, Default, Copy, Clone)]
+    struct SheetGridMock;
+    #[async_trait(?Send)]
+    impl GridProxy for SheetGridMock {
+        async fn get(&mut self, sheet: Option<&str>, pos: Pos) -> Cell {
+            let sheet = sheet.unwrap_or("Sheet1").to_ascii_lowercase();
+            Cell::Text(format!("{sheet}:{}", pos.x * 10 + pos.y))
+        }
+        async fn bounds(&mut self, sheet: Option<&str>) -> Option<Rect> {
+            match sheet {
+                Some(s) if s.eq_ignore_ascii_case("My Sheet") => {
+                    Some(Rect::new_span(Pos::new(0, 1), Pos::new(1, 2)))
+                }
+                _ => None,
+            }
+        }
+    }
+
+    let g = &mut SheetGridMock;
+    assert_eq!("sheet1:12", eval_to_string(g, "B2"));
+    assert_eq!("sheet2:12", eval_to_string(g, "Sheet2!B2"));
+    assert_eq!("my sheet:12", eval_to_string(g, "'My Sheet'!B2"));
+    assert_eq!("it's:0", eval_to_string(g, "'It''s'!$A$0"));
+    assert_eq!(
+        "{sheet2:12, sheet2:22; sheet2:13, sheet2:23}",
+        eval_to_string(g, "Sheet2!B2:C3"),
+    );
+    assert_eq!(
+        "{my sheet:1, my sheet:11; my sheet:2, my sheet:12}",
+        eval_to_string(g, "'My Sheet'!1:2"),
+    );
+    assert_eq!("{sheet2:12; sheet2:13}", eval_to_string(g, "B2:Sheet2!B3"));
+
+    // A range can't span multiple sheets.
+    assert_eq!(
+        FormulaErrorMsg::BadCellReference,
+        eval(g, "Sheet2!B2:Sheet3!B3").unwrap_err().msg,
+    );
+
+    // References to the same cell on another sheet aren't circular.
+    let pos = Pos::new(1, 2);
+    let mut ctx = Ctx::new(g, pos);
+    ctx.sheet = Some("Sheet1".to_string());
+    let eval_at = |ctx: &mut Ctx<'_>, s: &str| {
+        pollster::block_on(parse_formula(s, pos).unwrap().eval_in_ctx(ctx))
+    };
+    assert_eq!(
+        "sheet2:12",
+        eval_at(&mut ctx, "Sheet2!B2").unwrap().to_string()
+    );
+    assert_eq!(
+        FormulaErrorMsg::CircularReference,
+        eval_at(&mut ctx, "SHEET1!B2").unwrap_err().msg,
+    );
+    assert_eq!(
+        FormulaErrorMsg::CircularReference,
+        eval_at(&mut ctx, "SUM(B1:B3)").unwrap_err().msg,
+    );
+
+    // Sheet names are preserved when displaying references.
+    assert_eq!(
+        "'My Sheet'!R[1]C2",
+        parse_formula("'My Sheet'!$C1", Pos::ORIGIN)
+            .unwrap()
+            .to_string(),
+    );
+}
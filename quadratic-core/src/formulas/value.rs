@@ -1,30 +1,36 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
 use itertools::Itertools;
 use smallvec::{smallvec, SmallVec};
 use std::fmt;
+use std::rc::Rc;
 
-use super::{FormulaErrorMsg, FormulaResult, Spanned};
+use super::{FormulaError, FormulaErrorMsg, FormulaResult, Lambda, Spanned};
 
 const CURRENCY_PREFIX: &[char] = &['$', '¥', '£', '€'];
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub enum Value {
+    /// Contents of an empty cell, which is different from empty text. Blank
+    /// values become `0` or `""` when an operation needs a number or text.
+    #[default]
+    Blank,
     String(String),
     Number(f64),
     Bool(bool),
     Array(Vec<SmallVec<[Value; 1]>>),
-    // TODO: remove this or replace it with a more generic error type
-    MissingErr,
-}
-
-impl Default for Value {
-    fn default() -> Self {
-        Value::String(String::new())
-    }
+    Error(Box<FormulaError>),
+    Lambda(Rc<Lambda>),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    /// Length of time, such as a time of day returned by `TIME()` or the
+    /// difference between two date-times.
+    Duration(Duration),
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Value::Blank => Ok(()),
             Value::String(s) => write!(f, "{s}"),
             Value::Number(n) => write!(f, "{n}"),
             Value::Bool(true) => write!(f, "TRUE"),
@@ -36,7 +42,11 @@ impl fmt::Display for Value {
                     rows.iter().map(|row| row.iter().join(", ")).join("; "),
                 )
             }
-            Value::MissingErr => write!(f, "[missing]"),
+            Value::Error(e) => write!(f, "{}", e.msg.kind()),
+            Value::Lambda(lambda) => write!(f, "{lambda}"),
+            Value::Date(d) => write!(f, "{}", d.format("%Y-%m-%d")),
+            Value::DateTime(dt) => write!(f, "{}", dt.format("%Y-%m-%d %H:%M:%S")),
+            Value::Duration(d) => write!(f, "{}", format_duration(*d)),
         }
     }
 }
@@ -44,25 +54,55 @@ impl fmt::Display for Value {
 impl Value {
     pub fn type_name(&self) -> &'static str {
         match self {
+            Value::Blank => "blank",
             Value::String(_) => "string",
             Value::Number(_) => "number",
             Value::Bool(_) => "boolean",
             Value::Array(_) => "array",
-            Value::MissingErr => "missing value",
+            Value::Error(_) => "error",
+            Value::Lambda(_) => "lambda",
+            Value::Date(_) => "date",
+            Value::DateTime(_) => "date and time",
+            Value::Duration(_) => "duration",
         }
     }
 
     /// Returns the number of values.
     ///
-    /// Empty strings count as zero. Each value in an array counts separately.
-    /// Other values count as 1.
+    /// Blank values count as zero, but empty text and errors count as 1. Each
+    /// value in an array counts separately. Other values count as 1.
     pub fn count(&self) -> usize {
         match self {
             Value::Array(a) => a.iter().flat_map(|row| row.iter().map(|v| v.count())).sum(),
-            Value::String(n) if n.is_empty() => 0,
-            Value::MissingErr => 0,
+            Value::Blank => 0,
+
+            Value::String(_)
+            | Value::Error(_)
+            | Value::Number(_)
+            | Value::Bool(_)
+            | Value::Lambda(_)
+            | Value::Date(_)
+            | Value::DateTime(_)
+            | Value::Duration(_) => 1,
+        }
+    }
+
+    /// Returns the error contained in this value, if it is an error value.
+    pub fn error(&self) -> Option<&FormulaError> {
+        match self {
+            Value::Error(e) => Some(e),
+            _ => None,
+        }
+    }
 
-            Value::String(_) | Value::Number(_) | Value::Bool(_) => 1,
+    /// Returns the serial number of a date, date-time, or duration, or `None`
+    /// for other values.
+    pub fn to_serial(&self) -> Option<f64> {
+        match self {
+            Value::Date(d) => Some(date_to_serial(*d)),
+            Value::DateTime(dt) => Some(datetime_to_serial(*dt)),
+            Value::Duration(d) => Some(duration_to_serial(*d)),
+            _ => None,
         }
     }
 
@@ -76,28 +116,166 @@ impl Value {
     }
 }
 
+/// Parses a number from a string, ignoring surrounding whitespace and an
+/// optional currency prefix. Returns `None` if the string is not a number.
+pub fn parse_number(s: &str) -> Option<f64> {
+    let mut s = s.trim();
+    if let Some(rest) = s.strip_prefix(CURRENCY_PREFIX) {
+        s = rest;
+    }
+    s.parse().ok()
+}
+
+/// Number of days from serial number zero to 1970-01-01.
+///
+/// Serial number zero is 1899-12-30, so serial numbers agree with other
+/// spreadsheets for dates from 1900-03-01 onward. (Other spreadsheets count
+/// 1900-02-29, which did not exist.)
+const UNIX_EPOCH_SERIAL: i64 = 25569;
+const MILLISECONDS_PER_DAY: f64 = 86_400_000.0;
+/// Largest serial number that can be converted to a date, which is
+/// 9999-12-31.
+const MAX_SERIAL: f64 = 2_958_465.0;
+
+/// Formats for date-times in text, tried in order.
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%m/%d/%Y %H:%M:%S",
+    "%m/%d/%Y %H:%M",
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %I:%M %p",
+];
+/// Formats for dates in text, tried in order.
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%m/%d/%y",
+    "%m/%d/%Y",
+    "%d.%m.%Y",
+    "%d-%b-%Y",
+    "%d %b %Y",
+    "%d %B %Y",
+    "%b %d, %Y",
+    "%B %d, %Y",
+    "%b %d %Y",
+    "%B %d %Y",
+];
+/// Formats for times of day in text, tried in order.
+const TIME_FORMATS: &[&str] = &["%H:%M:%S%.f", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+
+/// Parses a date, date-time, or time of day from a string, ignoring
+/// surrounding whitespace. Times of day are returned as durations since
+/// midnight. Returns `None` if the string is not a date or time.
+///
+/// Accepts ISO 8601 (`2024-01-15`, `2024-01-15T13:45:00Z`), US-style
+/// (`1/15/2024`, `1/15/2024 1:45 PM`), and written-out dates (`15-Jan-2024`,
+/// `January 15, 2024`).
+pub fn parse_date_time(s: &str) -> Option<Value> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Some(Value::DateTime(dt.naive_local()));
+    }
+    if let Some(dt) = DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+    {
+        return Some(Value::DateTime(dt));
+    }
+    if let Some(d) = DATE_FORMATS
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(s, f).ok())
+    {
+        return Some(Value::Date(d));
+    }
+    TIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveTime::parse_from_str(s, f).ok())
+        .map(|t| Value::Duration(t - NaiveTime::MIN))
+}
+
+/// Returns the date that serial number zero refers to.
+pub fn serial_epoch() -> NaiveDateTime {
+    chrono::DateTime::UNIX_EPOCH.naive_utc() - Duration::days(UNIX_EPOCH_SERIAL)
+}
+
+/// Returns the serial number of a date, which is the number of days since
+/// 1899-12-30.
+pub fn date_to_serial(date: NaiveDate) -> f64 {
+    (date - serial_epoch().date()).num_days() as f64
+}
+/// Returns the serial number of a date-time, in which the fractional part is
+/// the time of day.
+pub fn datetime_to_serial(datetime: NaiveDateTime) -> f64 {
+    duration_to_serial(datetime - serial_epoch())
+}
+/// Returns a duration as a number of days.
+pub fn duration_to_serial(duration: Duration) -> f64 {
+    duration.num_milliseconds() as f64 / MILLISECONDS_PER_DAY
+}
+/// Returns the date-time for a serial number, rounded to the nearest
+/// millisecond, or `None` if it is out of range.
+pub fn serial_to_datetime(serial: f64) -> Option<NaiveDateTime> {
+    if !(0.0..MAX_SERIAL + 1.0).contains(&serial) {
+        return None;
+    }
+    serial_epoch().checked_add_signed(serial_to_duration(serial)?)
+}
+/// Returns the duration for a number of days, rounded to the nearest
+/// millisecond, or `None` if it is out of range.
+pub fn serial_to_duration(serial: f64) -> Option<Duration> {
+    let ms = (serial * MILLISECONDS_PER_DAY).round();
+    if ms.is_nan() || ms.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    Duration::try_milliseconds(ms as i64)
+}
+
+/// Formats a duration as hours, minutes, and seconds, such as `1:05:00`.
+/// Hours are not limited to 24.
+pub fn format_duration(duration: Duration) -> String {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let total_seconds = duration.num_seconds().unsigned_abs();
+    let (hours, minutes, seconds) = (
+        total_seconds / 3600,
+        total_seconds / 60 % 60,
+        total_seconds % 60,
+    );
+    format!("{sign}{hours}:{minutes:02}:{seconds:02}")
+}
+
 impl Spanned<Value> {
     pub fn to_number(&self) -> FormulaResult<f64> {
         match &self.inner {
             Value::String(s) => {
-                let mut s = s.trim();
+                let s = s.trim();
                 if s.is_empty() {
                     return Ok(0.0);
                 }
-                if let Some(rest) = s.strip_prefix(CURRENCY_PREFIX) {
-                    s = rest;
-                }
-                s.parse().map_err(|_| {
-                    FormulaErrorMsg::Expected {
-                        expected: "number".into(),
-                        got: Some(format!("{s:?}").into()),
-                    }
-                    .with_span(self)
-                })
+                parse_number(s)
+                    .or_else(|| parse_date_time(s)?.to_serial())
+                    .ok_or_else(|| {
+                        let s = s.strip_prefix(CURRENCY_PREFIX).unwrap_or(s);
+                        FormulaErrorMsg::Expected {
+                            expected: "number".into(),
+                            got: Some(format!("{s:?}").into()),
+                        }
+                        .with_span(self)
+                    })
             }
+            Value::Blank => Ok(0.0),
             Value::Number(n) => Ok(*n),
+            Value::Date(_) | Value::DateTime(_) | Value::Duration(_) => {
+                Ok(self.inner.to_serial().unwrap_or_default())
+            }
             Value::Bool(true) => Ok(1.0),
             Value::Bool(false) => Ok(0.0),
+            Value::Error(e) => Err((**e).clone()),
             _ => Err(FormulaErrorMsg::Expected {
                 expected: "number".into(),
                 got: Some(self.inner.type_name().into()),
@@ -108,11 +286,60 @@ impl Spanned<Value> {
     pub fn to_integer(&self) -> FormulaResult<i64> {
         Ok(self.to_number()?.round() as i64)
     }
+    /// Returns the value as a date, ignoring any time of day. Numbers are
+    /// treated as serial numbers and text is parsed.
+    pub fn to_date(&self) -> FormulaResult<NaiveDate> {
+        Ok(self.to_datetime()?.date())
+    }
+    /// Returns the value as a date-time. Numbers are treated as serial numbers
+    /// and text is parsed. Durations are counted from serial number zero, so
+    /// a time of day is on 1899-12-30.
+    pub fn to_datetime(&self) -> FormulaResult<NaiveDateTime> {
+        match &self.inner {
+            Value::Date(d) => Ok(d.and_time(NaiveTime::MIN)),
+            Value::DateTime(dt) => Ok(*dt),
+            Value::String(s) if parse_number(s).is_none() => match parse_date_time(s) {
+                Some(Value::Date(d)) => Ok(d.and_time(NaiveTime::MIN)),
+                Some(Value::DateTime(dt)) => Ok(dt),
+                Some(Value::Duration(d)) => serial_epoch()
+                    .checked_add_signed(d)
+                    .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(self.span)),
+                _ => Err(FormulaErrorMsg::Expected {
+                    expected: "date".into(),
+                    got: Some(format!("{:?}", s.trim()).into()),
+                }
+                .with_span(self.span)),
+            },
+            _ => serial_to_datetime(self.to_number()?)
+                .ok_or_else(|| FormulaErrorMsg::InvalidDate.with_span(self.span)),
+        }
+    }
+    /// Returns the value as a duration. Numbers are treated as a number of
+    /// days, and dates as the time since serial number zero.
+    pub fn to_duration(&self) -> FormulaResult<Duration> {
+        match &self.inner {
+            Value::Duration(d) => Ok(*d),
+            _ => serial_to_duration(self.to_number()?)
+                .ok_or_else(|| FormulaErrorMsg::Overflow.with_span(self.span)),
+        }
+    }
+    /// Returns the time of day of the value, which may be a date-time, a
+    /// duration, or a serial number.
+    pub fn to_time(&self) -> FormulaResult<NaiveTime> {
+        let time = self.to_datetime()?.time();
+        // Round to the nearest second.
+        Ok(time.with_nanosecond(0).unwrap_or(time)
+            + Duration::seconds((time.nanosecond() >= 500_000_000) as i64))
+    }
+    /// Converts the value to a boolean. Use `to_condition()` to also accept
+    /// numbers.
     pub fn to_bool(&self) -> FormulaResult<bool> {
         match &self.inner {
+            Value::Blank => Ok(false),
             Value::Bool(b) => Ok(*b),
             Value::String(s) if s.eq_ignore_ascii_case("TRUE") => Ok(true),
             Value::String(s) if s.eq_ignore_ascii_case("FALSE") => Ok(false),
+            Value::Error(e) => Err((**e).clone()),
             _ => Err(FormulaErrorMsg::Expected {
                 expected: "boolean".into(),
                 got: Some(self.inner.type_name().into()),
@@ -121,20 +348,51 @@ impl Spanned<Value> {
         }
     }
 
+    /// Converts a condition, such as the first argument to `IF()`, to a
+    /// boolean. Unlike `to_bool()`, numbers are accepted and are `TRUE`
+    /// unless they are zero.
+    pub fn to_condition(&self) -> FormulaResult<bool> {
+        match &self.inner {
+            Value::Number(n) => Ok(*n != 0.0),
+            _ => self.to_bool(),
+        }
+    }
+
+    /// Returns the text representation of the value, or the error if this is
+    /// an error value.
+    pub fn to_text(&self) -> FormulaResult<String> {
+        match &self.inner {
+            Value::Error(e) => Err((**e).clone()),
+            Value::Lambda(_) => Err(FormulaErrorMsg::Expected {
+                expected: "text".into(),
+                got: Some(self.inner.type_name().into()),
+            }
+            .with_span(self.span)),
+            other => Ok(other.to_string()),
+        }
+    }
+
     pub fn to_numbers(&self) -> FormulaResult<SmallVec<[f64; 1]>> {
         self.to_flat_array_of(Self::to_number)
     }
     pub fn to_bools(&self) -> FormulaResult<SmallVec<[bool; 1]>> {
         self.to_flat_array_of(Self::to_bool)
     }
+    pub fn to_conditions(&self) -> FormulaResult<SmallVec<[bool; 1]>> {
+        self.to_flat_array_of(Self::to_condition)
+    }
+    pub fn to_dates(&self) -> FormulaResult<SmallVec<[NaiveDate; 1]>> {
+        self.to_flat_array_of(Self::to_date)
+    }
     pub fn to_strings(&self) -> FormulaResult<SmallVec<[String; 1]>> {
-        self.to_flat_array_of(|x| Ok(x.to_string()))
+        self.to_flat_array_of(Self::to_text)
     }
     fn to_flat_array_of<T>(
         &self,
         conv: fn(&Self) -> FormulaResult<T>,
     ) -> FormulaResult<SmallVec<[T; 1]>> {
         match &self.inner {
+            Value::Blank => Ok(smallvec![]),
             Value::String(s) if s.is_empty() => Ok(smallvec![]),
 
             Value::Array(a) => a
@@ -148,11 +406,32 @@ impl Spanned<Value> {
                 })
                 .collect(),
 
-            Value::String(_) | Value::Number(_) | Value::Bool(_) => {
-                conv(self).map(|x| smallvec![x])
-            }
+            Value::String(_)
+            | Value::Number(_)
+            | Value::Bool(_)
+            | Value::Lambda(_)
+            | Value::Date(_)
+            | Value::DateTime(_)
+            | Value::Duration(_) => conv(self).map(|x| smallvec![x]),
+
+            Value::Error(e) => Err((**e).clone()),
+        }
+    }
 
-            Value::MissingErr => Ok(smallvec![]),
+    /// Returns the value itself if it is not an array, or the only element of
+    /// an array with one element. Returns an error for larger arrays.
+    pub fn into_single_value(self) -> FormulaResult<Self> {
+        match self.inner {
+            Value::Array(a) if a.len() == 1 && a[0].len() == 1 => Ok(Spanned {
+                span: self.span,
+                inner: a.into_iter().flatten().next().unwrap_or_default(),
+            }),
+            Value::Array(_) => Err(FormulaErrorMsg::Expected {
+                expected: "single value".into(),
+                got: Some("array".into()),
+            }
+            .with_span(self.span)),
+            _ => Ok(self),
         }
     }
 
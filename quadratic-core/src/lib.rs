@@ -3,18 +3,27 @@
 use async_trait::async_trait;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cell::{Cell as StdCell, RefCell};
+use std::collections::{HashMap, HashSet};
 use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
 
 #[macro_use]
 pub mod util;
 mod cell;
+mod dependencies;
 pub mod formulas;
 mod position;
 
 pub use cell::{Cell, CellTypes, JsCell};
-use formulas::{GridProxy, Value};
-pub use position::Pos;
+pub use dependencies::{
+    DependencyGraph, IterationOutcome, IterativeCalculation, RecalculationOrder,
+};
+use formulas::{
+    CellRefNotation, Ctx, ErrorKind, FormulaErrorMsg, GridProxy, NameDefinition, NameRegistry,
+    Value,
+};
+pub use position::{Pos, Rect};
 
 pub const QUADRANT_SIZE: u64 = 16;
 
@@ -32,44 +41,187 @@ pub fn hello() {
     log("[WASM/Rust] quadratic-core ready")
 }
 
+/// Position of a cell in the workbook, where `sheet` is the index of the
+/// sheet's name in `SHEET_NAMES`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+struct SheetPos {
+    sheet: usize,
+    pos: Pos,
+}
+impl SheetPos {
+    /// Returns the position of a cell on a sheet, or `None` if there is no
+    /// sheet with that name.
+    fn new(sheet_name: Option<&str>, pos: Pos) -> Option<Self> {
+        Some(Self {
+            sheet: sheet_index(sheet_name)?,
+            pos,
+        })
+    }
+}
+
+/// Position of a cell as passed to and from JS: `[x, y]` for a cell on the
+/// default sheet, or `[x, y, sheetName]` for a cell on a named sheet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum JsCellPos {
+    Default(i64, i64),
+    Named(i64, i64, String),
+}
+impl TryFrom<JsCellPos> for SheetPos {
+    type Error = JsValue;
+
+    fn try_from(pos: JsCellPos) -> Result<Self, Self::Error> {
+        let (x, y, sheet_name) = match pos {
+            JsCellPos::Default(x, y) => (x, y, None),
+            JsCellPos::Named(x, y, sheet_name) => (x, y, Some(sheet_name)),
+        };
+        SheetPos::new(sheet_name.as_deref(), Pos { x, y })
+            .ok_or_else(|| unknown_sheet_error(sheet_name.as_deref().unwrap_or_default()))
+    }
+}
+impl From<SheetPos> for JsCellPos {
+    fn from(SheetPos { sheet, pos }: SheetPos) -> Self {
+        match sheet_name(sheet) {
+            None => JsCellPos::Default(pos.x, pos.y),
+            Some(sheet_name) => JsCellPos::Named(pos.x, pos.y, sheet_name),
+        }
+    }
+}
+
+/// Returns the index of a sheet in `SHEET_NAMES`, or `None` if there is no
+/// sheet with that name. `None` is the default sheet, which has index 0.
+/// Sheet names are case-insensitive.
+fn sheet_index(sheet_name: Option<&str>) -> Option<usize> {
+    match sheet_name {
+        None => Some(0),
+        Some(sheet_name) => SHEET_NAMES.with(|names| find_sheet(&names.borrow(), sheet_name)),
+    }
+}
+/// Returns the index of a named sheet in `names`, ignoring case.
+fn find_sheet(names: &[String], sheet_name: &str) -> Option<usize> {
+    (1..names.len()).find(|&i| names[i].eq_ignore_ascii_case(sheet_name))
+}
+/// Returns the name of the sheet with index `sheet` in `SHEET_NAMES`, or
+/// `None` for the default sheet.
+fn sheet_name(sheet: usize) -> Option<String> {
+    match sheet {
+        0 => None,
+        _ => SHEET_NAMES.with(|names| names.borrow().get(sheet).cloned()),
+    }
+}
+fn unknown_sheet_error(sheet_name: &str) -> JsValue {
+    JsValue::from_str(&format!("there is no sheet named {sheet_name:?}"))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct JsFormulaResult {
-    pub cells_accessed: Vec<[i64; 2]>,
+    pub cells_accessed: Vec<JsCellPos>,
     pub success: bool,
     pub error_span: Option<[usize; 2]>,
     pub error_msg: Option<String>,
+    pub error_code: Option<String>,
+    /// Cells in the circular reference that this formula is part of, starting
+    /// with the formula's own cell.
+    pub circular_reference: Option<Vec<JsCellPos>>,
+    /// Whether the formula calls a function such as `NOW()`, so it must be
+    /// recalculated whenever any cell changes.
+    pub volatile: bool,
     pub output_value: Option<String>,
     pub array_output: Option<Vec<Vec<String>>>,
+    /// `[width, height]` of `array_output`, so that the host can check
+    /// whether the array would spill into occupied cells.
+    pub array_size: Option<[usize; 2]>,
 }
 
+/// Evaluates a formula at `(x, y)` on the sheet named `sheet_name`, or on the
+/// default sheet if `sheet_name` is `undefined`.
+///
+/// `grid_accessor_fn(x0, y0, x1, y1, sheetName)` should return a promise of the
+/// non-empty cells in a rectangle, and `grid_bounds_fn(sheetName)` should
+/// return a promise of the bounds of a sheet. They are only called for sheets
+/// that have been added with `add_sheet()`; cells on any other sheet are
+/// `#REF!` errors.
 #[wasm_bindgen]
 pub async fn eval_formula(
     formula_string: &str,
     x: f64,
     y: f64,
     grid_accessor_fn: js_sys::Function,
-) -> JsValue {
-    let mut grid_proxy = JsGridProxy::new(grid_accessor_fn);
+    grid_bounds_fn: js_sys::Function,
+    sheet_name: Option<String>,
+) -> Result<JsValue, JsValue> {
     let x = x as i64;
     let y = y as i64;
     let pos = Pos { x, y };
+    let cell = SheetPos::new(sheet_name.as_deref(), pos)
+        .ok_or_else(|| unknown_sheet_error(sheet_name.as_deref().unwrap_or_default()))?;
+    let mut grid_proxy =
+        JsGridProxy::new(grid_accessor_fn, grid_bounds_fn, sheet_name.clone(), pos);
 
-    let formula_result = match formulas::parse_formula(formula_string, pos) {
-        Ok(formula) => formula.eval(&mut grid_proxy, pos).await,
-        Err(e) => Err(e),
-    };
-    let cells_accessed = grid_proxy
+    let iterative = ITERATIVE_CALCULATION.with(|it| it.get());
+    let notation = REFERENCE_NOTATION.with(|n| n.get());
+    // Clone the names so that they can't change while the formula is waiting
+    // on JS.
+    let names = NAME_REGISTRY.with(|names| names.borrow().clone());
+
+    let (formula_result, volatile) =
+        match formulas::parse_formula_with_notation(formula_string, pos, notation) {
+            Ok(formula) => {
+                let mut ctx = Ctx::new(&mut grid_proxy, pos);
+                ctx.sheet = sheet_name;
+                ctx.allow_self_reference = iterative.is_some();
+                ctx.names = Some(&names);
+                let result = formula.eval_in_ctx(&mut ctx).await;
+                (result, ctx.volatile)
+            }
+            Err(e) => (Err(e), false),
+        };
+    VOLATILE_CELLS.with(|cells| {
+        let mut cells = cells.borrow_mut();
+        if volatile {
+            cells.insert(cell);
+        } else {
+            cells.remove(&cell);
+        }
+    });
+    let mut cells_accessed: HashSet<SheetPos> = grid_proxy
         .cells_accessed
         .into_iter()
-        .map(|pos| [pos.x, pos.y])
+        .filter_map(|(sheet_name, pos)| SheetPos::new(sheet_name.as_deref(), pos))
+        .collect();
+    if matches!(&formula_result, Err(e) if e.msg == FormulaErrorMsg::CircularReference) {
+        // The formula refused to read its own cell.
+        cells_accessed.insert(cell);
+    }
+
+    // Look for a circular reference through other formulas, including ones
+    // on other sheets.
+    let circular_reference = DEPENDENCY_GRAPH.with(|graph| {
+        let mut graph = graph.borrow_mut();
+        graph.set_precedents(cell, cells_accessed.iter().copied());
+        graph.cycle_through(cell)
+    });
+    let formula_result = match &circular_reference {
+        Some(_) if iterative.is_none() => Err(FormulaErrorMsg::CircularReference.without_span()),
+        _ => formula_result,
+    };
+    let circular_reference =
+        circular_reference.map(|cycle| cycle.into_iter().map(JsCellPos::from).collect_vec());
+
+    let cells_accessed = cells_accessed
+        .into_iter()
+        .sorted()
+        .map(JsCellPos::from)
         .collect_vec();
 
     let result = match formula_result {
         Ok(formula_output) => {
             let mut output_value = None;
             let mut array_output = None;
+            let mut array_size = None;
             match formula_output.inner {
                 Value::Array(a) => {
+                    array_size = Some([a.first().map_or(0, |row| row.len()), a.len()]);
                     array_output = Some(
                         a.iter()
                             .map(|row| row.iter().map(|cell| cell.to_string()).collect())
@@ -83,8 +235,12 @@ pub async fn eval_formula(
                 success: true,
                 error_span: None,
                 error_msg: None,
+                error_code: None,
+                circular_reference,
+                volatile,
                 output_value,
                 array_output,
+                array_size,
             }
         }
         Err(error) => JsFormulaResult {
@@ -92,44 +248,640 @@ pub async fn eval_formula(
             success: false,
             error_span: error.span.map(|span| [span.start, span.end]),
             error_msg: Some(error.msg.to_string()),
+            error_code: Some(error.msg.kind().code().to_string()),
+            circular_reference,
+            volatile,
             output_value: None,
             array_output: None,
+            array_size: None,
         },
     };
 
-    serde_wasm_bindgen::to_value(&result).unwrap()
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+thread_local! {
+    /// Dependencies between cells in the workbook, maintained across calls
+    /// from JS.
+    static DEPENDENCY_GRAPH: RefCell<DependencyGraph<SheetPos>> = RefCell::default();
+
+    /// Cells whose formulas call a function such as `NOW()`, which are
+    /// recalculated whenever any cell changes.
+    static VOLATILE_CELLS: RefCell<HashSet<SheetPos>> = RefCell::default();
+
+    /// Names of the sheets that have been added with `add_sheet()`, indexed
+    /// by `SheetPos::sheet`. Index 0 is the default sheet, which has no name.
+    static SHEET_NAMES: RefCell<Vec<String>> = RefCell::new(vec![String::new()]);
+
+    /// Settings for calculating circular references iteratively, or `None` if
+    /// circular references are errors.
+    static ITERATIVE_CALCULATION: StdCell<Option<IterativeCalculation>> = const { StdCell::new(None) };
+
+    /// Notation used for cell references in formulas.
+    static REFERENCE_NOTATION: StdCell<CellRefNotation> = const { StdCell::new(CellRefNotation::A1) };
+
+    /// Workbook-level names that can be used in formulas.
+    static NAME_REGISTRY: RefCell<NameRegistry> = RefCell::default();
+}
+
+fn notation_from_r1c1(r1c1: bool) -> CellRefNotation {
+    if r1c1 {
+        CellRefNotation::R1C1
+    } else {
+        CellRefNotation::A1
+    }
+}
+
+/// Sets whether formulas use R1C1-style cell references instead of A1-style
+/// ones.
+#[wasm_bindgen]
+pub fn set_r1c1_notation(r1c1: bool) {
+    REFERENCE_NOTATION.with(|n| n.set(notation_from_r1c1(r1c1)));
+}
+
+/// Converts the cell references in a formula at `(x, y)` between A1 and R1C1
+/// notation. If `to_r1c1` is true, converts from A1 to R1C1; otherwise
+/// converts from R1C1 to A1.
+#[wasm_bindgen]
+pub fn convert_formula_notation(formula_string: &str, x: f64, y: f64, to_r1c1: bool) -> String {
+    let pos = Pos::new(x as i64, y as i64);
+    let to = notation_from_r1c1(to_r1c1);
+    let from = notation_from_r1c1(!to_r1c1);
+    formulas::convert_notation(formula_string, pos, from, to)
+}
+
+/// Translates the relative references in a formula that is copied from
+/// `(from_x, from_y)` to `(to_x, to_y)`. References that would go outside the
+/// range of valid coordinates become `#REF!`.
+#[wasm_bindgen]
+pub fn translate_formula(
+    formula_string: &str,
+    from_x: f64,
+    from_y: f64,
+    to_x: f64,
+    to_y: f64,
+) -> String {
+    let from = Pos::new(from_x as i64, from_y as i64);
+    let to = Pos::new(to_x as i64, to_y as i64);
+    let notation = REFERENCE_NOTATION.with(|n| n.get());
+    formulas::translate_formula(formula_string, from, to, notation)
+}
+
+/// Returns the source code of the formula at `(x, y)` on the sheet
+/// `sheet_name` after a structural edit (see `GridEdit`) to the sheet
+/// `edited_sheet_name`, or `undefined` if the formula's cell was deleted.
+/// `undefined` sheet names refer to the default sheet. References to deleted
+/// cells become `#REF!`.
+#[wasm_bindgen]
+pub fn formula_source_after_edit(
+    formula_string: &str,
+    x: f64,
+    y: f64,
+    edit: JsValue,
+    sheet_name: Option<String>,
+    edited_sheet_name: Option<String>,
+) -> Result<Option<String>, JsValue> {
+    let pos = Pos::new(x as i64, y as i64);
+    let edit: formulas::GridEdit = serde_wasm_bindgen::from_value(edit)?;
+    let notation = REFERENCE_NOTATION.with(|n| n.get());
+    let formula = formulas::parse_formula_with_notation(formula_string, pos, notation)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(formula.source_after_edit(
+        pos,
+        sheet_name.as_deref(),
+        edit,
+        edited_sheet_name.as_deref(),
+    ))
+}
+
+/// Defines a workbook-level name that refers to a cell, a range, or a
+/// constant, such as `$B$2`, `C2:C500`, or `0.07`.
+#[wasm_bindgen]
+pub fn define_name(name: &str, definition: &str) -> Result<(), JsValue> {
+    let notation = REFERENCE_NOTATION.with(|n| n.get());
+    let definition = NameDefinition::parse(definition, notation)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    NAME_REGISTRY
+        .with(|names| names.borrow_mut().define(name, definition))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Removes a workbook-level name.
+#[wasm_bindgen]
+pub fn remove_name(name: &str) {
+    NAME_REGISTRY.with(|names| names.borrow_mut().remove(name));
+}
+
+/// Renames a workbook-level name. Use `rename_name_in_formula()` to update
+/// each formula that uses it.
+#[wasm_bindgen]
+pub fn rename_name(old_name: &str, new_name: &str) -> Result<(), JsValue> {
+    NAME_REGISTRY
+        .with(|names| names.borrow_mut().rename(old_name, new_name))
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Replaces every use of a name in a formula with a new name.
+#[wasm_bindgen]
+pub fn rename_name_in_formula(formula_string: &str, old_name: &str, new_name: &str) -> String {
+    let notation = REFERENCE_NOTATION.with(|n| n.get());
+    formulas::rename_name(formula_string, notation, old_name, new_name)
+}
+
+/// Adds a named sheet, so that formulas can refer to it.
+#[wasm_bindgen]
+pub fn add_sheet(name: &str) -> Result<(), JsValue> {
+    SHEET_NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        if find_sheet(&names, name).is_some() {
+            return Err(JsValue::from_str("sheet name is already in use"));
+        }
+        names.push(name.to_string());
+        Ok(())
+    })
+}
+
+/// Renames a sheet. Use `rename_sheet_in_formula()` to update each formula
+/// that refers to it.
+#[wasm_bindgen]
+pub fn rename_sheet(old_name: &str, new_name: &str) -> Result<(), JsValue> {
+    SHEET_NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        if !old_name.eq_ignore_ascii_case(new_name) && find_sheet(&names, new_name).is_some() {
+            return Err(JsValue::from_str("sheet name is already in use"));
+        }
+        let i = find_sheet(&names, old_name).ok_or_else(|| unknown_sheet_error(old_name))?;
+        names[i] = new_name.to_string();
+        Ok(())
+    })
+}
+
+/// Replaces the sheet name in every reference to a sheet in a formula with
+/// a new name.
+#[wasm_bindgen]
+pub fn rename_sheet_in_formula(formula_string: &str, old_name: &str, new_name: &str) -> String {
+    let notation = REFERENCE_NOTATION.with(|n| n.get());
+    formulas::rename_sheet(formula_string, notation, old_name, new_name)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct JsRecalculationOrder {
+    pub cells: Vec<JsCellPos>,
+    pub blocked_cells: Vec<JsCellPos>,
+    pub circular_references: Vec<Vec<JsCellPos>>,
+}
+impl From<RecalculationOrder<SheetPos>> for JsRecalculationOrder {
+    fn from(order: RecalculationOrder<SheetPos>) -> Self {
+        let to_js = |cells: Vec<SheetPos>| cells.into_iter().map(JsCellPos::from).collect_vec();
+        Self {
+            cells: to_js(order.cells),
+            blocked_cells: to_js(order.blocked_cells),
+            circular_references: order.circular_references.into_iter().map(to_js).collect(),
+        }
+    }
+}
+
+/// Records which cells the cell at `(x, y)` on the sheet `sheet_name` reads,
+/// replacing any cells it read before. `cells_accessed` is an array of
+/// `[x, y]` or `[x, y, sheetName]` arrays. `volatile` is whether the cell must
+/// be recalculated whenever any cell changes, such as when its formula calls
+/// `NOW()`.
+///
+/// `eval_formula()` already does this for the formula it evaluates, so this
+/// is for other code and for cells that are loaded from a file.
+#[wasm_bindgen]
+pub fn set_cell_dependencies(
+    x: f64,
+    y: f64,
+    cells_accessed: JsValue,
+    volatile: bool,
+    sheet_name: Option<String>,
+) -> Result<(), JsValue> {
+    let cells_accessed: Vec<JsCellPos> = serde_wasm_bindgen::from_value(cells_accessed)?;
+    let cell = SheetPos::new(sheet_name.as_deref(), Pos::new(x as i64, y as i64))
+        .ok_or_else(|| unknown_sheet_error(sheet_name.as_deref().unwrap_or_default()))?;
+    let precedents: Vec<SheetPos> = cells_accessed
+        .into_iter()
+        .map(SheetPos::try_from)
+        .try_collect()?;
+    DEPENDENCY_GRAPH.with(|graph| graph.borrow_mut().set_precedents(cell, precedents));
+    VOLATILE_CELLS.with(|cells| {
+        let mut cells = cells.borrow_mut();
+        if volatile {
+            cells.insert(cell);
+        } else {
+            cells.remove(&cell);
+        }
+    });
+    Ok(())
+}
+
+/// Forgets which cells the cell at `(x, y)` on the sheet `sheet_name` reads,
+/// such as when it is deleted.
+#[wasm_bindgen]
+pub fn remove_cell_dependencies(x: f64, y: f64, sheet_name: Option<String>) {
+    let Some(cell) = SheetPos::new(sheet_name.as_deref(), Pos::new(x as i64, y as i64)) else {
+        return;
+    };
+    DEPENDENCY_GRAPH.with(|graph| graph.borrow_mut().remove_precedents(cell));
+    VOLATILE_CELLS.with(|cells| cells.borrow_mut().remove(&cell));
+}
+
+/// Forgets all dependencies between cells, such as when a new sheet is loaded.
+#[wasm_bindgen]
+pub fn clear_cell_dependencies() {
+    DEPENDENCY_GRAPH.with(|graph| graph.borrow_mut().clear());
+    VOLATILE_CELLS.with(|cells| cells.borrow_mut().clear());
+}
+
+/// Returns every cell downstream of the `changed_cells` (an array of `[x, y]`
+/// or `[x, y, sheetName]` arrays) in the order they should be recalculated,
+/// along with any circular references that prevent recalculating some of
+/// them. If `include_volatile` is true, volatile cells, such as ones that call
+/// `NOW()`, and the cells downstream of them are included too.
+#[wasm_bindgen]
+pub fn get_cells_to_recalculate(
+    changed_cells: JsValue,
+    include_volatile: bool,
+) -> Result<JsValue, JsValue> {
+    let changed_cells: Vec<JsCellPos> = serde_wasm_bindgen::from_value(changed_cells)?;
+    let volatile_cells = match include_volatile {
+        true => VOLATILE_CELLS.with(|cells| cells.borrow().iter().copied().sorted().collect_vec()),
+        false => vec![],
+    };
+    let changed_cells = changed_cells
+        .into_iter()
+        // Nothing can depend on a cell on a sheet that doesn't exist.
+        .filter_map(|pos| SheetPos::try_from(pos).ok())
+        .chain(volatile_cells.iter().copied())
+        .collect_vec();
+    let mut order =
+        DEPENDENCY_GRAPH.with(|graph| graph.borrow().cells_to_recalculate(&changed_cells));
+    // Volatile cells that don't read any other changed cell aren't included
+    // yet, and they can go first because nothing they read is changing.
+    let missing_volatile_cells = volatile_cells
+        .into_iter()
+        .filter(|cell| !order.cells.contains(cell) && !order.blocked_cells.contains(cell))
+        .collect_vec();
+    order.cells.splice(0..0, missing_volatile_cells);
+    Ok(serde_wasm_bindgen::to_value(&JsRecalculationOrder::from(
+        order,
+    ))?)
+}
+
+/// Enables or disables iterative calculation of circular references. When
+/// enabled, a formula that reads its own cell gets the cell's previous value
+/// instead of a circular reference error.
+#[wasm_bindgen]
+pub fn set_iterative_calculation(enabled: bool, max_iterations: u32, epsilon: f64) {
+    let settings = enabled.then_some(IterativeCalculation {
+        max_iterations,
+        epsilon,
+    });
+    ITERATIVE_CALCULATION.with(|it| it.set(settings));
+}
+
+/// Returns whether circular references are calculated iteratively, using
+/// `calculate_iteratively()`, instead of being errors.
+#[wasm_bindgen]
+pub fn is_iterative_calculation_enabled() -> bool {
+    ITERATIVE_CALCULATION.with(|it| it.get()).is_some()
+}
+
+/// Recalculates the cells in a circular reference (an array of `[x, y]` or
+/// `[x, y, sheetName]` arrays) until their values converge.
+/// `eval_cell_fn(x, y, sheetName)` should recalculate a single cell and return
+/// a promise of its new numeric value.
+///
+/// Returns the number of iterations and whether the values converged.
+#[wasm_bindgen]
+pub async fn calculate_iteratively(
+    cells: JsValue,
+    eval_cell_fn: js_sys::Function,
+) -> Result<JsValue, JsValue> {
+    let cells: Vec<JsCellPos> = serde_wasm_bindgen::from_value(cells)?;
+    let settings = ITERATIVE_CALCULATION
+        .with(|it| it.get())
+        .ok_or_else(|| JsValue::from_str("iterative calculation is disabled"))?;
+
+    let eval_cell_fn = &eval_cell_fn;
+    let cells = &cells;
+    let outcome = settings
+        .run(|| async move {
+            let mut values = vec![];
+            for cell in cells {
+                let js_this = JsValue::UNDEFINED;
+                let (x, y, sheet_name) = match cell {
+                    JsCellPos::Default(x, y) => (*x, *y, JsValue::UNDEFINED),
+                    JsCellPos::Named(x, y, sheet_name) => (*x, *y, sheet_name.into()),
+                };
+                let value = match eval_cell_fn.call3(&js_this, &x.into(), &y.into(), &sheet_name) {
+                    Ok(promise) => {
+                        wasm_bindgen_futures::JsFuture::from(js_sys::Promise::from(promise))
+                            .await
+                            .ok()
+                            .and_then(|v| v.as_f64())
+                    }
+                    Err(_) => None,
+                };
+                values.push(value.unwrap_or(0.0));
+            }
+            values
+        })
+        .await;
+    Ok(serde_wasm_bindgen::to_value(&outcome)?)
 }
 
 #[derive(Debug, Clone)]
 struct JsGridProxy {
     grid_accessor_fn: js_sys::Function,
-    cells_accessed: HashSet<Pos>,
+    grid_bounds_fn: js_sys::Function,
+    /// Name of the sheet containing the formula, or `None` for the default
+    /// sheet.
+    sheet_name: Option<String>,
+    /// Position of the cell containing the formula.
+    pos: Pos,
+    /// Cells that were accessed, along with the names of their sheets.
+    cells_accessed: HashSet<(Option<String>, Pos)>,
 }
 impl JsGridProxy {
-    fn new(grid_accessor_fn: js_sys::Function) -> Self {
+    fn new(
+        grid_accessor_fn: js_sys::Function,
+        grid_bounds_fn: js_sys::Function,
+        sheet_name: Option<String>,
+        pos: Pos,
+    ) -> Self {
         Self {
             grid_accessor_fn,
+            grid_bounds_fn,
+            sheet_name,
+            pos,
             cells_accessed: HashSet::new(),
         }
     }
 }
+
+/// Contents of a cell returned by the grid accessor function.
+#[derive(Debug, Clone)]
+struct JsGridCell {
+    value: Cell,
+    /// Whether the cell contains a formula, as opposed to other code or a
+    /// plain value.
+    is_formula: bool,
+    /// Cells that the array returned by this cell's code spilled into,
+    /// including this cell. This is empty if the cell doesn't contain an
+    /// array.
+    array_cells: Vec<Pos>,
+}
+/// Converts a cell value from the grid accessor function to a typed cell.
+/// Dates are converted to local time. JavaScript has no date-only type, so
+/// dates always become `Cell::DateTime`, never `Cell::Date`.
+fn js_cell_value(value: &JsValue) -> Cell {
+    if let Some(b) = value.as_bool() {
+        Cell::Bool(b)
+    } else if let Some(n) = value.as_f64() {
+        Cell::Number(n)
+    } else if let Some(s) = value.as_string() {
+        Cell::Text(s)
+    } else if let Some(date) = value.dyn_ref::<js_sys::Date>() {
+        let millis = date.get_time() - date.get_timezone_offset() * 60_000.0;
+        chrono::DateTime::from_timestamp_millis(millis as i64)
+            .map_or(Cell::Empty, |dt| Cell::DateTime(dt.naive_utc()))
+    } else {
+        Cell::Empty
+    }
+}
+
+impl JsGridProxy {
+    /// Returns the name of a sheet referenced by a formula, where `None` is
+    /// the sheet containing the formula.
+    fn resolve_sheet(&self, sheet: Option<&str>) -> Option<String> {
+        sheet
+            .map(str::to_string)
+            .or_else(|| self.sheet_name.clone())
+    }
+
+    /// Fetches all the non-empty cells in `rect` on a sheet with a single
+    /// call to the grid accessor function, returning their positions and
+    /// contents. Returns `None` if there is no sheet with that name.
+    async fn get_cells(
+        &mut self,
+        sheet_name: Option<&str>,
+        rect: Rect,
+    ) -> Option<HashMap<Pos, JsGridCell>> {
+        sheet_index(sheet_name)?;
+        let js_this = JsValue::UNDEFINED;
+        let sheet_name = sheet_name.map_or(JsValue::UNDEFINED, JsValue::from_str);
+        let cells_array = self
+            .grid_accessor_fn
+            .bind2(&js_this, &rect.min.x.into(), &rect.min.y.into()) // Upper-left corner
+            .call3(
+                &js_this,
+                &rect.max.x.into(),
+                &rect.max.y.into(),
+                &sheet_name,
+            ) // Lower-right corner
+            .map(js_sys::Promise::from)
+            .map(wasm_bindgen_futures::JsFuture::from)
+            .ok()?
+            .await
+            .ok()?;
+
+        let get_field = |cell: &JsValue, field: &str| js_sys::Reflect::get(cell, &field.into());
+        let mut cells = HashMap::new();
+        for cell in js_sys::Array::from(&cells_array).iter() {
+            let (Ok(x), Ok(y)) = (get_field(&cell, "x"), get_field(&cell, "y")) else {
+                continue;
+            };
+            // Formulas that failed have an empty value, so use the error
+            // code from their evaluation result instead.
+            let error_code = get_field(&cell, "evaluation_result")
+                .and_then(|result| get_field(&result, "error_code"))
+                .ok()
+                .and_then(|code| code.as_string());
+            let value = match error_code.as_deref().and_then(ErrorKind::from_code) {
+                Some(kind) => Cell::Error(kind),
+                None => get_field(&cell, "value").map_or(Cell::Empty, |v| js_cell_value(&v)),
+            };
+            let array_cells: Vec<(i64, i64)> = get_field(&cell, "array_cells")
+                .ok()
+                .and_then(|array_cells| serde_wasm_bindgen::from_value(array_cells).ok())
+                .unwrap_or_default();
+            let is_formula = get_field(&cell, "type")
+                .ok()
+                .and_then(|cell_type| cell_type.as_string())
+                .is_some_and(|cell_type| cell_type == "FORMULA");
+            if let (Some(x), Some(y)) = (x.as_f64(), y.as_f64()) {
+                let array_cells = array_cells
+                    .into_iter()
+                    .map(|(x, y)| Pos::new(x, y))
+                    .collect();
+                let cell = JsGridCell {
+                    value,
+                    is_formula,
+                    array_cells,
+                };
+                cells.insert(Pos::new(x as i64, y as i64), cell);
+            }
+        }
+        Some(cells)
+    }
+}
 #[async_trait(?Send)]
 impl GridProxy for JsGridProxy {
-    async fn get(&mut self, pos: Pos) -> Option<String> {
-        let js_this = JsValue::UNDEFINED;
+    async fn get(&mut self, sheet: Option<&str>, pos: Pos) -> Cell {
+        let sheet_name = self.resolve_sheet(sheet);
+        self.cells_accessed.insert((sheet_name.clone(), pos));
+        if sheet_index(sheet_name.as_deref()).is_none() {
+            return Cell::Error(ErrorKind::Ref);
+        }
+        self.get_cells(sheet_name.as_deref(), Rect::single_pos(pos))
+            .await
+            .and_then(|mut cells| cells.remove(&pos))
+            .map(|cell| cell.value)
+            .unwrap_or_default()
+    }
 
-        self.cells_accessed.insert(pos);
-        let cell_value_array = self
-            .grid_accessor_fn
-            .bind2(&js_this, &pos.x.into(), &pos.y.into()) // Upper-left corner
-            .call2(&js_this, &pos.x.into(), &pos.y.into()) // Lower-right corner
+    async fn get_range(&mut self, sheet: Option<&str>, rect: Rect) -> Vec<Vec<Cell>> {
+        let sheet_name = self.resolve_sheet(sheet);
+        self.cells_accessed.extend(rect.y_range().flat_map(|y| {
+            let sheet_name = &sheet_name;
+            rect.x_range()
+                .map(move |x| (sheet_name.clone(), Pos { x, y }))
+        }));
+        if sheet_index(sheet_name.as_deref()).is_none() {
+            let row = vec![Cell::Error(ErrorKind::Ref); rect.width() as usize];
+            return vec![row; rect.height() as usize];
+        }
+        let mut cells = self
+            .get_cells(sheet_name.as_deref(), rect)
+            .await
+            .unwrap_or_default();
+        rect.y_range()
+            .map(|y| {
+                rect.x_range()
+                    .map(|x| {
+                        cells
+                            .remove(&Pos { x, y })
+                            .map(|cell| cell.value)
+                            .unwrap_or_default()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    async fn bounds(&mut self, sheet: Option<&str>) -> Option<Rect> {
+        let sheet_name = self.resolve_sheet(sheet);
+        sheet_index(sheet_name.as_deref())?;
+        let sheet_name = sheet_name.map_or(JsValue::UNDEFINED, |name| JsValue::from_str(&name));
+        let bounds = self
+            .grid_bounds_fn
+            .call1(&JsValue::UNDEFINED, &sheet_name)
             .map(js_sys::Promise::from)
             .map(wasm_bindgen_futures::JsFuture::from)
             .ok()?
             .await
             .ok()?;
-        let cell_value = js_sys::Reflect::get(&cell_value_array, &0.into()).ok()?;
-        let cell_string = js_sys::Reflect::get(&cell_value, &"value".into()).ok()?;
-        cell_string.as_string()
+        serde_wasm_bindgen::from_value(bounds).ok()?
+    }
+
+    async fn occupied_cells(&mut self, sheet: Option<&str>, rect: Rect) -> Vec<Pos> {
+        let sheet_name = self.resolve_sheet(sheet);
+        // Values spilled from this formula's previous result don't block it.
+        let own_spill = match sheet {
+            None => self
+                .get_cells(sheet_name.as_deref(), Rect::single_pos(self.pos))
+                .await
+                .and_then(|mut cells| cells.remove(&self.pos))
+                .map(|cell| cell.array_cells)
+                .unwrap_or_default(),
+            Some(_) => vec![],
+        };
+        let occupied = self
+            .get_cells(sheet_name.as_deref(), rect)
+            .await
+            .unwrap_or_default()
+            .into_keys()
+            .filter(|pos| !own_spill.contains(pos))
+            .collect_vec();
+        // Depend on the occupied cells so that the formula is recalculated
+        // when they are cleared.
+        self.cells_accessed
+            .extend(occupied.iter().map(|&pos| (sheet_name.clone(), pos)));
+        occupied
+    }
+
+    async fn is_formula(&mut self, sheet: Option<&str>, pos: Pos) -> bool {
+        let sheet_name = self.resolve_sheet(sheet);
+        self.cells_accessed.insert((sheet_name.clone(), pos));
+        self.get_cells(sheet_name.as_deref(), Rect::single_pos(pos))
+            .await
+            .and_then(|mut cells| cells.remove(&pos))
+            .is_some_and(|cell| cell.is_formula)
+    }
+
+    async fn spill_rect(&mut self, sheet: Option<&str>, pos: Pos) -> Option<Rect> {
+        let sheet_name = self.resolve_sheet(sheet);
+        self.cells_accessed.insert((sheet_name.clone(), pos));
+        let cell = self
+            .get_cells(sheet_name.as_deref(), Rect::single_pos(pos))
+            .await?
+            .remove(&pos)?;
+        if cell.array_cells.is_empty() {
+            return None;
+        }
+        Some(
+            cell.array_cells
+                .into_iter()
+                .fold(Rect::single_pos(pos), Rect::expand_to_include),
+        )
+    }
+}
+
+// These call into JS, so they only run on wasm (`wasm-pack test --node`).
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use super::*;
+
+    #[wasm_bindgen_test]
+    async fn test_js_grid_proxy_typed_values() {
+        let grid_accessor_fn = js_sys::Function::new_with_args(
+            "x0, y0, x1, y1, sheetName",
+            "return Promise.resolve([
+                { x: 0, y: 0, value: 2.5 },
+                { x: 1, y: 0, value: '2.5' },
+                { x: 2, y: 0, value: true },
+                { x: 3, y: 0, value: new Date(2024, 1, 29) },
+                { x: 4, y: 0, value: '', evaluation_result: { error_code: '#N/A' } },
+            ].filter((cell) => x0 <= cell.x && cell.x <= x1 && y0 <= cell.y && cell.y <= y1))",
+        );
+        let grid_bounds_fn = js_sys::Function::new_no_args("return Promise.resolve(undefined)");
+        let mut grid = JsGridProxy::new(grid_accessor_fn, grid_bounds_fn, None, Pos::new(0, 5));
+
+        assert_eq!(Cell::Number(2.5), grid.get(None, Pos::new(0, 0)).await);
+        assert_eq!(
+            Cell::Text("2.5".to_string()),
+            grid.get(None, Pos::new(1, 0)).await,
+        );
+        assert_eq!(Cell::Bool(true), grid.get(None, Pos::new(2, 0)).await);
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(
+            Cell::DateTime(date.and_hms_opt(0, 0, 0).unwrap()),
+            grid.get(None, Pos::new(3, 0)).await,
+        );
+        assert_eq!(
+            Cell::Error(ErrorKind::NotAvailable),
+            grid.get(None, Pos::new(4, 0)).await,
+        );
+        assert_eq!(Cell::Empty, grid.get(None, Pos::new(5, 0)).await);
+
+        // The number reaches formulas as a number.
+        let pos = Pos::new(0, 5);
+        let formula = formulas::parse_formula("IF(ISNUMBER(A0), A0 * 2, 'text')", pos).unwrap();
+        let result = formula.eval_in_ctx(&mut Ctx::new(&mut grid, pos)).await;
+        assert_eq!("5", result.unwrap().inner.to_string());
     }
 }
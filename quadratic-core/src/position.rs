@@ -30,6 +30,16 @@ impl Pos {
             self.y.div_euclid(crate::QUADRANT_SIZE as _),
         )
     }
+
+    /// Returns the A1-style name of the cell, such as `B3` or `An2`.
+    pub fn a1_string(self) -> String {
+        let col = crate::util::column_name(self.x);
+        if self.y < 0 {
+            format!("{col}n{}", -self.y)
+        } else {
+            format!("{col}{}", self.y)
+        }
+    }
 }
 
 impl fmt::Display for Pos {
@@ -37,3 +47,70 @@ impl fmt::Display for Pos {
         write!(f, "({}, {})", self.x, self.y)
     }
 }
+
+/// Rectangular region of cells, including both corners.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rect {
+    /// Upper-left corner.
+    pub min: Pos,
+    /// Lower-right corner.
+    pub max: Pos,
+}
+impl Rect {
+    /// Constructs a rectangle spanning two cells, which may be any two
+    /// opposite corners.
+    pub fn new_span(pos1: Pos, pos2: Pos) -> Self {
+        use std::cmp::{max, min};
+
+        Rect {
+            min: Pos {
+                x: min(pos1.x, pos2.x),
+                y: min(pos1.y, pos2.y),
+            },
+            max: Pos {
+                x: max(pos1.x, pos2.x),
+                y: max(pos1.y, pos2.y),
+            },
+        }
+    }
+    /// Constructs a rectangle containing a single cell.
+    pub fn single_pos(pos: Pos) -> Self {
+        Rect { min: pos, max: pos }
+    }
+
+    /// Returns the smallest rectangle containing this rectangle and a cell.
+    pub fn expand_to_include(self, pos: Pos) -> Self {
+        Rect {
+            min: Pos {
+                x: self.min.x.min(pos.x),
+                y: self.min.y.min(pos.y),
+            },
+            max: Pos {
+                x: self.max.x.max(pos.x),
+                y: self.max.y.max(pos.y),
+            },
+        }
+    }
+
+    /// Returns whether a cell is inside the rectangle.
+    pub fn contains(self, pos: Pos) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x) && (self.min.y..=self.max.y).contains(&pos.y)
+    }
+
+    /// Returns the number of columns in the rectangle.
+    pub fn width(self) -> u64 {
+        self.max.x.abs_diff(self.min.x) + 1
+    }
+    /// Returns the number of rows in the rectangle.
+    pub fn height(self) -> u64 {
+        self.max.y.abs_diff(self.min.y) + 1
+    }
+    /// Returns the range of columns in the rectangle.
+    pub fn x_range(self) -> std::ops::RangeInclusive<i64> {
+        self.min.x..=self.max.x
+    }
+    /// Returns the range of rows in the rectangle.
+    pub fn y_range(self) -> std::ops::RangeInclusive<i64> {
+        self.min.y..=self.max.y
+    }
+}
@@ -9,6 +9,7 @@ import { clearFormattingAction } from '../clearFormattingAction';
 import { clearBordersAction } from '../clearBordersAction';
 import { PixiApp } from '../../../gridGL/pixiApp/PixiApp';
 import { copyAsPNG } from '../../../gridGL/pixiApp/copyAsPNG';
+import { translate_formula } from 'quadratic-core';
 
 const CLIPBOARD_FORMAT_VERSION = 'quadratic/clipboard/json/1.1';
 
@@ -62,6 +63,11 @@ const pasteFromTextOrHtml = async (sheet_controller: SheetController, pasteToCel
                 ...cell, // take old cell
                 x: cell.x + x_offset, // transpose it to new location
                 y: cell.y + y_offset,
+                // shift relative references in formulas
+                formula_code:
+                  cell.formula_code !== undefined
+                    ? translate_formula(cell.formula_code, cell.x, cell.y, cell.x + x_offset, cell.y + y_offset)
+                    : undefined,
                 last_modified: new Date().toISOString(), // update last_modified
               });
 
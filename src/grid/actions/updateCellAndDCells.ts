@@ -1,9 +1,11 @@
+import { calculate_iteratively, get_cells_to_recalculate, is_iterative_calculation_enabled } from 'quadratic-core';
 import { Cell } from '../../schemas';
 import { PixiApp } from '../../gridGL/pixiApp/PixiApp';
 import { Coordinate } from '../../gridGL/types/size';
 import { SheetController } from '../controller/sheetController';
 import { runCellComputation } from '../computations/runCellComputation';
 import { ArrayOutput } from '../computations/types';
+import { isComputed } from '../sheet/cellDependencies';
 
 interface ArgsType {
   starting_cells: Cell[];
@@ -14,6 +16,14 @@ interface ArgsType {
   create_transaction?: boolean;
 }
 
+// order in which quadratic-core says cells should be recalculated
+interface RecalculationOrder {
+  cells: [number, number][];
+  // cells in or downstream of a circular reference, which can't be put in order
+  blocked_cells: [number, number][];
+  circular_references: [number, number][][];
+}
+
 export const updateCellAndDCells = async (args: ArgsType) => {
   const { starting_cells, sheetController, app, pyodide, delete_starting_cells, create_transaction } = args;
 
@@ -23,65 +33,18 @@ export const updateCellAndDCells = async (args: ArgsType) => {
   // keep track of cells that have been updated so we can update the quadrant cache
   const updatedCells: Coordinate[] = [];
 
-  // start with a plan to just update the current cells
-  let cells_to_update: [number, number][] = starting_cells.map((c) => [c.x, c.y]);
-
-  // update cells, starting with the current cell
-  while (cells_to_update.length > 0) {
-    // dedupe cells_to_update
-    let seen = Array<string>();
-    for (let i = 0; i < cells_to_update.length; null) {
-      let string_id = cells_to_update[i].join(',');
-      if (seen.includes(string_id)) {
-        cells_to_update.splice(i, 1);
-      } else {
-        i++;
-      }
-      seen.push(string_id);
-    }
-
-    // get next cell to update
-    const ref_current_cell = cells_to_update.shift();
-    if (ref_current_cell === undefined) break;
-
-    // get cell from db or starting_cell if it is the starting cell passed in to this function
-    let cell = sheetController.sheet.getCellCopy(ref_current_cell[0], ref_current_cell[1]);
-    let old_array_cells: Coordinate[] = [];
-
+  // updates a single cell and returns the array cells that it added or deleted
+  const updateCell = async (cell: Cell, delete_cell: boolean): Promise<[number, number][]> => {
     // keep track of previous array cells for this cell
-    old_array_cells =
-      cell?.array_cells?.map((cell) => {
+    let old_array_cells: Coordinate[] =
+      sheetController.sheet.getCellCopy(cell.x, cell.y)?.array_cells?.map((cell) => {
         return { x: cell[0], y: cell[1] };
       }) || [];
     old_array_cells.unshift(); // remove this cell
 
-    // ref_current_cell is in starting_cells
-    if (starting_cells.some((c) => c.x === ref_current_cell[0] && c.y === ref_current_cell[1])) {
-      // if the ref_cell_to_update is the starting_cell
-      // then we need to update the cell with data from the starting_cell
-
-      const passed_in_cell = starting_cells.find((c) => c.x === ref_current_cell[0] && c.y === ref_current_cell[1]);
-      if (passed_in_cell === undefined) continue;
-      cell = { ...passed_in_cell };
-    }
-
-    if (cell === undefined) continue;
-
-    // remove old deps from graph
-    if (cell.dependent_cells)
-      cell.dependent_cells.forEach((dcell) => {
-        sheetController.execute_statement({
-          type: 'REMOVE_CELL_DEPENDENCY',
-          data: {
-            position: dcell,
-            updates: ref_current_cell,
-          },
-        });
-      });
-
     // Compute cell value
     let array_cells_to_output: Cell[] = [];
-    if (delete_starting_cells === true && starting_cells.some((c) => c.x === cell?.x && c.y === cell?.y)) {
+    if (delete_cell) {
       // we are deleting one of the starting cells
       // with delete_starting_cells = true
       // delete cell
@@ -91,7 +54,7 @@ export const updateCellAndDCells = async (args: ArgsType) => {
       });
     } else {
       // We are evaluating a cell
-      if (cell.type === 'PYTHON' || cell.type === 'FORMULA' || cell.type === 'AI') {
+      if (isComputed(cell)) {
         // run cell and format results
         // let result = await runPython(cell.python_code || '', pyodide);
         let result = await runCellComputation(cell, pyodide);
@@ -105,20 +68,6 @@ export const updateCellAndDCells = async (args: ArgsType) => {
           cell.value = ''; // clear value if python code fails
         }
 
-        // add new cell deps to graph
-        if (result.cells_accessed.length) {
-          // add new deps to graph
-          result.cells_accessed.forEach((cell_accessed) => {
-            sheetController.execute_statement({
-              type: 'ADD_CELL_DEPENDENCY',
-              data: {
-                position: cell_accessed,
-                updates: ref_current_cell,
-              },
-            });
-          });
-        }
-
         // if array output
         if (result.array_output !== undefined && result.array_output.length > 0) {
           if (Array.isArray(result.array_output[0])) {
@@ -126,13 +75,13 @@ export const updateCellAndDCells = async (args: ArgsType) => {
             let y_offset = 0;
             for (const row of result.array_output) {
               let x_offset = 0;
-              for (const cell of row as ArrayOutput) {
-                if (cell !== undefined)
+              for (const value of row as ArrayOutput) {
+                if (value !== undefined)
                   array_cells_to_output.push({
-                    x: ref_current_cell[0] + x_offset,
-                    y: ref_current_cell[1] + y_offset,
+                    x: cell.x + x_offset,
+                    y: cell.y + y_offset,
                     type: 'COMPUTED',
-                    value: cell.toString(),
+                    value: value.toString(),
                     last_modified: new Date().toISOString(),
                   });
                 x_offset++;
@@ -142,12 +91,12 @@ export const updateCellAndDCells = async (args: ArgsType) => {
           } else {
             // 1d array
             let y_offset = 0;
-            for (const cell of result.array_output) {
+            for (const value of result.array_output) {
               array_cells_to_output.push({
-                x: ref_current_cell[0],
-                y: ref_current_cell[1] + y_offset,
+                x: cell.x,
+                y: cell.y + y_offset,
                 type: 'COMPUTED',
-                value: cell.toString(),
+                value: value.toString(),
                 last_modified: new Date().toISOString(),
               });
               y_offset++;
@@ -208,28 +157,62 @@ export const updateCellAndDCells = async (args: ArgsType) => {
 
     // delete old array cells
     array_cells_to_delete.forEach((aCell) => {
-      if (aCell.x === cell?.x && aCell.y === cell?.y) return; // don't delete the cell we just updated (it's in array_cells_to_output)
+      if (aCell.x === cell.x && aCell.y === cell.y) return; // don't delete the cell we just updated (it's in array_cells_to_output)
       sheetController.execute_statement({
         type: 'SET_CELL',
         data: { position: [aCell.x, aCell.y], value: undefined },
       });
     });
 
-    // if any updated cells have other cells depending on them, add to list to update
-    for (const array_cell of array_cells_to_output) {
-      let deps = sheetController.sheet.cell_dependency.getDependencies([array_cell.x, array_cell.y]);
-      if (deps) cells_to_update.push(...deps);
-    }
+    return [...array_cells_to_output, ...array_cells_to_delete]
+      .filter((aCell) => aCell.x !== cell.x || aCell.y !== cell.y)
+      .map((aCell) => [aCell.x, aCell.y]);
+  };
+
+  // update the starting cells first
+  let changed_cells: [number, number][] = [];
+  for (const starting_cell of starting_cells) {
+    const array_cells = await updateCell({ ...starting_cell }, delete_starting_cells === true);
+    changed_cells.push([starting_cell.x, starting_cell.y], ...array_cells);
+  }
 
-    // any deleted cells have other cells depending on them, add to list to update
-    for (const array_cell of array_cells_to_delete) {
-      let deps = sheetController.sheet.cell_dependency.getDependencies([array_cell.x, array_cell.y]);
-      if (deps) cells_to_update.push(...deps);
+  // then recalculate every cell downstream of a changed cell. Cells that depend on array cells that were added or
+  // deleted may not be downstream of anything that changed yet, so they are recalculated in the next round.
+  // Volatile cells, such as ones that call NOW(), are recalculated once whenever anything changes.
+  let include_volatile = true;
+  while (changed_cells.length > 0) {
+    const order = get_cells_to_recalculate(changed_cells, include_volatile) as RecalculationOrder;
+    include_volatile = false;
+    changed_cells = [];
+
+    for (const [x, y] of order.cells) {
+      const cell = sheetController.sheet.getCellCopy(x, y);
+      if (cell === undefined || !isComputed(cell)) continue;
+      changed_cells.push(...(await updateCell(cell, false)));
     }
 
-    // if this cell updates other cells add them to the list to update
-    let deps = sheetController.sheet.cell_dependency.getDependencies([cell.x, cell.y]);
-    if (deps) cells_to_update.push(...deps);
+    // cells in a circular reference can't be recalculated in order. With iterative calculation, recalculate each
+    // circular reference until its values converge. Otherwise recalculate each cell once to show the error. Either
+    // way, don't follow their array cells, which would never finish.
+    const iterated_cells = new Set<string>();
+    if (is_iterative_calculation_enabled()) {
+      for (const circular_reference of order.circular_references) {
+        await calculate_iteratively(circular_reference, async (x: number, y: number) => {
+          const cell = sheetController.sheet.getCellCopy(x, y);
+          if (cell === undefined || !isComputed(cell)) return undefined;
+          await updateCell(cell, false);
+          const value = parseFloat(sheetController.sheet.getCellCopy(x, y)?.value ?? '');
+          return isNaN(value) ? undefined : value;
+        });
+        circular_reference.forEach((position) => iterated_cells.add(position.join(',')));
+      }
+    }
+    for (const [x, y] of order.blocked_cells) {
+      if (iterated_cells.has([x, y].join(','))) continue;
+      const cell = sheetController.sheet.getCellCopy(x, y);
+      if (cell === undefined || !isComputed(cell)) continue;
+      await updateCell(cell, false);
+    }
   }
 
   // Officially end the transaction
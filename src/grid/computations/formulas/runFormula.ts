@@ -1,5 +1,5 @@
 import { eval_formula } from 'quadratic-core';
-import { GetCellsDB } from '../../sheet/Cells/GetCellsDB';
+import { GetCellBoundsDB, GetTypedCellsDB } from '../../sheet/Cells/GetCellsDB';
 import { Coordinate } from '../../../gridGL/types/size';
 
 export interface runFormulaReturnType {
@@ -7,12 +7,16 @@ export interface runFormulaReturnType {
   success: boolean;
   error_span: [number, number] | null;
   error_msg: string | null;
+  error_code: string | null;
+  circular_reference: [number, number][] | null;
+  volatile: boolean;
   output_value: string | null;
   array_output: string[][] | null;
+  array_size: [number, number] | null;
 }
 
 export async function runFormula(formula_code: string, pos: Coordinate): Promise<runFormulaReturnType> {
-  const output = await eval_formula(formula_code, pos.x, pos.y, GetCellsDB);
+  const output = await eval_formula(formula_code, pos.x, pos.y, GetTypedCellsDB, GetCellBoundsDB, undefined);
 
   return output as runFormulaReturnType;
 }
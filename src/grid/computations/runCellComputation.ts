@@ -16,6 +16,8 @@ export const runCellComputation = async (cell: Cell, pyodide?: any): Promise<Cel
       array_output: result.array_output || [],
       formatted_code: cell.formula_code || '',
       error_span: null,
+      circular_reference: result.circular_reference,
+      volatile: result.volatile,
     };
   } else if (cell.type === 'PYTHON') {
     let result = await runPython(cell.python_code || '', pyodide);
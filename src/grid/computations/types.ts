@@ -11,6 +11,9 @@ export const CellEvaluationResultSchema = z.object({
   array_output: z.union([ArrayOutputSchema, z.array(ArrayOutputSchema)]).optional(), // 1 or 2d array
   formatted_code: z.string(),
   error_span: z.tuple([z.number(), z.number()]).or(z.null()),
+  circular_reference: z.tuple([z.number(), z.number()]).array().or(z.null()).optional(),
+  // whether the cell must be recalculated whenever any cell changes, such as when a formula calls NOW()
+  volatile: z.boolean().optional(),
 });
 
 export type ArrayOutput = z.infer<typeof ArrayOutputSchema>;
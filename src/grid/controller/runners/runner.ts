@@ -1,7 +1,6 @@
 import { Sheet } from '../../sheet/Sheet';
 import { Statement } from '../statement';
 import { SetCellRunner } from './setCellRunner';
-import { AddCellDependenciesRunner, RemoveCellDependenciesRunner } from './setCellDependenciesRunner';
 import { SetCellFormatRunner } from './setCellFormatRunner';
 import { PixiApp } from '../../../gridGL/pixiApp/PixiApp';
 import { SetHeadingSizeRunner } from './setHeadingSizeRunner';
@@ -10,10 +9,6 @@ import { SetBorderRunner } from './setBorderRunner';
 export const StatementRunner = (sheet: Sheet, statement: Statement, app?: PixiApp): Statement => {
   if (statement.type === 'SET_CELL') {
     return SetCellRunner(sheet, statement, app);
-  } else if (statement.type === 'ADD_CELL_DEPENDENCY') {
-    return AddCellDependenciesRunner(sheet, statement);
-  } else if (statement.type === 'REMOVE_CELL_DEPENDENCY') {
-    return RemoveCellDependenciesRunner(sheet, statement);
   } else if (statement.type === 'SET_CELL_FORMAT') {
     return SetCellFormatRunner(sheet, statement, app);
   } else if (statement.type === 'SET_HEADING_SIZE') {
@@ -1,12 +1,15 @@
 import { Sheet } from '../../sheet/Sheet';
 import { Statement } from '../statement';
 import { PixiApp } from '../../../gridGL/pixiApp/PixiApp';
+import { updateCellDependencies } from '../../sheet/cellDependencies';
 
 export const SetCellRunner = (sheet: Sheet, statement: Statement, app?: PixiApp): Statement => {
   if (statement.type !== 'SET_CELL') throw new Error('Incorrect statement type.');
   // Applies the SET_CELL statement to the sheet and returns the reverse statement
   const { position, value: new_value } = statement.data;
   const old_value = sheet.getCellCopy(position[0], position[1]);
+  // keep the dependency graph in sync with the cell, including on undo and redo
+  updateCellDependencies(position, new_value);
   if (new_value === undefined) {
     // if we are deleting a cell, we need to delete it from the grid
     // and return a statement that applies the old value.
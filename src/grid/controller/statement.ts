@@ -10,20 +10,6 @@ export type Statement =
         value: Cell | undefined; // TODO: Make this accept more than one cell
       };
     }
-  | {
-      type: 'ADD_CELL_DEPENDENCY';
-      data: {
-        position: [number, number];
-        updates: [number, number];
-      };
-    }
-  | {
-      type: 'REMOVE_CELL_DEPENDENCY';
-      data: {
-        position: [number, number];
-        updates: [number, number];
-      };
-    }
   | {
       type: 'SET_CELL_FORMAT';
       data: {
@@ -74,3 +74,48 @@ test('SheetController - cell error prev array output', async () => {
   expect(cell_after?.last_modified).toBeDefined();
   expect(cell_after?.type).toBe('PYTHON');
 });
+
+test('SheetController - circular reference error goes away when the cycle is broken', async () => {
+  const sc = new SheetController();
+  GetCellsDBSetSheet(sc.sheet);
+
+  const cell_0_100 = {
+    x: 0,
+    y: 100,
+    value: '',
+    type: 'FORMULA',
+    formula_code: 'B100 + 1',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  const cell_1_100 = {
+    x: 1,
+    y: 100,
+    value: '',
+    type: 'FORMULA',
+    formula_code: 'A100 * 2',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  await updateCellAndDCells({ starting_cells: [cell_0_100, cell_1_100], sheetController: sc, pyodide });
+
+  const cell_after_cycle = sc.sheet.grid.getCell(1, 100);
+  expect(cell_after_cycle?.evaluation_result?.success).toBe(false);
+  expect(cell_after_cycle?.evaluation_result?.std_err).toBe('Circular reference');
+
+  // replace one of the formulas with a value
+  const value_0_100 = {
+    x: 0,
+    y: 100,
+    value: '5',
+    type: 'TEXT',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  await updateCellAndDCells({ starting_cells: [value_0_100], sheetController: sc, pyodide });
+
+  const cell_after_value = sc.sheet.grid.getCell(1, 100);
+  expect(cell_after_value?.evaluation_result?.success).toBe(true);
+  expect(cell_after_value?.evaluation_result?.std_err).toBeUndefined();
+  expect(cell_after_value?.value).toBe('10');
+});
@@ -385,3 +385,91 @@ test('SheetController - test deleted array cells update dependent cells', async
     "TypeError on line 1: unsupported operand type(s) for +: 'NoneType' and 'int'"
   );
 });
+
+test('SheetController - volatile formulas are recalculated when any cell changes', async () => {
+  const sc = new SheetController();
+  GetCellsDBSetSheet(sc.sheet);
+
+  const cell_volatile = {
+    x: 0,
+    y: 200,
+    value: '',
+    type: 'FORMULA',
+    formula_code: 'RAND()',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  await updateCellAndDCells({ starting_cells: [cell_volatile], sheetController: sc, pyodide });
+
+  const value_before = sc.sheet.grid.getCell(0, 200)?.value;
+  expect(value_before).not.toBe('');
+
+  // an unrelated cell changes
+  const cell_other = {
+    x: 5,
+    y: 200,
+    value: 'hello',
+    type: 'TEXT',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  await updateCellAndDCells({ starting_cells: [cell_other], sheetController: sc, pyodide });
+
+  const value_after = sc.sheet.grid.getCell(0, 200)?.value;
+  expect(value_after).not.toBe('');
+  expect(value_after).not.toBe(value_before);
+});
+
+test('SheetController - formulas see Python numbers as numbers', async () => {
+  const sc = new SheetController();
+  GetCellsDBSetSheet(sc.sheet);
+
+  const cell_python = {
+    x: 0,
+    y: 300,
+    value: '',
+    type: 'PYTHON',
+    python_code: '2.5 * 2',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  const cell_formula = {
+    x: 1,
+    y: 300,
+    value: '',
+    type: 'FORMULA',
+    formula_code: 'IF(ISNUMBER(A300), A300 + 1, "text")',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  await updateCellAndDCells({ starting_cells: [cell_python, cell_formula], sheetController: sc, pyodide });
+
+  expect(sc.sheet.grid.getCell(0, 300)?.value).toBe('5.0');
+  expect(sc.sheet.grid.getCell(1, 300)?.value).toBe('6');
+});
+
+test('SheetController - formulas see text cells as text', async () => {
+  const sc = new SheetController();
+  GetCellsDBSetSheet(sc.sheet);
+
+  const cell_text = {
+    x: 0,
+    y: 301,
+    value: '007',
+    type: 'TEXT',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  const cell_formula = {
+    x: 1,
+    y: 301,
+    value: '',
+    type: 'FORMULA',
+    formula_code: 'IF(ISTEXT(A301), A301, "number")',
+    last_modified: '2023-01-19T19:12:21.745Z',
+  } as Cell;
+
+  await updateCellAndDCells({ starting_cells: [cell_text, cell_formula], sheetController: sc, pyodide });
+
+  expect(sc.sheet.grid.getCell(1, 301)?.value).toBe('007');
+});
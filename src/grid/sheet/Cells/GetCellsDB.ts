@@ -4,17 +4,63 @@ import { Sheet } from '../Sheet';
 // use to fake entry to sheet (this is only temporary as rust will directly handle this call)
 let sheet: Sheet | undefined = undefined;
 
+// the app has a single sheet, which formulas refer to without a sheet name. quadratic-core reads references to other
+// sheets as #REF! without asking for their cells.
+const getSheet = (sheet_name?: string): Sheet => {
+  if (sheet_name !== undefined) throw new Error(`There is no sheet named "${sheet_name}"`);
+  if (sheet !== undefined) return sheet;
+  throw new Error('Expected `sheet` to be defined in GetCellsDB');
+};
+
 // todo: this file goes away once we have rust backend
 export const GetCellsDB = async (
   p0_x = -Infinity,
   p0_y = -Infinity,
   p1_x = Infinity,
-  p1_y = Infinity
+  p1_y = Infinity,
+  sheet_name?: string
 ): Promise<Cell[]> => {
-  if (sheet !== undefined) {
-    return sheet.grid.getNakedCells(p0_x, p0_y, p1_x, p1_y);
+  return getSheet(sheet_name).grid.getNakedCells(p0_x, p0_y, p1_x, p1_y);
+};
+
+// computed cells store their output as text, so this recovers the type that formulas should see: numbers, such as
+// ones returned by Python, booleans, and ISO dates in local time. Anything else stays text. JavaScript has no date-only
+// type, so quadratic-core reads every date as a date and time, with dates at midnight.
+const typedCellValue = (value: string): string | number | boolean | Date => {
+  const trimmed = value.trim();
+  if (/^[+-]?(\d+\.?\d*|\.\d+)(e[+-]?\d+)?$/i.test(trimmed)) return Number(trimmed);
+  if (/^(true|false)$/i.test(trimmed)) return trimmed.toLowerCase() === 'true';
+  const date = /^(\d{4})-(\d{2})-(\d{2})(?:[T ](\d{2}):(\d{2})(?::(\d{2}))?)?$/.exec(trimmed);
+  if (date) {
+    const [year, month, day, hours, minutes, seconds] = date.slice(1).map((part) => Number(part ?? 0));
+    return new Date(year, month - 1, day, hours, minutes, seconds);
   }
-  throw new Error('Expected `sheet` to be defined in GetCellsDB');
+  return value;
+};
+
+// same as GetCellsDB, but with typed values for formulas. Text cells stay text, even if they look like a number, so
+// that `007` keeps its leading zeros; formulas convert text to a number only when they need one.
+export const GetTypedCellsDB = async (
+  p0_x = -Infinity,
+  p0_y = -Infinity,
+  p1_x = Infinity,
+  p1_y = Infinity,
+  sheet_name?: string
+): Promise<(Omit<Cell, 'value'> & { value: ReturnType<typeof typedCellValue> })[]> => {
+  const cells = await GetCellsDB(p0_x, p0_y, p1_x, p1_y, sheet_name);
+  return cells.map((cell) => ({ ...cell, value: cell.type === 'TEXT' ? cell.value : typedCellValue(cell.value) }));
+};
+
+// returns the smallest rectangle containing every cell with data, or undefined if the sheet is empty
+export const GetCellBoundsDB = async (
+  sheet_name?: string
+): Promise<{ min: { x: number; y: number }; max: { x: number; y: number } } | undefined> => {
+  const bounds = getSheet(sheet_name).grid.getGridBounds(true);
+  if (bounds === undefined) return undefined;
+  return {
+    min: { x: bounds.left, y: bounds.top },
+    max: { x: bounds.right, y: bounds.bottom },
+  };
 };
 
 export const GetCellsDBSetSheet = (value: Sheet): void => {
@@ -6,7 +6,7 @@ import { GridRenderDependency } from './GridRenderDependency';
 import { GridOffsets } from './GridOffsets';
 import { CellAndFormat, GridSparse } from './GridSparse';
 import { Cell, CellFormat } from '../../schemas';
-import { CellDependencyManager } from './CellDependencyManager';
+import { loadCellDependencies } from './cellDependencies';
 import { Coordinate } from '../../gridGL/types/size';
 
 export class Sheet {
@@ -20,9 +20,6 @@ export class Sheet {
   // visual dependency for drawing array lines
   array_dependency: GridRenderDependency;
 
-  // cell calculation dependency
-  cell_dependency: CellDependencyManager;
-
   onRebuild?: () => void;
 
   constructor() {
@@ -31,7 +28,6 @@ export class Sheet {
     this.borders = new GridBorders(this.gridOffsets);
     this.render_dependency = new GridRenderDependency();
     this.array_dependency = new GridRenderDependency();
-    this.cell_dependency = new CellDependencyManager();
   }
 
   newFile(): void {
@@ -39,7 +35,7 @@ export class Sheet {
     this.grid = new GridSparse(this.gridOffsets);
     this.borders = new GridBorders(this.gridOffsets);
     this.render_dependency = new GridRenderDependency();
-    this.cell_dependency = new CellDependencyManager();
+    loadCellDependencies([]);
     this.onRebuild?.();
   }
 
@@ -48,7 +44,7 @@ export class Sheet {
     this.grid.populate(sheet.cells, sheet.formats);
     this.borders.populate(sheet.borders);
     this.render_dependency.load(sheet.render_dependency);
-    this.cell_dependency.loadFromString(sheet.cell_dependency);
+    loadCellDependencies(sheet.cells);
     this.onRebuild?.();
   }
 
@@ -60,7 +56,8 @@ export class Sheet {
       cells,
       formats,
       borders: this.borders.getArray(),
-      cell_dependency: this.cell_dependency.exportToString(),
+      // cell calculation dependencies are rebuilt from the cells when the file is loaded
+      cell_dependency: '',
       render_dependency: this.render_dependency.save(),
     };
   }
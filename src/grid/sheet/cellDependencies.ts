@@ -0,0 +1,25 @@
+import { clear_cell_dependencies, remove_cell_dependencies, set_cell_dependencies } from 'quadratic-core';
+import { Cell } from '../../schemas';
+
+// cell calculation dependencies are tracked by quadratic-core, which uses them to decide what to recalculate
+
+export const isComputed = (cell: Cell): boolean =>
+  cell.type === 'FORMULA' || cell.type === 'PYTHON' || cell.type === 'AI';
+
+// records the cells that a computed cell read and whether it is volatile, or forgets them if the cell is now a value
+// or deleted
+export const updateCellDependencies = (position: [number, number], cell: Cell | undefined): void => {
+  const evaluation_result = cell !== undefined && isComputed(cell) ? cell.evaluation_result : undefined;
+  if (evaluation_result !== undefined) {
+    const volatile = evaluation_result.volatile ?? false;
+    set_cell_dependencies(position[0], position[1], evaluation_result.cells_accessed, volatile, undefined);
+  } else {
+    remove_cell_dependencies(position[0], position[1], undefined);
+  }
+};
+
+// replaces all dependencies with the ones of the cells in a newly loaded sheet
+export const loadCellDependencies = (cells: Cell[]): void => {
+  clear_cell_dependencies();
+  cells.forEach((cell) => updateCellDependencies([cell.x, cell.y], cell));
+};
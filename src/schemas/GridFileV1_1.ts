@@ -46,6 +46,7 @@ export const GridFileSchemaV1_1 = z.object({
           array_output: z.union([ArrayOutputSchema, z.array(ArrayOutputSchema)]).optional(), // 1 or 2d array
           formatted_code: z.string(),
           error_span: z.tuple([z.number(), z.number()]).or(z.null()),
+          volatile: z.boolean().optional(),
         })
         .optional(),
       formula_code: z.string().optional(),
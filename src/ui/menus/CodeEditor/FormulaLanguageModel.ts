@@ -4,6 +4,40 @@ const keywords = [
   // MATHEMATICAL OPERATORS
   'SUM',
   'PRODUCT',
+  'ABS',
+  'SIGN',
+  'ROUND',
+  'ROUNDUP',
+  'ROUNDDOWN',
+  'MROUND',
+  'CEILING',
+  'FLOOR',
+  'INT',
+  'TRUNC',
+  'MOD',
+  'QUOTIENT',
+  'SQRT',
+  'EXP',
+  'LN',
+  'LOG',
+  'LOG10',
+  'PI',
+  'SIN',
+  'COS',
+  'TAN',
+  'ASIN',
+  'ACOS',
+  'ATAN',
+  'ATAN2',
+  'DEGREES',
+  'RADIANS',
+  'FACT',
+  'COMBIN',
+  'PERMUT',
+  'GCD',
+  'LCM',
+  'RAND',
+  'RANDBETWEEN',
   // LOGIC FUNCTIONS
   'TRUE',
   'FALSE',
@@ -12,13 +46,168 @@ const keywords = [
   'OR',
   'XOR',
   'IF',
+  'IFS',
+  'SWITCH',
+  'CHOOSE',
+  'IFERROR',
+  'IFNA',
   // STATISTICS FUNCTIONS
   'AVERAGE',
   'COUNT',
   'MIN',
   'MAX',
+  'AVERAGEA',
+  'MEDIAN',
+  'MODE',
+  'MODE.SNGL',
+  'COUNTA',
+  'COUNTBLANK',
+  'LARGE',
+  'SMALL',
+  'RANK',
+  'RANK.EQ',
+  'PERCENTILE',
+  'PERCENTILE.INC',
+  'PERCENTILE.EXC',
+  'QUARTILE',
+  'QUARTILE.INC',
+  'QUARTILE.EXC',
+  'VAR',
+  'VAR.S',
+  'VAR.P',
+  'VARP',
+  'STDEV',
+  'STDEV.S',
+  'STDEV.P',
+  'STDEVP',
+  'CORREL',
+  'COVAR',
+  'COVARIANCE.S',
+  'COVARIANCE.P',
+  'SLOPE',
+  'INTERCEPT',
+  'RSQ',
+  'FORECAST',
+  'FORECAST.LINEAR',
+  // CONDITIONAL FUNCTIONS
+  'SUMIF',
+  'SUMIFS',
+  'COUNTIF',
+  'COUNTIFS',
+  'AVERAGEIF',
+  'AVERAGEIFS',
+  'MAXIFS',
+  'MINIFS',
+  // FINANCIAL FUNCTIONS
+  'NPV',
+  'XNPV',
+  'IRR',
+  'XIRR',
+  'PMT',
+  'IPMT',
+  'PPMT',
+  'PV',
+  'FV',
+  'RATE',
+  'NPER',
+  'SLN',
+  'DDB',
+  'EFFECT',
+  'NOMINAL',
+  // DYNAMIC ARRAY FUNCTIONS
+  'FILTER',
+  'SORT',
+  'SORTBY',
+  'UNIQUE',
+  'SEQUENCE',
+  'TRANSPOSE',
+  'HSTACK',
+  'VSTACK',
+  'TAKE',
+  'DROP',
+  'CHOOSEROWS',
+  'CHOOSECOLS',
   // STRING FUNCTIONS
   'CONCAT',
+  // LOOKUP FUNCTIONS
+  'VLOOKUP',
+  'HLOOKUP',
+  'XLOOKUP',
+  'INDEX',
+  'MATCH',
+  'XMATCH',
+  // INFORMATION FUNCTIONS
+  'NA',
+  'ISERROR',
+  'ISERR',
+  'ISNA',
+  'ERROR.TYPE',
+  'ISBLANK',
+  'ISNUMBER',
+  'ISTEXT',
+  'ISLOGICAL',
+  'ISEVEN',
+  'ISODD',
+  'ISFORMULA',
+  'TYPE',
+  'N',
+  'T',
+  // LAMBDA FUNCTIONS
+  'LET',
+  'LAMBDA',
+  'MAP',
+  'REDUCE',
+  'SCAN',
+  'BYROW',
+  'BYCOL',
+  'MAKEARRAY',
+  // DATE AND TIME FUNCTIONS
+  'TODAY',
+  'NOW',
+  'DATE',
+  'TIME',
+  'YEAR',
+  'MONTH',
+  'DAY',
+  'HOUR',
+  'MINUTE',
+  'SECOND',
+  'WEEKDAY',
+  'WEEKNUM',
+  'EDATE',
+  'EOMONTH',
+  'DATEDIF',
+  'NETWORKDAYS',
+  'WORKDAY',
+  'DATEVALUE',
+  // TEXT FUNCTIONS
+  'LEN',
+  'LEFT',
+  'RIGHT',
+  'MID',
+  'UPPER',
+  'LOWER',
+  'PROPER',
+  'TRIM',
+  'SUBSTITUTE',
+  'REPLACE',
+  'FIND',
+  'SEARCH',
+  'TEXTJOIN',
+  'SPLIT',
+  'TEXTSPLIT',
+  'REPT',
+  'EXACT',
+  'VALUE',
+  'TEXT',
+  'CHAR',
+  'CODE',
+  'UNICODE',
+  'CLEAN',
+  // REGULAR EXPRESSION FUNCTIONS
+  'REGEXMATCH',
+  'REGEXEXTRACT',
+  'REGEXREPLACE',
 ];
 export const FormulaLanguageConfig = {
   ignore_case: true,
@@ -52,7 +241,7 @@ export const FormulaLanguageConfig = {
   ],
   tokenizer: {
     root: [
-      [/[a-zA-Z_$][\w$]*/, { cases: { '@keywords': 'keyword', '@default': 'variable' } }],
+      [/[a-zA-Z_$][\w$.]*/, { cases: { '@keywords': 'keyword', '@default': 'variable' } }],
 
       // cell references
       [/\$?[A-Z]+\$?n?\d+/, ''],
@@ -118,6 +307,44 @@ export const FormulaCompletionProvider = {
       // Mathematical operators
       suggestion('SUM', '${1:addends}', 'Adds multiple values together'),
       suggestion('PRODUCT', '${1:factors}', 'Multiplies multiple values together'),
+      suggestion('ABS', '${1:number}', 'Returns the absolute value of a number'),
+      suggestion('SIGN', '${1:number}', 'Returns -1, 0, or 1 depending on the sign of a number'),
+      suggestion('ROUND', '${1:number}, ${2:digits}', 'Rounds a number to a number of decimal places'),
+      suggestion('ROUNDUP', '${1:number}, ${2:digits}', 'Rounds a number away from zero to a number of decimal places'),
+      suggestion('ROUNDDOWN', '${1:number}, ${2:digits}', 'Rounds a number toward zero to a number of decimal places'),
+      suggestion('MROUND', '${1:number}, ${2:multiple}', 'Rounds a number to the nearest multiple'),
+      suggestion('CEILING', '${1:number}, ${2:significance}', 'Rounds a number up to a multiple of significance'),
+      suggestion('FLOOR', '${1:number}, ${2:significance}', 'Rounds a number down to a multiple of significance'),
+      suggestion('INT', '${1:number}', 'Rounds a number down to an integer'),
+      suggestion('TRUNC', '${1:number}, ${2:digits}', 'Truncates a number to a number of decimal places'),
+      suggestion(
+        'MOD',
+        '${1:number}, ${2:divisor}',
+        'Returns the remainder after division, with the same sign as the divisor'
+      ),
+      suggestion('QUOTIENT', '${1:number}, ${2:divisor}', 'Returns the integer part of a division'),
+      suggestion('SQRT', '${1:number}', 'Returns the square root of a number'),
+      suggestion('EXP', '${1:number}', 'Returns e raised to a power'),
+      suggestion('LN', '${1:number}', 'Returns the natural logarithm of a number'),
+      suggestion('LOG', '${1:number}, ${2:base}', 'Returns the logarithm of a number to a base, which defaults to 10'),
+      suggestion('LOG10', '${1:number}', 'Returns the base-10 logarithm of a number'),
+      suggestion('PI', '', 'Returns the value of pi'),
+      suggestion('SIN', '${1:radians}', 'Returns the sine of an angle'),
+      suggestion('COS', '${1:radians}', 'Returns the cosine of an angle'),
+      suggestion('TAN', '${1:radians}', 'Returns the tangent of an angle'),
+      suggestion('ASIN', '${1:number}', 'Returns the inverse sine of a number, in radians'),
+      suggestion('ACOS', '${1:number}', 'Returns the inverse cosine of a number, in radians'),
+      suggestion('ATAN', '${1:number}', 'Returns the inverse tangent of a number, in radians'),
+      suggestion('ATAN2', '${1:x}, ${2:y}', 'Returns the angle of the point (x, y) from the x-axis, in radians'),
+      suggestion('DEGREES', '${1:radians}', 'Converts radians to degrees'),
+      suggestion('RADIANS', '${1:degrees}', 'Converts degrees to radians'),
+      suggestion('FACT', '${1:number}', 'Returns the factorial of a number'),
+      suggestion('COMBIN', '${1:n}, ${2:k}', 'Returns the number of ways to choose k items from n'),
+      suggestion('PERMUT', '${1:n}, ${2:k}', 'Returns the number of ordered arrangements of k items from n'),
+      suggestion('GCD', '${1:numbers}', 'Returns the greatest common divisor of integers'),
+      suggestion('LCM', '${1:numbers}', 'Returns the least common multiple of integers'),
+      suggestion('RAND', '', 'Returns a random number between 0 and 1'),
+      suggestion('RANDBETWEEN', '${1:low}, ${2:high}', 'Returns a random integer between two values, inclusive'),
       // Logic functions
       suggestion('TRUE', '', 'Returns TRUE (1)'),
       suggestion('FALSE', '', 'Returns FALSE (0)'),
@@ -142,13 +369,395 @@ export const FormulaCompletionProvider = {
         '${1:condition}, ${2:value_if_true}, ${3:value_if_false}',
         'If the first argument is truthy, returns the second argument; otherwise returns the third argument'
       ),
+      suggestion(
+        'IFS',
+        '${1:condition1}, ${2:value1}, ${3:condition2}, ${4:value2}',
+        'Returns the value after the first truthy condition, or #N/A if there is none'
+      ),
+      suggestion(
+        'SWITCH',
+        '${1:expression}, ${2:case1}, ${3:value1}, ${4:default}',
+        'Returns the value after the first case equal to the expression, or the default if there is none'
+      ),
+      suggestion('CHOOSE', '${1:index}, ${2:values}', 'Returns the value at the given 1-based index among the remaining arguments'),
+      suggestion(
+        'IFERROR',
+        '${1:value}, ${2:value_if_error}',
+        'Returns the first argument if it is not an error; otherwise returns the second argument'
+      ),
+      suggestion(
+        'IFNA',
+        '${1:value}, ${2:value_if_na}',
+        'Returns the first argument if it is not a #N/A error; otherwise returns the second argument'
+      ),
       // Statistics functions
       suggestion('AVERAGE', '${1:values}', 'Returns the arithmetic mean of multiple values'),
-      suggestion('COUNT', '${1:values}', 'Returns the number of values present'),
+      suggestion('COUNT', '${1:values}', 'Returns the number of numeric values'),
       suggestion('MIN', '${1:values}', 'Returns the minimum value'),
       suggestion('MAX', '${1:values}', 'Returns the maximum value'),
+      suggestion('AVERAGEA', '${1:values}', 'Returns the arithmetic mean of multiple values, counting text as 0'),
+      suggestion('MEDIAN', '${1:values}', 'Returns the middle value'),
+      suggestion('MODE', '${1:values}', 'Returns the most common value'),
+      suggestion('COUNTA', '${1:values}', 'Returns the number of values that are not blank'),
+      suggestion('COUNTBLANK', '${1:range}', 'Returns the number of blank cells'),
+      suggestion('LARGE', '${1:values}, ${2:k}', 'Returns the kth largest value'),
+      suggestion('SMALL', '${1:values}, ${2:k}', 'Returns the kth smallest value'),
+      suggestion('RANK', '${1:value}, ${2:values}, ${3:ascending}', 'Returns the rank of a value among other values'),
+      suggestion(
+        'PERCENTILE',
+        '${1:values}, ${2:k}',
+        'Returns the kth percentile of values, where k is between 0 and 1'
+      ),
+      suggestion(
+        'PERCENTILE.INC',
+        '${1:values}, ${2:k}',
+        'Returns the kth percentile of values, where k is between 0 and 1'
+      ),
+      suggestion(
+        'PERCENTILE.EXC',
+        '${1:values}, ${2:k}',
+        'Returns the kth percentile of values, where k is between 0 and 1 exclusive'
+      ),
+      suggestion(
+        'QUARTILE',
+        '${1:values}, ${2:quartile}',
+        'Returns a quartile of values, where quartile is between 0 and 4'
+      ),
+      suggestion(
+        'QUARTILE.INC',
+        '${1:values}, ${2:quartile}',
+        'Returns a quartile of values, where quartile is between 0 and 4'
+      ),
+      suggestion(
+        'QUARTILE.EXC',
+        '${1:values}, ${2:quartile}',
+        'Returns a quartile of values, where quartile is between 1 and 3'
+      ),
+      suggestion('VAR.S', '${1:values}', 'Returns the variance of a sample'),
+      suggestion('VAR.P', '${1:values}', 'Returns the variance of a population'),
+      suggestion('STDEV.S', '${1:values}', 'Returns the standard deviation of a sample'),
+      suggestion('STDEV.P', '${1:values}', 'Returns the standard deviation of a population'),
+      suggestion('CORREL', '${1:values1}, ${2:values2}', 'Returns the correlation coefficient of two sets of values'),
+      suggestion('COVARIANCE.S', '${1:values1}, ${2:values2}', 'Returns the sample covariance of two sets of values'),
+      suggestion(
+        'COVARIANCE.P',
+        '${1:values1}, ${2:values2}',
+        'Returns the population covariance of two sets of values'
+      ),
+      suggestion('SLOPE', '${1:known_ys}, ${2:known_xs}', 'Returns the slope of the linear regression line'),
+      suggestion('INTERCEPT', '${1:known_ys}, ${2:known_xs}', 'Returns the y-intercept of the linear regression line'),
+      suggestion('RSQ', '${1:known_ys}, ${2:known_xs}', 'Returns the square of the correlation coefficient'),
+      suggestion(
+        'FORECAST.LINEAR',
+        '${1:x}, ${2:known_ys}, ${3:known_xs}',
+        'Predicts a y value using linear regression'
+      ),
+      // Conditional functions
+      suggestion(
+        'SUMIF',
+        '${1:range}, ${2:criterion}, ${3:sum_range}',
+        'Adds the values where a range satisfies a criterion'
+      ),
+      suggestion(
+        'SUMIFS',
+        '${1:sum_range}, ${2:range1}, ${3:criterion1}',
+        'Adds the values where every range satisfies its criterion'
+      ),
+      suggestion('COUNTIF', '${1:range}, ${2:criterion}', 'Counts the cells in a range that satisfy a criterion'),
+      suggestion(
+        'COUNTIFS',
+        '${1:range1}, ${2:criterion1}',
+        'Counts the cells where every range satisfies its criterion'
+      ),
+      suggestion(
+        'AVERAGEIF',
+        '${1:range}, ${2:criterion}, ${3:average_range}',
+        'Returns the mean of the values where a range satisfies a criterion'
+      ),
+      suggestion(
+        'AVERAGEIFS',
+        '${1:average_range}, ${2:range1}, ${3:criterion1}',
+        'Returns the mean of the values where every range satisfies its criterion'
+      ),
+      suggestion(
+        'MAXIFS',
+        '${1:max_range}, ${2:range1}, ${3:criterion1}',
+        'Returns the maximum of the values where every range satisfies its criterion'
+      ),
+      suggestion(
+        'MINIFS',
+        '${1:min_range}, ${2:range1}, ${3:criterion1}',
+        'Returns the minimum of the values where every range satisfies its criterion'
+      ),
+      // Financial functions
+      suggestion('NPV', '${1:rate}, ${2:cash_flows}', 'Returns the net present value of periodic cash flows'),
+      suggestion(
+        'XNPV',
+        '${1:rate}, ${2:cash_flows}, ${3:dates}',
+        'Returns the net present value of cash flows on specific dates'
+      ),
+      suggestion('IRR', '${1:cash_flows}, ${2:guess}', 'Returns the internal rate of return of periodic cash flows'),
+      suggestion(
+        'XIRR',
+        '${1:cash_flows}, ${2:dates}, ${3:guess}',
+        'Returns the internal rate of return of cash flows on specific dates'
+      ),
+      suggestion(
+        'PMT',
+        '${1:rate}, ${2:nper}, ${3:pv}, ${4:fv}, ${5:type}',
+        'Returns the payment each period for a loan or investment'
+      ),
+      suggestion(
+        'IPMT',
+        '${1:rate}, ${2:period}, ${3:nper}, ${4:pv}, ${5:fv}, ${6:type}',
+        'Returns the interest portion of a payment'
+      ),
+      suggestion(
+        'PPMT',
+        '${1:rate}, ${2:period}, ${3:nper}, ${4:pv}, ${5:fv}, ${6:type}',
+        'Returns the principal portion of a payment'
+      ),
+      suggestion(
+        'PV',
+        '${1:rate}, ${2:nper}, ${3:pmt}, ${4:fv}, ${5:type}',
+        'Returns the present value of a loan or investment'
+      ),
+      suggestion(
+        'FV',
+        '${1:rate}, ${2:nper}, ${3:pmt}, ${4:pv}, ${5:type}',
+        'Returns the future value of a loan or investment'
+      ),
+      suggestion(
+        'RATE',
+        '${1:nper}, ${2:pmt}, ${3:pv}, ${4:fv}, ${5:type}, ${6:guess}',
+        'Returns the interest rate per period of a loan or investment'
+      ),
+      suggestion(
+        'NPER',
+        '${1:rate}, ${2:pmt}, ${3:pv}, ${4:fv}, ${5:type}',
+        'Returns the number of periods for a loan or investment'
+      ),
+      suggestion(
+        'SLN',
+        '${1:cost}, ${2:salvage}, ${3:life}',
+        'Returns the straight-line depreciation of an asset for one period'
+      ),
+      suggestion(
+        'DDB',
+        '${1:cost}, ${2:salvage}, ${3:life}, ${4:period}, ${5:factor}',
+        'Returns the double-declining-balance depreciation of an asset for a period'
+      ),
+      suggestion('EFFECT', '${1:nominal_rate}, ${2:periods_per_year}', 'Returns the effective annual interest rate'),
+      suggestion('NOMINAL', '${1:effective_rate}, ${2:periods_per_year}', 'Returns the nominal annual interest rate'),
+      // Dynamic array functions
+      suggestion('FILTER', '${1:array}, ${2:include}', 'Returns the rows or columns of an array where include is TRUE'),
+      suggestion(
+        'SORT',
+        '${1:array}, ${2:sort_index}, ${3:sort_order}, ${4:by_col}',
+        'Sorts the rows or columns of an array'
+      ),
+      suggestion(
+        'SORTBY',
+        '${1:array}, ${2:by_array}, ${3:sort_order}',
+        'Sorts the rows or columns of an array by the values in other arrays'
+      ),
+      suggestion(
+        'UNIQUE',
+        '${1:array}, ${2:by_col}, ${3:exactly_once}',
+        'Returns the unique rows or columns of an array'
+      ),
+      suggestion(
+        'SEQUENCE',
+        '${1:rows}, ${2:columns}, ${3:start}, ${4:step}',
+        'Returns an array of evenly spaced numbers'
+      ),
+      suggestion('TRANSPOSE', '${1:array}', 'Swaps the rows and columns of an array'),
+      suggestion('HSTACK', '${1:arrays}', 'Joins arrays side by side'),
+      suggestion('VSTACK', '${1:arrays}', 'Joins arrays one above the other'),
+      suggestion(
+        'TAKE',
+        '${1:array}, ${2:rows}, ${3:columns}',
+        'Returns rows or columns from the start or end of an array'
+      ),
+      suggestion(
+        'DROP',
+        '${1:array}, ${2:rows}, ${3:columns}',
+        'Removes rows or columns from the start or end of an array'
+      ),
+      suggestion('CHOOSEROWS', '${1:array}, ${2:row_nums}', 'Returns the given rows of an array'),
+      suggestion('CHOOSECOLS', '${1:array}, ${2:col_nums}', 'Returns the given columns of an array'),
       // String functions
       suggestion('CONCAT', '${1:values}', 'Concatenates multiple values'),
+      // Lookup functions
+      suggestion(
+        'VLOOKUP',
+        '${1:search_key}, ${2:range}, ${3:index}, ${4:is_sorted}',
+        'Searches down the first column of a range for a key and returns the value in the given column of the row found'
+      ),
+      suggestion(
+        'HLOOKUP',
+        '${1:search_key}, ${2:range}, ${3:index}, ${4:is_sorted}',
+        'Searches across the first row of a range for a key and returns the value in the given row of the column found'
+      ),
+      suggestion(
+        'XLOOKUP',
+        '${1:search_key}, ${2:lookup_range}, ${3:result_range}, ${4:if_not_found}, ${5:match_mode}, ${6:search_mode}',
+        'Searches a row or column for a key and returns the corresponding value from another range'
+      ),
+      suggestion('INDEX', '${1:range}, ${2:row}, ${3:column}', 'Returns the value at a row and column within a range'),
+      suggestion(
+        'MATCH',
+        '${1:search_key}, ${2:range}, ${3:match_type}',
+        'Returns the position of a key within a row or column'
+      ),
+      suggestion(
+        'XMATCH',
+        '${1:search_key}, ${2:range}, ${3:match_mode}, ${4:search_mode}',
+        'Returns the position of a key within a row or column, with more match and search options'
+      ),
+      // Information functions
+      suggestion('NA', '', 'Returns the #N/A error value'),
+      suggestion('ISERROR', '${1:value}', 'Returns TRUE if the value is any error'),
+      suggestion('ISERR', '${1:value}', 'Returns TRUE if the value is any error other than #N/A'),
+      suggestion('ISNA', '${1:value}', 'Returns TRUE if the value is the #N/A error'),
+      suggestion('ERROR.TYPE', '${1:error}', 'Returns a number identifying the kind of an error value'),
+      suggestion('ISBLANK', '${1:value}', 'Returns TRUE if the value comes from an empty cell'),
+      suggestion('ISNUMBER', '${1:value}', 'Returns TRUE if the value is a number'),
+      suggestion('ISTEXT', '${1:value}', 'Returns TRUE if the value is text'),
+      suggestion('ISLOGICAL', '${1:value}', 'Returns TRUE if the value is TRUE or FALSE'),
+      suggestion('ISEVEN', '${1:number}', 'Returns TRUE if the number is even'),
+      suggestion('ISODD', '${1:number}', 'Returns TRUE if the number is odd'),
+      suggestion('ISFORMULA', '${1:reference}', 'Returns TRUE if the referenced cell contains a formula'),
+      suggestion('TYPE', '${1:value}', 'Returns a number identifying the type of a value'),
+      suggestion('N', '${1:value}', 'Returns the value as a number, or 0 if it is text'),
+      suggestion('T', '${1:value}', 'Returns the value if it is text, or empty text otherwise'),
+      // Lambda functions
+      suggestion(
+        'LET',
+        '${1:name}, ${2:value}, ${3:expression}',
+        'Binds names to values and evaluates an expression using those names'
+      ),
+      suggestion('LAMBDA', '${1:parameters}, ${2:expression}', 'Creates a function that can be bound to a name using LET'),
+      suggestion('MAP', '${1:arrays}, ${2:lambda}', 'Calls a function on each element of one or more arrays'),
+      suggestion(
+        'REDUCE',
+        '${1:initial_value}, ${2:array}, ${3:lambda}',
+        'Combines the elements of an array into a single value by calling a function with an accumulator and each element'
+      ),
+      suggestion(
+        'SCAN',
+        '${1:initial_value}, ${2:array}, ${3:lambda}',
+        'Like REDUCE, but returns an array of every intermediate value'
+      ),
+      suggestion('BYROW', '${1:array}, ${2:lambda}', 'Calls a function on each row of an array'),
+      suggestion('BYCOL', '${1:array}, ${2:lambda}', 'Calls a function on each column of an array'),
+      suggestion(
+        'MAKEARRAY',
+        '${1:rows}, ${2:columns}, ${3:lambda}',
+        'Creates an array by calling a function with the row and column of each element'
+      ),
+      // Date and time functions
+      suggestion('TODAY', '', 'Returns the current date'),
+      suggestion('NOW', '', 'Returns the current date and time'),
+      suggestion('DATE', '${1:year}, ${2:month}, ${3:day}', 'Returns a date from a year, month, and day'),
+      suggestion(
+        'TIME',
+        '${1:hour}, ${2:minute}, ${3:second}',
+        'Returns a time of day from hours, minutes, and seconds'
+      ),
+      suggestion('YEAR', '${1:date}', 'Returns the year of a date'),
+      suggestion('MONTH', '${1:date}', 'Returns the month of a date, from 1 to 12'),
+      suggestion('DAY', '${1:date}', 'Returns the day of the month of a date'),
+      suggestion('HOUR', '${1:time}', 'Returns the hour of a time, from 0 to 23'),
+      suggestion('MINUTE', '${1:time}', 'Returns the minute of a time'),
+      suggestion('SECOND', '${1:time}', 'Returns the second of a time'),
+      suggestion('WEEKDAY', '${1:date}, ${2:return_type}', 'Returns the day of the week of a date as a number'),
+      suggestion('WEEKNUM', '${1:date}, ${2:return_type}', 'Returns the week of the year of a date'),
+      suggestion('EDATE', '${1:start_date}, ${2:months}', 'Returns the date a number of months before or after a date'),
+      suggestion(
+        'EOMONTH',
+        '${1:start_date}, ${2:months}',
+        'Returns the last day of the month a number of months before or after a date'
+      ),
+      suggestion(
+        'DATEDIF',
+        '${1:start_date}, ${2:end_date}, ${3:unit}',
+        'Returns the number of years, months, or days between two dates'
+      ),
+      suggestion(
+        'NETWORKDAYS',
+        '${1:start_date}, ${2:end_date}, ${3:holidays}',
+        'Returns the number of weekdays between two dates, excluding holidays'
+      ),
+      suggestion(
+        'WORKDAY',
+        '${1:start_date}, ${2:days}, ${3:holidays}',
+        'Returns the date a number of weekdays before or after a date, skipping holidays'
+      ),
+      suggestion('DATEVALUE', '${1:text}', 'Converts text to a date'),
+      // Text functions
+      suggestion('LEN', '${1:text}', 'Returns the number of characters in text'),
+      suggestion('LEFT', '${1:text}, ${2:count}', 'Returns the first characters of text'),
+      suggestion('RIGHT', '${1:text}, ${2:count}', 'Returns the last characters of text'),
+      suggestion('MID', '${1:text}, ${2:start}, ${3:count}', 'Returns characters from the middle of text'),
+      suggestion('UPPER', '${1:text}', 'Converts text to uppercase'),
+      suggestion('LOWER', '${1:text}', 'Converts text to lowercase'),
+      suggestion('PROPER', '${1:text}', 'Capitalizes the first letter of each word'),
+      suggestion('TRIM', '${1:text}', 'Removes extra spaces from text'),
+      suggestion(
+        'SUBSTITUTE',
+        '${1:text}, ${2:old_text}, ${3:new_text}, ${4:instance}',
+        'Replaces occurrences of some text with other text'
+      ),
+      suggestion(
+        'REPLACE',
+        '${1:text}, ${2:start}, ${3:count}, ${4:new_text}',
+        'Replaces characters at a position in text with other text'
+      ),
+      suggestion(
+        'FIND',
+        '${1:search_for}, ${2:text}, ${3:start}',
+        'Returns the position of some text within other text, matching case'
+      ),
+      suggestion(
+        'SEARCH',
+        '${1:search_for}, ${2:text}, ${3:start}',
+        'Returns the position of some text within other text, ignoring case and allowing wildcards'
+      ),
+      suggestion(
+        'TEXTJOIN',
+        '${1:delimiter}, ${2:ignore_empty}, ${3:texts}',
+        'Joins text with a delimiter between each piece'
+      ),
+      suggestion(
+        'SPLIT',
+        '${1:text}, ${2:delimiter}, ${3:split_by_each}, ${4:remove_empty}',
+        'Splits text into a row at each delimiter'
+      ),
+      suggestion(
+        'TEXTSPLIT',
+        '${1:text}, ${2:column_delimiter}, ${3:row_delimiter}, ${4:ignore_empty}, ${5:match_mode}, ${6:pad_with}',
+        'Splits text into rows and columns at each delimiter'
+      ),
+      suggestion('REPT', '${1:text}, ${2:count}', 'Repeats text a number of times'),
+      suggestion('EXACT', '${1:text1}, ${2:text2}', 'Returns TRUE if two pieces of text are exactly the same'),
+      suggestion('VALUE', '${1:text}', 'Converts text to a number'),
+      suggestion('TEXT', '${1:value}, ${2:format}', 'Formats a number or date as text using a format code'),
+      suggestion('CHAR', '${1:code}', 'Returns the character with a Unicode code point'),
+      suggestion('CODE', '${1:text}', 'Returns the Unicode code point of the first character of text'),
+      suggestion('UNICODE', '${1:text}', 'Returns the Unicode code point of the first character of text'),
+      suggestion('CLEAN', '${1:text}', 'Removes non-printable characters from text'),
+      suggestion('REGEXMATCH', '${1:text}, ${2:pattern}', 'Returns TRUE if text matches a regular expression'),
+      suggestion(
+        'REGEXEXTRACT',
+        '${1:text}, ${2:pattern}',
+        'Returns the first match of a regular expression, with each capture group in its own column'
+      ),
+      suggestion(
+        'REGEXREPLACE',
+        '${1:text}, ${2:pattern}, ${3:replacement}',
+        'Replaces every match of a regular expression in text'
+      ),
     ];
     return { suggestions: suggestions };
   },
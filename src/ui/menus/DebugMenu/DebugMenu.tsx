@@ -2,6 +2,7 @@ import { colors } from '../../../theme/colors';
 import TextField from '@mui/material/TextField';
 import { Button } from '@mui/material';
 import { Sheet } from '../../../grid/sheet/Sheet';
+import { loadCellDependencies } from '../../../grid/sheet/cellDependencies';
 
 interface Props {
   sheet: Sheet;
@@ -9,17 +10,12 @@ interface Props {
 
 export default function DebugMenu(props: Props) {
   const { sheet } = props;
-  const { cell_dependency: dgraph } = sheet;
 
   const cells = sheet.debugGetCells();
   let file_state: string;
 
-  const HUMAN_READABLE_DGRAPH = true;
-  let dgraph_str = JSON.stringify(dgraph);
-  if (!HUMAN_READABLE_DGRAPH) dgraph_str = JSON.stringify(dgraph);
-
   try {
-    file_state = `${dgraph_str}\n${JSON.stringify(cells || '', null, '\t')}`;
+    file_state = JSON.stringify(cells || '', null, '\t');
   } catch {
     file_state = '';
   }
@@ -41,7 +37,7 @@ export default function DebugMenu(props: Props) {
     >
       <Button
         onClick={() => {
-          dgraph.clear();
+          loadCellDependencies(sheet.debugGetCells());
         }}
       >
         Reset DGraph
@@ -49,7 +45,7 @@ export default function DebugMenu(props: Props) {
       <Button
         onClick={() => {
           sheet.grid.clear();
-          dgraph.clear();
+          loadCellDependencies([]);
         }}
       >
         Reset Grid
@@ -170,6 +170,13 @@ export const QuadraticMenu = (props: Props) => {
           >
             Presentation mode
           </MenuItem>
+          <MenuItem
+            type="checkbox"
+            checked={settings.iterativeCalculation}
+            onClick={() => settings.setIterativeCalculation(!settings.iterativeCalculation)}
+          >
+            Iterative calculation
+          </MenuItem>
           {/*
           Commented out because the editor switches this state automatically when the user
           is editing a formula.
@@ -1,5 +1,6 @@
 import mixpanel from 'mixpanel-browser';
 import { atom, useRecoilState, AtomEffect } from 'recoil';
+import { set_iterative_calculation } from 'quadratic-core';
 import { debugGridSettings } from '../../../../debugFlags';
 
 const SETTINGS_KEY = 'viewSettings';
@@ -10,6 +11,7 @@ export interface GridSettings {
   showCellTypeOutlines: boolean;
   showA1Notation: boolean;
   presentationMode: boolean;
+  iterativeCalculation: boolean;
 }
 
 export const defaultGridSettings: GridSettings = {
@@ -19,8 +21,13 @@ export const defaultGridSettings: GridSettings = {
   showCellTypeOutlines: true,
   showA1Notation: false,
   presentationMode: false,
+  iterativeCalculation: false,
 };
 
+// Same defaults as Excel's "Enable iterative calculation"
+const ITERATIVE_CALCULATION_MAX_ITERATIONS = 100;
+const ITERATIVE_CALCULATION_EPSILON = 0.001;
+
 // Persist the GrdiSettings
 const localStorageEffect: AtomEffect<GridSettings> = ({ setSelf, onSet }) => {
   // Initialize from localStorage
@@ -49,10 +56,24 @@ const emitGridSettingsChange: AtomEffect<GridSettings> = ({ onSet }) => {
   });
 };
 
+// Pass the calculation settings to quadratic-core, which evaluates formulas
+const applyCalculationSettings: AtomEffect<GridSettings> = ({ onSet }) => {
+  const apply = (settings: GridSettings) =>
+    set_iterative_calculation(
+      settings.iterativeCalculation ?? false,
+      ITERATIVE_CALCULATION_MAX_ITERATIONS,
+      ITERATIVE_CALCULATION_EPSILON
+    );
+
+  const savedValue = localStorage.getItem(SETTINGS_KEY);
+  apply(savedValue != null ? JSON.parse(savedValue) : defaultGridSettings);
+  onSet((newValue) => apply(newValue));
+};
+
 const gridSettingsAtom = atom({
   key: 'gridSettings',
   default: defaultGridSettings,
-  effects: [localStorageEffect, emitGridSettingsChange],
+  effects: [localStorageEffect, emitGridSettingsChange, applyCalculationSettings],
 });
 
 interface GridSettingsReturn {
@@ -62,12 +83,14 @@ interface GridSettingsReturn {
   showCellTypeOutlines: boolean;
   showA1Notation: boolean;
   presentationMode: boolean;
+  iterativeCalculation: boolean;
   setShowGridAxes: (value: boolean) => void;
   setShowHeadings: (value: boolean) => void;
   setShowGridLines: (value: boolean) => void;
   setShowCellTypeOutlines: (value: boolean) => void;
   setShowA1Notation: (value: boolean) => void;
   setPresentationMode: (value: boolean) => void;
+  setIterativeCalculation: (value: boolean) => void;
 }
 
 export const useGridSettings = (): GridSettingsReturn => {
@@ -128,6 +151,15 @@ export const useGridSettings = (): GridSettingsReturn => {
     });
   };
 
+  const setIterativeCalculation = (value: boolean) =>
+    setSettings((currentState) => {
+      if (value !== currentState.iterativeCalculation) {
+        mixpanel.track('[Grid].[Settings].setIterativeCalculation', { value });
+        return { ...currentState, iterativeCalculation: value };
+      }
+      return currentState;
+    });
+
   return {
     ...settings,
     setShowGridAxes,
@@ -136,5 +168,6 @@ export const useGridSettings = (): GridSettingsReturn => {
     setShowCellTypeOutlines,
     setShowA1Notation,
     setPresentationMode,
+    setIterativeCalculation,
   };
 };